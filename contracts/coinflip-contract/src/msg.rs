@@ -44,6 +44,10 @@ pub enum ExecuteMsg {
     ReceiveRewards {},
     DrainGame {},
     StopGame {},
+    /// Points the game to a new reserve, only callable by the current reserve
+    MigrateReserve {
+        new_reserve: String,
+    },
     #[cfg(feature = "debug")]
     ChangeConfig {
         native_denom: Option<String>,
@@ -0,0 +1,277 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+
+use crate::state::{read_config, store_config, store_sweep_balance, take_sweep_balance, Config};
+
+use astroport::asset::{Asset, AssetInfo, PairInfo};
+use astroport::pair::ExecuteMsg as AstroportExecuteMsg;
+use astroport::querier::{query_balance, query_pair_info, query_token_balance};
+use cosmwasm_std::{
+    to_binary, Binary, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response,
+    StdError, StdResult, SubMsg, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terra_vegas::collector::{ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+
+pub const SWEEP_REPLY_ID: u64 = 1;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    assert_reward_factor(msg.reward_factor)?;
+
+    let config = Config {
+        gov_contract: deps.api.addr_canonicalize(&msg.gov_contract)?,
+        astroport_factory: deps.api.addr_canonicalize(&msg.astroport_factory)?,
+        anchor_token: deps.api.addr_canonicalize(&msg.anchor_token)?,
+        reward_factor: msg.reward_factor,
+        max_spread: msg.max_spread,
+        remainder_receiver: msg
+            .remainder_receiver
+            .map(|receiver| deps.api.addr_canonicalize(&receiver))
+            .transpose()?,
+    };
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::UpdateConfig {
+            reward_factor,
+            gov_contract,
+            astroport_factory,
+            max_spread,
+            remainder_receiver,
+        } => update_config(
+            deps,
+            info,
+            reward_factor,
+            gov_contract,
+            astroport_factory,
+            max_spread,
+            remainder_receiver,
+        ),
+        ExecuteMsg::Sweep { denom } => sweep(deps, env, denom),
+    }
+}
+
+fn assert_reward_factor(reward_factor: Decimal) -> StdResult<()> {
+    if reward_factor > Decimal::one() {
+        return Err(StdError::generic_err("reward factor cannot exceed 1"));
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    reward_factor: Option<Decimal>,
+    gov_contract: Option<String>,
+    astroport_factory: Option<String>,
+    max_spread: (bool, Option<Decimal>),
+    remainder_receiver: (bool, Option<String>),
+) -> StdResult<Response> {
+    let mut config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.gov_contract {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    if let Some(reward_factor) = reward_factor {
+        assert_reward_factor(reward_factor)?;
+        config.reward_factor = reward_factor;
+    }
+
+    if let Some(gov_contract) = gov_contract {
+        config.gov_contract = deps.api.addr_canonicalize(&gov_contract)?;
+    }
+
+    if let Some(astroport_factory) = astroport_factory {
+        config.astroport_factory = deps.api.addr_canonicalize(&astroport_factory)?;
+    }
+
+    if max_spread.0 {
+        config.max_spread = max_spread.1;
+    }
+
+    if remainder_receiver.0 {
+        config.remainder_receiver = remainder_receiver
+            .1
+            .map(|receiver| deps.api.addr_canonicalize(&receiver))
+            .transpose()?;
+    }
+
+    store_config(deps.storage, &config)?;
+    Ok(Response::new().add_attribute("action", "update_config"))
+}
+
+/// Sweep
+/// Anyone can execute sweep function to swap
+/// the given denom balance to VEGA token,
+/// the proceeds are distributed once the swap succeeded
+pub fn sweep(deps: DepsMut, env: Env, denom: String) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    let anchor_token = deps.api.addr_humanize(&config.anchor_token)?;
+    let astroport_factory = deps.api.addr_humanize(&config.astroport_factory)?;
+
+    let pair_info: PairInfo = query_pair_info(
+        &deps.querier,
+        astroport_factory,
+        &[
+            AssetInfo::NativeToken {
+                denom: denom.to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: anchor_token.clone(),
+            },
+        ],
+    )?;
+
+    let amount = query_balance(
+        &deps.querier,
+        env.contract.address.clone(),
+        denom.to_string(),
+    )?;
+    if amount.is_zero() {
+        return Err(StdError::generic_err(format!("No {} to sweep", denom)));
+    }
+
+    // the VEGA held before the swap is not part of the proceeds
+    let vega_balance = query_token_balance(&deps.querier, anchor_token, env.contract.address)?;
+    store_sweep_balance(deps.storage, &vega_balance)?;
+
+    let swap_asset = Asset {
+        info: AssetInfo::NativeToken {
+            denom: denom.to_string(),
+        },
+        amount,
+    };
+
+    // deduct tax first
+    let offer = swap_asset.deduct_tax(&deps.querier)?;
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: pair_info.contract_addr.to_string(),
+                msg: to_binary(&AstroportExecuteMsg::Swap {
+                    offer_asset: Asset {
+                        amount: offer.amount,
+                        ..swap_asset
+                    },
+                    max_spread: config.max_spread,
+                    belief_price: None,
+                    to: None,
+                })?,
+                funds: vec![offer.clone()],
+            }),
+            SWEEP_REPLY_ID,
+        ))
+        .add_attributes(vec![
+            ("action", "sweep"),
+            ("collected_rewards", &offer.to_string()),
+        ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    if msg.id == SWEEP_REPLY_ID {
+        return distribute(deps, env);
+    }
+
+    Err(StdError::generic_err("not supported reply"))
+}
+
+/// Sends `reward_factor` of the VEGA received from the swap to the gov contract
+/// and burns or forwards the remainder
+pub fn distribute(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    let anchor_token = deps.api.addr_humanize(&config.anchor_token)?;
+
+    let balance = query_token_balance(&deps.querier, anchor_token.clone(), env.contract.address)?;
+    let amount = balance.checked_sub(take_sweep_balance(deps.storage)?)?;
+    let distribute_amount = amount * config.reward_factor;
+    let left_amount = amount.checked_sub(distribute_amount)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !distribute_amount.is_zero() {
+        messages.push(token_msg(
+            anchor_token.as_ref(),
+            Cw20ExecuteMsg::Transfer {
+                recipient: deps.api.addr_humanize(&config.gov_contract)?.to_string(),
+                amount: distribute_amount,
+            },
+        )?);
+    }
+
+    if !left_amount.is_zero() {
+        let msg = match &config.remainder_receiver {
+            Some(receiver) => Cw20ExecuteMsg::Transfer {
+                recipient: deps.api.addr_humanize(receiver)?.to_string(),
+                amount: left_amount,
+            },
+            None => Cw20ExecuteMsg::Burn {
+                amount: left_amount,
+            },
+        };
+        messages.push(token_msg(anchor_token.as_ref(), msg)?);
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "distribute"),
+        ("distribute_amount", &distribute_amount.to_string()),
+        ("left_amount", &left_amount.to_string()),
+    ]))
+}
+
+fn token_msg(token: &str, msg: Cw20ExecuteMsg) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_binary(&msg)?,
+        funds: vec![],
+    }))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+    }
+}
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let state = read_config(deps.storage)?;
+    let resp = ConfigResponse {
+        gov_contract: deps.api.addr_humanize(&state.gov_contract)?.to_string(),
+        astroport_factory: deps
+            .api
+            .addr_humanize(&state.astroport_factory)?
+            .to_string(),
+        anchor_token: deps.api.addr_humanize(&state.anchor_token)?.to_string(),
+        reward_factor: state.reward_factor,
+        max_spread: state.max_spread,
+        remainder_receiver: state
+            .remainder_receiver
+            .map(|receiver| deps.api.addr_humanize(&receiver))
+            .transpose()?
+            .map(|receiver| receiver.to_string()),
+    };
+
+    Ok(resp)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    let mut config: Config = read_config(deps.storage)?;
+    config.astroport_factory = deps.api.addr_canonicalize(&msg.astroport_factory)?;
+    config.max_spread = Some(msg.max_spread);
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default())
+}
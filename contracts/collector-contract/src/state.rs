@@ -0,0 +1,37 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{CanonicalAddr, Decimal, StdResult, Storage, Uint128};
+use cosmwasm_storage::{singleton, singleton_read};
+
+static KEY_CONFIG: &[u8] = b"config";
+static KEY_SWEEP_BALANCE: &[u8] = b"sweep_balance";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub gov_contract: CanonicalAddr, // collected rewards receiver
+    pub astroport_factory: CanonicalAddr,
+    pub anchor_token: CanonicalAddr, // vega token address
+    pub reward_factor: Decimal,
+    pub max_spread: Option<Decimal>,
+    pub remainder_receiver: Option<CanonicalAddr>, // the remainder is burnt when not set
+}
+
+pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
+    singleton(storage, KEY_CONFIG).save(config)
+}
+
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    singleton_read(storage, KEY_CONFIG).load()
+}
+
+/// VEGA balance before the swap of a sweep, only the proceeds of the swap are distributed
+pub fn store_sweep_balance(storage: &mut dyn Storage, balance: &Uint128) -> StdResult<()> {
+    singleton(storage, KEY_SWEEP_BALANCE).save(balance)
+}
+
+pub fn take_sweep_balance(storage: &mut dyn Storage) -> StdResult<Uint128> {
+    let balance = singleton_read(storage, KEY_SWEEP_BALANCE).load()?;
+    singleton::<Uint128>(storage, KEY_SWEEP_BALANCE).remove();
+    Ok(balance)
+}
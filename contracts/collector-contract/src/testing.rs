@@ -0,0 +1,244 @@
+use crate::contract::{execute, instantiate, query, reply, SWEEP_REPLY_ID};
+use crate::mock_querier::{mock_dependencies, WasmMockQuerier, FACTORY_ADDR, PAIR_ADDR};
+use astroport::asset::{Asset, AssetInfo};
+use astroport::pair::ExecuteMsg as AstroportExecuteMsg;
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    coins, from_binary, to_binary, Coin, ContractResult, CosmosMsg, Decimal, OwnedDeps, Reply,
+    StdError, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terra_vegas::collector::{ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+
+const VEGA: &str = "vega";
+const GOV: &str = "gov";
+
+fn init() -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        gov_contract: GOV.to_string(),
+        astroport_factory: FACTORY_ADDR.to_string(),
+        anchor_token: VEGA.to_string(),
+        reward_factor: Decimal::percent(90),
+        max_spread: None,
+        remainder_receiver: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    deps
+}
+
+fn config(deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>) -> ConfigResponse {
+    from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap()
+}
+
+fn vega_msg(msg: Cw20ExecuteMsg) -> SubMsg {
+    SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: VEGA.to_string(),
+        msg: to_binary(&msg).unwrap(),
+        funds: vec![],
+    }))
+}
+
+// sweeps the uusd balance and replies once the swap added `proceeds` to the VEGA balance
+fn sweep_reply(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    vega_balance: u128,
+    proceeds: u128,
+) -> Vec<SubMsg> {
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(1000, "uusd"));
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(vega_balance));
+    let msg = ExecuteMsg::Sweep {
+        denom: "uusd".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+
+    deps.querier.with_token_balance(
+        VEGA,
+        MOCK_CONTRACT_ADDR,
+        Uint128::from(vega_balance + proceeds),
+    );
+    let msg = Reply {
+        id: SWEEP_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    reply(deps.as_mut(), mock_env(), msg).unwrap().messages
+}
+
+#[test]
+fn proper_initialization() {
+    let deps = init();
+    assert_eq!(
+        config(&deps),
+        ConfigResponse {
+            gov_contract: GOV.to_string(),
+            astroport_factory: FACTORY_ADDR.to_string(),
+            anchor_token: VEGA.to_string(),
+            reward_factor: Decimal::percent(90),
+            max_spread: None,
+            remainder_receiver: None,
+        }
+    );
+
+    let msg = InstantiateMsg {
+        gov_contract: GOV.to_string(),
+        astroport_factory: FACTORY_ADDR.to_string(),
+        anchor_token: VEGA.to_string(),
+        reward_factor: Decimal::percent(101),
+        max_spread: None,
+        remainder_receiver: None,
+    };
+    let mut deps = mock_dependencies(&[]);
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap_err();
+}
+
+#[test]
+fn update_config() {
+    let mut deps = init();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        reward_factor: Some(Decimal::percent(80)),
+        gov_contract: None,
+        astroport_factory: Some("new_factory".to_string()),
+        max_spread: (true, Some(Decimal::percent(1))),
+        remainder_receiver: (true, Some("community".to_string())),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("unauthorized"));
+    execute(deps.as_mut(), mock_env(), mock_info(GOV, &[]), msg).unwrap();
+    assert_eq!(
+        config(&deps),
+        ConfigResponse {
+            gov_contract: GOV.to_string(),
+            astroport_factory: "new_factory".to_string(),
+            anchor_token: VEGA.to_string(),
+            reward_factor: Decimal::percent(80),
+            max_spread: Some(Decimal::percent(1)),
+            remainder_receiver: Some("community".to_string()),
+        }
+    );
+
+    // the optional fields are only updated when flagged
+    let msg = ExecuteMsg::UpdateConfig {
+        reward_factor: None,
+        gov_contract: Some("new_gov".to_string()),
+        astroport_factory: None,
+        max_spread: (false, None),
+        remainder_receiver: (true, None),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(GOV, &[]), msg).unwrap();
+    let config = config(&deps);
+    assert_eq!(config.gov_contract, "new_gov");
+    assert_eq!(config.max_spread, Some(Decimal::percent(1)));
+    assert_eq!(config.remainder_receiver, None);
+}
+
+#[test]
+fn sweep_swaps_to_vega() {
+    let mut deps = init();
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[("uusd", Uint128::from(1_000_000u128))],
+    );
+
+    let msg = ExecuteMsg::Sweep {
+        denom: "uusd".to_string(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("No uusd to sweep"));
+
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(1010, "uusd"));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_success(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: PAIR_ADDR.to_string(),
+                msg: to_binary(&AstroportExecuteMsg::Swap {
+                    offer_asset: Asset {
+                        info: AssetInfo::NativeToken {
+                            denom: "uusd".to_string(),
+                        },
+                        amount: Uint128::from(1000u128),
+                    },
+                    max_spread: None,
+                    belief_price: None,
+                    to: None,
+                })
+                .unwrap(),
+                funds: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            }),
+            SWEEP_REPLY_ID
+        )]
+    );
+}
+
+#[test]
+fn distribute_proceeds() {
+    let mut deps = init();
+
+    // the remainder is burnt by default, the VEGA held before the swap is kept
+    assert_eq!(
+        sweep_reply(&mut deps, 50, 100),
+        vec![
+            vega_msg(Cw20ExecuteMsg::Transfer {
+                recipient: GOV.to_string(),
+                amount: Uint128::from(90u128),
+            }),
+            vega_msg(Cw20ExecuteMsg::Burn {
+                amount: Uint128::from(10u128),
+            }),
+        ]
+    );
+
+    let msg = ExecuteMsg::UpdateConfig {
+        reward_factor: None,
+        gov_contract: None,
+        astroport_factory: None,
+        max_spread: (false, None),
+        remainder_receiver: (true, Some("community".to_string())),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(GOV, &[]), msg).unwrap();
+    assert_eq!(
+        sweep_reply(&mut deps, 50, 100),
+        vec![
+            vega_msg(Cw20ExecuteMsg::Transfer {
+                recipient: GOV.to_string(),
+                amount: Uint128::from(90u128),
+            }),
+            vega_msg(Cw20ExecuteMsg::Transfer {
+                recipient: "community".to_string(),
+                amount: Uint128::from(10u128),
+            }),
+        ]
+    );
+
+    let msg = Reply {
+        id: 2,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    reply(deps.as_mut(), mock_env(), msg).unwrap_err();
+}
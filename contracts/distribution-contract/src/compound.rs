@@ -0,0 +1,358 @@
+use crate::contract::COMPOUND_REPLY_ID;
+use crate::global::query_asset_balance;
+use crate::ledger::record_claim;
+use crate::lock::expire_holder_locks;
+use crate::math::decimal_summation_in_256;
+use crate::state::{
+    read_holder, read_holder_reward, store_holder, store_holder_reward, ClaimReceipt,
+    CompoundConfig, PendingCompound, AUTO_COMPOUND, COMPOUND_CONFIG, COMPOUND_CURSOR, CONFIG,
+    PENDING_COMPOUND, REWARD_ASSETS, STATE,
+};
+use crate::taxation::deduct_tax;
+use crate::user::{bond_stake, calculate_decimal_rewards, get_decimals};
+use crate::utils::only_owner;
+use astroport::asset::{Asset, AssetInfo as AstroportAssetInfo};
+use astroport::pair::ExecuteMsg as PairExecuteMsg;
+use astroport::querier::query_pair_info;
+use cosmwasm_std::{
+    to_binary, CanonicalAddr, Coin, ContractResult, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Order, Response, StdError, StdResult, Storage, SubMsg, SubMsgExecutionResponse,
+    Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::Bound;
+use terra_vegas::distribution::{AssetInfo, AutoCompoundResponse, CompoundConfigResponse};
+use terra_vegas::gov::Cw20HookMsg;
+
+// holders compounded by a single swap
+const COMPOUND_BATCH: usize = 10;
+
+pub fn update_compound_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    astroport_factory: String,
+    vega_token: String,
+    max_spread: Option<Decimal>,
+) -> StdResult<Response> {
+    only_owner(deps.as_ref(), &info)?;
+
+    if matches!(max_spread, Some(spread) if spread > Decimal::one()) {
+        return Err(StdError::generic_err("max spread cannot exceed 1"));
+    }
+
+    COMPOUND_CONFIG.save(
+        deps.storage,
+        &CompoundConfig {
+            astroport_factory: deps.api.addr_validate(&astroport_factory)?,
+            vega_token: deps.api.addr_validate(&vega_token)?,
+            max_spread,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_compound_config")
+        .add_attribute("astroport_factory", astroport_factory)
+        .add_attribute("vega_token", vega_token))
+}
+
+pub fn set_auto_compound(deps: DepsMut, info: MessageInfo, enabled: bool) -> StdResult<Response> {
+    let address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    if enabled {
+        AUTO_COMPOUND.save(deps.storage, address_raw.as_slice(), &true)?;
+    } else {
+        AUTO_COMPOUND.remove(deps.storage, address_raw.as_slice());
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_auto_compound")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+/// Next holders to compound, starting over once every holder was visited
+fn next_batch(storage: &dyn Storage) -> StdResult<Vec<Vec<u8>>> {
+    let start = COMPOUND_CURSOR.may_load(storage)?.map(Bound::exclusive);
+    let mut batch: Vec<Vec<u8>> = AUTO_COMPOUND
+        .keys(storage, start, None, Order::Ascending)
+        .take(COMPOUND_BATCH)
+        .collect();
+    if batch.is_empty() {
+        batch = AUTO_COMPOUND
+            .keys(storage, None, None, Order::Ascending)
+            .take(COMPOUND_BATCH)
+            .collect();
+    }
+    Ok(batch)
+}
+
+/// Public entry point, fails when there is nothing to compound
+pub fn handle_compound(deps: DepsMut, env: Env) -> StdResult<Response> {
+    match compound(deps, &env)? {
+        Some(submessage) => Ok(Response::new()
+            .add_attribute("action", "compound")
+            .add_submessage(submessage)),
+        None => Err(StdError::generic_err("No rewards to compound")),
+    }
+}
+
+/// Moves the reward denom rewards of the next batch out of the holders' balances
+/// and swaps them to the governance token.
+/// Apart from the lock expiries, which stand on their own, nothing is written before every query
+/// succeeded, so index updates can skip a failed compound.
+pub fn compound(deps: DepsMut, env: &Env) -> StdResult<Option<SubMsg>> {
+    let compound_config = COMPOUND_CONFIG
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("auto compound is not configured"))?;
+    if PENDING_COMPOUND.may_load(deps.storage)?.is_some() {
+        return Err(StdError::generic_err("compound is already in progress"));
+    }
+    let config = CONFIG.load(deps.storage)?;
+
+    let key = AssetInfo::NativeToken {
+        denom: config.reward_denom.clone(),
+    }
+    .key();
+    let mut reward_asset = REWARD_ASSETS.load(deps.storage, &key)?;
+
+    let batch = next_batch(deps.storage)?;
+    let cursor = match batch.last() {
+        Some(last) if batch.len() == COMPOUND_BATCH => Some(last.clone()),
+        _ => None,
+    };
+    let mut state = STATE.load(deps.storage)?;
+    let mut holders: Vec<_> = vec![];
+    let mut total_rewards = Uint128::zero();
+    for address in batch {
+        let address_raw: CanonicalAddr = address.into();
+        let mut holder = read_holder(deps.storage, &address_raw)?;
+        expire_holder_locks(deps.storage, env, &address_raw, &mut holder, &mut state)?;
+        store_holder(deps.storage, &address_raw, &holder)?;
+        let mut reward = read_holder_reward(deps.storage, &address_raw, &key)?;
+
+        let reward_with_decimals =
+            calculate_decimal_rewards(reward_asset.global_index, reward.index, holder.weight())?;
+        let all_reward_with_decimals =
+            decimal_summation_in_256(reward_with_decimals, reward.pending_rewards);
+        let rewards = all_reward_with_decimals * Uint128::from(1u128);
+        if rewards.is_zero() {
+            continue;
+        }
+
+        reward.pending_rewards = get_decimals(all_reward_with_decimals)?;
+        reward.index = reward_asset.global_index;
+
+        total_rewards += rewards;
+        holders.push((address_raw, rewards, reward));
+    }
+    STATE.save(deps.storage, &state)?;
+
+    if total_rewards.is_zero() {
+        save_cursor(deps.storage, cursor)?;
+        return Ok(None);
+    }
+
+    let pair = query_pair_info(
+        &deps.querier,
+        compound_config.astroport_factory,
+        &[
+            AstroportAssetInfo::NativeToken {
+                denom: config.reward_denom.clone(),
+            },
+            AstroportAssetInfo::Token {
+                contract_addr: compound_config.vega_token.clone(),
+            },
+        ],
+    )?;
+    let vega_balance = query_asset_balance(
+        &deps.querier,
+        &AssetInfo::Token {
+            contract_addr: compound_config.vega_token.to_string(),
+        },
+        &env.contract.address,
+    )?;
+    let offer = deduct_tax(
+        &deps.querier,
+        Coin {
+            denom: config.reward_denom.clone(),
+            amount: total_rewards,
+        },
+    )?;
+    reward_asset.prev_reward_balance = reward_asset
+        .prev_reward_balance
+        .checked_sub(total_rewards)?;
+
+    save_cursor(deps.storage, cursor)?;
+    REWARD_ASSETS.save(deps.storage, &key, &reward_asset)?;
+    for (address_raw, _, reward) in holders.iter() {
+        store_holder_reward(deps.storage, address_raw, &key, reward)?;
+    }
+    PENDING_COMPOUND.save(
+        deps.storage,
+        &PendingCompound {
+            holders: holders
+                .into_iter()
+                .map(|(address_raw, rewards, _)| (address_raw, rewards))
+                .collect(),
+            total_rewards,
+            offer_amount: offer.amount,
+            vega_balance,
+        },
+    )?;
+
+    let swap = WasmMsg::Execute {
+        contract_addr: pair.contract_addr.to_string(),
+        msg: to_binary(&PairExecuteMsg::Swap {
+            offer_asset: Asset {
+                info: AstroportAssetInfo::NativeToken {
+                    denom: config.reward_denom,
+                },
+                amount: offer.amount,
+            },
+            belief_price: None,
+            max_spread: compound_config.max_spread,
+            to: None,
+        })?,
+        funds: vec![offer],
+    };
+    Ok(Some(SubMsg::reply_always(
+        CosmosMsg::Wasm(swap),
+        COMPOUND_REPLY_ID,
+    )))
+}
+
+fn save_cursor(storage: &mut dyn Storage, cursor: Option<Vec<u8>>) -> StdResult<()> {
+    match cursor {
+        Some(cursor) => COMPOUND_CURSOR.save(storage, &cursor),
+        None => {
+            COMPOUND_CURSOR.remove(storage);
+            Ok(())
+        }
+    }
+}
+
+/// Restakes the swapped tokens pro rata, into governance or into this contract
+/// when they are its staking token, or gives the rewards back if the swap failed
+pub fn handle_compound_reply(
+    deps: DepsMut,
+    env: Env,
+    result: ContractResult<SubMsgExecutionResponse>,
+) -> StdResult<Response> {
+    let pending = PENDING_COMPOUND.load(deps.storage)?;
+    PENDING_COMPOUND.remove(deps.storage);
+
+    if let ContractResult::Err(error) = result {
+        let config = CONFIG.load(deps.storage)?;
+        let key = AssetInfo::NativeToken {
+            denom: config.reward_denom,
+        }
+        .key();
+
+        let mut reward_asset = REWARD_ASSETS.load(deps.storage, &key)?;
+        reward_asset.prev_reward_balance += pending.total_rewards;
+        REWARD_ASSETS.save(deps.storage, &key, &reward_asset)?;
+
+        for (address_raw, amount) in pending.holders.iter() {
+            let mut reward = read_holder_reward(deps.storage, address_raw, &key)?;
+            reward.pending_rewards = decimal_summation_in_256(
+                reward.pending_rewards,
+                Decimal::from_ratio(*amount, Uint128::from(1u128)),
+            );
+            store_holder_reward(deps.storage, address_raw, &key, &reward)?;
+        }
+
+        return Ok(Response::new()
+            .add_attribute("action", "compound_failed")
+            .add_attribute("error", error));
+    }
+
+    let compound_config = COMPOUND_CONFIG.load(deps.storage)?;
+    let received = query_asset_balance(
+        &deps.querier,
+        &AssetInfo::Token {
+            contract_addr: compound_config.vega_token.to_string(),
+        },
+        &env.contract.address,
+    )?
+    .checked_sub(pending.vega_balance)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let governance = config.governance_token_addr;
+    let tax = pending.total_rewards.checked_sub(pending.offer_amount)?;
+    // the swapped tokens are bonded here when they are the contract's own staking token
+    let bond_locally = config.staking_token.as_ref() == Some(&compound_config.vega_token);
+    let recipient = if bond_locally {
+        env.contract.address.clone()
+    } else {
+        governance.clone()
+    };
+
+    // the last holder gets the rounding remainder so no tokens are left behind
+    let mut remaining = received;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (i, (address_raw, amount)) in pending.holders.iter().enumerate() {
+        record_claim(
+            deps.storage,
+            address_raw,
+            &ClaimReceipt {
+                timestamp: env.block.time.seconds(),
+                asset: AssetInfo::NativeToken {
+                    denom: config.reward_denom.clone(),
+                },
+                amount: *amount,
+                recipient: recipient.clone(),
+                tax: tax.multiply_ratio(*amount, pending.total_rewards),
+            },
+        )?;
+
+        let share = if i + 1 == pending.holders.len() {
+            remaining
+        } else {
+            received.multiply_ratio(*amount, pending.total_rewards)
+        };
+        remaining = remaining.checked_sub(share)?;
+        if share.is_zero() {
+            continue;
+        }
+        if bond_locally {
+            bond_stake(deps.storage, &env, address_raw, share)?;
+            continue;
+        }
+
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: compound_config.vega_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: governance.to_string(),
+                amount: share,
+                msg: to_binary(&Cw20HookMsg::StakeVotingTokensFor {
+                    staker: deps.api.addr_humanize(address_raw)?.to_string(),
+                })?,
+            })?,
+            funds: vec![],
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "compound")
+        .add_attribute("rewards", pending.total_rewards)
+        .add_attribute("restaked", received))
+}
+
+pub fn query_compound_config(deps: Deps) -> StdResult<CompoundConfigResponse> {
+    let config = COMPOUND_CONFIG.load(deps.storage)?;
+    Ok(CompoundConfigResponse {
+        astroport_factory: config.astroport_factory.to_string(),
+        vega_token: config.vega_token.to_string(),
+        max_spread: config.max_spread,
+    })
+}
+
+pub fn query_auto_compound(deps: Deps, address: String) -> StdResult<AutoCompoundResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    Ok(AutoCompoundResponse {
+        enabled: AUTO_COMPOUND
+            .may_load(deps.storage, address_raw.as_slice())?
+            .unwrap_or(false),
+    })
+}
@@ -1,21 +1,38 @@
+use crate::compound::{
+    handle_compound, handle_compound_reply, query_auto_compound, query_compound_config,
+    set_auto_compound, update_compound_config,
+};
+use crate::emission::{
+    close_epoch, handle_epoch_reply, query_emission, query_epoch, query_epochs, set_emission,
+};
 use crate::global::{
-    calculate_reserve_request_amount, handle_update_global_index, query_reserve,
-    request_reserve_or_update_global_index, update_config, update_reserve,
+    calculate_reserve_request_amount, handle_update_global_index, migrate_reserve, query_reserve,
+    register_reward_asset, remove_reward_asset, request_reserve_or_update_global_index,
+    save_reward_asset, update_config, update_reserve,
+};
+use crate::history::{query_apr, query_snapshots};
+use crate::ledger::{query_accrual_checkpoints, query_claim_receipts};
+use crate::lock::{
+    handle_lock, handle_unlock, query_lock, query_lock_config, query_locks, update_lock_config,
+};
+use crate::operator::{add_operator, query_operators, remove_operator};
+use crate::state::{
+    read_reward_assets, store_holder_reward, Config, HolderReward, RewardAsset, State, CONFIG,
+    EMISSION, LEGACY_HOLDERS, LEGACY_STATE, OWNER, REWARD_ASSETS, STATE,
 };
-use crate::state::{Config, State, CONFIG, OWNER, STATE};
 use crate::user::{
-    handle_claim_rewards, handle_receive, handle_unbound, query_accrued_rewards, query_holder,
-    query_holders,
+    handle_claim_and_call, handle_claim_rewards, handle_receive, handle_unbond_stake,
+    handle_unbound, handle_withdraw_stake, query_accrued_rewards, query_holder, query_holders,
 };
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response,
-    StdError, StdResult, Uint128,
+    entry_point, to_binary, Binary, CanonicalAddr, Deps, DepsMut, Env, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, Uint128,
 };
 
 use crate::claim::query_claims;
 use terra_vegas::distribution::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ReserveRequestFundsResponse,
-    StateResponse,
+    AssetInfo, ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+    ReserveRequestFundsResponse, RewardAssetResponse, StateResponse,
 };
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -35,20 +52,30 @@ pub fn instantiate(
             .api
             .addr_validate(&msg.reserve_contract_addr.as_str())?,
         unbonding_period: msg.unbonding_period,
+        staking_token: msg
+            .staking_token
+            .map(|token| deps.api.addr_validate(&token))
+            .transpose()?,
     };
 
     OWNER.save(deps.storage, &info.sender)?;
+    save_reward_asset(
+        deps.storage,
+        AssetInfo::NativeToken {
+            denom: conf.reward_denom.clone(),
+        },
+    )?;
     CONFIG.save(deps.storage, &conf)?;
     STATE.save(
         deps.storage,
         &State {
-            global_index: Decimal::zero(),
             total_balance: Uint128::zero(),
-            prev_reward_balance: Uint128::zero(),
             last_reserve_residue: match msg.initial_reserve_amount {
                 Some(v) => v,
                 None => Uint128::zero(),
             },
+            staked_tokens: Uint128::zero(),
+            total_bonus: Uint128::zero(),
         },
     )?;
 
@@ -75,18 +102,53 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             distribution_ratio,
             unbonding_period,
         ),
-        ExecuteMsg::ClaimRewards { recipient } => handle_claim_rewards(deps, env, info, recipient),
+        ExecuteMsg::RegisterRewardAsset { asset } => register_reward_asset(deps, info, asset),
+        ExecuteMsg::RemoveRewardAsset { asset } => remove_reward_asset(deps, info, asset),
+        ExecuteMsg::SetEmission {
+            policy,
+            epoch_length,
+        } => set_emission(deps, env, info, policy, epoch_length),
+        ExecuteMsg::UpdateCompoundConfig {
+            astroport_factory,
+            vega_token,
+            max_spread,
+        } => update_compound_config(deps, info, astroport_factory, vega_token, max_spread),
+        ExecuteMsg::UpdateLockConfig {
+            max_multiplier,
+            early_exit_penalty,
+        } => update_lock_config(deps, info, max_multiplier, early_exit_penalty),
+        ExecuteMsg::MigrateReserve { new_reserve } => migrate_reserve(deps, info, new_reserve),
+        ExecuteMsg::ClaimRewards {
+            holder,
+            assets,
+            recipient,
+        } => handle_claim_rewards(deps, env, info, holder, assets, recipient),
+        ExecuteMsg::ClaimAndCall {
+            holder,
+            assets,
+            contract,
+            msg,
+        } => handle_claim_and_call(deps, env, info, holder, assets, contract, msg),
+        ExecuteMsg::AddOperator { operator } => add_operator(deps, info, operator),
+        ExecuteMsg::RemoveOperator { operator } => remove_operator(deps, info, operator),
+        ExecuteMsg::SetAutoCompound { enabled } => set_auto_compound(deps, info, enabled),
         ExecuteMsg::UpdateGlobalIndex {} => request_reserve_or_update_global_index(deps, env),
+        ExecuteMsg::CloseEpoch {} => close_epoch(deps, env),
+        ExecuteMsg::Compound {} => handle_compound(deps, env),
         ExecuteMsg::UnbondStake { address, amount } => {
             handle_unbound(deps, env, info, address, amount)
         }
-        // unused
-        // ExecuteMsg::WithdrawStake { cap } => handle_withdraw_stake(deps, env, info, cap),
+        ExecuteMsg::Unbond { amount } => handle_unbond_stake(deps, env, info, amount),
+        ExecuteMsg::WithdrawStake { cap } => handle_withdraw_stake(deps, env, info, cap),
+        ExecuteMsg::Lock { amount, weeks } => handle_lock(deps, env, info, amount, weeks),
+        ExecuteMsg::Unlock { lock_id } => handle_unlock(deps, env, info, lock_id),
         ExecuteMsg::Receive(msg) => handle_receive(deps, env, info, msg),
     }
 }
 
 pub const RESERVE_REQUEST_REPLY_ID: u64 = 1;
+pub const EPOCH_REQUEST_REPLY_ID: u64 = 2;
+pub const COMPOUND_REPLY_ID: u64 = 3;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
@@ -101,22 +163,64 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
 
             return handle_update_global_index(deps, &env);
         }
+        EPOCH_REQUEST_REPLY_ID => handle_epoch_reply(deps, env),
+        COMPOUND_REPLY_ID => handle_compound_reply(deps, env, msg.result),
         _ => Err(StdError::generic_err("unknown reply id")),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::Config {} => to_binary(&query_config(deps, _env, msg)?),
-        QueryMsg::State {} => to_binary(&query_state(deps, _env, msg)?),
-        QueryMsg::AccruedRewards { address } => to_binary(&query_accrued_rewards(deps, address)?),
-        QueryMsg::Holder { address } => to_binary(&query_holder(deps, address)?),
+        QueryMsg::Config {} => to_binary(&query_config(deps, env, msg)?),
+        QueryMsg::State {} => to_binary(&query_state(deps, env, msg)?),
+        QueryMsg::AccruedRewards { address } => {
+            to_binary(&query_accrued_rewards(deps, env, address)?)
+        }
+        QueryMsg::Holder { address } => to_binary(&query_holder(deps, env, address)?),
         QueryMsg::Holders { start_after, limit } => {
-            to_binary(&query_holders(deps, start_after, limit)?)
+            to_binary(&query_holders(deps, env, start_after, limit)?)
         }
         QueryMsg::Claims { address } => to_binary(&query_claims(deps, address)?),
         QueryMsg::ReserveRequestFunds {} => to_binary(&query_reserve_funds(deps)?),
+        QueryMsg::Emission {} => to_binary(&query_emission(deps)?),
+        QueryMsg::Epoch { id } => to_binary(&query_epoch(deps, id)?),
+        QueryMsg::Epochs { start_after, limit } => {
+            to_binary(&query_epochs(deps, start_after, limit)?)
+        }
+        QueryMsg::CompoundConfig {} => to_binary(&query_compound_config(deps)?),
+        QueryMsg::AutoCompound { address } => to_binary(&query_auto_compound(deps, address)?),
+        QueryMsg::Snapshots { start_after, limit } => {
+            to_binary(&query_snapshots(deps, start_after, limit)?)
+        }
+        QueryMsg::Apr { window_seconds } => to_binary(&query_apr(deps, env, window_seconds)?),
+        QueryMsg::ClaimReceipts {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_claim_receipts(deps, address, start_after, limit)?),
+        QueryMsg::AccrualCheckpoints {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_accrual_checkpoints(
+            deps,
+            address,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Operators {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_operators(deps, address, start_after, limit)?),
+        QueryMsg::LockConfig {} => to_binary(&query_lock_config(deps)?),
+        QueryMsg::Lock { address, lock_id } => to_binary(&query_lock(deps, env, address, lock_id)?),
+        QueryMsg::Locks {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_locks(deps, env, address, start_after, limit)?),
     }
 }
 
@@ -125,16 +229,25 @@ pub fn query_config(deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<ConfigRe
 
     Ok(ConfigResponse {
         governance_token_addr: config.governance_token_addr.to_string(),
+        staking_token: config.staking_token.map(|token| token.to_string()),
         reward_denom: config.reward_denom,
         unbonding_period: config.unbonding_period,
     })
 }
 pub fn query_state(deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<StateResponse> {
     let state = STATE.load(deps.storage)?;
+    let assets = read_reward_assets(deps.storage)?
+        .into_iter()
+        .map(|asset| RewardAssetResponse {
+            asset: asset.info,
+            global_index: asset.global_index,
+            prev_reward_balance: asset.prev_reward_balance,
+        })
+        .collect();
     Ok(StateResponse {
-        global_index: state.global_index,
         total_balance: state.total_balance,
-        prev_reward_balance: state.prev_reward_balance,
+        staked_tokens: state.staked_tokens,
+        assets,
     })
 }
 
@@ -142,6 +255,13 @@ pub fn query_reserve_funds(deps: Deps) -> StdResult<ReserveRequestFundsResponse>
     let state = STATE.load(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
 
+    // index updates do not pull from the reserve when epochs are paid out
+    if EMISSION.may_load(deps.storage)?.is_some() {
+        return Ok(ReserveRequestFundsResponse {
+            reserve_request_funds: Uint128::zero(),
+        });
+    }
+
     let reserve_balance = query_reserve(&deps)?;
 
     let request_reserve_amount =
@@ -153,6 +273,54 @@ pub fn query_reserve_funds(deps: Deps) -> StdResult<ReserveRequestFundsResponse>
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    // rewards are already tracked per asset
+    if !read_reward_assets(deps.storage)?.is_empty() {
+        return Ok(Response::default());
+    }
+
+    // move the single reward denom index into the per asset storage
+    let config = CONFIG.load(deps.storage)?;
+    let legacy_state = LEGACY_STATE.load(deps.storage)?;
+    let asset = AssetInfo::NativeToken {
+        denom: config.reward_denom,
+    };
+    let key = asset.key();
+    REWARD_ASSETS.save(
+        deps.storage,
+        &key,
+        &RewardAsset {
+            info: asset,
+            global_index: legacy_state.global_index,
+            prev_reward_balance: legacy_state.prev_reward_balance,
+        },
+    )?;
+    STATE.save(
+        deps.storage,
+        &State {
+            total_balance: legacy_state.total_balance,
+            last_reserve_residue: legacy_state.last_reserve_residue,
+            staked_tokens: Uint128::zero(),
+            total_bonus: Uint128::zero(),
+        },
+    )?;
+
+    let holders = LEGACY_HOLDERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (address, holder) in holders.iter() {
+        store_holder_reward(
+            deps.storage,
+            &CanonicalAddr::from(address.as_slice()),
+            &key,
+            &HolderReward {
+                index: holder.index,
+                pending_rewards: holder.pending_rewards,
+            },
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("migrated_holders", holders.len().to_string()))
 }
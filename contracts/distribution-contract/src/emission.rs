@@ -0,0 +1,303 @@
+use crate::contract::EPOCH_REQUEST_REPLY_ID;
+use crate::global::{handle_update_global_index, query_asset_balance, query_reserve};
+use crate::math::decimal_subtraction_in_256;
+use crate::state::{
+    Emission, Epoch, PendingEpoch, CONFIG, DEFAULT_LIMIT, EMISSION, EPOCHS, EPOCH_COUNT, MAX_LIMIT,
+    PENDING_EPOCH, REWARD_ASSETS, STATE,
+};
+use crate::utils::only_owner;
+use cosmwasm_std::{
+    to_binary, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError,
+    StdResult, Storage, SubMsg, Uint128, WasmMsg,
+};
+use cw_storage_plus::{Bound, U64Key};
+use reserve_contract::msg::ExecuteMsg as ReserveExecuteMsg;
+use std::convert::TryInto;
+use terra_vegas::distribution::{
+    AssetInfo, EmissionPolicy, EmissionResponse, EpochResponse, EpochsResponse,
+};
+
+pub fn set_emission(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    policy: Option<EmissionPolicy>,
+    epoch_length: u64,
+) -> StdResult<Response> {
+    only_owner(deps.as_ref(), &info)?;
+
+    let policy = match policy {
+        Some(policy) => policy,
+        None => {
+            EMISSION.remove(deps.storage);
+            return Ok(Response::new()
+                .add_attribute("action", "set_emission")
+                .add_attribute("policy", "ratio"));
+        }
+    };
+
+    if epoch_length == 0 {
+        return Err(StdError::generic_err("epoch length must be positive"));
+    }
+    match &policy {
+        EmissionPolicy::ProfitShare { ratio } if *ratio > Decimal::one() => {
+            return Err(StdError::generic_err("profit share cannot exceed 1"));
+        }
+        EmissionPolicy::Schedule { schedule } if schedule.iter().any(|(s, e, _)| s >= e) => {
+            return Err(StdError::generic_err(
+                "schedule start time must be before end time",
+            ));
+        }
+        _ => {}
+    }
+
+    // the open epoch restarts with the new policy
+    EMISSION.save(
+        deps.storage,
+        &Emission {
+            policy,
+            epoch_length,
+            epoch_start: env.block.time.seconds(),
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_emission")
+        .add_attribute("epoch_length", epoch_length.to_string()))
+}
+
+/// Amount scheduled between `start` and `end`, every entry is paid linearly over its period
+fn scheduled_amount(schedule: &[(u64, u64, Uint128)], start: u64, end: u64) -> Uint128 {
+    schedule
+        .iter()
+        .filter(|(s, e, _)| *s < end && *e > start)
+        .map(|(s, e, amount)| {
+            let passed = end.min(*e) - start.max(*s);
+            amount.multiply_ratio(passed, *e - *s)
+        })
+        .sum()
+}
+
+fn epoch_payout(policy: &EmissionPolicy, reserve_profit: Uint128, epoch: &Epoch) -> Uint128 {
+    match policy {
+        EmissionPolicy::Fixed { amount } => *amount,
+        EmissionPolicy::ProfitShare { ratio } => reserve_profit * *ratio,
+        EmissionPolicy::Schedule { schedule } => {
+            scheduled_amount(schedule, epoch.start_time, epoch.end_time)
+        }
+    }
+}
+
+pub fn close_epoch(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let mut emission = EMISSION
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("emission schedule is not set"))?;
+
+    let end_time = emission.epoch_start + emission.epoch_length;
+    if env.block.time.seconds() < end_time {
+        return Err(StdError::generic_err(format!(
+            "epoch is open until {}",
+            end_time
+        )));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    let mut epoch = Epoch {
+        start_time: emission.epoch_start,
+        end_time,
+        closed_at: env.block.time.seconds(),
+        payout: Uint128::zero(),
+        index_delta: Decimal::zero(),
+    };
+
+    // nothing is paid out while nobody is bonded
+    let reserve_balance = query_reserve(&deps.as_ref())?;
+    if !state.total_balance.is_zero() {
+        let reserve_profit = reserve_balance
+            .checked_sub(state.last_reserve_residue)
+            .unwrap_or_else(|_| Uint128::zero());
+        epoch.payout = epoch_payout(&emission.policy, reserve_profit, &epoch).min(reserve_balance);
+    }
+
+    let id = EPOCH_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    if epoch.payout.is_zero() {
+        store_epoch(deps.storage, id, &epoch, &mut emission)?;
+        return Ok(Response::new()
+            .add_attribute("action", "close_epoch")
+            .add_attribute("epoch", id.to_string())
+            .add_attribute("payout", epoch.payout));
+    }
+
+    // the payout is what actually arrives, a suspended request sends nothing
+    let reward_asset = AssetInfo::NativeToken {
+        denom: config.reward_denom,
+    };
+    let reward_balance = query_asset_balance(&deps.querier, &reward_asset, &env.contract.address)?;
+    let requested = epoch.payout;
+    PENDING_EPOCH.save(
+        deps.storage,
+        &PendingEpoch {
+            epoch,
+            reward_balance,
+        },
+    )?;
+
+    let msg = WasmMsg::Execute {
+        contract_addr: config.reserve_contract_addr.into(),
+        funds: vec![],
+        msg: to_binary(&ReserveExecuteMsg::RequestFunds {
+            asset: None,
+            amount: requested,
+        })?,
+    };
+    Ok(Response::new()
+        .add_attribute("action", "close_epoch")
+        .add_attribute("epoch", id.to_string())
+        .add_attribute("requested", requested)
+        .add_submessage(SubMsg::reply_on_success(
+            CosmosMsg::Wasm(msg),
+            EPOCH_REQUEST_REPLY_ID,
+        )))
+}
+
+fn store_epoch(
+    storage: &mut dyn Storage,
+    id: u64,
+    epoch: &Epoch,
+    emission: &mut Emission,
+) -> StdResult<()> {
+    EPOCHS.save(storage, U64Key::from(id), epoch)?;
+    EPOCH_COUNT.save(storage, &(id + 1))?;
+    emission.epoch_start = epoch.end_time;
+    EMISSION.save(storage, emission)
+}
+
+fn reward_denom_index(storage: &dyn Storage) -> StdResult<Decimal> {
+    let config = CONFIG.load(storage)?;
+    let asset = AssetInfo::NativeToken {
+        denom: config.reward_denom,
+    };
+    Ok(REWARD_ASSETS.load(storage, &asset.key())?.global_index)
+}
+
+/// Distributes the epoch payout and closes the epoch,
+/// it stays open when the reserve did not send anything
+pub fn handle_epoch_reply(mut deps: DepsMut, env: Env) -> StdResult<Response> {
+    let PendingEpoch {
+        mut epoch,
+        reward_balance,
+    } = PENDING_EPOCH.load(deps.storage)?;
+    PENDING_EPOCH.remove(deps.storage);
+
+    let config = CONFIG.load(deps.storage)?;
+    let reward_asset = AssetInfo::NativeToken {
+        denom: config.reward_denom,
+    };
+    let balance = query_asset_balance(&deps.querier, &reward_asset, &env.contract.address)?;
+    epoch.payout = balance.saturating_sub(reward_balance);
+    if epoch.payout.is_zero() {
+        return Ok(Response::new()
+            .add_attribute("action", "epoch_not_funded")
+            .add_attribute("epoch_start", epoch.start_time.to_string()));
+    }
+
+    let mut state = STATE.load(deps.storage)?;
+    state.last_reserve_residue = query_reserve(&deps.as_ref())?;
+    STATE.save(deps.storage, &state)?;
+
+    let index_before = reward_denom_index(deps.storage)?;
+    let response = handle_update_global_index(deps.branch(), &env)?;
+    epoch.index_delta = decimal_subtraction_in_256(reward_denom_index(deps.storage)?, index_before);
+
+    let id = EPOCH_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    let mut emission = EMISSION.load(deps.storage)?;
+    store_epoch(deps.storage, id, &epoch, &mut emission)?;
+
+    Ok(response
+        .add_attribute("epoch", id.to_string())
+        .add_attribute("payout", epoch.payout)
+        .add_attribute("index_delta", epoch.index_delta.to_string()))
+}
+
+pub fn query_emission(deps: Deps) -> StdResult<EmissionResponse> {
+    let next_epoch = EPOCH_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    Ok(match EMISSION.may_load(deps.storage)? {
+        Some(emission) => EmissionResponse {
+            policy: Some(emission.policy),
+            epoch_length: emission.epoch_length,
+            next_epoch,
+            next_epoch_start: emission.epoch_start,
+        },
+        None => EmissionResponse {
+            policy: None,
+            epoch_length: 0,
+            next_epoch,
+            next_epoch_start: 0,
+        },
+    })
+}
+
+fn to_epoch_response(id: u64, epoch: Epoch) -> EpochResponse {
+    EpochResponse {
+        id,
+        start_time: epoch.start_time,
+        end_time: epoch.end_time,
+        closed_at: epoch.closed_at,
+        payout: epoch.payout,
+        index_delta: epoch.index_delta,
+    }
+}
+
+pub fn query_epoch(deps: Deps, id: u64) -> StdResult<EpochResponse> {
+    let epoch = EPOCHS.load(deps.storage, U64Key::from(id))?;
+    Ok(to_epoch_response(id, epoch))
+}
+
+pub fn query_epochs(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<EpochsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let epochs = EPOCHS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, epoch) = item?;
+            let id = u64::from_be_bytes(k.as_slice().try_into().unwrap());
+            Ok(to_epoch_response(id, epoch))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(EpochsResponse { epochs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proper_scheduled_amount() {
+        let schedule = vec![
+            (100, 200, Uint128::from(1000u128)),
+            (200, 300, Uint128::from(500u128)),
+        ];
+        assert_eq!(scheduled_amount(&schedule, 0, 100), Uint128::zero());
+        assert_eq!(
+            scheduled_amount(&schedule, 150, 200),
+            Uint128::from(500u128)
+        );
+        assert_eq!(
+            scheduled_amount(&schedule, 150, 250),
+            Uint128::from(750u128)
+        );
+        assert_eq!(
+            scheduled_amount(&schedule, 0, 1000),
+            Uint128::from(1500u128)
+        );
+    }
+}
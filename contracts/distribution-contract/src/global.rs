@@ -1,13 +1,21 @@
+use crate::compound::compound;
 use crate::contract::RESERVE_REQUEST_REPLY_ID;
-use crate::state::{Config, State, CONFIG, STATE};
+use crate::history::record_snapshot;
+use crate::lock::expire_locks;
+use crate::state::{
+    read_reward_assets, Config, RewardAsset, State, COMPOUND_CONFIG, CONFIG, EMISSION,
+    REMOVED_REWARD_ASSETS, REWARD_ASSETS, STATE,
+};
 
 use crate::math::decimal_summation_in_256;
 use crate::utils::only_owner;
 use cosmwasm_std::{
-    to_binary, Addr, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, SubMsg, Uint128, WasmMsg, WasmQuery,
+    to_binary, Addr, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, Response,
+    StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg, WasmQuery,
 };
+use cw20::{BalanceResponse, Cw20QueryMsg};
 use reserve_contract::msg::{CurrentBalance, ExecuteMsg, QueryMsg};
+use terra_vegas::distribution::AssetInfo;
 
 pub fn query_reserve(deps: &Deps) -> StdResult<Uint128> {
     let msg = QueryMsg::CurrentBalance {};
@@ -73,6 +81,10 @@ pub fn update_config(
         config.reserve_contract_addr = res;
     };
     if let Some(rew) = reward_denom {
+        let asset = AssetInfo::NativeToken { denom: rew.clone() };
+        if !REWARD_ASSETS.has(deps.storage, &asset.key()) {
+            save_reward_asset(deps.storage, asset)?;
+        }
         config.reward_denom = rew;
     };
     if let Some(d) = distribution_ratio {
@@ -92,6 +104,143 @@ pub fn update_config(
         .add_attribute("unbonding_period", config.unbonding_period.to_string()))
 }
 
+pub fn register_reward_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: AssetInfo,
+) -> StdResult<Response> {
+    only_owner(deps.as_ref(), &info)?;
+
+    let asset = match asset {
+        AssetInfo::Token { contract_addr } => AssetInfo::Token {
+            contract_addr: deps.api.addr_validate(&contract_addr)?.to_string(),
+        },
+        native => native,
+    };
+    if REWARD_ASSETS.has(deps.storage, &asset.key()) {
+        return Err(StdError::generic_err("reward asset is already registered"));
+    }
+    save_reward_asset(deps.storage, asset.clone())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_reward_asset")
+        .add_attribute("asset", asset.to_string()))
+}
+
+pub fn remove_reward_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: AssetInfo,
+) -> StdResult<Response> {
+    only_owner(deps.as_ref(), &info)?;
+
+    // the reward denom is paid by the reserve and the staking token takes the lock penalties
+    let config = CONFIG.load(deps.storage)?;
+    if asset.key() == config.reward_denom
+        || Some(asset.key()) == config.staking_token.map(String::from)
+    {
+        return Err(StdError::generic_err("reward asset cannot be removed"));
+    }
+    if !REWARD_ASSETS.has(deps.storage, &asset.key()) {
+        return Err(StdError::generic_err("reward asset is not registered"));
+    }
+    REWARD_ASSETS.remove(deps.storage, &asset.key());
+    REMOVED_REWARD_ASSETS.save(deps.storage, &asset.key(), &true)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_reward_asset")
+        .add_attribute("asset", asset.to_string()))
+}
+
+/// Registers the asset with a zero index,
+/// the balance already held by the contract is distributed on the next index update
+pub fn save_reward_asset(storage: &mut dyn Storage, asset: AssetInfo) -> StdResult<()> {
+    if REMOVED_REWARD_ASSETS.has(storage, &asset.key()) {
+        return Err(StdError::generic_err("reward asset was removed"));
+    }
+    REWARD_ASSETS.save(
+        storage,
+        &asset.key(),
+        &RewardAsset {
+            info: asset,
+            global_index: Decimal::zero(),
+            prev_reward_balance: Uint128::zero(),
+        },
+    )
+}
+
+pub fn query_asset_balance(
+    querier: &QuerierWrapper,
+    asset: &AssetInfo,
+    address: &Addr,
+) -> StdResult<Uint128> {
+    match asset {
+        AssetInfo::NativeToken { denom } => Ok(querier.query_balance(address, denom)?.amount),
+        AssetInfo::Token { contract_addr } => {
+            let balance: BalanceResponse = querier.query_wasm_smart(
+                contract_addr,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )?;
+            Ok(balance.balance)
+        }
+    }
+}
+
+/// Adds the rewards received since the last update to the asset global index
+/// and returns the amount of new rewards
+pub fn update_asset_index(
+    deps: &mut DepsMut,
+    env: &Env,
+    state: &State,
+    asset: &mut RewardAsset,
+) -> StdResult<Uint128> {
+    // keep the rewards for the first holders when nobody is bonded yet
+    if state.total_balance.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let mut balance = query_asset_balance(&deps.querier, &asset.info, &env.contract.address)?;
+    // the bonded tokens are not rewards when the staking token is distributed as well
+    let config = CONFIG.load(deps.storage)?;
+    if let (Some(staking_token), AssetInfo::Token { contract_addr }) =
+        (config.staking_token, &asset.info)
+    {
+        if staking_token.as_str() == contract_addr.as_str() {
+            balance = balance.checked_sub(state.staked_tokens)?;
+        }
+    }
+
+    // claimed_rewards = current_balance - prev_balance;
+    let claimed_rewards = balance.checked_sub(asset.prev_reward_balance)?;
+    asset.prev_reward_balance = balance;
+
+    // global_index += claimed_rewards / total_weight;
+    asset.global_index = decimal_summation_in_256(
+        asset.global_index,
+        Decimal::from_ratio(claimed_rewards, state.total_weight()),
+    );
+    REWARD_ASSETS.save(deps.storage, &asset.info.key(), asset)?;
+
+    Ok(claimed_rewards)
+}
+
+pub fn migrate_reserve(deps: DepsMut, info: MessageInfo, new_reserve: Addr) -> StdResult<Response> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.reserve_contract_addr {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // the reserve balance moves along with the registration, so the residue stays valid
+    config.reserve_contract_addr = deps.api.addr_validate(new_reserve.as_str())?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate_reserve")
+        .add_attribute("reserve_contract_addr", config.reserve_contract_addr))
+}
+
 pub fn request_reserve_or_update_global_index(deps: DepsMut, env: Env) -> StdResult<Response> {
     let state = STATE.load(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
@@ -100,6 +249,11 @@ pub fn request_reserve_or_update_global_index(deps: DepsMut, env: Env) -> StdRes
         return Err(StdError::generic_err("No asset is bonded by Hub"));
     }
 
+    // the reserve is only paid out when an epoch is closed
+    if EMISSION.may_load(deps.storage)?.is_some() {
+        return handle_update_global_index(deps, &env);
+    }
+
     let reserve_balance = query_reserve(&deps.as_ref())?;
 
     let request_reserve_amount =
@@ -110,6 +264,7 @@ pub fn request_reserve_or_update_global_index(deps: DepsMut, env: Env) -> StdRes
             contract_addr: config.reserve_contract_addr.into(),
             funds: vec![],
             msg: to_binary(&ExecuteMsg::RequestFunds {
+                asset: None,
                 amount: request_reserve_amount,
             })?,
         };
@@ -122,42 +277,31 @@ pub fn request_reserve_or_update_global_index(deps: DepsMut, env: Env) -> StdRes
     }
 }
 
-/// Increase global_index according to claimed rewards amount
-/// Only hub_contract is allowed to execute
-pub fn handle_update_global_index(deps: DepsMut, env: &Env) -> StdResult<Response> {
-    let mut state = STATE.load(deps.storage)?;
-    let config = CONFIG.load(deps.storage)?;
+/// Increase the global index of every reward asset according to the claimed rewards
+pub fn handle_update_global_index(mut deps: DepsMut, env: &Env) -> StdResult<Response> {
+    expire_locks(deps.storage, env)?;
+    let state = STATE.load(deps.storage)?;
+    let reward_denom = CONFIG.load(deps.storage)?.reward_denom;
 
     // anybody can trigger update_global_index
-    /*
-    if config.lottery_contract != deps.api.canonical_address(&env.message.sender)? {
-        return Err(StdError::unauthorized());
+    let mut response = Response::new().add_attribute("action", "update_global_index");
+    for mut asset in read_reward_assets(deps.storage)? {
+        let claimed_rewards = update_asset_index(&mut deps, env, &state, &mut asset)?;
+        if asset.info.key() == reward_denom {
+            record_snapshot(deps.storage, env, &state, &asset, claimed_rewards)?;
+        }
+        response = response.add_attribute(
+            "claimed_rewards",
+            format!("{}:{}", asset.info, claimed_rewards),
+        );
     }
-     */
-
-    let reward_denom = config.reward_denom;
-
-    // Load the reward contract balance
-    let balance = deps
-        .querier
-        .query_balance(&env.contract.address, reward_denom.as_str())?;
-
-    let previous_balance = state.prev_reward_balance;
 
-    // claimed_rewards = current_balance - prev_balance;
-    let claimed_rewards = balance.amount.checked_sub(previous_balance)?;
-
-    state.prev_reward_balance = balance.amount;
-
-    // global_index += claimed_rewards / total_balance;
-    state.global_index = decimal_summation_in_256(
-        state.global_index,
-        Decimal::from_ratio(claimed_rewards, state.total_balance),
-    );
-
-    STATE.save(deps.storage, &state)?;
+    // a failing swap setup must not block the index update
+    if COMPOUND_CONFIG.may_load(deps.storage)?.is_some() {
+        if let Ok(Some(submessage)) = compound(deps.branch(), env) {
+            response = response.add_submessage(submessage);
+        }
+    }
 
-    Ok(Response::new()
-        .add_attribute("action", "update_global_index")
-        .add_attribute("claimed_rewards", claimed_rewards))
+    Ok(response)
 }
@@ -0,0 +1,119 @@
+use crate::math::{decimal_multiplication_in_256, decimal_subtraction_in_256};
+use crate::state::{
+    RewardAsset, Snapshot, State, CONFIG, DEFAULT_LIMIT, MAX_LIMIT, REWARD_ASSETS, SNAPSHOTS,
+};
+use cosmwasm_std::{Decimal, Deps, Env, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, U64Key};
+use std::convert::TryInto;
+use terra_vegas::distribution::{AprResponse, AssetInfo, SnapshotResponse, SnapshotsResponse};
+
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// Records the reward denom index, updates within the same block are merged
+pub fn record_snapshot(
+    storage: &mut dyn Storage,
+    env: &Env,
+    state: &State,
+    asset: &RewardAsset,
+    rewards_added: Uint128,
+) -> StdResult<()> {
+    SNAPSHOTS.update(
+        storage,
+        U64Key::from(env.block.time.seconds()),
+        |snapshot| -> StdResult<_> {
+            Ok(Snapshot {
+                global_index: asset.global_index,
+                total_weight: state.total_weight(),
+                rewards_added: snapshot.map_or(Uint128::zero(), |s| s.rewards_added)
+                    + rewards_added,
+            })
+        },
+    )?;
+    Ok(())
+}
+
+fn to_snapshot_response(key: Vec<u8>, snapshot: Snapshot) -> SnapshotResponse {
+    SnapshotResponse {
+        timestamp: u64::from_be_bytes(key.as_slice().try_into().unwrap()),
+        global_index: snapshot.global_index,
+        total_weight: snapshot.total_weight,
+        rewards_added: snapshot.rewards_added,
+    }
+}
+
+pub fn query_snapshots(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SnapshotsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let snapshots = SNAPSHOTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, snapshot) = item?;
+            Ok(to_snapshot_response(k, snapshot))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(SnapshotsResponse { snapshots })
+}
+
+/// Annualized index growth since the last snapshot taken before the window,
+/// or since the first snapshot when the history is shorter than the window.
+/// The result is an amount of reward denom per staked token, not a percentage
+pub fn query_apr(deps: Deps, env: Env, window_seconds: u64) -> StdResult<AprResponse> {
+    let now = env.block.time.seconds();
+    let window_start = now.saturating_sub(window_seconds);
+
+    let start = match SNAPSHOTS
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive_int(window_start)),
+            Order::Descending,
+        )
+        .next()
+    {
+        Some(item) => Some(item?),
+        None => SNAPSHOTS
+            .range(deps.storage, None, None, Order::Ascending)
+            .next()
+            .transpose()?,
+    };
+
+    let start = match start {
+        Some((k, snapshot)) => to_snapshot_response(k, snapshot),
+        None => {
+            return Ok(AprResponse {
+                window_seconds,
+                start_time: now,
+                index_delta: Decimal::zero(),
+                annualized_reward_per_token: Decimal::zero(),
+            })
+        }
+    };
+
+    let config = CONFIG.load(deps.storage)?;
+    let asset = AssetInfo::NativeToken {
+        denom: config.reward_denom,
+    };
+    let global_index = REWARD_ASSETS.load(deps.storage, &asset.key())?.global_index;
+    let index_delta = decimal_subtraction_in_256(global_index, start.global_index);
+
+    let elapsed = now.saturating_sub(start.timestamp);
+    let annualized_reward_per_token = if elapsed == 0 {
+        Decimal::zero()
+    } else {
+        decimal_multiplication_in_256(index_delta, Decimal::from_ratio(SECONDS_PER_YEAR, elapsed))
+    };
+
+    Ok(AprResponse {
+        window_seconds,
+        start_time: start.timestamp,
+        index_delta,
+        annualized_reward_per_token,
+    })
+}
@@ -0,0 +1,95 @@
+use crate::state::{
+    AccrualCheckpoint, ClaimReceipt, ACCRUAL_CHECKPOINTS, ACCRUAL_CHECKPOINT_COUNT, CLAIM_RECEIPTS,
+    CLAIM_RECEIPT_COUNT, DEFAULT_LIMIT, MAX_LIMIT,
+};
+use cosmwasm_std::{CanonicalAddr, Deps, Order, StdResult, Storage};
+use cw_storage_plus::{Bound, U64Key};
+use std::convert::TryInto;
+use terra_vegas::distribution::{
+    AccrualCheckpointResponse, AccrualCheckpointsResponse, AccrualResponse, ClaimReceiptResponse,
+    ClaimReceiptsResponse,
+};
+
+pub fn record_claim(
+    storage: &mut dyn Storage,
+    holder: &CanonicalAddr,
+    receipt: &ClaimReceipt,
+) -> StdResult<()> {
+    let id = CLAIM_RECEIPT_COUNT.may_load(storage)?.unwrap_or_default();
+    CLAIM_RECEIPTS.save(storage, (holder.as_slice(), U64Key::from(id)), receipt)?;
+    CLAIM_RECEIPT_COUNT.save(storage, &(id + 1))
+}
+
+pub fn record_accrual(
+    storage: &mut dyn Storage,
+    holder: &CanonicalAddr,
+    checkpoint: &AccrualCheckpoint,
+) -> StdResult<()> {
+    let id = ACCRUAL_CHECKPOINT_COUNT
+        .may_load(storage)?
+        .unwrap_or_default();
+    ACCRUAL_CHECKPOINTS.save(storage, (holder.as_slice(), U64Key::from(id)), checkpoint)?;
+    ACCRUAL_CHECKPOINT_COUNT.save(storage, &(id + 1))
+}
+
+pub fn query_claim_receipts(
+    deps: Deps,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ClaimReceiptsResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let receipts = CLAIM_RECEIPTS
+        .prefix(address_raw.as_slice())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, receipt) = item?;
+            Ok(ClaimReceiptResponse {
+                id: u64::from_be_bytes(k.as_slice().try_into().unwrap()),
+                timestamp: receipt.timestamp,
+                asset: receipt.asset,
+                amount: receipt.amount,
+                recipient: receipt.recipient.to_string(),
+                tax: receipt.tax,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ClaimReceiptsResponse { receipts })
+}
+
+pub fn query_accrual_checkpoints(
+    deps: Deps,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<AccrualCheckpointsResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let checkpoints = ACCRUAL_CHECKPOINTS
+        .prefix(address_raw.as_slice())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, checkpoint) = item?;
+            Ok(AccrualCheckpointResponse {
+                id: u64::from_be_bytes(k.as_slice().try_into().unwrap()),
+                timestamp: checkpoint.timestamp,
+                balance: checkpoint.balance,
+                accrued: checkpoint
+                    .accrued
+                    .into_iter()
+                    .map(|(asset, amount)| AccrualResponse { asset, amount })
+                    .collect(),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AccrualCheckpointsResponse { checkpoints })
+}
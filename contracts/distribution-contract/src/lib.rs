@@ -1,12 +1,20 @@
 pub mod contract;
 pub mod state;
 
+mod compound;
+mod emission;
 mod global;
+mod history;
+mod ledger;
+mod lock;
 mod math;
+mod operator;
 mod taxation;
 mod user;
 mod utils;
 
 pub mod claim;
 #[cfg(test)]
+mod mock_querier;
+#[cfg(test)]
 mod testing;
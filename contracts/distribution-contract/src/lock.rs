@@ -0,0 +1,457 @@
+use crate::global::{save_reward_asset, update_asset_index};
+use crate::math::{
+    decimal_multiplication_in_256, decimal_subtraction_in_256, decimal_summation_in_256,
+};
+use crate::state::{
+    read_holder, read_holder_reward, read_reward_assets, store_holder, store_holder_reward, Holder,
+    HolderReward, Lock, LockConfig, RewardAsset, State, CONFIG, DEFAULT_LIMIT, EXPIRY_INDEXES,
+    LOCKS, LOCK_CONFIG, LOCK_COUNT, LOCK_EXPIRIES, MAX_LIMIT, REWARD_ASSETS, STATE,
+};
+use crate::user::{calculate_decimal_rewards, settle_rewards};
+use crate::utils::only_owner;
+use cosmwasm_std::{
+    CanonicalAddr, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult,
+    Storage, Uint128,
+};
+use cw_storage_plus::{Bound, U64Key};
+use std::convert::TryInto;
+use terra_vegas::distribution::{AssetInfo, LockConfigResponse, LockResponse, LocksResponse};
+
+const SECONDS_PER_WEEK: u64 = 7 * 24 * 60 * 60;
+const MAX_LOCK_WEEKS: u64 = 52;
+
+pub fn update_lock_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_multiplier: Decimal,
+    early_exit_penalty: Decimal,
+) -> StdResult<Response> {
+    only_owner(deps.as_ref(), &info)?;
+
+    if max_multiplier < Decimal::one() {
+        return Err(StdError::generic_err("max multiplier cannot be below 1"));
+    }
+    if early_exit_penalty > Decimal::one() {
+        return Err(StdError::generic_err("early exit penalty cannot exceed 1"));
+    }
+
+    LOCK_CONFIG.save(
+        deps.storage,
+        &LockConfig {
+            max_multiplier,
+            early_exit_penalty,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_lock_config")
+        .add_attribute("max_multiplier", max_multiplier.to_string())
+        .add_attribute("early_exit_penalty", early_exit_penalty.to_string()))
+}
+
+/// 1 for no lock up to `max_multiplier` for the longest lock
+fn lock_multiplier(config: &LockConfig, weeks: u64) -> Decimal {
+    let boost = decimal_subtraction_in_256(config.max_multiplier, Decimal::one());
+    Decimal::one()
+        + decimal_multiplication_in_256(boost, Decimal::from_ratio(weeks, MAX_LOCK_WEEKS))
+}
+
+/// Share of the locked amount lost when released at `now`
+fn early_exit_penalty(config: &LockConfig, lock: &Lock, now: u64) -> Uint128 {
+    let remaining = lock.end_time.saturating_sub(now);
+    (lock.amount * config.early_exit_penalty)
+        .multiply_ratio(remaining, lock.end_time - lock.start_time)
+}
+
+pub fn handle_lock(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    weeks: u64,
+) -> StdResult<Response> {
+    // the stake can only be held back when it is bonded in this contract
+    if CONFIG.load(deps.storage)?.staking_token.is_none() {
+        return Err(StdError::generic_err(
+            "locks require the contract to have its own staking token",
+        ));
+    }
+    let config = LOCK_CONFIG
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("locks are not enabled"))?;
+
+    if weeks == 0 || weeks > MAX_LOCK_WEEKS {
+        return Err(StdError::generic_err(format!(
+            "lock duration must be between 1 and {} weeks",
+            MAX_LOCK_WEEKS
+        )));
+    }
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Amount required"));
+    }
+
+    let address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let mut state = STATE.load(deps.storage)?;
+    let mut holder = read_holder(deps.storage, &address_raw)?;
+    let unlocked = holder.balance.checked_sub(holder.locked)?;
+    if unlocked < amount {
+        return Err(StdError::generic_err(format!(
+            "Lock amount cannot exceed unlocked balance: {}",
+            unlocked
+        )));
+    }
+
+    settle_rewards(deps.storage, &env, &address_raw, &mut holder, &mut state)?;
+
+    let multiplier = lock_multiplier(&config, weeks);
+    let lock = Lock {
+        amount,
+        multiplier,
+        bonus: amount * decimal_subtraction_in_256(multiplier, Decimal::one()),
+        start_time: env.block.time.seconds(),
+        end_time: env.block.time.seconds() + weeks * SECONDS_PER_WEEK,
+    };
+
+    holder.locked += amount;
+    holder.bonus += lock.bonus;
+    state.total_bonus += lock.bonus;
+    store_holder(deps.storage, &address_raw, &holder)?;
+    STATE.save(deps.storage, &state)?;
+
+    let id = LOCK_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    LOCKS.save(
+        deps.storage,
+        (address_raw.as_slice(), U64Key::from(id)),
+        &lock,
+    )?;
+    LOCK_COUNT.save(deps.storage, &(id + 1))?;
+    LOCK_EXPIRIES.update(
+        deps.storage,
+        U64Key::from(lock.end_time),
+        |bonus| -> StdResult<_> { Ok(bonus.unwrap_or_default() + lock.bonus) },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "lock")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("lock_id", id.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("multiplier", multiplier.to_string())
+        .add_attribute("end_time", lock.end_time.to_string()))
+}
+
+/// Releases the lock, before its end a part of the stake is left to the other stakers
+/// as rewards of the staking token
+pub fn handle_unlock(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    lock_id: u64,
+) -> StdResult<Response> {
+    let address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let key = (address_raw.as_slice(), U64Key::from(lock_id));
+    if !LOCKS.has(deps.storage, key.clone()) {
+        return Err(StdError::generic_err("lock does not exist"));
+    }
+
+    let mut state = STATE.load(deps.storage)?;
+    let mut holder = read_holder(deps.storage, &address_raw)?;
+    settle_rewards(deps.storage, &env, &address_raw, &mut holder, &mut state)?;
+
+    // settling drops the bonus of an ended lock
+    let lock = LOCKS.load(deps.storage, key.clone())?;
+    LOCKS.remove(deps.storage, key);
+    if !lock.bonus.is_zero() {
+        remove_expiry(deps.storage, lock.end_time, lock.bonus)?;
+    }
+
+    let config = LOCK_CONFIG.load(deps.storage)?;
+    let penalty = early_exit_penalty(&config, &lock, env.block.time.seconds());
+
+    let staking_asset = if penalty.is_zero() {
+        None
+    } else {
+        let staking_token = CONFIG
+            .load(deps.storage)?
+            .staking_token
+            .ok_or_else(|| StdError::generic_err("staking token is not set"))?;
+        let asset = AssetInfo::Token {
+            contract_addr: staking_token.to_string(),
+        };
+        if !REWARD_ASSETS.has(deps.storage, &asset.key()) {
+            save_reward_asset(deps.storage, asset.clone())?;
+        }
+        // the staking token rewards received so far are shared with the holder as well
+        let mut reward_asset = REWARD_ASSETS.load(deps.storage, &asset.key())?;
+        update_asset_index(&mut deps, &env, &state, &mut reward_asset)?;
+        let mut reward = read_holder_reward(deps.storage, &address_raw, &asset.key())?;
+        let rewards =
+            calculate_decimal_rewards(reward_asset.global_index, reward.index, holder.weight())?;
+        reward.index = reward_asset.global_index;
+        reward.pending_rewards = decimal_summation_in_256(rewards, reward.pending_rewards);
+        store_holder_reward(deps.storage, &address_raw, &asset.key(), &reward)?;
+        Some((reward_asset, reward))
+    };
+
+    holder.locked = holder.locked.checked_sub(lock.amount)?;
+    holder.bonus = holder.bonus.checked_sub(lock.bonus)?;
+    state.total_bonus = state.total_bonus.checked_sub(lock.bonus)?;
+    holder.balance = holder.balance.checked_sub(penalty)?;
+    state.total_balance = state.total_balance.checked_sub(penalty)?;
+    state.staked_tokens = state.staked_tokens.checked_sub(penalty)?;
+    store_holder(deps.storage, &address_raw, &holder)?;
+    STATE.save(deps.storage, &state)?;
+
+    if let Some((mut reward_asset, mut reward)) = staking_asset {
+        // the penalty is split among the other holders only
+        let others = State {
+            total_balance: state.total_balance.checked_sub(holder.balance)?,
+            total_bonus: state.total_bonus.checked_sub(holder.bonus)?,
+            ..state
+        };
+        update_asset_index(&mut deps, &env, &others, &mut reward_asset)?;
+        reward.index = reward_asset.global_index;
+        store_holder_reward(
+            deps.storage,
+            &address_raw,
+            &reward_asset.info.key(),
+            &reward,
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "unlock")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("lock_id", lock_id.to_string())
+        .add_attribute("amount", lock.amount)
+        .add_attribute("penalty", penalty))
+}
+
+fn remove_expiry(storage: &mut dyn Storage, end_time: u64, bonus: Uint128) -> StdResult<()> {
+    let key = U64Key::from(end_time);
+    let remaining = LOCK_EXPIRIES
+        .load(storage, key.clone())?
+        .checked_sub(bonus)?;
+    if remaining.is_zero() {
+        LOCK_EXPIRIES.remove(storage, key);
+        Ok(())
+    } else {
+        LOCK_EXPIRIES.save(storage, key, &remaining)
+    }
+}
+
+/// Takes the bonus of every lock ended by now out of the total weight,
+/// the holders earn with it up to the current index
+pub(crate) fn expire_locks(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    let expired = LOCK_EXPIRIES
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive_int(env.block.time.seconds())),
+            Order::Ascending,
+        )
+        .collect::<StdResult<Vec<_>>>()?;
+    if expired.is_empty() {
+        return Ok(());
+    }
+
+    let assets = read_reward_assets(storage)?;
+    let mut state = STATE.load(storage)?;
+    for (k, bonus) in expired {
+        let end_time = u64::from_be_bytes(k.as_slice().try_into().unwrap());
+        for asset in assets.iter() {
+            EXPIRY_INDEXES.save(
+                storage,
+                (U64Key::from(end_time), &asset.info.key()),
+                &asset.global_index,
+            )?;
+        }
+        state.total_bonus = state.total_bonus.checked_sub(bonus)?;
+        LOCK_EXPIRIES.remove(storage, U64Key::from(end_time));
+    }
+    STATE.save(storage, &state)
+}
+
+/// Locks of the holder that ended while their bonus still counts, by end time
+pub(crate) fn ended_locks(
+    storage: &dyn Storage,
+    env: &Env,
+    address_raw: &CanonicalAddr,
+) -> StdResult<Vec<(u64, Lock)>> {
+    let now = env.block.time.seconds();
+    let mut locks = LOCKS
+        .prefix(address_raw.as_slice())
+        .range(storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((k, lock)) if lock.end_time <= now && !lock.bonus.is_zero() => Some(Ok((
+                u64::from_be_bytes(k.as_slice().try_into().unwrap()),
+                lock,
+            ))),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    locks.sort_by_key(|(_, lock)| lock.end_time);
+    Ok(locks)
+}
+
+/// Moves the rewards earned with the bonus of the ended locks to pending,
+/// up to the index each bonus expired at, and returns the weight left without the bonus
+pub(crate) fn accrue_ended_bonus(
+    storage: &dyn Storage,
+    ended: &[(u64, Lock)],
+    asset: &RewardAsset,
+    weight: Uint128,
+    reward: &mut HolderReward,
+) -> StdResult<Uint128> {
+    let mut weight = weight;
+    for (_, lock) in ended {
+        let end_time = U64Key::from(lock.end_time);
+        // the bonus not expired by the index update yet counted up to the current index,
+        // no index is recorded for an asset registered after the expiry
+        let index = if LOCK_EXPIRIES.has(storage, end_time.clone()) {
+            Some(asset.global_index)
+        } else {
+            EXPIRY_INDEXES.may_load(storage, (end_time, &asset.info.key()))?
+        };
+        if let Some(index) = index {
+            if index > reward.index {
+                let rewards = calculate_decimal_rewards(index, reward.index, weight)?;
+                reward.pending_rewards = decimal_summation_in_256(rewards, reward.pending_rewards);
+                reward.index = index;
+            }
+        }
+        weight = weight.checked_sub(lock.bonus)?;
+    }
+    Ok(weight)
+}
+
+/// Settles the rewards earned with the bonus of the ended locks and drops the bonus,
+/// the holder is left to be stored by the caller
+pub(crate) fn expire_holder_locks(
+    storage: &mut dyn Storage,
+    env: &Env,
+    address_raw: &CanonicalAddr,
+    holder: &mut Holder,
+    state: &mut State,
+) -> StdResult<()> {
+    let ended = ended_locks(storage, env, address_raw)?;
+    if ended.is_empty() {
+        return Ok(());
+    }
+    for asset in read_reward_assets(storage)? {
+        let key = asset.info.key();
+        let mut reward = read_holder_reward(storage, address_raw, &key)?;
+        accrue_ended_bonus(storage, &ended, &asset, holder.weight(), &mut reward)?;
+        store_holder_reward(storage, address_raw, &key, &reward)?;
+    }
+    drop_ended_bonus(storage, address_raw, ended, holder, state)
+}
+
+/// Drops the bonus of the ended locks from the holder,
+/// and from the total weight when the index update has not expired it yet
+fn drop_ended_bonus(
+    storage: &mut dyn Storage,
+    address_raw: &CanonicalAddr,
+    ended: Vec<(u64, Lock)>,
+    holder: &mut Holder,
+    state: &mut State,
+) -> StdResult<()> {
+    for (id, mut lock) in ended {
+        if LOCK_EXPIRIES.has(storage, U64Key::from(lock.end_time)) {
+            remove_expiry(storage, lock.end_time, lock.bonus)?;
+            state.total_bonus = state.total_bonus.checked_sub(lock.bonus)?;
+        }
+        holder.bonus = holder.bonus.checked_sub(lock.bonus)?;
+        lock.bonus = Uint128::zero();
+        LOCKS.save(storage, (address_raw.as_slice(), U64Key::from(id)), &lock)?;
+    }
+    Ok(())
+}
+
+pub fn query_lock_config(deps: Deps) -> StdResult<LockConfigResponse> {
+    let config = LOCK_CONFIG.load(deps.storage)?;
+    Ok(LockConfigResponse {
+        max_multiplier: config.max_multiplier,
+        early_exit_penalty: config.early_exit_penalty,
+    })
+}
+
+fn to_lock_response(deps: Deps, env: &Env, id: u64, lock: Lock) -> StdResult<LockResponse> {
+    let now = env.block.time.seconds();
+    let config = LOCK_CONFIG.load(deps.storage)?;
+    Ok(LockResponse {
+        id,
+        amount: lock.amount,
+        multiplier: lock.multiplier,
+        start_time: lock.start_time,
+        end_time: lock.end_time,
+        remaining_seconds: lock.end_time.saturating_sub(now),
+        early_exit_penalty: early_exit_penalty(&config, &lock, now),
+    })
+}
+
+pub fn query_lock(deps: Deps, env: Env, address: String, lock_id: u64) -> StdResult<LockResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let lock = LOCKS.load(
+        deps.storage,
+        (address_raw.as_slice(), U64Key::from(lock_id)),
+    )?;
+    to_lock_response(deps, &env, lock_id, lock)
+}
+
+pub fn query_locks(
+    deps: Deps,
+    env: Env,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<LocksResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let locks = LOCKS
+        .prefix(address_raw.as_slice())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, lock) = item?;
+            let id = u64::from_be_bytes(k.as_slice().try_into().unwrap());
+            to_lock_response(deps, &env, id, lock)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(LocksResponse { locks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proper_lock_multiplier() {
+        let config = LockConfig {
+            max_multiplier: Decimal::percent(300),
+            early_exit_penalty: Decimal::percent(50),
+        };
+        assert_eq!(lock_multiplier(&config, 0), Decimal::one());
+        assert_eq!(lock_multiplier(&config, 26), Decimal::percent(200));
+        assert_eq!(lock_multiplier(&config, 52), Decimal::percent(300));
+
+        let lock = Lock {
+            amount: Uint128::from(100u128),
+            multiplier: Decimal::percent(300),
+            bonus: Uint128::from(200u128),
+            start_time: 0,
+            end_time: 100,
+        };
+        assert_eq!(early_exit_penalty(&config, &lock, 0), Uint128::from(50u128));
+        assert_eq!(
+            early_exit_penalty(&config, &lock, 60),
+            Uint128::from(20u128)
+        );
+        assert_eq!(early_exit_penalty(&config, &lock, 100), Uint128::zero());
+        assert_eq!(early_exit_penalty(&config, &lock, 200), Uint128::zero());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::state::{DEFAULT_LIMIT, MAX_LIMIT, OPERATORS};
+use cosmwasm_std::{
+    Addr, CanonicalAddr, Deps, DepsMut, MessageInfo, Order, Response, StdError, StdResult,
+};
+use cw_storage_plus::Bound;
+use terra_vegas::distribution::OperatorsResponse;
+
+pub fn add_operator(deps: DepsMut, info: MessageInfo, operator: String) -> StdResult<Response> {
+    let holder_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let operator_raw = deps.api.addr_canonicalize(&operator)?;
+    if holder_raw == operator_raw {
+        return Err(StdError::generic_err("cannot add yourself as an operator"));
+    }
+
+    OPERATORS.save(
+        deps.storage,
+        (holder_raw.as_slice(), operator_raw.as_slice()),
+        &true,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_operator")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("operator", operator))
+}
+
+pub fn remove_operator(deps: DepsMut, info: MessageInfo, operator: String) -> StdResult<Response> {
+    let holder_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let operator_raw = deps.api.addr_canonicalize(&operator)?;
+
+    OPERATORS.remove(
+        deps.storage,
+        (holder_raw.as_slice(), operator_raw.as_slice()),
+    );
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_operator")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("operator", operator))
+}
+
+/// Holder whose rewards are claimed, the sender itself or a holder that authorized it
+pub fn claiming_holder(
+    deps: Deps,
+    info: &MessageInfo,
+    holder: Option<String>,
+) -> StdResult<(Addr, CanonicalAddr)> {
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let holder = match holder {
+        Some(holder) => deps.api.addr_validate(&holder)?,
+        None => return Ok((info.sender.clone(), sender_raw)),
+    };
+    let holder_raw = deps.api.addr_canonicalize(holder.as_str())?;
+
+    if holder_raw != sender_raw
+        && !OPERATORS.has(deps.storage, (holder_raw.as_slice(), sender_raw.as_slice()))
+    {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    Ok((holder, holder_raw))
+}
+
+pub fn query_operators(
+    deps: Deps,
+    address: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<OperatorsResponse> {
+    let holder_raw = deps.api.addr_canonicalize(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(start_after) => Some(Bound::exclusive(
+            deps.api.addr_canonicalize(&start_after)?.as_slice(),
+        )),
+        None => None,
+    };
+
+    let operators = OPERATORS
+        .prefix(holder_raw.as_slice())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .map(|operator| {
+            deps.api
+                .addr_humanize(&CanonicalAddr::from(operator))
+                .map(|operator| operator.to_string())
+        })
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(OperatorsResponse { operators })
+}
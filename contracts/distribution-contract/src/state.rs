@@ -1,8 +1,8 @@
 use cosmwasm_std::{Addr, Api, CanonicalAddr, Decimal, Deps, Order, StdResult, Storage, Uint128};
-use cw_storage_plus::{Bound, Item, Map};
+use cw_storage_plus::{Bound, Item, Map, U64Key};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use terra_vegas::distribution::HolderResponse;
+use terra_vegas::distribution::{AssetInfo, EmissionPolicy, HolderResponse, HolderRewardResponse};
 
 pub const OWNER: Item<Addr> = Item::new("owner");
 
@@ -13,24 +13,247 @@ pub struct Config {
     pub reward_denom: String,
     pub distribution_ratio: u64,
     pub unbonding_period: u64,
+    /// Standalone staking token, the governance stake is mirrored when not set
+    #[serde(default)]
+    pub staking_token: Option<Addr>,
 }
 pub const CONFIG: Item<Config> = Item::new("\u{0}\u{6}config");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
+    pub total_balance: Uint128,
+    pub last_reserve_residue: Uint128,
+    /// Staking tokens held for the holders, excluded from the rewards
+    #[serde(default)]
+    pub staked_tokens: Uint128,
+    /// Extra reward weight of the locked stake
+    #[serde(default)]
+    pub total_bonus: Uint128,
+}
+
+impl State {
+    /// Weight the rewards are split by, the bonded balance plus the lock bonuses
+    pub fn total_weight(&self) -> Uint128 {
+        self.total_balance + self.total_bonus
+    }
+}
+pub const STATE: Item<State> = Item::new("\u{0}\u{5}state");
+
+/// State stored before the rewards were split per asset, only read by the migration
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyState {
     pub global_index: Decimal,
     pub total_balance: Uint128,
     pub prev_reward_balance: Uint128,
     pub last_reserve_residue: Uint128,
 }
-pub const STATE: Item<State> = Item::new("\u{0}\u{5}state");
+pub const LEGACY_STATE: Item<LegacyState> = Item::new("\u{0}\u{5}state");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Emission {
+    pub policy: EmissionPolicy,
+    pub epoch_length: u64,
+    /// Start time of the epoch that is closed next
+    pub epoch_start: u64,
+}
+// not set while the reserve is pulled on index updates
+pub const EMISSION: Item<Emission> = Item::new("emission");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Epoch {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub closed_at: u64,
+    pub payout: Uint128,
+    pub index_delta: Decimal,
+}
+// epoch ids keep increasing when the policy is changed
+pub const EPOCHS: Map<U64Key, Epoch> = Map::new("epochs");
+pub const EPOCH_COUNT: Item<u64> = Item::new("epoch_count");
+
+/// Epoch waiting for the reserve payout, it is only closed once the funds arrived
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingEpoch {
+    pub epoch: Epoch,
+    /// Reward denom balance of the contract before the request
+    pub reward_balance: Uint128,
+}
+pub const PENDING_EPOCH: Item<PendingEpoch> = Item::new("pending_epoch");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Snapshot {
+    pub global_index: Decimal,
+    /// Bonded balance plus lock bonuses, the index was spread over this weight
+    #[serde(alias = "total_balance")]
+    pub total_weight: Uint128,
+    pub rewards_added: Uint128,
+}
+// timestamp -> reward denom index after the last update of that block
+pub const SNAPSHOTS: Map<U64Key, Snapshot> = Map::new("snapshots");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimReceipt {
+    pub timestamp: u64,
+    pub asset: AssetInfo,
+    pub amount: Uint128,
+    pub recipient: Addr,
+    pub tax: Uint128,
+}
+// (holder, receipt id) -> receipt
+pub const CLAIM_RECEIPTS: Map<(&[u8], U64Key), ClaimReceipt> = Map::new("claim_receipts");
+pub const CLAIM_RECEIPT_COUNT: Item<u64> = Item::new("claim_receipt_count");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccrualCheckpoint {
+    pub timestamp: u64,
+    pub balance: Uint128,
+    pub accrued: Vec<(AssetInfo, Decimal)>,
+}
+// (holder, checkpoint id) -> checkpoint
+pub const ACCRUAL_CHECKPOINTS: Map<(&[u8], U64Key), AccrualCheckpoint> =
+    Map::new("accrual_checkpoints");
+pub const ACCRUAL_CHECKPOINT_COUNT: Item<u64> = Item::new("accrual_checkpoint_count");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockConfig {
+    /// Multiplier of the reward weight for the longest lock
+    pub max_multiplier: Decimal,
+    /// Share of the locked stake lost when unlocking right after locking,
+    /// decreasing linearly until the lock ends
+    pub early_exit_penalty: Decimal,
+}
+pub const LOCK_CONFIG: Item<LockConfig> = Item::new("lock_config");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Lock {
+    pub amount: Uint128,
+    pub multiplier: Decimal,
+    /// Reward weight added on top of the amount, dropped once the lock ended
+    pub bonus: Uint128,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+// (holder, lock id) -> lock
+pub const LOCKS: Map<(&[u8], U64Key), Lock> = Map::new("locks");
+pub const LOCK_COUNT: Item<u64> = Item::new("lock_count");
+// end time -> bonus of the locks ending then, until the index update expires it
+pub const LOCK_EXPIRIES: Map<U64Key, Uint128> = Map::new("lock_expiries");
+// (end time, asset key) -> global index of the asset when the bonus expired
+pub const EXPIRY_INDEXES: Map<(U64Key, &str), Decimal> = Map::new("expiry_indexes");
+
+// (holder, operator) -> operator is allowed to claim the holder rewards
+pub const OPERATORS: Map<(&[u8], &[u8]), bool> = Map::new("operators");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CompoundConfig {
+    pub astroport_factory: Addr,
+    pub vega_token: Addr,
+    pub max_spread: Option<Decimal>,
+}
+pub const COMPOUND_CONFIG: Item<CompoundConfig> = Item::new("compound_config");
+
+// holders restaking their reward denom rewards
+pub const AUTO_COMPOUND: Map<&[u8], bool> = Map::new("auto_compound");
+// last holder of the previous compound batch
+pub const COMPOUND_CURSOR: Item<Vec<u8>> = Item::new("compound_cursor");
+
+/// Swap waiting for its reply, the rewards are restored if it fails
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingCompound {
+    pub holders: Vec<(CanonicalAddr, Uint128)>,
+    pub total_rewards: Uint128,
+    pub offer_amount: Uint128,
+    pub vega_balance: Uint128,
+}
+pub const PENDING_COMPOUND: Item<PendingCompound> = Item::new("pending_compound");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardAsset {
+    pub info: AssetInfo,
+    pub global_index: Decimal,
+    pub prev_reward_balance: Uint128,
+}
+// asset key -> reward asset
+pub const REWARD_ASSETS: Map<&str, RewardAsset> = Map::new("reward_assets");
+// holder indexes of removed assets are kept, so they can not be registered again
+pub const REMOVED_REWARD_ASSETS: Map<&str, bool> = Map::new("removed_reward_assets");
+
+pub fn read_reward_assets(storage: &dyn Storage) -> StdResult<Vec<RewardAsset>> {
+    REWARD_ASSETS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| Ok(item?.1))
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct Holder {
     pub balance: Uint128,
+    /// Part of the balance committed to locks
+    #[serde(default)]
+    pub locked: Uint128,
+    #[serde(default)]
+    pub bonus: Uint128,
+}
+
+impl Holder {
+    pub fn weight(&self) -> Uint128 {
+        self.balance + self.bonus
+    }
+}
+
+/// Holder before the rewards were split per asset, only read by the migration
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyHolder {
+    pub balance: Uint128,
+    pub index: Decimal,
+    pub pending_rewards: Decimal,
+}
+pub const LEGACY_HOLDERS: Map<&[u8], LegacyHolder> = Map::new("holders");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct HolderReward {
     pub index: Decimal,
     pub pending_rewards: Decimal,
 }
+// (holder, asset key) -> holder index of the asset
+pub const HOLDER_REWARDS: Map<(&[u8], &str), HolderReward> = Map::new("holder_rewards");
+
+// every asset starts with a zero global index, so a missing entry means nothing has accrued yet
+pub fn read_holder_reward(
+    storage: &dyn Storage,
+    holder_address: &CanonicalAddr,
+    asset_key: &str,
+) -> StdResult<HolderReward> {
+    Ok(HOLDER_REWARDS
+        .may_load(storage, (holder_address.as_slice(), asset_key))?
+        .unwrap_or_default())
+}
+
+pub fn store_holder_reward(
+    storage: &mut dyn Storage,
+    holder_address: &CanonicalAddr,
+    asset_key: &str,
+    reward: &HolderReward,
+) -> StdResult<()> {
+    HOLDER_REWARDS.save(storage, (holder_address.as_slice(), asset_key), reward)
+}
+
+pub fn read_holder_rewards(
+    storage: &dyn Storage,
+    holder_address: &CanonicalAddr,
+) -> StdResult<Vec<HolderRewardResponse>> {
+    read_reward_assets(storage)?
+        .into_iter()
+        .map(|asset| {
+            let reward = read_holder_reward(storage, holder_address, &asset.info.key())?;
+            Ok(HolderRewardResponse {
+                asset: asset.info,
+                index: reward.index,
+                pending_rewards: reward.pending_rewards,
+            })
+        })
+        .collect()
+}
 
 pub const PREFIXED_HOLDERS: Map<&[u8], Holder> = Map::new("holders");
 // This is similar to HashMap<holder's address, Hodler>
@@ -47,17 +270,13 @@ pub fn read_holder(storage: &dyn Storage, holder_address: &CanonicalAddr) -> Std
 
     match res {
         Some(holder) => Ok(holder),
-        None => Ok(Holder {
-            balance: Uint128::zero(),
-            index: Decimal::zero(),
-            pending_rewards: Decimal::zero(),
-        }),
+        None => Ok(Holder::default()),
     }
 }
 
 // settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_LIMIT: u32 = 30;
+pub const DEFAULT_LIMIT: u32 = 10;
 pub fn read_holders(
     deps: Deps,
     start_after: Option<Addr>,
@@ -74,12 +293,14 @@ pub fn read_holders(
         .take(limit)
         .map(|elem| {
             let (k, v) = elem?;
-            let address = deps.api.addr_humanize(&CanonicalAddr::from(k))?.to_string();
+            let address_raw = CanonicalAddr::from(k);
+            let address = deps.api.addr_humanize(&address_raw)?.to_string();
             Ok(HolderResponse {
                 address,
                 balance: v.balance,
-                index: v.index,
-                pending_rewards: v.pending_rewards,
+                locked: v.locked,
+                weight: v.weight(),
+                rewards: read_holder_rewards(deps.storage, &address_raw)?,
             })
         })
         .collect()
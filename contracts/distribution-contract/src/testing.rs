@@ -0,0 +1,1633 @@
+use crate::claim::{Claim, ClaimsResponse};
+use crate::contract::{
+    execute, instantiate, migrate, query, reply, COMPOUND_REPLY_ID, EPOCH_REQUEST_REPLY_ID,
+};
+use crate::mock_querier::{
+    mock_dependencies, WasmMockQuerier, FACTORY_ADDR, PAIR_ADDR, RESERVE_ADDR,
+};
+use crate::state::{LegacyHolder, LegacyState, LEGACY_HOLDERS, LEGACY_STATE};
+use astroport::asset::{Asset, AssetInfo as AstroportAssetInfo};
+use astroport::pair::ExecuteMsg as PairExecuteMsg;
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    coins, from_binary, to_binary, Addr, Api, BankMsg, ContractResult, CosmosMsg, Decimal, Env,
+    OwnedDeps, Reply, Response, StdResult, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Expiration};
+use reserve_contract::msg::ExecuteMsg as ReserveExecuteMsg;
+use terra_vegas::distribution::{
+    AccrualCheckpointsResponse, AccruedReward, AccruedRewardsResponse, AprResponse, AssetInfo,
+    AutoCompoundResponse, ClaimReceiptsResponse, EmissionPolicy, EmissionResponse, EpochResponse,
+    EpochsResponse, ExecuteMsg, HolderResponse, InstantiateMsg, LockResponse, LocksResponse,
+    MigrateMsg, OperatorsResponse, QueryMsg, ReceiveMsg, RewardsHookMsg, RewardsReceiveMsg,
+    SnapshotsResponse, StateResponse,
+};
+use terra_vegas::gov::Cw20HookMsg;
+
+const GOV: &str = "gov";
+const VEGA: &str = "vega";
+
+fn uusd() -> AssetInfo {
+    AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    }
+}
+
+fn vega() -> AssetInfo {
+    AssetInfo::Token {
+        contract_addr: VEGA.to_string(),
+    }
+}
+
+fn init() -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    init_with(None)
+}
+
+fn init_with(staking_token: Option<&str>) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        governance_token_addr: Addr::unchecked(GOV),
+        reserve_contract_addr: Addr::unchecked(RESERVE_ADDR),
+        reward_denom: "uusd".to_string(),
+        unbonding_period: 0,
+        distribution_ratio: 2,
+        initial_reserve_amount: None,
+        staking_token: staking_token.map(|token| token.to_string()),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    deps
+}
+
+fn bond(deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>, holder: &str, amount: u128) {
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: holder.to_string(),
+        amount: Uint128::from(amount),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info(GOV, &[]), msg).unwrap();
+}
+
+fn register_vega(deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>) {
+    let msg = ExecuteMsg::RegisterRewardAsset { asset: vega() };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+}
+
+fn deposit_vega(deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>, amount: u128) {
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "incentives".to_string(),
+        amount: Uint128::from(amount),
+        msg: to_binary(&ReceiveMsg::DepositRewards {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info(VEGA, &[]), msg).unwrap();
+}
+
+fn accrued(
+    deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    holder: &str,
+) -> Vec<AccruedReward> {
+    let res: AccruedRewardsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AccruedRewards {
+                address: holder.to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    res.rewards
+}
+
+#[test]
+fn proper_initialization() {
+    let deps = init();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_balance, Uint128::zero());
+    assert_eq!(state.assets.len(), 1);
+    assert_eq!(state.assets[0].asset, uusd());
+    assert_eq!(state.assets[0].global_index, Decimal::zero());
+}
+
+#[test]
+fn multi_asset_rewards() {
+    let mut deps = init();
+    bond(&mut deps, "alice", 100);
+    bond(&mut deps, "bob", 300);
+
+    register_vega(&mut deps);
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(800u128));
+    deposit_vega(&mut deps, 800);
+
+    // house profit
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(400, "uusd"));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.assets.len(), 2);
+    assert_eq!(state.assets[0].global_index, Decimal::one());
+    assert_eq!(state.assets[1].asset, vega());
+    assert_eq!(state.assets[1].global_index, Decimal::percent(200));
+
+    assert_eq!(
+        accrued(&deps, "alice"),
+        vec![
+            AccruedReward {
+                asset: uusd(),
+                amount: Uint128::from(100u128),
+            },
+            AccruedReward {
+                asset: vega(),
+                amount: Uint128::from(200u128),
+            },
+        ]
+    );
+
+    // claim only the vega rewards
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::ClaimRewards {
+            holder: None,
+            assets: Some(vec![vega()]),
+            recipient: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VEGA.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "alice".to_string(),
+                amount: Uint128::from(200u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+    assert_eq!(accrued(&deps, "alice")[1].amount, Uint128::zero());
+    assert_eq!(accrued(&deps, "alice")[0].amount, Uint128::from(100u128));
+
+    // nothing left to claim
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::ClaimRewards {
+            holder: None,
+            assets: Some(vec![vega()]),
+            recipient: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: No rewards have accrued yet"
+    );
+
+    // unregistered asset
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::ClaimRewards {
+            holder: None,
+            assets: Some(vec![AssetInfo::NativeToken {
+                denom: "uluna".to_string(),
+            }]),
+            recipient: None,
+        },
+    )
+    .unwrap_err();
+
+    // claim every asset
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        ExecuteMsg::ClaimRewards {
+            holder: None,
+            assets: None,
+            recipient: Some("carol".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "carol".to_string(),
+            amount: coins(300, "uusd"),
+        }))
+    );
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.assets[0].prev_reward_balance, Uint128::from(100u128));
+    assert_eq!(state.assets[1].prev_reward_balance, Uint128::zero());
+}
+
+#[test]
+fn bonding_settles_every_asset() {
+    let mut deps = init();
+    register_vega(&mut deps);
+    bond(&mut deps, "alice", 100);
+
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(100u128));
+    deposit_vega(&mut deps, 100);
+
+    // alice keeps the rewards accrued before her balance changes
+    bond(&mut deps, "alice", 100);
+    let holder: HolderResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Holder {
+                address: "alice".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(holder.balance, Uint128::from(200u128));
+    assert_eq!(holder.rewards[1].asset, vega());
+    assert_eq!(holder.rewards[1].index, Decimal::one());
+    assert_eq!(holder.rewards[1].pending_rewards, Decimal::percent(10000));
+
+    bond(&mut deps, "bob", 200);
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(500u128));
+    deposit_vega(&mut deps, 400);
+
+    assert_eq!(accrued(&deps, "alice")[1].amount, Uint128::from(300u128));
+    assert_eq!(accrued(&deps, "bob")[1].amount, Uint128::from(200u128));
+}
+
+#[test]
+fn rewards_wait_for_holders() {
+    let mut deps = init();
+    register_vega(&mut deps);
+
+    // nobody is bonded, the deposit is distributed on the next update
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(100u128));
+    deposit_vega(&mut deps, 100);
+
+    bond(&mut deps, "alice", 50);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    assert_eq!(accrued(&deps, "alice")[1].amount, Uint128::from(100u128));
+}
+
+#[test]
+fn register_reward_asset() {
+    let mut deps = init();
+
+    let msg = ExecuteMsg::RegisterRewardAsset {
+        asset: AssetInfo::NativeToken {
+            denom: "uluna".to_string(),
+        },
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    // already registered
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+
+    bond(&mut deps, "alice", 10);
+    deps.querier.with_native_balance(
+        MOCK_CONTRACT_ADDR,
+        &[
+            cosmwasm_std::coin(20, "uluna"),
+            cosmwasm_std::coin(10, "uusd"),
+        ],
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    let rewards = accrued(&deps, "alice");
+    assert_eq!(rewards.len(), 2);
+    assert_eq!(rewards[0].amount, Uint128::from(20u128));
+    assert_eq!(rewards[1].amount, Uint128::from(10u128));
+
+    // tokens are only taken from registered reward assets
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "incentives".to_string(),
+        amount: Uint128::from(50u128),
+        msg: to_binary(&ReceiveMsg::DepositRewards {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("spam", &[]), msg).unwrap_err();
+
+    let msg = ExecuteMsg::RemoveRewardAsset {
+        asset: AssetInfo::NativeToken {
+            denom: "uluna".to_string(),
+        },
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+    let msg = ExecuteMsg::RemoveRewardAsset { asset: uusd() };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.assets.len(), 1);
+
+    // a removed asset keeps the holder indexes and can not come back
+    let msg = ExecuteMsg::RegisterRewardAsset {
+        asset: AssetInfo::NativeToken {
+            denom: "uluna".to_string(),
+        },
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+}
+
+#[test]
+fn migrate_single_denom_storage() {
+    let mut deps = init();
+
+    // storage written before the rewards were split per asset
+    crate::state::REWARD_ASSETS.remove(deps.as_mut().storage, "uusd");
+    LEGACY_STATE
+        .save(
+            deps.as_mut().storage,
+            &LegacyState {
+                global_index: Decimal::percent(150),
+                total_balance: Uint128::from(100u128),
+                prev_reward_balance: Uint128::from(150u128),
+                last_reserve_residue: Uint128::from(7u128),
+            },
+        )
+        .unwrap();
+    let alice = deps.api.addr_canonicalize("alice").unwrap();
+    LEGACY_HOLDERS
+        .save(
+            deps.as_mut().storage,
+            alice.as_slice(),
+            &LegacyHolder {
+                balance: Uint128::from(100u128),
+                index: Decimal::percent(50),
+                pending_rewards: Decimal::percent(20),
+            },
+        )
+        .unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    assert_eq!(
+        accrued(&deps, "alice"),
+        vec![AccruedReward {
+            asset: uusd(),
+            amount: Uint128::from(100u128),
+        }]
+    );
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_balance, Uint128::from(100u128));
+    assert_eq!(state.assets[0].global_index, Decimal::percent(150));
+}
+
+fn env_after(seconds: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(seconds);
+    env
+}
+
+fn set_emission(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    policy: EmissionPolicy,
+) {
+    let msg = ExecuteMsg::SetEmission {
+        policy: Some(policy),
+        epoch_length: 100,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+}
+
+fn close_epoch(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    env: Env,
+) -> Option<Uint128> {
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("anyone", &[]),
+        ExecuteMsg::CloseEpoch {},
+    )
+    .unwrap();
+    res.messages.first().map(|msg| match &msg.msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_binary(msg).unwrap() {
+            ReserveExecuteMsg::RequestFunds { amount, .. } => amount,
+            _ => panic!("unexpected reserve message"),
+        },
+        _ => panic!("unexpected message"),
+    })
+}
+
+fn epoch_reply(deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>, env: Env) {
+    let msg = Reply {
+        id: EPOCH_REQUEST_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    reply(deps.as_mut(), env, msg).unwrap();
+}
+
+#[test]
+fn fixed_emission_epochs() {
+    let mut deps = init();
+    bond(&mut deps, "alice", 100);
+    deps.querier.with_reserve_balance(Uint128::from(10_000u128));
+
+    set_emission(
+        &mut deps,
+        EmissionPolicy::Fixed {
+            amount: Uint128::from(500u128),
+        },
+    );
+
+    // the epoch is still open
+    execute(
+        deps.as_mut(),
+        env_after(99),
+        mock_info("anyone", &[]),
+        ExecuteMsg::CloseEpoch {},
+    )
+    .unwrap_err();
+
+    // index updates no longer pull from the reserve
+    let res = execute(
+        deps.as_mut(),
+        env_after(99),
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+
+    assert_eq!(
+        close_epoch(&mut deps, env_after(100)),
+        Some(Uint128::from(500u128))
+    );
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(500, "uusd"));
+    deps.querier.with_reserve_balance(Uint128::from(9_500u128));
+    epoch_reply(&mut deps, env_after(100));
+
+    // the closed epoch cannot be paid twice
+    execute(
+        deps.as_mut(),
+        env_after(150),
+        mock_info("anyone", &[]),
+        ExecuteMsg::CloseEpoch {},
+    )
+    .unwrap_err();
+
+    let epoch: EpochResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Epoch { id: 0 }).unwrap()).unwrap();
+    assert_eq!(epoch.start_time, mock_env().block.time.seconds());
+    assert_eq!(epoch.end_time, mock_env().block.time.seconds() + 100);
+    assert_eq!(epoch.payout, Uint128::from(500u128));
+    assert_eq!(epoch.index_delta, Decimal::percent(500));
+    assert_eq!(accrued(&deps, "alice")[0].amount, Uint128::from(500u128));
+
+    // an epoch closed late still covers its own period
+    assert_eq!(
+        close_epoch(&mut deps, env_after(350)),
+        Some(Uint128::from(500u128))
+    );
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(1000, "uusd"));
+    deps.querier.with_reserve_balance(Uint128::from(9_000u128));
+    epoch_reply(&mut deps, env_after(350));
+    let emission: EmissionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Emission {}).unwrap()).unwrap();
+    assert_eq!(emission.next_epoch, 2);
+    assert_eq!(
+        emission.next_epoch_start,
+        mock_env().block.time.seconds() + 200
+    );
+
+    let epochs: EpochsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Epochs {
+                start_after: Some(0),
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(epochs.epochs.len(), 1);
+    assert_eq!(epochs.epochs[0].id, 1);
+}
+
+#[test]
+fn unfunded_epoch_stays_open() {
+    let mut deps = init();
+    bond(&mut deps, "alice", 100);
+    deps.querier.with_reserve_balance(Uint128::from(10_000u128));
+    set_emission(
+        &mut deps,
+        EmissionPolicy::Fixed {
+            amount: Uint128::from(500u128),
+        },
+    );
+
+    // the reserve suspended the request and sent nothing
+    assert_eq!(
+        close_epoch(&mut deps, env_after(100)),
+        Some(Uint128::from(500u128))
+    );
+    epoch_reply(&mut deps, env_after(100));
+
+    let emission: EmissionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Emission {}).unwrap()).unwrap();
+    assert_eq!(emission.next_epoch, 0);
+    assert_eq!(emission.next_epoch_start, mock_env().block.time.seconds());
+    query(deps.as_ref(), mock_env(), QueryMsg::Epoch { id: 0 }).unwrap_err();
+
+    // the payout is what arrived, not what was requested
+    assert_eq!(
+        close_epoch(&mut deps, env_after(150)),
+        Some(Uint128::from(500u128))
+    );
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(300, "uusd"));
+    epoch_reply(&mut deps, env_after(150));
+
+    let epoch: EpochResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Epoch { id: 0 }).unwrap()).unwrap();
+    assert_eq!(epoch.payout, Uint128::from(300u128));
+    assert_eq!(epoch.index_delta, Decimal::percent(300));
+    assert_eq!(accrued(&deps, "alice")[0].amount, Uint128::from(300u128));
+}
+
+#[test]
+fn profit_share_emission() {
+    let mut deps = init();
+    bond(&mut deps, "alice", 100);
+    deps.querier.with_reserve_balance(Uint128::from(1_000u128));
+
+    set_emission(
+        &mut deps,
+        EmissionPolicy::ProfitShare {
+            ratio: Decimal::percent(10),
+        },
+    );
+    assert_eq!(
+        close_epoch(&mut deps, env_after(100)),
+        Some(Uint128::from(100u128))
+    );
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(100, "uusd"));
+    deps.querier.with_reserve_balance(Uint128::from(900u128));
+    epoch_reply(&mut deps, env_after(100));
+
+    // only the profit since the last epoch is shared
+    deps.querier.with_reserve_balance(Uint128::from(1_400u128));
+    assert_eq!(
+        close_epoch(&mut deps, env_after(200)),
+        Some(Uint128::from(50u128))
+    );
+
+    // no profit, no payout
+    let mut deps = init();
+    bond(&mut deps, "alice", 100);
+    set_emission(
+        &mut deps,
+        EmissionPolicy::ProfitShare {
+            ratio: Decimal::percent(10),
+        },
+    );
+    assert_eq!(close_epoch(&mut deps, env_after(100)), None);
+}
+
+#[test]
+fn scheduled_emission() {
+    let mut deps = init();
+    bond(&mut deps, "alice", 100);
+    deps.querier.with_reserve_balance(Uint128::from(10_000u128));
+
+    let start = mock_env().block.time.seconds();
+    set_emission(
+        &mut deps,
+        EmissionPolicy::Schedule {
+            schedule: vec![(start + 50, start + 250, Uint128::from(1_000u128))],
+        },
+    );
+
+    let mut paid = 0u128;
+    for (time, payout) in [(100, 250u128), (200, 500), (300, 250)] {
+        assert_eq!(
+            close_epoch(&mut deps, env_after(time)),
+            Some(Uint128::from(payout))
+        );
+        paid += payout;
+        deps.querier
+            .with_native_balance(MOCK_CONTRACT_ADDR, &coins(paid, "uusd"));
+        epoch_reply(&mut deps, env_after(time));
+    }
+    assert_eq!(close_epoch(&mut deps, env_after(400)), None);
+
+    // invalid schedule
+    let msg = ExecuteMsg::SetEmission {
+        policy: Some(EmissionPolicy::Schedule {
+            schedule: vec![(start, start, Uint128::from(1u128))],
+        }),
+        epoch_length: 100,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+}
+
+fn setup_compound(deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>) {
+    let msg = ExecuteMsg::UpdateCompoundConfig {
+        astroport_factory: FACTORY_ADDR.to_string(),
+        vega_token: VEGA.to_string(),
+        max_spread: Some(Decimal::percent(1)),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    bond(deps, "alice", 100);
+    bond(deps, "bob", 100);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::SetAutoCompound { enabled: true },
+    )
+    .unwrap();
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(200, "uusd"));
+}
+
+fn compound_reply(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    result: ContractResult<SubMsgExecutionResponse>,
+) -> Vec<SubMsg> {
+    let msg = Reply {
+        id: COMPOUND_REPLY_ID,
+        result,
+    };
+    reply(deps.as_mut(), mock_env(), msg).unwrap().messages
+}
+
+#[test]
+fn auto_compound_on_index_update() {
+    let mut deps = init();
+    setup_compound(&mut deps);
+
+    let enabled: AutoCompoundResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AutoCompound {
+                address: "alice".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(enabled.enabled);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: PAIR_ADDR.to_string(),
+                msg: to_binary(&PairExecuteMsg::Swap {
+                    offer_asset: Asset {
+                        info: AstroportAssetInfo::NativeToken {
+                            denom: "uusd".to_string(),
+                        },
+                        amount: Uint128::from(100u128),
+                    },
+                    belief_price: None,
+                    max_spread: Some(Decimal::percent(1)),
+                    to: None,
+                })
+                .unwrap(),
+                funds: coins(100, "uusd"),
+            }),
+            COMPOUND_REPLY_ID,
+        )]
+    );
+
+    // only alice opted in
+    assert_eq!(accrued(&deps, "alice")[0].amount, Uint128::zero());
+    assert_eq!(accrued(&deps, "bob")[0].amount, Uint128::from(100u128));
+
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(40u128));
+    let messages = compound_reply(
+        &mut deps,
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    );
+    assert_eq!(
+        messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VEGA.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: GOV.to_string(),
+                amount: Uint128::from(40u128),
+                msg: to_binary(&Cw20HookMsg::StakeVotingTokensFor {
+                    staker: "alice".to_string(),
+                })
+                .unwrap(),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    let receipts: ClaimReceiptsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ClaimReceipts {
+                address: "alice".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(receipts.receipts.len(), 1);
+    assert_eq!(receipts.receipts[0].recipient, GOV);
+    assert_eq!(receipts.receipts[0].amount, Uint128::from(100u128));
+
+    // opting out
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::SetAutoCompound { enabled: false },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::Compound {},
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "Generic error: No rewards to compound");
+}
+
+#[test]
+fn failed_compound_restores_rewards() {
+    let mut deps = init();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::Compound {},
+    )
+    .unwrap_err();
+
+    setup_compound(&mut deps);
+    // rewards that do not split evenly leave decimals behind
+    bond(&mut deps, "carol", 100);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    compound_reply(
+        &mut deps,
+        ContractResult::Err("max spread assertion".to_string()),
+    );
+
+    let alice_rewards = accrued(&deps, "alice")[0].amount;
+    assert!(!alice_rewards.is_zero());
+    assert_eq!(alice_rewards, accrued(&deps, "bob")[0].amount);
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.assets[0].prev_reward_balance, Uint128::from(200u128));
+
+    // the next compound picks the restored rewards up
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::Compound {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+}
+
+fn update_index_at(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    env: Env,
+    balance: u128,
+) {
+    deps.querier
+        .with_native_balance(MOCK_CONTRACT_ADDR, &coins(balance, "uusd"));
+    execute(
+        deps.as_mut(),
+        env,
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+}
+
+#[test]
+fn index_snapshots_and_apr() {
+    let mut deps = init();
+    bond(&mut deps, "alice", 1000);
+
+    // no history yet
+    let apr: AprResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Apr {
+                window_seconds: 86400,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(apr.annualized_reward_per_token, Decimal::zero());
+
+    // updates of the same block are merged
+    update_index_at(&mut deps, mock_env(), 50);
+    update_index_at(&mut deps, mock_env(), 100);
+    // nothing added is still recorded
+    update_index_at(&mut deps, env_after(3600), 100);
+    update_index_at(&mut deps, env_after(86400), 200);
+
+    let snapshots: SnapshotsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Snapshots {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(snapshots.snapshots.len(), 3);
+    assert_eq!(
+        snapshots.snapshots[0].timestamp,
+        mock_env().block.time.seconds()
+    );
+    assert_eq!(snapshots.snapshots[0].rewards_added, Uint128::from(100u128));
+    assert_eq!(snapshots.snapshots[0].global_index, Decimal::percent(10));
+    assert_eq!(snapshots.snapshots[0].total_weight, Uint128::from(1000u128));
+    assert_eq!(snapshots.snapshots[1].rewards_added, Uint128::zero());
+    assert_eq!(snapshots.snapshots[1].global_index, Decimal::percent(10));
+    assert_eq!(snapshots.snapshots[2].global_index, Decimal::percent(20));
+
+    let snapshots: SnapshotsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Snapshots {
+                start_after: Some(mock_env().block.time.seconds()),
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(snapshots.snapshots.len(), 2);
+
+    // 0.1 uusd per staked token over a day
+    let apr: AprResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env_after(86400),
+            QueryMsg::Apr {
+                window_seconds: 86400,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(apr.start_time, mock_env().block.time.seconds());
+    assert_eq!(apr.index_delta, Decimal::percent(10));
+    assert_eq!(apr.annualized_reward_per_token, Decimal::percent(3650));
+
+    // the window is longer than the history
+    let apr: AprResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env_after(2 * 86400),
+            QueryMsg::Apr {
+                window_seconds: 30 * 86400,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(apr.start_time, mock_env().block.time.seconds());
+    assert_eq!(apr.annualized_reward_per_token, Decimal::percent(1825));
+}
+
+fn claim_receipts(
+    deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    holder: &str,
+    start_after: Option<u64>,
+) -> ClaimReceiptsResponse {
+    from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ClaimReceipts {
+                address: holder.to_string(),
+                start_after,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn claim_receipts_and_accrual_checkpoints() {
+    let mut deps = init();
+    register_vega(&mut deps);
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[("uusd", Uint128::from(1_000_000u128))],
+    );
+    bond(&mut deps, "alice", 100);
+
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(50u128));
+    deposit_vega(&mut deps, 50);
+    update_index_at(&mut deps, mock_env(), 100);
+
+    // the second bond moves the accrued rewards to pending
+    bond(&mut deps, "alice", 100);
+    let checkpoints: AccrualCheckpointsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AccrualCheckpoints {
+                address: "alice".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(checkpoints.checkpoints.len(), 2);
+    assert_eq!(checkpoints.checkpoints[0].balance, Uint128::zero());
+    let checkpoint = &checkpoints.checkpoints[1];
+    assert_eq!(checkpoint.id, 1);
+    assert_eq!(checkpoint.balance, Uint128::from(100u128));
+    assert_eq!(checkpoint.accrued[0].asset, uusd());
+    assert_eq!(checkpoint.accrued[0].amount, Decimal::percent(10000));
+    assert_eq!(checkpoint.accrued[1].amount, Decimal::percent(5000));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::ClaimRewards {
+            holder: None,
+            assets: None,
+            recipient: Some("treasury".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(99, "uusd"),
+        }))
+    );
+
+    let receipts = claim_receipts(&deps, "alice", None).receipts;
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(receipts[0].asset, uusd());
+    assert_eq!(receipts[0].amount, Uint128::from(100u128));
+    assert_eq!(receipts[0].tax, Uint128::from(1u128));
+    assert_eq!(receipts[0].recipient, "treasury");
+    assert_eq!(receipts[0].timestamp, mock_env().block.time.seconds());
+    assert_eq!(receipts[1].asset, vega());
+    assert_eq!(receipts[1].amount, Uint128::from(50u128));
+    assert_eq!(receipts[1].tax, Uint128::zero());
+
+    assert_eq!(claim_receipts(&deps, "alice", Some(0)).receipts.len(), 1);
+    assert!(claim_receipts(&deps, "bob", None).receipts.is_empty());
+}
+
+#[test]
+fn operators_claim_and_call() {
+    let mut deps = init();
+    register_vega(&mut deps);
+    bond(&mut deps, "alice", 100);
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(50u128));
+    deposit_vega(&mut deps, 50);
+    update_index_at(&mut deps, mock_env(), 100);
+
+    let claim_and_call = ExecuteMsg::ClaimAndCall {
+        holder: Some("alice".to_string()),
+        assets: None,
+        contract: "vault".to_string(),
+        msg: to_binary("deposit").unwrap(),
+    };
+
+    // bob is not an operator of alice yet
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        claim_and_call.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "Generic error: unauthorized");
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::AddOperator {
+            operator: "bob".to_string(),
+        },
+    )
+    .unwrap();
+    let operators: OperatorsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Operators {
+                address: "alice".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(operators.operators, vec!["bob".to_string()]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        claim_and_call.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "vault".to_string(),
+                msg: to_binary(&RewardsHookMsg::ReceiveRewards(RewardsReceiveMsg {
+                    sender: "alice".to_string(),
+                    msg: to_binary("deposit").unwrap(),
+                }))
+                .unwrap(),
+                funds: coins(100, "uusd"),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: VEGA.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: "vault".to_string(),
+                    amount: Uint128::from(50u128),
+                    msg: to_binary("deposit").unwrap(),
+                })
+                .unwrap(),
+                funds: vec![],
+            })),
+        ]
+    );
+    let receipts = claim_receipts(&deps, "alice", None).receipts;
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(receipts[0].recipient, "vault");
+    assert!(claim_receipts(&deps, "bob", None).receipts.is_empty());
+
+    // the rewards were claimed for alice, not for the operator
+    assert!(accrued(&deps, "alice").iter().all(|r| r.amount.is_zero()));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::RemoveOperator {
+            operator: "bob".to_string(),
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        ExecuteMsg::ClaimRewards {
+            holder: Some("alice".to_string()),
+            assets: None,
+            recipient: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "Generic error: unauthorized");
+}
+
+fn bond_tokens(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    token: &str,
+    holder: &str,
+    amount: u128,
+) -> StdResult<Response> {
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: holder.to_string(),
+        amount: Uint128::from(amount),
+        msg: to_binary(&ReceiveMsg::BondStake {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info(token, &[]), msg)
+}
+
+#[test]
+fn standalone_staking() {
+    let mut deps = init_with(Some(VEGA));
+    register_vega(&mut deps);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            governance_token_addr: None,
+            reserve_contract_addr: None,
+            reward_denom: None,
+            distribution_ratio: None,
+            unbonding_period: Some(10),
+        },
+    )
+    .unwrap();
+
+    // the governance stake is not mirrored
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "alice".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info(GOV, &[]), msg).unwrap_err();
+    bond_tokens(&mut deps, "other", "alice", 100).unwrap_err();
+
+    bond_tokens(&mut deps, VEGA, "alice", 100).unwrap();
+
+    // the bonded tokens are not distributed as rewards
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(150u128));
+    deposit_vega(&mut deps, 50);
+    assert_eq!(accrued(&deps, "alice")[1].amount, Uint128::from(50u128));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Unbond {
+            amount: Uint128::from(40u128),
+        },
+    )
+    .unwrap();
+    let claims: ClaimsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Claims {
+                address: "alice".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        claims.claims,
+        vec![Claim {
+            amount: Uint128::from(40u128),
+            release_at: Expiration::AtHeight(mock_env().block.height + 10),
+        }]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::WithdrawStake { cap: None },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Wait for the unbonding period"
+    );
+
+    let mut env = mock_env();
+    env.block.height += 10;
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("alice", &[]),
+        ExecuteMsg::WithdrawStake { cap: None },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VEGA.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "alice".to_string(),
+                amount: Uint128::from(40u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(110u128));
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_balance, Uint128::from(60u128));
+    assert_eq!(state.staked_tokens, Uint128::from(60u128));
+
+    // governance cannot unbond the stake
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(GOV, &[]),
+        ExecuteMsg::UnbondStake {
+            address: Addr::unchecked("alice"),
+            amount: Uint128::from(10u128),
+        },
+    )
+    .unwrap_err();
+
+    // compounded tokens are bonded in this contract
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateCompoundConfig {
+            astroport_factory: FACTORY_ADDR.to_string(),
+            vega_token: VEGA.to_string(),
+            max_spread: None,
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::SetAutoCompound { enabled: true },
+    )
+    .unwrap();
+    update_index_at(&mut deps, mock_env(), 60);
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(140u128));
+    let messages = compound_reply(
+        &mut deps,
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    );
+    assert!(messages.is_empty());
+
+    let holder: HolderResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Holder {
+                address: "alice".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(holder.balance, Uint128::from(90u128));
+    let receipts = claim_receipts(&deps, "alice", None).receipts;
+    assert_eq!(receipts[0].recipient, MOCK_CONTRACT_ADDR);
+    assert_eq!(receipts[0].amount, Uint128::from(60u128));
+
+    // the vega rewards are unchanged by the restaked tokens
+    update_index_at(&mut deps, mock_env(), 0);
+    assert_eq!(accrued(&deps, "alice")[1].amount, Uint128::from(50u128));
+}
+
+#[test]
+fn lock_boosts_and_early_exit() {
+    let mut deps = init_with(Some(VEGA));
+    let msg = ExecuteMsg::UpdateLockConfig {
+        max_multiplier: Decimal::percent(300),
+        early_exit_penalty: Decimal::percent(50),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    bond_tokens(&mut deps, VEGA, "alice", 100).unwrap();
+    bond_tokens(&mut deps, VEGA, "bob", 100).unwrap();
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(200u128));
+
+    let lock = |amount: u128, weeks: u64| ExecuteMsg::Lock {
+        amount: Uint128::from(amount),
+        weeks,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        lock(100, 53),
+    )
+    .unwrap_err();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        lock(101, 52),
+    )
+    .unwrap_err();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        lock(100, 52),
+    )
+    .unwrap();
+
+    let holder: HolderResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Holder {
+                address: "alice".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(holder.locked, Uint128::from(100u128));
+    assert_eq!(holder.weight, Uint128::from(300u128));
+
+    // rewards are split by weight
+    update_index_at(&mut deps, mock_env(), 400);
+    assert_eq!(accrued(&deps, "alice")[0].amount, Uint128::from(300u128));
+    assert_eq!(accrued(&deps, "bob")[0].amount, Uint128::from(100u128));
+
+    // locked stake cannot be unbonded
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Unbond {
+            amount: Uint128::from(10u128),
+        },
+    )
+    .unwrap_err();
+
+    let env = env_after(26 * 7 * 24 * 60 * 60);
+    let locks: LocksResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Locks {
+                address: "alice".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        locks.locks,
+        vec![LockResponse {
+            id: 0,
+            amount: Uint128::from(100u128),
+            multiplier: Decimal::percent(300),
+            start_time: mock_env().block.time.seconds(),
+            end_time: mock_env().block.time.seconds() + 52 * 7 * 24 * 60 * 60,
+            remaining_seconds: 26 * 7 * 24 * 60 * 60,
+            early_exit_penalty: Uint128::from(25u128),
+        }]
+    );
+
+    // half way through the lock, a quarter of the stake goes to the stakers
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Unlock { lock_id: 0 },
+    )
+    .unwrap();
+    query(
+        deps.as_ref(),
+        env,
+        QueryMsg::Lock {
+            address: "alice".to_string(),
+            lock_id: 0,
+        },
+    )
+    .unwrap_err();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_balance, Uint128::from(175u128));
+    assert_eq!(state.staked_tokens, Uint128::from(175u128));
+    // the penalty goes to the other stakers only
+    assert_eq!(accrued(&deps, "bob")[1].asset, vega());
+    assert_eq!(accrued(&deps, "bob")[1].amount, Uint128::from(25u128));
+    assert_eq!(accrued(&deps, "alice")[1].amount, Uint128::zero());
+}
+
+fn accrued_at(
+    deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    env: Env,
+    holder: &str,
+) -> Uint128 {
+    let res: AccruedRewardsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::AccruedRewards {
+                address: holder.to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    res.rewards[0].amount
+}
+
+#[test]
+fn lock_bonus_expires() {
+    let mut deps = init_with(Some(VEGA));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateLockConfig {
+            max_multiplier: Decimal::percent(300),
+            early_exit_penalty: Decimal::percent(50),
+        },
+    )
+    .unwrap();
+    bond_tokens(&mut deps, VEGA, "alice", 100).unwrap();
+    bond_tokens(&mut deps, VEGA, "bob", 100).unwrap();
+    deps.querier
+        .with_token_balance(VEGA, MOCK_CONTRACT_ADDR, Uint128::from(200u128));
+
+    let week = 7 * 24 * 60 * 60;
+    for (holder, weeks) in [("alice", 52), ("bob", 26)] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(holder, &[]),
+            ExecuteMsg::Lock {
+                amount: Uint128::from(100u128),
+                weeks,
+            },
+        )
+        .unwrap();
+    }
+
+    // weights of 300 and 200
+    update_index_at(&mut deps, mock_env(), 500);
+    assert_eq!(
+        accrued_at(&deps, mock_env(), "alice"),
+        Uint128::from(300u128)
+    );
+    assert_eq!(accrued_at(&deps, mock_env(), "bob"), Uint128::from(200u128));
+
+    // the ended lock is released before any index update, its bonus leaves the total weight
+    execute(
+        deps.as_mut(),
+        env_after(26 * week),
+        mock_info("bob", &[]),
+        ExecuteMsg::Unlock { lock_id: 1 },
+    )
+    .unwrap();
+    update_index_at(&mut deps, env_after(26 * week), 800);
+    assert_eq!(
+        accrued_at(&deps, env_after(26 * week), "alice"),
+        Uint128::from(525u128)
+    );
+    assert_eq!(
+        accrued_at(&deps, env_after(26 * week), "bob"),
+        Uint128::from(275u128)
+    );
+
+    // the index update expires the bonus of the untouched lock
+    update_index_at(&mut deps, env_after(52 * week), 1200);
+    let env = env_after(52 * week);
+    assert_eq!(
+        accrued_at(&deps, env.clone(), "alice"),
+        Uint128::from(725u128)
+    );
+    assert_eq!(
+        accrued_at(&deps, env.clone(), "bob"),
+        Uint128::from(475u128)
+    );
+    let holder: HolderResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "alice".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(holder.weight, Uint128::from(100u128));
+
+    // settling the holder keeps the rewards earned with the bonus
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("alice", &[]),
+        ExecuteMsg::Unlock { lock_id: 0 },
+    )
+    .unwrap();
+    assert_eq!(accrued_at(&deps, env, "alice"), Uint128::from(725u128));
+}
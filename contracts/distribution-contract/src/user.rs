@@ -1,72 +1,259 @@
-use crate::state::{read_holder, read_holders, store_holder, Config, Holder, State, CONFIG, STATE};
+use crate::global::update_asset_index;
+use crate::state::{
+    read_holder, read_holder_reward, read_holder_rewards, read_holders, read_reward_assets,
+    store_holder, store_holder_reward, AccrualCheckpoint, ClaimReceipt, Holder, State, CONFIG,
+    REWARD_ASSETS, STATE,
+};
 
 use cosmwasm_std::{
-    from_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
-    Response, StdError, StdResult, Uint128,
+    from_binary, to_binary, Addr, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Decimal, Deps,
+    DepsMut, Env, MessageInfo, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
 
+use crate::claim::{claim_tokens, create_claim};
+use crate::ledger::{record_accrual, record_claim};
+use crate::lock::{accrue_ended_bonus, ended_locks, expire_holder_locks};
 use crate::math::{
     decimal_multiplication_in_256, decimal_subtraction_in_256, decimal_summation_in_256,
 };
+use crate::operator::claiming_holder;
 use crate::taxation::deduct_tax;
-use cw20::Cw20ReceiveMsg;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Expiration};
 use std::str::FromStr;
-use terra_vegas::distribution::{AccruedRewardsResponse, HolderResponse, HoldersResponse};
+use terra_vegas::distribution::{
+    AccruedReward, AccruedRewardsResponse, AssetInfo, HolderResponse, HoldersResponse, ReceiveMsg,
+    RewardsHookMsg, RewardsReceiveMsg,
+};
 use terra_vegas::gov::Cw20HookMsg;
 
+/// Moves the accrued rewards of the holder out of the reward balances,
+/// returns every claimed asset with its amount
+fn settle_claim(
+    deps: &mut DepsMut,
+    env: &Env,
+    holder_addr_raw: &CanonicalAddr,
+    assets: Option<Vec<AssetInfo>>,
+) -> StdResult<Vec<(AssetInfo, Uint128)>> {
+    let mut holder: Holder = read_holder(deps.storage, holder_addr_raw)?;
+    let mut state = STATE.load(deps.storage)?;
+    expire_holder_locks(deps.storage, env, holder_addr_raw, &mut holder, &mut state)?;
+    store_holder(deps.storage, holder_addr_raw, &holder)?;
+    STATE.save(deps.storage, &state)?;
+
+    let reward_assets = match assets {
+        Some(assets) => assets
+            .iter()
+            .map(|asset| {
+                REWARD_ASSETS
+                    .may_load(deps.storage, &asset.key())?
+                    .ok_or_else(|| {
+                        StdError::generic_err(format!("{} is not a reward asset", asset))
+                    })
+            })
+            .collect::<StdResult<Vec<_>>>()?,
+        None => read_reward_assets(deps.storage)?,
+    };
+
+    let mut claimed = vec![];
+    for mut asset in reward_assets {
+        let key = asset.info.key();
+        let mut reward = read_holder_reward(deps.storage, holder_addr_raw, &key)?;
+
+        let reward_with_decimals =
+            calculate_decimal_rewards(asset.global_index, reward.index, holder.weight())?;
+        let all_reward_with_decimals =
+            decimal_summation_in_256(reward_with_decimals, reward.pending_rewards);
+        let rewards = all_reward_with_decimals * Uint128::from(1u128);
+
+        if rewards.is_zero() {
+            continue;
+        }
+
+        asset.prev_reward_balance = asset.prev_reward_balance.checked_sub(rewards)?;
+        REWARD_ASSETS.save(deps.storage, &key, &asset)?;
+
+        reward.pending_rewards = get_decimals(all_reward_with_decimals)?;
+        reward.index = asset.global_index;
+        store_holder_reward(deps.storage, holder_addr_raw, &key, &reward)?;
+
+        claimed.push((asset.info, rewards));
+    }
+
+    if claimed.is_empty() {
+        return Err(StdError::generic_err("No rewards have accrued yet"));
+    }
+    Ok(claimed)
+}
+
 pub fn handle_claim_rewards(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
+    holder: Option<String>,
+    assets: Option<Vec<AssetInfo>>,
     recipient: Option<String>,
 ) -> StdResult<Response> {
-    let holder_addr = info.sender;
-    let holder_addr_raw = deps.api.addr_canonicalize(&holder_addr.as_str())?;
+    let (holder_addr, holder_addr_raw) = claiming_holder(deps.as_ref(), &info, holder)?;
 
     let recipient = match recipient {
-        Some(value) => deps.api.addr_validate(value.as_str()).unwrap(),
+        Some(value) => deps.api.addr_validate(value.as_str())?,
         None => holder_addr.clone(),
     };
 
-    let mut holder: Holder = read_holder(deps.storage, &holder_addr_raw)?;
-    let mut state: State = STATE.load(deps.storage)?;
-    let config: Config = CONFIG.load(deps.storage)?;
-
-    let reward_with_decimals =
-        calculate_decimal_rewards(state.global_index, holder.index, holder.balance)?;
-
-    let all_reward_with_decimals =
-        decimal_summation_in_256(reward_with_decimals, holder.pending_rewards);
-    let decimals = get_decimals(all_reward_with_decimals).unwrap();
+    let claimed = settle_claim(&mut deps, &env, &holder_addr_raw, assets)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (asset, rewards) in claimed.iter() {
+        let (message, tax) = reward_transfer_msg(&deps.as_ref(), asset, &recipient, *rewards)?;
+        messages.push(message);
+        record_claim(
+            deps.storage,
+            &holder_addr_raw,
+            &ClaimReceipt {
+                timestamp: env.block.time.seconds(),
+                asset: asset.clone(),
+                amount: *rewards,
+                recipient: recipient.clone(),
+                tax,
+            },
+        )?;
+    }
 
-    let rewards = all_reward_with_decimals * Uint128::from(1u128);
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "claim_reward")
+        .add_attribute("holder_address", holder_addr)
+        .add_attribute("claimer", info.sender)
+        .add_attribute("rewards", claimed_attribute(&claimed)))
+}
 
-    if rewards.is_zero() {
-        return Err(StdError::generic_err("No rewards have accrued yet"));
+/// Claims the rewards to `contract`, mirroring cw20 `Send`:
+/// the native rewards are attached to a single `ReceiveRewards` hook,
+/// every cw20 reward is sent with its own `Send` carrying the same hook message
+pub fn handle_claim_and_call(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    holder: Option<String>,
+    assets: Option<Vec<AssetInfo>>,
+    contract: String,
+    msg: Binary,
+) -> StdResult<Response> {
+    let (holder_addr, holder_addr_raw) = claiming_holder(deps.as_ref(), &info, holder)?;
+    let contract = deps.api.addr_validate(&contract)?;
+
+    let claimed = settle_claim(&mut deps, &env, &holder_addr_raw, assets)?;
+
+    let mut funds: Vec<Coin> = vec![];
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (asset, rewards) in claimed.iter() {
+        let tax = match asset {
+            AssetInfo::NativeToken { denom } => {
+                let coin = deduct_tax(
+                    &deps.querier,
+                    Coin {
+                        denom: denom.clone(),
+                        amount: *rewards,
+                    },
+                )?;
+                let tax = rewards.checked_sub(coin.amount)?;
+                funds.push(coin);
+                tax
+            }
+            AssetInfo::Token { contract_addr } => {
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: contract_addr.clone(),
+                    msg: to_binary(&Cw20ExecuteMsg::Send {
+                        contract: contract.to_string(),
+                        amount: *rewards,
+                        msg: msg.clone(),
+                    })?,
+                    funds: vec![],
+                }));
+                Uint128::zero()
+            }
+        };
+        record_claim(
+            deps.storage,
+            &holder_addr_raw,
+            &ClaimReceipt {
+                timestamp: env.block.time.seconds(),
+                asset: asset.clone(),
+                amount: *rewards,
+                recipient: contract.clone(),
+                tax,
+            },
+        )?;
     }
-    //let f = state.prev_reward_balance.wrapping_sub(rewards);
-    let new_balance = (state.prev_reward_balance.checked_sub(rewards))?;
-    state.prev_reward_balance = new_balance;
-    STATE.save(deps.storage, &state)?;
 
-    holder.pending_rewards = decimals;
-    holder.index = state.global_index;
-    store_holder(deps.storage, &holder_addr_raw, &holder)?;
+    if !funds.is_empty() {
+        messages.insert(
+            0,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract.to_string(),
+                msg: to_binary(&RewardsHookMsg::ReceiveRewards(RewardsReceiveMsg {
+                    sender: holder_addr.to_string(),
+                    msg,
+                }))?,
+                funds,
+            }),
+        );
+    }
 
     Ok(Response::new()
-        .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: recipient.to_string(),
-            amount: vec![deduct_tax(
+        .add_messages(messages)
+        .add_attribute("action", "claim_and_call")
+        .add_attribute("holder_address", holder_addr)
+        .add_attribute("claimer", info.sender)
+        .add_attribute("contract", contract)
+        .add_attribute("rewards", claimed_attribute(&claimed)))
+}
+
+fn claimed_attribute(claimed: &[(AssetInfo, Uint128)]) -> String {
+    claimed
+        .iter()
+        .map(|(asset, rewards)| format!("{}:{}", asset, rewards))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Transfer of the rewards and the tax deducted from them
+fn reward_transfer_msg(
+    deps: &Deps,
+    asset: &AssetInfo,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<(CosmosMsg, Uint128)> {
+    Ok(match asset {
+        AssetInfo::NativeToken { denom } => {
+            let coin = deduct_tax(
                 &deps.querier,
                 Coin {
-                    denom: config.reward_denom,
-                    amount: rewards,
+                    denom: denom.clone(),
+                    amount,
                 },
-            )?],
-        }))
-        .add_attribute("action", "claim_reward")
-        .add_attribute("holder_address", holder_addr)
-        .add_attribute("rewards", rewards))
+            )?;
+            let tax = amount.checked_sub(coin.amount)?;
+            (
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: recipient.to_string(),
+                    amount: vec![coin],
+                }),
+                tax,
+            )
+        }
+        AssetInfo::Token { contract_addr } => (
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract_addr.clone(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            }),
+            Uint128::zero(),
+        ),
+    })
 }
 
 pub fn handle_receive(
@@ -77,25 +264,96 @@ pub fn handle_receive(
 ) -> StdResult<Response> {
     let config = CONFIG.load(deps.storage)?;
 
-    // only governance contract can send receieve msg
-    if info.sender != config.governance_token_addr {
-        return Err(StdError::generic_err(
-            "only governance contract can send receive messages",
-        ));
+    // governance contract mirrors the stake unless the contract has its own staking token
+    if info.sender == config.governance_token_addr && config.staking_token.is_none() {
+        let msg: Cw20HookMsg = from_binary(&wrapper.msg)?;
+        return match msg {
+            Cw20HookMsg::StakeVotingTokens {} => {
+                handle_bond(deps, env, info, wrapper.sender, wrapper.amount)
+            }
+            _ => Err(StdError::generic_err("only stake message allowed")),
+        };
     }
 
-    let msg: Cw20HookMsg = from_binary(&wrapper.msg)?;
+    // any other token is distributed as a reward
+    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
     match msg {
-        Cw20HookMsg::StakeVotingTokens {} => {
+        ReceiveMsg::BondStake {} => {
+            if config.staking_token != Some(info.sender.clone()) {
+                return Err(StdError::generic_err(
+                    "only the staking token can be bonded",
+                ));
+            }
             handle_bond(deps, env, info, wrapper.sender, wrapper.amount)
         }
-        _ => return Err(StdError::generic_err("only stake message allowed")),
+        ReceiveMsg::DepositRewards {} => handle_deposit_rewards(deps, env, info, wrapper.amount),
+    }
+}
+
+pub fn handle_deposit_rewards(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let asset = AssetInfo::Token {
+        contract_addr: info.sender.to_string(),
+    };
+    if !REWARD_ASSETS.has(deps.storage, &asset.key()) {
+        return Err(StdError::generic_err("reward asset is not registered"));
+    }
+
+    let state = STATE.load(deps.storage)?;
+    let mut reward_asset = REWARD_ASSETS.load(deps.storage, &asset.key())?;
+    let claimed_rewards = update_asset_index(&mut deps, &env, &state, &mut reward_asset)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "deposit_rewards")
+        .add_attribute("asset", asset.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("claimed_rewards", claimed_rewards))
+}
+
+/// Moves the rewards accrued with the current balance to pending rewards,
+/// sets the holder index of every asset to its global index and records the checkpoint.
+/// The bonus of the ended locks is dropped first
+pub(crate) fn settle_rewards(
+    storage: &mut dyn Storage,
+    env: &Env,
+    address_raw: &CanonicalAddr,
+    holder: &mut Holder,
+    state: &mut State,
+) -> StdResult<()> {
+    expire_holder_locks(storage, env, address_raw, holder, state)?;
+
+    let mut accrued = vec![];
+    for asset in read_reward_assets(storage)? {
+        let key = asset.info.key();
+        let mut reward = read_holder_reward(storage, address_raw, &key)?;
+
+        // get decimals
+        let rewards = calculate_decimal_rewards(asset.global_index, reward.index, holder.weight())?;
+
+        reward.index = asset.global_index;
+        reward.pending_rewards = decimal_summation_in_256(rewards, reward.pending_rewards);
+        store_holder_reward(storage, address_raw, &key, &reward)?;
+        accrued.push((asset.info, rewards));
     }
+
+    record_accrual(
+        storage,
+        address_raw,
+        &AccrualCheckpoint {
+            timestamp: env.block.time.seconds(),
+            balance: holder.balance,
+            accrued,
+        },
+    )
 }
 
 pub fn handle_bond(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     holder_addr: String,
     amount: Uint128,
@@ -107,31 +365,71 @@ pub fn handle_bond(
         return Err(StdError::generic_err("Amount required"));
     }
 
-    let address_raw = deps.api.addr_canonicalize(&holder_addr.as_str())?;
+    let address_raw = deps.api.addr_canonicalize(holder_addr.as_str())?;
+    bond_stake(deps.storage, &env, &address_raw, amount)?;
 
-    let mut state: State = STATE.load(deps.storage)?;
-    let mut holder: Holder = read_holder(deps.storage, &address_raw)?;
+    Ok(Response::new()
+        .add_attribute("action", "bond_stake")
+        .add_attribute("holder_address", holder_addr.as_str())
+        .add_attribute("amount", &amount.to_string()))
+}
 
-    // get decimals
-    let rewards = calculate_decimal_rewards(state.global_index, holder.index, holder.balance)?;
+/// Increases the holder balance, the tokens are held by the contract in standalone mode
+pub fn bond_stake(
+    storage: &mut dyn Storage,
+    env: &Env,
+    address_raw: &CanonicalAddr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let config = CONFIG.load(storage)?;
+    let mut state: State = STATE.load(storage)?;
+    let mut holder: Holder = read_holder(storage, address_raw)?;
+
+    settle_rewards(storage, env, address_raw, &mut holder, &mut state)?;
 
-    holder.index = state.global_index;
-    holder.pending_rewards = decimal_summation_in_256(rewards, holder.pending_rewards);
     holder.balance += amount;
     state.total_balance += amount;
+    if config.staking_token.is_some() {
+        state.staked_tokens += amount;
+    }
 
-    store_holder(deps.storage, &address_raw, &holder)?;
-    STATE.save(deps.storage, &state)?;
+    store_holder(storage, address_raw, &holder)?;
+    STATE.save(storage, &state)
+}
 
-    Ok(Response::new()
-        .add_attribute("action", "bond_stake")
-        .add_attribute("holder_address", holder_addr.as_str())
-        .add_attribute("amount", &amount.to_string()))
+fn unbond_stake(
+    storage: &mut dyn Storage,
+    env: &Env,
+    address_raw: &CanonicalAddr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let mut state: State = STATE.load(storage)?;
+    let mut holder: Holder = read_holder(storage, address_raw)?;
+    if holder.balance < amount {
+        return Err(StdError::generic_err(format!(
+            "Decrease amount cannot exceed user balance: {}",
+            holder.balance
+        )));
+    }
+    if holder.balance - amount < holder.locked {
+        return Err(StdError::generic_err(format!(
+            "Cannot unbond locked stake: {}",
+            holder.locked
+        )));
+    }
+
+    settle_rewards(storage, env, address_raw, &mut holder, &mut state)?;
+
+    holder.balance = holder.balance.checked_sub(amount)?;
+    state.total_balance = state.total_balance.checked_sub(amount)?;
+
+    store_holder(storage, address_raw, &holder)?;
+    STATE.save(storage, &state)
 }
 
 pub fn handle_unbound(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     address: Addr,
     amount: Uint128,
@@ -144,6 +442,11 @@ pub fn handle_unbound(
             "only loterra contract can send unbound messages",
         ));
     }
+    if config.staking_token.is_some() {
+        return Err(StdError::generic_err(
+            "stake is not mirrored from the governance contract",
+        ));
+    }
 
     if !info.funds.is_empty() {
         return Err(StdError::generic_err("Do not send funds with stake"));
@@ -152,97 +455,147 @@ pub fn handle_unbound(
         return Err(StdError::generic_err("Amount required"));
     }
 
-    let address_raw = deps.api.addr_canonicalize(&address.as_str())?;
+    let address_raw = deps.api.addr_canonicalize(address.as_str())?;
 
-    let mut state: State = STATE.load(deps.storage)?;
-    let mut holder: Holder = read_holder(deps.storage, &address_raw)?;
-    if holder.balance < amount {
-        return Err(StdError::generic_err(format!(
-            "Decrease amount cannot exceed user balance: {}",
-            holder.balance
-        )));
-    }
+    // distribution contract reflects governance contract state,
+    // there is no need to create claim in this contract as it is handled in the governance contract
+    unbond_stake(deps.storage, &env, &address_raw, amount)?;
 
-    let rewards = calculate_decimal_rewards(state.global_index, holder.index, holder.balance)?;
+    Ok(Response::new()
+        .add_attribute("action", "unbond_stake")
+        .add_attribute("holder_address", info.sender.as_str())
+        .add_attribute("amount", &amount.to_string()))
+}
 
-    holder.index = state.global_index;
-    holder.pending_rewards = decimal_summation_in_256(rewards, holder.pending_rewards);
-    holder.balance = holder.balance.checked_sub(amount)?;
-    state.total_balance = state.total_balance.checked_sub(amount)?;
+/// Unbonds the sender's own stake, the tokens are released after the unbonding period
+pub fn handle_unbond_stake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.staking_token.is_none() {
+        return Err(StdError::generic_err(
+            "stake is unbonded through the governance contract",
+        ));
+    }
 
-    store_holder(deps.storage, &address_raw, &holder)?;
-    STATE.save(deps.storage, &state)?;
+    if !info.funds.is_empty() {
+        return Err(StdError::generic_err("Do not send funds with stake"));
+    }
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Amount required"));
+    }
 
-    // distribution contract reflects governance contract state,
-    // there is no need to create claim in this contract as it is handled in the governance contract
+    let address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    unbond_stake(deps.storage, &env, &address_raw, amount)?;
 
-    // let release_height = Expiration::AtHeight(env.block.height + config.unbonding_period);
-    // create_claim(deps.storage, address_raw, amount, release_height)?;
+    let release_at = Expiration::AtHeight(env.block.height + config.unbonding_period);
+    create_claim(deps.storage, address_raw, amount, release_at)?;
 
     Ok(Response::new()
         .add_attribute("action", "unbond_stake")
         .add_attribute("holder_address", info.sender.as_str())
-        .add_attribute("amount", &amount.to_string()))
+        .add_attribute("amount", amount)
+        .add_attribute("release_at", release_at.to_string()))
+}
+
+pub fn handle_withdraw_stake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cap: Option<Uint128>,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    let staking_token = config
+        .staking_token
+        .ok_or_else(|| StdError::generic_err("stake is withdrawn from the governance contract"))?;
+    let address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    let amount = claim_tokens(deps.storage, address_raw, &env.block, cap)?;
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Wait for the unbonding period"));
+    }
+
+    let mut state = STATE.load(deps.storage)?;
+    state.staked_tokens = state.staked_tokens.checked_sub(amount)?;
+    STATE.save(deps.storage, &state)?;
+
+    let cw20_transfer_msg = Cw20ExecuteMsg::Transfer {
+        recipient: info.sender.to_string(),
+        amount,
+    };
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: staking_token.to_string(),
+            msg: to_binary(&cw20_transfer_msg)?,
+            funds: vec![],
+        }))
+        .add_attribute("action", "withdraw_stake")
+        .add_attribute("holder_address", info.sender.as_str())
+        .add_attribute("amount", amount))
 }
 
-// unused
-// pub fn handle_withdraw_stake(
-//     deps: DepsMut,
-//     env: Env,
-//     info: MessageInfo,
-//     cap: Option<Uint128>,
-// ) -> StdResult<Response> {
-//     let config = CONFIG.load(deps.storage)?;
-//     let address_raw = deps.api.addr_canonicalize(&info.sender.as_str())?;
-
-//     let amount = claim_tokens(deps.storage, address_raw, &env.block, cap)?;
-//     if amount.is_zero() {
-//         return Err(StdError::generic_err("Wait for the unbonding period"));
-//     }
-
-//     let cw20_human_addr = config.governance_token_addr;
-
-//     let cw20_transfer_msg = Cw20ExecuteMsg::Transfer {
-//         recipient: info.sender.to_string(),
-//         amount,
-//     };
-//     Ok(Response::new()
-//         .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-//             contract_addr: cw20_human_addr.to_string(),
-//             msg: to_binary(&cw20_transfer_msg)?,
-//             funds: vec![],
-//         }))
-//         .add_attribute("action", "withdraw_stake")
-//         .add_attribute("holder_address", info.sender.as_str())
-//         .add_attribute("amount", amount))
-// }
-
-pub fn query_accrued_rewards(deps: Deps, address: String) -> StdResult<AccruedRewardsResponse> {
-    let global_index = STATE.load(deps.storage)?.global_index;
-
-    let holder: Holder = read_holder(deps.storage, &deps.api.addr_canonicalize(&address)?)?;
-    let reward_with_decimals =
-        calculate_decimal_rewards(global_index, holder.index, holder.balance)?;
-    let all_reward_with_decimals =
-        decimal_summation_in_256(reward_with_decimals, holder.pending_rewards);
-
-    let rewards = all_reward_with_decimals * Uint128::from(1u128);
+pub fn query_accrued_rewards(
+    deps: Deps,
+    env: Env,
+    address: String,
+) -> StdResult<AccruedRewardsResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let holder: Holder = read_holder(deps.storage, &address_raw)?;
+    let ended = ended_locks(deps.storage, &env, &address_raw)?;
+
+    let rewards = read_reward_assets(deps.storage)?
+        .into_iter()
+        .map(|asset| {
+            let key = asset.info.key();
+            let mut reward = read_holder_reward(deps.storage, &address_raw, &key)?;
+            let weight =
+                accrue_ended_bonus(deps.storage, &ended, &asset, holder.weight(), &mut reward)?;
+            let reward_with_decimals =
+                calculate_decimal_rewards(asset.global_index, reward.index, weight)?;
+            let all_reward_with_decimals =
+                decimal_summation_in_256(reward_with_decimals, reward.pending_rewards);
+
+            Ok(AccruedReward {
+                asset: asset.info,
+                amount: all_reward_with_decimals * Uint128::from(1u128),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
 
     Ok(AccruedRewardsResponse { rewards })
 }
 
-pub fn query_holder(deps: Deps, address: String) -> StdResult<HolderResponse> {
-    let holder: Holder = read_holder(deps.storage, &deps.api.addr_canonicalize(&address)?)?;
+/// Weight without the bonus of the ended locks
+fn current_weight(
+    deps: Deps,
+    env: &Env,
+    address_raw: &CanonicalAddr,
+    weight: Uint128,
+) -> StdResult<Uint128> {
+    ended_locks(deps.storage, env, address_raw)?
+        .iter()
+        .try_fold(weight, |weight, (_, lock)| weight.checked_sub(lock.bonus))
+        .map_err(StdError::from)
+}
+
+pub fn query_holder(deps: Deps, env: Env, address: String) -> StdResult<HolderResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let holder: Holder = read_holder(deps.storage, &address_raw)?;
     Ok(HolderResponse {
         address,
         balance: holder.balance,
-        index: holder.index,
-        pending_rewards: holder.pending_rewards,
+        locked: holder.locked,
+        weight: current_weight(deps, &env, &address_raw, holder.weight())?,
+        rewards: read_holder_rewards(deps.storage, &address_raw)?,
     })
 }
 
 pub fn query_holders(
     deps: Deps,
+    env: Env,
     start_after: Option<String>,
     limit: Option<u32>,
 ) -> StdResult<HoldersResponse> {
@@ -252,13 +605,22 @@ pub fn query_holders(
         None
     };
 
-    let holders: Vec<HolderResponse> = read_holders(deps, start_after, limit)?;
+    let holders = read_holders(deps, start_after, limit)?
+        .into_iter()
+        .map(|holder| {
+            let address_raw = deps.api.addr_canonicalize(&holder.address)?;
+            Ok(HolderResponse {
+                weight: current_weight(deps, &env, &address_raw, holder.weight)?,
+                ..holder
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
 
     Ok(HoldersResponse { holders })
 }
 
 // calculate the reward based on the sender's index and the global index.
-fn calculate_decimal_rewards(
+pub(crate) fn calculate_decimal_rewards(
     global_index: Decimal,
     user_index: Decimal,
     user_balance: Uint128,
@@ -271,7 +633,7 @@ fn calculate_decimal_rewards(
 }
 
 // calculate the reward with decimal
-fn get_decimals(value: Decimal) -> StdResult<Decimal> {
+pub(crate) fn get_decimals(value: Decimal) -> StdResult<Decimal> {
     let stringed: &str = &*value.to_string();
     let parts: &[&str] = &*stringed.split('.').collect::<Vec<&str>>();
     match parts.len() {
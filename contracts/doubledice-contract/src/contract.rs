@@ -1,8 +1,8 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, MessageInfo, Order,
-    Response, StdResult, Uint128, Uint64, WasmMsg,
+    to_binary, Addr, BankMsg, Binary, Coin, ContractResult, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Order, Reply, Response, StdResult, SubMsg, Uint128, Uint64, WasmMsg,
 };
 use cw0::{must_pay, nonpayable};
 use cw2::set_contract_version;
@@ -34,6 +34,8 @@ use crate::utils::{
 const CONTRACT_NAME: &str = "crates.io:game-contract";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const REQUEST_FUNDS_REPLY_ID: u64 = 1;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -97,9 +99,12 @@ pub fn execute(
             execute_change_max_cashflow(deps, info, cashflow)
         }
         ExecuteMsg::Bet { outcome } => execute_bet(deps, env, info, outcome),
-        ExecuteMsg::ReceiveRewards {} => execute_receive_rewards(deps, info),
+        ExecuteMsg::ReceiveRewards {} => execute_receive_rewards(deps, env, info),
         ExecuteMsg::DrainGame {} => execute_drain_game(deps, info, env),
         ExecuteMsg::StopGame {} => execute_stop_game(deps, info),
+        ExecuteMsg::MigrateReserve { new_reserve } => {
+            execute_migrate_reserve(deps, info, new_reserve)
+        }
 
         #[cfg(feature = "debug")]
         ExecuteMsg::ChangeConfig {
@@ -279,6 +284,27 @@ pub fn execute_stop_game(deps: DepsMut, info: MessageInfo) -> Result<Response, C
     Ok(Response::default())
 }
 
+pub fn execute_migrate_reserve(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_reserve: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let new_reserve = deps.api.addr_validate(&new_reserve)?;
+
+    CASINO_CONFIG.update(
+        deps.storage,
+        move |mut casino_config| -> Result<_, ContractError> {
+            if info.sender != casino_config.reserve_address {
+                return Err(ContractError::Unauthorized {});
+            }
+            casino_config.reserve_address = new_reserve;
+            Ok(casino_config)
+        },
+    )?;
+    Ok(Response::new().add_attribute("action", "migrate reserve"))
+}
+
 pub fn execute_change_config(
     deps: DepsMut,
     info: MessageInfo,
@@ -527,10 +553,14 @@ pub fn end_round(
             contract_addr: casino_config.reserve_address.to_string(),
             funds: vec![],
             msg: to_binary(&ReserveMsg::RequestFunds {
+                asset: None,
                 amount: total_rewards - contract_balance.amount,
             })?,
         };
-        Ok(Response::new().add_message(msg))
+        // the round is settled even if the reserve can not cover the request,
+        // unpaid winnings become debts of the reserve once they are claimed
+        let msg = SubMsg::reply_on_error(msg, REQUEST_FUNDS_REPLY_ID);
+        Ok(Response::new().add_submessage(msg))
     } else {
         let diff = contract_balance.amount - total_rewards;
         if diff > casino_config.max_cashflow {
@@ -552,6 +582,7 @@ pub fn end_round(
 
 pub fn execute_receive_rewards(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
@@ -565,12 +596,41 @@ pub fn execute_receive_rewards(
                         Ok(total_rewards - reward)
                     })?;
                 PLAYERS_REWARDS.save(deps.storage, info.sender.clone(), &Uint128::zero())?;
-                let denom = CASINO_CONFIG.load(deps.storage)?.native_denom;
-                let msg = BankMsg::Send {
-                    to_address: info.sender.to_string(),
-                    amount: vec![deduct_tax(deps.as_ref(), Coin::new(reward.u128(), denom))?],
-                };
-                Response::new().add_message(msg)
+                let casino_config = CASINO_CONFIG.load(deps.storage)?;
+
+                // if the reserve could not fund the round the player gets what the game
+                // holds and the rest is owed by the reserve
+                let contract_balance = deps
+                    .querier
+                    .query_balance(&env.contract.address, &casino_config.native_denom)?;
+                let payout = std::cmp::min(reward, contract_balance.amount);
+                let debt = reward - payout;
+
+                let mut response = Response::new();
+                if !payout.is_zero() {
+                    let msg = BankMsg::Send {
+                        to_address: info.sender.to_string(),
+                        amount: vec![deduct_tax(
+                            deps.as_ref(),
+                            Coin::new(payout.u128(), casino_config.native_denom),
+                        )?],
+                    };
+                    response = response.add_message(msg);
+                }
+                if !debt.is_zero() {
+                    let msg = WasmMsg::Execute {
+                        contract_addr: casino_config.reserve_address.to_string(),
+                        funds: vec![],
+                        msg: to_binary(&ReserveMsg::RecordDebt {
+                            player: info.sender.to_string(),
+                            amount: debt,
+                        })?,
+                    };
+                    response = response
+                        .add_attribute("debt", debt)
+                        .add_message(msg);
+                }
+                response
             }
         }
         None => Response::default(),
@@ -578,6 +638,22 @@ pub fn execute_receive_rewards(
     Ok(response)
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        REQUEST_FUNDS_REPLY_ID => {
+            let error = match msg.result {
+                ContractResult::Err(err) => err,
+                ContractResult::Ok(_) => String::new(),
+            };
+            Ok(Response::new()
+                .add_attribute("action", "request funds failed")
+                .add_attribute("error", error))
+        }
+        _ => Err(ContractError::InvalidReplyId {}),
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -1441,11 +1517,12 @@ mod tests {
                 contract_addr: "reserve".to_string(),
                 funds: vec![],
                 msg: to_binary(&ReserveMsg::RequestFunds {
+                    asset: None,
                     amount: 1186u128.into(),
                 })
                 .unwrap(),
             };
-            Response::new().add_message(msg)
+            Response::new().add_submessage(SubMsg::reply_on_error(msg, REQUEST_FUNDS_REPLY_ID))
         });
 
         let res = query(deps.as_ref(), env.clone(), QueryMsg::OutcomeHistory {}).unwrap();
@@ -1476,10 +1553,33 @@ mod tests {
             }
         );
 
+        // the reserve could not fund the round, so the whole reward is owed by it
+        let res = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: REQUEST_FUNDS_REPLY_ID,
+                result: ContractResult::Err("insufficient funds".to_string()),
+            },
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+
         let user_info = mock_info("user", &[]);
         let msg = ExecuteMsg::ReceiveRewards {};
         let res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
-        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "reserve".to_string(),
+                funds: vec![],
+                msg: to_binary(&ReserveMsg::RecordDebt {
+                    player: "user".to_string(),
+                    amount: 1186u128.into(),
+                })
+                .unwrap(),
+            })]
+        );
 
         let res = query(
             deps.as_ref(),
@@ -35,4 +35,6 @@ pub enum ContractError {
     RoundEnded {},
     #[error("Game stopped")]
     GameStopped {},
+    #[error("InvalidReplyId")]
+    InvalidReplyId {},
 }
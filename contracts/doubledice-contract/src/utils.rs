@@ -191,6 +191,7 @@ pub mod tests_utils {
                         "reserve" => SystemResult::Ok(ContractResult::Ok(
                             to_binary(&reserve_contract::msg::CurrentBalance {
                                 balance: 1000u128.into(),
+                                assets: vec![],
                             })
                             .unwrap(),
                         )),
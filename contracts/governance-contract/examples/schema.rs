@@ -3,7 +3,9 @@ use std::env::current_dir;
 use std::fs::create_dir_all;
 
 use terra_vegas::gov::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, PollResponse, QueryMsg, StakerResponse,
+    ConfigResponse, Cw20HookMsg, DelegatePowerResponse, DelegatorsResponse, ExecuteMsg,
+    InstantiateMsg, MigrateMsg, PollResponse, QueryMsg, SimulationResponse, StakerResponse,
+    TotalVotingEscrowResponse, VotingEscrowResponse,
 };
 
 fn main() {
@@ -15,8 +17,14 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(Cw20HookMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(StakerResponse), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(PollResponse), &out_dir);
+    export_schema(&schema_for!(DelegatePowerResponse), &out_dir);
+    export_schema(&schema_for!(DelegatorsResponse), &out_dir);
+    export_schema(&schema_for!(VotingEscrowResponse), &out_dir);
+    export_schema(&schema_for!(TotalVotingEscrowResponse), &out_dir);
+    export_schema(&schema_for!(SimulationResponse), &out_dir);
 }
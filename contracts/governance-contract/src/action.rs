@@ -0,0 +1,206 @@
+use crate::error::ContractError;
+use crate::state::ExecuteData;
+
+use cosmwasm_std::{to_binary, Api, CosmosMsg, Decimal, StdResult, Uint128, WasmMsg};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use terra_vegas::gov::{GameParam, PollAction, PollCategory, PollExecuteMsg};
+use terra_vegas::{community, distribution};
+
+/// Messages the polls send to the games
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum GameMsg {
+    ChangeAdwantageValue { advantage_value: String },
+    ChangeWinTax { win_tax: String },
+    ChangeMaxNumberOfBets { number_of_bets: u64 },
+    ChangeMaxBettingRatio { ratio: u64 },
+    ChangeRoundDuration { duration: u64 },
+    ChangeMaxCashflow { cashflow: Uint128 },
+}
+
+/// Messages the polls send to the reserve
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ReserveMsg {
+    AddGame { addr: String },
+    RemoveGame { addr: String },
+}
+
+/// Category of a new poll, taken from the first typed action when it is not given
+pub fn poll_category(
+    category: Option<PollCategory>,
+    execute_msgs: &[PollExecuteMsg],
+) -> Result<PollCategory, ContractError> {
+    let category = match category {
+        Some(category) => category,
+        None if execute_msgs.is_empty() => PollCategory::Text,
+        None => execute_msgs
+            .iter()
+            .find_map(|msg| msg.action.category())
+            .unwrap_or(PollCategory::ParameterChange),
+    };
+
+    let fits = |action: &PollAction| match category {
+        PollCategory::Text => false,
+        PollCategory::Emergency => matches!(
+            action,
+            PollAction::RemoveGame { .. } | PollAction::UpdateGameParams { .. }
+        ),
+        _ => action.category().unwrap_or(category) == category,
+    };
+    if !execute_msgs.iter().all(|msg| fits(&msg.action)) {
+        return Err(ContractError::PollCategoryMismatch(category.to_string()));
+    }
+
+    Ok(category)
+}
+
+/// Validates the action and encodes the message it executes
+pub fn build_execute_data(
+    api: &dyn Api,
+    order: u64,
+    action: PollAction,
+) -> Result<ExecuteData, ContractError> {
+    let contract = api.addr_canonicalize(action.contract())?;
+    let msg = match &action {
+        PollAction::UpdateGameParams { param, .. } => to_binary(&game_msg(param)?)?,
+        PollAction::AddGame { game, .. } => to_binary(&ReserveMsg::AddGame {
+            addr: api.addr_validate(game)?.to_string(),
+        })?,
+        PollAction::RemoveGame { game, .. } => to_binary(&ReserveMsg::RemoveGame {
+            addr: api.addr_validate(game)?.to_string(),
+        })?,
+        PollAction::UpdateDistributionConfig {
+            reward_denom,
+            distribution_ratio,
+            unbonding_period,
+            ..
+        } => {
+            if reward_denom.is_none() && distribution_ratio.is_none() && unbonding_period.is_none()
+            {
+                return Err(ContractError::InvalidPollAction(
+                    "nothing to update".to_string(),
+                ));
+            }
+
+            to_binary(&distribution::ExecuteMsg::UpdateConfig {
+                governance_token_addr: None,
+                reserve_contract_addr: None,
+                reward_denom: reward_denom.clone(),
+                distribution_ratio: *distribution_ratio,
+                unbonding_period: *unbonding_period,
+            })?
+        }
+        PollAction::CommunitySpend {
+            recipient, amount, ..
+        } => {
+            if amount.is_zero() {
+                return Err(ContractError::InvalidPollAction(
+                    "spend amount is zero".to_string(),
+                ));
+            }
+
+            to_binary(&community::ExecuteMsg::Spend {
+                recipient: api.addr_validate(recipient)?.to_string(),
+                amount: *amount,
+            })?
+        }
+        PollAction::MigrateContract {
+            new_code_id, msg, ..
+        } => {
+            if *new_code_id == 0 {
+                return Err(ContractError::InvalidPollAction(
+                    "code id is zero".to_string(),
+                ));
+            }
+
+            msg.clone()
+        }
+        PollAction::Raw { msg, .. } => msg.clone(),
+    };
+
+    Ok(ExecuteData {
+        order,
+        contract,
+        msg,
+        action: Some(action),
+    })
+}
+
+fn game_msg(param: &GameParam) -> Result<GameMsg, ContractError> {
+    Ok(match param {
+        GameParam::AdvantageValue(value) => GameMsg::ChangeAdwantageValue {
+            advantage_value: validate_ratio(*value, "advantage value")?,
+        },
+        GameParam::WinTax(value) => GameMsg::ChangeWinTax {
+            win_tax: validate_ratio(*value, "win tax")?,
+        },
+        GameParam::MaxNumberOfBets(number_of_bets) => GameMsg::ChangeMaxNumberOfBets {
+            number_of_bets: *number_of_bets,
+        },
+        GameParam::MaxBettingRatio(ratio) => GameMsg::ChangeMaxBettingRatio { ratio: *ratio },
+        GameParam::RoundDuration(duration) => GameMsg::ChangeRoundDuration {
+            duration: *duration,
+        },
+        GameParam::MaxCashflow(cashflow) => GameMsg::ChangeMaxCashflow {
+            cashflow: *cashflow,
+        },
+    })
+}
+
+fn validate_ratio(value: Decimal, name: &str) -> Result<String, ContractError> {
+    if value > Decimal::one() {
+        return Err(ContractError::InvalidPollAction(format!(
+            "{} must be 0 to 1",
+            name
+        )));
+    }
+
+    Ok(value.to_string())
+}
+
+/// Message a passed poll sends for the stored action
+pub fn execute_data_to_msg(api: &dyn Api, data: ExecuteData) -> StdResult<CosmosMsg> {
+    let contract_addr = api.addr_humanize(&data.contract)?.to_string();
+    Ok(match data.action {
+        Some(PollAction::MigrateContract { new_code_id, .. }) => {
+            CosmosMsg::Wasm(WasmMsg::Migrate {
+                contract_addr,
+                new_code_id,
+                msg: data.msg,
+            })
+        }
+        _ => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg: data.msg,
+            funds: vec![],
+        }),
+    })
+}
+
+/// Stored action as shown in the poll queries,
+/// polls created before the actions were typed show raw messages
+pub fn execute_data_to_poll_msg(api: &dyn Api, data: ExecuteData) -> StdResult<PollExecuteMsg> {
+    let action = match data.action {
+        Some(action) => action,
+        None => PollAction::Raw {
+            contract: api.addr_humanize(&data.contract)?.to_string(),
+            msg: data.msg,
+        },
+    };
+
+    Ok(PollExecuteMsg {
+        order: data.order,
+        action,
+    })
+}
+
+/// Polls executing raw messages have to pass the raw message threshold as well
+pub fn has_raw_msgs(execute_data: &Option<Vec<ExecuteData>>) -> bool {
+    execute_data
+        .iter()
+        .flatten()
+        .any(|data| matches!(&data.action, Some(action) if action.is_raw()))
+}
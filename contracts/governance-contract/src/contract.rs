@@ -1,9 +1,21 @@
+use crate::action::{
+    build_execute_data, execute_data_to_msg, execute_data_to_poll_msg, has_raw_msgs, poll_category,
+};
+use crate::delegation::{
+    delegate_voting_power, override_delegated_vote, query_delegate_power, query_delegators,
+    share_to_balance, undelegate_voting_power,
+};
 use crate::error::ContractError;
+use crate::simulation::{query_simulate_execute_msgs, query_simulate_poll};
 use crate::staking::{query_staker, stake_voting_tokens, withdraw_voting_tokens};
 use crate::state::{
-    bank_read, bank_store, config_read, config_store, poll_indexer_store, poll_read, poll_store,
-    poll_voter_read, poll_voter_store, read_poll_voters, read_polls, read_tmp_poll_id, state_read,
-    state_store, store_tmp_poll_id, Config, ExecuteData, Poll, State,
+    migrate_polls, polls, read_poll_voters, read_polls, read_polls_by_creator, read_polls_ending,
+    read_staker_snapshot, read_total_share_snapshot, read_ve_point, Config, ExecuteData, Poll,
+    StakerSnapshot, State, BANK, CONFIG, POLL_DELEGATES, POLL_VOTERS, STATE, TMP_POLL_ID, VE_TOTAL,
+};
+use crate::voting_escrow::{
+    create_lock, extend_lock, increase_lock_amount, query_total_voting_escrow, query_voting_escrow,
+    weight_to_balance, withdraw_lock,
 };
 
 use astroport::querier::query_token_balance;
@@ -11,15 +23,16 @@ use astroport::querier::query_token_balance;
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     attr, from_binary, to_binary, Binary, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut, Env,
-    MessageInfo, Reply, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
+    MessageInfo, Order, Reply, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_storage_plus::U64Key;
 
 use terra_vegas::common::OrderBy;
 use terra_vegas::gov::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, PollExecuteMsg, PollResponse,
-    PollStatus, PollsResponse, QueryMsg, StateResponse, VoteOption, VoterInfo, VotersResponse,
-    VotersResponseItem,
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, PollCategory,
+    PollCategoryParams, PollExecuteMsg, PollResponse, PollStatus, PollsResponse, QueryMsg,
+    StateResponse, VoteOption, VoterInfo, VotersResponse, VotersResponseItem,
 };
 
 const MIN_TITLE_LENGTH: usize = 4;
@@ -40,6 +53,10 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     validate_quorum(msg.quorum)?;
     validate_threshold(msg.threshold)?;
+    validate_veto_threshold(msg.veto_threshold)?;
+    validate_raw_msg_threshold(msg.raw_msg_threshold, msg.threshold)?;
+    validate_categories(&msg.categories)?;
+    validate_cancel_refund_ratio(msg.cancel_refund_ratio)?;
 
     let config = Config {
         token_contract: CanonicalAddr::from(vec![]),
@@ -49,9 +66,19 @@ pub fn instantiate(
         threshold: msg.threshold,
         voting_period: msg.voting_period,
         timelock_period: msg.timelock_period,
-        expiration_period: 0u64, // Depricated
+        expiration_period: msg.expiration_period,
         proposal_deposit: msg.proposal_deposit,
         snapshot_period: msg.snapshot_period,
+        veto_threshold: msg.veto_threshold,
+        community_contract: CanonicalAddr::from(vec![]),
+        max_lock_period: msg.max_lock_period,
+        raw_msg_threshold: msg.raw_msg_threshold,
+        categories: msg.categories,
+        guardian: match msg.guardian {
+            Some(guardian) => deps.api.addr_canonicalize(&guardian)?,
+            None => CanonicalAddr::from(vec![]),
+        },
+        cancel_refund_ratio: msg.cancel_refund_ratio,
     };
 
     let state = State {
@@ -61,8 +88,8 @@ pub fn instantiate(
         total_deposit: Uint128::zero(),
     };
 
-    config_store(deps.storage).save(&config)?;
-    state_store(deps.storage).save(&state)?;
+    CONFIG.save(deps.storage, &config)?;
+    STATE.save(deps.storage, &state)?;
 
     Ok(Response::default())
 }
@@ -91,6 +118,13 @@ pub fn execute(
             timelock_period,
             proposal_deposit,
             snapshot_period,
+            veto_threshold,
+            community_contract,
+            max_lock_period,
+            raw_msg_threshold,
+            categories,
+            expiration_period,
+            cancel_refund_ratio,
         } => update_config(
             deps,
             info,
@@ -101,8 +135,17 @@ pub fn execute(
             timelock_period,
             proposal_deposit,
             snapshot_period,
+            veto_threshold,
+            community_contract,
+            max_lock_period,
+            raw_msg_threshold,
+            categories,
+            expiration_period,
+            cancel_refund_ratio,
         ),
-        ExecuteMsg::WithdrawVotingTokens { amount } => withdraw_voting_tokens(deps, info, amount),
+        ExecuteMsg::WithdrawVotingTokens { amount } => {
+            withdraw_voting_tokens(deps, env, info, amount)
+        }
         ExecuteMsg::CastVote {
             poll_id,
             vote,
@@ -111,6 +154,25 @@ pub fn execute(
         ExecuteMsg::EndPoll { poll_id } => end_poll(deps, env, poll_id),
         ExecuteMsg::ExecutePoll { poll_id } => execute_poll(deps, env, poll_id),
         ExecuteMsg::SnapshotPoll { poll_id } => snapshot_poll(deps, env, poll_id),
+        ExecuteMsg::Delegate { delegate } => delegate_voting_power(deps, env, info, delegate),
+        ExecuteMsg::Undelegate {} => undelegate_voting_power(deps, env, info),
+        ExecuteMsg::CreateLock { amount, end_height } => {
+            create_lock(deps, env, info, amount, end_height)
+        }
+        ExecuteMsg::IncreaseLockAmount { amount } => increase_lock_amount(deps, env, info, amount),
+        ExecuteMsg::ExtendLock { end_height } => extend_lock(deps, env, info, end_height),
+        ExecuteMsg::WithdrawLock {} => withdraw_lock(deps, env, info),
+        ExecuteMsg::VetoPoll { poll_id } => veto_poll(deps, env, info, poll_id),
+        ExecuteMsg::UpdateGuardian { guardian } => update_guardian(deps, env, info, guardian),
+        ExecuteMsg::CancelPoll { poll_id } => cancel_poll(deps, info, poll_id),
+        ExecuteMsg::AmendPoll {
+            poll_id,
+            title,
+            description,
+            link,
+        } => amend_poll(deps, info, poll_id, title, description, link),
+        ExecuteMsg::ExpirePoll { poll_id } => expire_poll(deps, env, poll_id),
+        ExecuteMsg::PrunePollVoters { poll_id, limit } => prune_poll_voters(deps, poll_id, limit),
     }
 }
 
@@ -118,7 +180,7 @@ pub fn execute(
 pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
     match msg.id {
         POLL_EXECUTE_REPLY_ID => {
-            let poll_id: u64 = read_tmp_poll_id(deps.storage)?;
+            let poll_id: u64 = TMP_POLL_ID.load(deps.storage)?;
             fail_poll(deps, poll_id)
         }
         _ => Err(ContractError::InvalidReplyId {}),
@@ -129,13 +191,13 @@ pub fn register_contracts(
     deps: DepsMut,
     token_contract: String,
 ) -> Result<Response, ContractError> {
-    let mut config: Config = config_read(deps.storage).load()?;
+    let mut config: Config = CONFIG.load(deps.storage)?;
     if config.token_contract != CanonicalAddr::from(vec![]) {
         return Err(ContractError::Unauthorized {});
     }
 
     config.token_contract = deps.api.addr_canonicalize(&token_contract)?;
-    config_store(deps.storage).save(&config)?;
+    CONFIG.save(deps.storage, &config)?;
 
     Ok(Response::default())
 }
@@ -144,13 +206,13 @@ pub fn register_distribution_contract(
     deps: DepsMut,
     distribution_contract: String,
 ) -> Result<Response, ContractError> {
-    let mut config: Config = config_read(deps.storage).load()?;
+    let mut config: Config = CONFIG.load(deps.storage)?;
     if config.distribution_contract != CanonicalAddr::from(vec![]) {
         return Err(ContractError::Unauthorized {});
     }
 
     config.distribution_contract = deps.api.addr_canonicalize(&distribution_contract)?;
-    config_store(deps.storage).save(&config)?;
+    CONFIG.save(deps.storage, &config)?;
 
     Ok(Response::default())
 }
@@ -162,7 +224,7 @@ pub fn receive_cw20(
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     // only asset contract can execute this message
-    let config: Config = config_read(deps.storage).load()?;
+    let config: Config = CONFIG.load(deps.storage)?;
     if config.token_contract != deps.api.addr_canonicalize(info.sender.as_str())? {
         return Err(ContractError::Unauthorized {});
     }
@@ -170,15 +232,44 @@ pub fn receive_cw20(
     match from_binary(&cw20_msg.msg) {
         Ok(Cw20HookMsg::StakeVotingTokens {}) => {
             let api = deps.api;
-            let mut res =
-                stake_voting_tokens(deps, api.addr_validate(&cw20_msg.sender)?, cw20_msg.amount)?;
+            let mut res = stake_voting_tokens(
+                deps,
+                &env,
+                api.addr_validate(&cw20_msg.sender)?,
+                cw20_msg.amount,
+            )?;
+
+            // the stake is only mirrored once a distribution contract is registered
+            if config.distribution_contract != CanonicalAddr::from(vec![]) {
+                res = res.add_message(
+                    cw20_msg.into_cosmos_msg(
+                        api.addr_humanize(&config.distribution_contract)?
+                            .to_string(),
+                    )?,
+                );
+            }
 
-            res = res.add_message(
-                cw20_msg.into_cosmos_msg(
-                    api.addr_humanize(&config.distribution_contract)?
-                        .to_string(),
-                )?,
-            );
+            Ok(res)
+        }
+        Ok(Cw20HookMsg::StakeVotingTokensFor { staker }) => {
+            let api = deps.api;
+            let staker = api.addr_validate(&staker)?;
+            let mut res = stake_voting_tokens(deps, &env, staker.clone(), cw20_msg.amount)?;
+
+            // distribution mirrors the stake of the staker, not of the sender
+            if config.distribution_contract != CanonicalAddr::from(vec![]) {
+                res = res.add_message(
+                    Cw20ReceiveMsg {
+                        sender: staker.to_string(),
+                        amount: cw20_msg.amount,
+                        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {})?,
+                    }
+                    .into_cosmos_msg(
+                        api.addr_humanize(&config.distribution_contract)?
+                            .to_string(),
+                    )?,
+                );
+            }
 
             Ok(res)
         }
@@ -187,6 +278,7 @@ pub fn receive_cw20(
             description,
             link,
             execute_msgs,
+            category,
         }) => create_poll(
             deps,
             env,
@@ -196,6 +288,7 @@ pub fn receive_cw20(
             description,
             link,
             execute_msgs,
+            category,
         ),
         _ => Err(ContractError::DataShouldBeGiven {}),
     }
@@ -212,9 +305,16 @@ pub fn update_config(
     timelock_period: Option<u64>,
     proposal_deposit: Option<Uint128>,
     snapshot_period: Option<u64>,
+    veto_threshold: Option<Decimal>,
+    community_contract: Option<String>,
+    max_lock_period: Option<u64>,
+    raw_msg_threshold: Option<Decimal>,
+    categories: Option<Vec<PollCategoryParams>>,
+    expiration_period: Option<u64>,
+    cancel_refund_ratio: Option<Decimal>,
 ) -> Result<Response, ContractError> {
     let api = deps.api;
-    config_store(deps.storage).update(|mut config| {
+    CONFIG.update(deps.storage, |mut config| {
         if config.owner != api.addr_canonicalize(info.sender.as_str())? {
             return Err(ContractError::Unauthorized {});
         }
@@ -247,12 +347,80 @@ pub fn update_config(
             config.snapshot_period = period;
         }
 
+        if let Some(veto_threshold) = veto_threshold {
+            validate_veto_threshold(veto_threshold)?;
+            config.veto_threshold = veto_threshold;
+        }
+
+        if let Some(community_contract) = community_contract {
+            config.community_contract = api.addr_canonicalize(&community_contract)?;
+        }
+
+        if let Some(max_lock_period) = max_lock_period {
+            config.max_lock_period = max_lock_period;
+        }
+
+        if let Some(raw_msg_threshold) = raw_msg_threshold {
+            config.raw_msg_threshold = raw_msg_threshold;
+        }
+        validate_raw_msg_threshold(config.raw_msg_threshold, config.threshold)?;
+
+        if let Some(categories) = categories {
+            validate_categories(&categories)?;
+            for params in categories {
+                config
+                    .categories
+                    .retain(|current| current.category != params.category);
+                config.categories.push(params);
+            }
+        }
+
+        if let Some(expiration_period) = expiration_period {
+            config.expiration_period = expiration_period;
+        }
+
+        if let Some(cancel_refund_ratio) = cancel_refund_ratio {
+            validate_cancel_refund_ratio(cancel_refund_ratio)?;
+            config.cancel_refund_ratio = cancel_refund_ratio;
+        }
+
         Ok(config)
     })?;
 
     Ok(Response::new().add_attributes(vec![("action", "update_config")]))
 }
 
+/// The guardian is only changed by governance itself, through a poll
+pub fn update_guardian(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    guardian: Option<String>,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let guardian = match guardian {
+        Some(guardian) => deps.api.addr_validate(&guardian)?.to_string(),
+        None => String::new(),
+    };
+    let guardian_raw = if guardian.is_empty() {
+        CanonicalAddr::from(vec![])
+    } else {
+        deps.api.addr_canonicalize(&guardian)?
+    };
+    CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
+        config.guardian = guardian_raw;
+        Ok(config)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_guardian"),
+        ("guardian", guardian.as_str()),
+    ]))
+}
+
 /// validate_title returns an error if the title is invalid
 fn validate_title(title: &str) -> StdResult<()> {
     if title.len() < MIN_TITLE_LENGTH {
@@ -310,6 +478,56 @@ fn validate_threshold(threshold: Decimal) -> StdResult<()> {
     }
 }
 
+/// validate_raw_msg_threshold returns an error if the raw message threshold is invalid
+/// (we require threshold-1)
+fn validate_raw_msg_threshold(raw_msg_threshold: Decimal, threshold: Decimal) -> StdResult<()> {
+    if raw_msg_threshold > Decimal::one() || raw_msg_threshold < threshold {
+        Err(StdError::generic_err(
+            "raw_msg_threshold must be threshold to 1",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// validate_veto_threshold returns an error if the veto threshold is invalid
+/// (we require 0-1)
+fn validate_veto_threshold(veto_threshold: Decimal) -> StdResult<()> {
+    if veto_threshold > Decimal::one() {
+        Err(StdError::generic_err("veto_threshold must be 0 to 1"))
+    } else {
+        Ok(())
+    }
+}
+
+/// validate_cancel_refund_ratio returns an error if the ratio is invalid
+/// (we require 0-1)
+fn validate_cancel_refund_ratio(ratio: Decimal) -> StdResult<()> {
+    if ratio > Decimal::one() {
+        Err(StdError::generic_err("cancel_refund_ratio must be 0 to 1"))
+    } else {
+        Ok(())
+    }
+}
+
+/// validate_categories returns an error if the parameters of a category are invalid,
+/// emergency polls skip the timelock so they need a supermajority
+fn validate_categories(categories: &[PollCategoryParams]) -> StdResult<()> {
+    for params in categories {
+        validate_quorum(params.quorum)?;
+        validate_threshold(params.threshold)?;
+        if params.category == PollCategory::Emergency
+            && params.threshold < Decimal::from_ratio(2u128, 3u128)
+        {
+            return Err(StdError::generic_err(
+                "emergency threshold must be a supermajority",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 /// create a new poll
 pub fn create_poll(
@@ -321,19 +539,32 @@ pub fn create_poll(
     description: String,
     link: Option<String>,
     execute_msgs: Option<Vec<PollExecuteMsg>>,
+    category: Option<PollCategory>,
 ) -> Result<Response, ContractError> {
     validate_title(&title)?;
     validate_description(&description)?;
     validate_link(&link)?;
+    let category = poll_category(category, execute_msgs.as_deref().unwrap_or_default())?;
+
+    let config: Config = CONFIG.load(deps.storage)?;
+    if category == PollCategory::Emergency
+        && !config
+            .categories
+            .iter()
+            .any(|params| params.category == PollCategory::Emergency)
+    {
+        return Err(ContractError::EmergencyPollsDisabled {});
+    }
 
-    let config: Config = config_store(deps.storage).load()?;
-    if deposit_amount < config.proposal_deposit {
+    let raw_msgs = execute_msgs.iter().flatten().any(|msg| msg.action.is_raw());
+    let params = config.execution_params(Some(category), raw_msgs);
+    if deposit_amount < params.proposal_deposit {
         return Err(ContractError::InsufficientProposalDeposit(
-            config.proposal_deposit.u128(),
+            params.proposal_deposit.u128(),
         ));
     }
 
-    let mut state: State = state_store(deps.storage).load()?;
+    let mut state: State = STATE.load(deps.storage)?;
     let poll_id = state.poll_count + 1;
 
     // Increase poll count & total deposit amount
@@ -343,18 +574,42 @@ pub fn create_poll(
     let mut data_list: Vec<ExecuteData> = vec![];
     let all_execute_data = if let Some(exe_msgs) = execute_msgs {
         for msgs in exe_msgs {
-            let execute_data = ExecuteData {
-                order: msgs.order,
-                contract: deps.api.addr_canonicalize(&msgs.contract)?,
-                msg: msgs.msg,
-            };
-            data_list.push(execute_data)
+            data_list.push(build_execute_data(deps.api, msgs.order, msgs.action)?)
         }
         Some(data_list)
     } else {
         None
     };
 
+    // voting weights and the quorum are taken before the poll started,
+    // so stake added afterwards can not vote on it
+    let vote_escrow = config.max_lock_period != 0;
+    let snapshot_share = if vote_escrow {
+        read_ve_point(deps.storage, VE_TOTAL, env.block.height)?.weight(env.block.height)?
+    } else {
+        read_total_share_snapshot(deps.storage, env.block.height, state.total_share)?
+    };
+    let staked_amount = if snapshot_share.is_zero() {
+        Uint128::zero()
+    } else {
+        let total_balance = query_token_balance(
+            &deps.querier,
+            deps.api.addr_humanize(&config.token_contract)?,
+            deps.api.addr_humanize(&state.contract_addr)?,
+        )?
+        .checked_sub(state.total_deposit)?;
+        if vote_escrow {
+            weight_to_balance(
+                snapshot_share,
+                total_balance,
+                state.total_share,
+                config.max_lock_period,
+            )
+        } else {
+            share_to_balance(snapshot_share, total_balance, state.total_share)
+        }
+    };
+
     let sender_address_raw = deps.api.addr_canonicalize(&proposer)?;
     let new_poll = Poll {
         id: poll_id,
@@ -362,21 +617,25 @@ pub fn create_poll(
         status: PollStatus::InProgress,
         yes_votes: Uint128::zero(),
         no_votes: Uint128::zero(),
-        end_height: env.block.height + config.voting_period,
+        abstain_votes: Uint128::zero(),
+        veto_votes: Uint128::zero(),
+        end_height: env.block.height + params.voting_period,
         title,
         description,
         link,
         execute_data: all_execute_data,
         deposit_amount,
         total_balance_at_end_poll: None,
-        staked_amount: None,
+        staked_amount: Some(staked_amount),
+        start_height: env.block.height,
+        snapshot_share: Some(snapshot_share),
+        vote_escrow,
+        category: Some(category),
     };
 
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &new_poll)?;
-    poll_indexer_store(deps.storage, &PollStatus::InProgress)
-        .save(&poll_id.to_be_bytes(), &true)?;
+    polls().save(deps.storage, U64Key::new(poll_id), &new_poll)?;
 
-    state_store(deps.storage).save(&state)?;
+    STATE.save(deps.storage, &state)?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "create_poll"),
@@ -396,7 +655,7 @@ pub fn create_poll(
  * Ends a poll.
  */
 pub fn end_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, ContractError> {
-    let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
+    let mut a_poll: Poll = polls().load(deps.storage, U64Key::new(poll_id))?;
 
     if a_poll.status != PollStatus::InProgress {
         return Err(ContractError::PollNotInProgress {});
@@ -408,19 +667,27 @@ pub fn end_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, Contr
 
     let no = a_poll.no_votes.u128();
     let yes = a_poll.yes_votes.u128();
+    let abstain = a_poll.abstain_votes.u128();
+    let veto = a_poll.veto_votes.u128();
 
-    let tallied_weight = yes + no;
+    // abstain votes only count toward the quorum
+    let tallied_weight = yes + no + abstain + veto;
+    let threshold_weight = yes + no + veto;
 
     let mut poll_status = PollStatus::Rejected;
     let mut rejected_reason = "";
     let mut passed = false;
 
     let mut messages: Vec<CosmosMsg> = vec![];
-    let config: Config = config_read(deps.storage).load()?;
-    let mut state: State = state_read(deps.storage).load()?;
+    let config: Config = CONFIG.load(deps.storage)?;
+    let params = config.poll_params(&a_poll);
+    let mut state: State = STATE.load(deps.storage)?;
 
     let (quorum, staked_weight) = if state.total_share.u128() == 0 {
         (Decimal::zero(), Uint128::zero())
+    } else if a_poll.staked_amount == Some(Uint128::zero()) {
+        // nothing was staked when the poll started
+        (Decimal::zero(), Uint128::zero())
     } else if let Some(staked_amount) = a_poll.staked_amount {
         (
             Decimal::from_ratio(tallied_weight, staked_amount),
@@ -440,12 +707,39 @@ pub fn end_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, Contr
         )
     };
 
-    if tallied_weight == 0 || quorum < config.quorum {
+    if tallied_weight == 0 || quorum < params.quorum {
         // Quorum: More than quorum of the total staked tokens at the end of the voting
         // period need to have participated in the vote.
         rejected_reason = "Quorum not reached";
+    } else if Decimal::from_ratio(veto, tallied_weight) > config.veto_threshold {
+        // Veto: the deposit is slashed to the community pool instead of refunded,
+        // it stays with the stakers while no community contract is registered
+        rejected_reason = "Vetoed";
+
+        if !a_poll.deposit_amount.is_zero()
+            && config.community_contract != CanonicalAddr::from(vec![])
+        {
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: deps.api.addr_humanize(&config.token_contract)?.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: deps
+                        .api
+                        .addr_humanize(&config.community_contract)?
+                        .to_string(),
+                    amount: a_poll.deposit_amount,
+                })?,
+            }))
+        }
     } else {
-        if Decimal::from_ratio(yes, tallied_weight) > config.threshold {
+        // raw messages can do anything, so they need the higher of the two thresholds
+        let threshold = if has_raw_msgs(&a_poll.execute_data) {
+            std::cmp::max(params.threshold, config.raw_msg_threshold)
+        } else {
+            params.threshold
+        };
+
+        if threshold_weight != 0 && Decimal::from_ratio(yes, threshold_weight) > threshold {
             //Threshold: More than 50% of the tokens that participated in the vote
             // (after excluding “Abstain” votes) need to have voted in favor of the proposal (“Yes”).
             poll_status = PollStatus::Passed;
@@ -469,16 +763,14 @@ pub fn end_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, Contr
 
     // Decrease total deposit amount
     state.total_deposit = state.total_deposit.checked_sub(a_poll.deposit_amount)?;
-    state_store(deps.storage).save(&state)?;
+    STATE.save(deps.storage, &state)?;
 
     // Update poll indexer
-    poll_indexer_store(deps.storage, &PollStatus::InProgress).remove(&a_poll.id.to_be_bytes());
-    poll_indexer_store(deps.storage, &poll_status).save(&a_poll.id.to_be_bytes(), &true)?;
 
     // Update poll status
     a_poll.status = poll_status;
     a_poll.total_balance_at_end_poll = Some(staked_weight);
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
 
     Ok(Response::new().add_messages(messages).add_attributes(vec![
         ("action", "end_poll"),
@@ -492,27 +784,235 @@ pub fn end_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, Contr
  * Execute a msgs of passed poll as one submsg to catch failures
  */
 pub fn execute_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, ContractError> {
-    let config: Config = config_read(deps.storage).load()?;
-    let a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
+    let config: Config = CONFIG.load(deps.storage)?;
+    let a_poll: Poll = polls().load(deps.storage, U64Key::new(poll_id))?;
 
     if a_poll.status != PollStatus::Passed {
         return Err(ContractError::PollNotPassed {});
     }
 
-    if a_poll.end_height + config.timelock_period > env.block.height {
+    // emergency polls are executed as soon as they pass
+    let emergency = a_poll.category == Some(PollCategory::Emergency);
+    if config.timelock_end(&a_poll) > env.block.height {
         return Err(ContractError::TimelockNotExpired {});
     }
 
-    store_tmp_poll_id(deps.storage, a_poll.id)?;
+    if matches!(config.expiration_height(&a_poll), Some(height) if height < env.block.height) {
+        return Err(ContractError::PollExpired {});
+    }
 
-    Ok(Response::new().add_submessage(SubMsg::reply_on_error(
+    TMP_POLL_ID.save(deps.storage, &a_poll.id)?;
+
+    let response = Response::new().add_submessage(SubMsg::reply_on_error(
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: env.contract.address.to_string(),
             msg: to_binary(&ExecuteMsg::ExecutePollMsgs { poll_id })?,
             funds: vec![],
         }),
         POLL_EXECUTE_REPLY_ID,
-    )))
+    ));
+    Ok(if emergency {
+        response.add_attributes(vec![
+            ("action", "execute_emergency_poll"),
+            ("poll_id", poll_id.to_string().as_str()),
+        ])
+    } else {
+        response
+    })
+}
+
+/*
+ * Veto a passed poll before its timelock expires
+ */
+pub fn veto_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.guardian == CanonicalAddr::from(vec![])
+        || deps.api.addr_canonicalize(info.sender.as_str())? != config.guardian
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut a_poll: Poll = polls().load(deps.storage, U64Key::new(poll_id))?;
+    if a_poll.status != PollStatus::Passed {
+        return Err(ContractError::PollNotPassed {});
+    }
+
+    if config.timelock_end(&a_poll) <= env.block.height {
+        return Err(ContractError::TimelockExpired {});
+    }
+
+    a_poll.status = PollStatus::Vetoed;
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "veto_poll"),
+        ("poll_id", poll_id.to_string().as_str()),
+        ("guardian", info.sender.as_str()),
+    ]))
+}
+
+/*
+ * Cancel a poll nobody has voted on, part of the deposit is refunded
+ * and the rest stays with the stakers
+ */
+pub fn cancel_poll(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_id: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let mut a_poll = load_unvoted_poll(deps.as_ref(), &info, poll_id)?;
+
+    let refund_amount = a_poll.deposit_amount * config.cancel_refund_ratio;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !refund_amount.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.token_contract)?.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.to_string(),
+                amount: refund_amount,
+            })?,
+        }))
+    }
+
+    let mut state: State = STATE.load(deps.storage)?;
+    state.total_deposit = state.total_deposit.checked_sub(a_poll.deposit_amount)?;
+    STATE.save(deps.storage, &state)?;
+
+    a_poll.status = PollStatus::Cancelled;
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "cancel_poll"),
+        ("poll_id", poll_id.to_string().as_str()),
+        ("refund_amount", refund_amount.to_string().as_str()),
+    ]))
+}
+
+/*
+ * Change the text of a poll nobody has voted on
+ */
+pub fn amend_poll(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_id: u64,
+    title: Option<String>,
+    description: Option<String>,
+    link: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut a_poll = load_unvoted_poll(deps.as_ref(), &info, poll_id)?;
+
+    if let Some(title) = title {
+        validate_title(&title)?;
+        a_poll.title = title;
+    }
+
+    if let Some(description) = description {
+        validate_description(&description)?;
+        a_poll.description = description;
+    }
+
+    if let Some(link) = link {
+        let link = Some(link);
+        validate_link(&link)?;
+        a_poll.link = link;
+    }
+
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "amend_poll"),
+        ("poll_id", poll_id.to_string().as_str()),
+    ]))
+}
+
+// the in-progress poll of the sender without any votes
+fn load_unvoted_poll(deps: Deps, info: &MessageInfo, poll_id: u64) -> Result<Poll, ContractError> {
+    let a_poll: Poll = match polls().may_load(deps.storage, U64Key::new(poll_id))? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound {}),
+    };
+
+    if a_poll.creator != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+
+    // a vote of zero leaves the tally untouched, so the voters are looked up instead
+    if POLL_VOTERS
+        .prefix(U64Key::new(poll_id))
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+    {
+        return Err(ContractError::PollHasVotes {});
+    }
+
+    Ok(a_poll)
+}
+
+/*
+ * Expire a passed poll that was not executed in time
+ */
+pub fn expire_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let mut a_poll: Poll = polls().load(deps.storage, U64Key::new(poll_id))?;
+
+    if a_poll.status != PollStatus::Passed {
+        return Err(ContractError::PollNotPassed {});
+    }
+
+    match config.expiration_height(&a_poll) {
+        Some(height) if height < env.block.height => {}
+        _ => return Err(ContractError::PollNotExpired {}),
+    }
+
+    a_poll.status = PollStatus::Expired;
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "expire_poll"),
+        ("poll_id", poll_id.to_string().as_str()),
+    ]))
+}
+
+/*
+ * Remove the voter records of a finished poll, they are no longer queried
+ */
+pub fn prune_poll_voters(
+    deps: DepsMut,
+    poll_id: u64,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let a_poll: Poll = match polls().may_load(deps.storage, U64Key::new(poll_id))? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound {}),
+    };
+
+    if a_poll.status == PollStatus::InProgress {
+        return Err(ContractError::PollNotEnded {});
+    }
+
+    let voters = read_poll_voters(deps.storage, poll_id, None, limit, Some(OrderBy::Asc))?;
+    for (voter, _) in voters.iter() {
+        POLL_VOTERS.remove(deps.storage, (U64Key::new(poll_id), voter.as_slice()));
+        POLL_DELEGATES.remove(deps.storage, (U64Key::new(poll_id), voter.as_slice()));
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "prune_poll_voters"),
+        ("poll_id", poll_id.to_string().as_str()),
+        ("pruned", voters.len().to_string().as_str()),
+    ]))
 }
 
 /*
@@ -528,24 +1028,17 @@ pub fn execute_poll_messages(
         return Err(ContractError::Unauthorized {});
     }
 
-    let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
-
-    poll_indexer_store(deps.storage, &PollStatus::Passed).remove(&poll_id.to_be_bytes());
-    poll_indexer_store(deps.storage, &PollStatus::Executed).save(&poll_id.to_be_bytes(), &true)?;
+    let mut a_poll: Poll = polls().load(deps.storage, U64Key::new(poll_id))?;
 
     a_poll.status = PollStatus::Executed;
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
 
     let mut messages: Vec<CosmosMsg> = vec![];
     if let Some(all_msgs) = a_poll.execute_data {
         let mut msgs = all_msgs;
         msgs.sort();
         for msg in msgs {
-            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: deps.api.addr_humanize(&msg.contract)?.to_string(),
-                msg: msg.msg,
-                funds: vec![],
-            }));
+            messages.push(execute_data_to_msg(deps.api, msg)?);
         }
     }
 
@@ -559,13 +1052,10 @@ pub fn execute_poll_messages(
  * Set the status of a poll to Failed if execute_poll fails
  */
 pub fn fail_poll(deps: DepsMut, poll_id: u64) -> Result<Response, ContractError> {
-    let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
-
-    poll_indexer_store(deps.storage, &PollStatus::Passed).remove(&poll_id.to_be_bytes());
-    poll_indexer_store(deps.storage, &PollStatus::Failed).save(&poll_id.to_be_bytes(), &true)?;
+    let mut a_poll: Poll = polls().load(deps.storage, U64Key::new(poll_id))?;
 
     a_poll.status = PollStatus::Failed;
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "fail_poll"),
@@ -575,8 +1065,8 @@ pub fn fail_poll(deps: DepsMut, poll_id: u64) -> Result<Response, ContractError>
 
 /// SnapshotPoll is used to take a snapshot of the staked amount for quorum calculation
 pub fn snapshot_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response, ContractError> {
-    let config: Config = config_read(deps.storage).load()?;
-    let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
+    let config: Config = CONFIG.load(deps.storage)?;
+    let mut a_poll: Poll = polls().load(deps.storage, U64Key::new(poll_id))?;
 
     if a_poll.status != PollStatus::InProgress {
         return Err(ContractError::PollNotInProgress {});
@@ -593,7 +1083,7 @@ pub fn snapshot_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response,
     }
 
     // store the current staked amount for quorum calculation
-    let state: State = state_store(deps.storage).load()?;
+    let state: State = STATE.load(deps.storage)?;
 
     let staked_amount = query_token_balance(
         &deps.querier,
@@ -604,7 +1094,7 @@ pub fn snapshot_poll(deps: DepsMut, env: Env, poll_id: u64) -> Result<Response,
 
     a_poll.staked_amount = Some(staked_amount);
 
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
 
     Ok(Response::new().add_attributes(vec![
         attr("action", "snapshot_poll"),
@@ -622,27 +1112,30 @@ pub fn cast_vote(
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     let sender_address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
-    let config = config_read(deps.storage).load()?;
-    let state = state_read(deps.storage).load()?;
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
     if poll_id == 0 || state.poll_count < poll_id {
         return Err(ContractError::PollNotFound {});
     }
 
-    let mut a_poll: Poll = poll_store(deps.storage).load(&poll_id.to_be_bytes())?;
+    let mut a_poll: Poll = polls().load(deps.storage, U64Key::new(poll_id))?;
     if a_poll.status != PollStatus::InProgress || env.block.height > a_poll.end_height {
         return Err(ContractError::PollNotInProgress {});
     }
 
     // Check the voter already has a vote on the poll
-    if poll_voter_read(deps.storage, poll_id)
-        .load(sender_address_raw.as_slice())
+    if POLL_VOTERS
+        .load(
+            deps.storage,
+            (U64Key::new(poll_id), sender_address_raw.as_slice()),
+        )
         .is_ok()
     {
         return Err(ContractError::AlreadyVoted {});
     }
 
     let key = &sender_address_raw.as_slice();
-    let mut token_manager = bank_read(deps.storage).may_load(key)?.unwrap_or_default();
+    let mut token_manager = BANK.may_load(deps.storage, key)?.unwrap_or_default();
 
     // convert share to amount
     let total_share = state.total_share;
@@ -653,32 +1146,91 @@ pub fn cast_vote(
     )?
     .checked_sub(state.total_deposit)?;
 
-    if token_manager
-        .share
-        .multiply_ratio(total_balance, total_share)
-        < amount
-    {
+    // the weights are taken before the poll started, unless it was created without a snapshot
+    let (snapshot, weight_balance, weight_share) = match a_poll.snapshot_share {
+        Some(snapshot_share) => (
+            read_staker_snapshot(deps.storage, key, a_poll.start_height)?,
+            a_poll.staked_amount.unwrap_or_default(),
+            snapshot_share,
+        ),
+        None => (
+            StakerSnapshot::from(&token_manager),
+            total_balance,
+            total_share,
+        ),
+    };
+    let (own_weight, delegated_weight) = if a_poll.vote_escrow {
+        let start_height = a_poll.start_height;
+        (
+            match &snapshot.lock {
+                Some(lock) => lock.weight(start_height)?,
+                None => Uint128::zero(),
+            },
+            read_ve_point(deps.storage, key, start_height)?.weight(start_height)?,
+        )
+    } else {
+        (snapshot.share, snapshot.delegated_share)
+    };
+    let own_balance = share_to_balance(own_weight, weight_balance, weight_share);
+
+    // delegated weight the delegators did not use themselves on this poll
+    let mut delegated_vote = POLL_DELEGATES
+        .may_load(deps.storage, (U64Key::new(poll_id), key))?
+        .unwrap_or_default();
+    let delegated_balance = share_to_balance(
+        delegated_weight
+            .checked_sub(delegated_vote.overridden_share)
+            .unwrap_or_default(),
+        weight_balance,
+        weight_share,
+    );
+
+    if own_balance + delegated_balance < amount {
         return Err(ContractError::InsufficientStaked {});
     }
 
+    // voting on its own takes the staker weight back from the delegate
+    override_delegated_vote(
+        deps.storage,
+        &mut a_poll,
+        snapshot.delegate.as_ref(),
+        own_weight,
+        own_balance,
+    )?;
+
     // update tally info
-    if VoteOption::Yes == vote {
-        a_poll.yes_votes += amount;
-    } else {
-        a_poll.no_votes += amount;
+    *a_poll.votes_mut(&vote) += amount;
+
+    // only the own part of the vote locks the stake the voter still has
+    let own_amount = std::cmp::min(amount, own_balance);
+    let delegated_amount = amount.checked_sub(own_amount)?;
+    if !delegated_amount.is_zero() {
+        delegated_vote.balance = delegated_amount;
+        POLL_DELEGATES.save(deps.storage, (U64Key::new(poll_id), key), &delegated_vote)?;
     }
 
     let vote_info = VoterInfo {
         vote,
         balance: amount,
     };
-    token_manager
-        .locked_balance
-        .push((poll_id, vote_info.clone()));
-    bank_store(deps.storage).save(key, &token_manager)?;
+    token_manager.locked_balance.push((
+        poll_id,
+        VoterInfo {
+            vote: vote_info.vote.clone(),
+            balance: std::cmp::min(
+                own_amount,
+                share_to_balance(token_manager.share, total_balance, total_share),
+            ),
+        },
+    ));
+    BANK.save(deps.storage, key, &token_manager)?;
 
     // store poll voter && and update poll data
-    poll_voter_store(deps.storage, poll_id).save(sender_address_raw.as_slice(), &vote_info)?;
+    POLL_VOTERS.save(
+        deps.storage,
+        (U64Key::new(poll_id), sender_address_raw.as_slice()),
+        &vote_info,
+    )?;
 
     // processing snapshot
     let time_to_end = a_poll.end_height - env.block.height;
@@ -687,7 +1239,7 @@ pub fn cast_vote(
         a_poll.staked_amount = Some(total_balance);
     }
 
-    poll_store(deps.storage).save(&poll_id.to_be_bytes(), &a_poll)?;
+    polls().save(deps.storage, U64Key::new(poll_id), &a_poll)?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "cast_vote"),
@@ -699,7 +1251,7 @@ pub fn cast_vote(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
         QueryMsg::Config {} => Ok(to_binary(&query_config(deps)?)?),
         QueryMsg::State {} => Ok(to_binary(&query_state(deps)?)?),
@@ -729,29 +1281,105 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             limit,
             order_by,
         )?)?),
+        QueryMsg::DelegatePower { delegate } => {
+            Ok(to_binary(&query_delegate_power(deps, env, delegate)?)?)
+        }
+        QueryMsg::Delegators {
+            delegate,
+            start_after,
+            limit,
+        } => Ok(to_binary(&query_delegators(
+            deps,
+            delegate,
+            start_after,
+            limit,
+        )?)?),
+        QueryMsg::VotingEscrow { address, height } => Ok(to_binary(&query_voting_escrow(
+            deps, env, address, height,
+        )?)?),
+        QueryMsg::TotalVotingEscrow { height } => {
+            Ok(to_binary(&query_total_voting_escrow(deps, env, height)?)?)
+        }
+        QueryMsg::SimulatePoll { poll_id } => {
+            Ok(to_binary(&query_simulate_poll(deps, env, poll_id)?)?)
+        }
+        QueryMsg::SimulateExecuteMsgs { msgs } => {
+            Ok(to_binary(&query_simulate_execute_msgs(deps, env, msgs)?)?)
+        }
+        QueryMsg::PollsByCreator {
+            creator,
+            start_after,
+            limit,
+            order_by,
+        } => Ok(to_binary(&query_polls_by_creator(
+            deps,
+            creator,
+            start_after,
+            limit,
+            order_by,
+        )?)?),
+        QueryMsg::PollsEndingSoon { blocks, limit } => Ok(to_binary(&query_polls_ending_soon(
+            deps, env, blocks, limit,
+        )?)?),
     }
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    // index the polls stored before the indexed map
+    let last_poll_id = migrate_polls(deps.storage, msg.start_after, msg.limit)?;
+
+    let mut response = Response::new().add_attribute("action", "migrate");
+    if let Some(poll_id) = last_poll_id {
+        response = response.add_attribute("last_poll_id", poll_id.to_string());
+    }
+    Ok(response)
+}
+
 fn query_config(deps: Deps) -> Result<ConfigResponse, ContractError> {
-    let config: Config = config_read(deps.storage).load()?;
+    let config: Config = CONFIG.load(deps.storage)?;
     Ok(ConfigResponse {
         owner: deps.api.addr_humanize(&config.owner)?.to_string(),
         token_contract: deps.api.addr_humanize(&config.token_contract)?.to_string(),
-        distribution_contract: deps
-            .api
-            .addr_humanize(&config.distribution_contract)?
-            .to_string(),
+        // empty until a distribution contract is registered
+        distribution_contract: if config.distribution_contract == CanonicalAddr::from(vec![]) {
+            String::new()
+        } else {
+            deps.api
+                .addr_humanize(&config.distribution_contract)?
+                .to_string()
+        },
         quorum: config.quorum,
         threshold: config.threshold,
         voting_period: config.voting_period,
         timelock_period: config.timelock_period,
         proposal_deposit: config.proposal_deposit,
         snapshot_period: config.snapshot_period,
+        veto_threshold: config.veto_threshold,
+        // empty until a community contract is registered
+        community_contract: if config.community_contract == CanonicalAddr::from(vec![]) {
+            String::new()
+        } else {
+            deps.api
+                .addr_humanize(&config.community_contract)?
+                .to_string()
+        },
+        max_lock_period: config.max_lock_period,
+        raw_msg_threshold: config.raw_msg_threshold,
+        categories: config.categories,
+        // empty when there is no guardian
+        guardian: if config.guardian == CanonicalAddr::from(vec![]) {
+            String::new()
+        } else {
+            deps.api.addr_humanize(&config.guardian)?.to_string()
+        },
+        expiration_period: config.expiration_period,
+        cancel_refund_ratio: config.cancel_refund_ratio,
     })
 }
 
 fn query_state(deps: Deps) -> Result<StateResponse, ContractError> {
-    let state: State = state_read(deps.storage).load()?;
+    let state: State = STATE.load(deps.storage)?;
     Ok(StateResponse {
         poll_count: state.poll_count,
         total_share: state.total_share,
@@ -760,7 +1388,7 @@ fn query_state(deps: Deps) -> Result<StateResponse, ContractError> {
 }
 
 fn query_poll(deps: Deps, poll_id: u64) -> Result<PollResponse, ContractError> {
-    let poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+    let poll = match polls().may_load(deps.storage, U64Key::new(poll_id))? {
         Some(poll) => Some(poll),
         None => return Err(ContractError::PollNotFound {}),
     }
@@ -772,6 +1400,7 @@ fn query_poll(deps: Deps, poll_id: u64) -> Result<PollResponse, ContractError> {
         id: poll.id,
         creator: deps.api.addr_humanize(&poll.creator)?.to_string(),
         status: poll.status,
+        category: poll.category,
         end_height: poll.end_height,
         title: poll.title,
         description: poll.description,
@@ -779,12 +1408,7 @@ fn query_poll(deps: Deps, poll_id: u64) -> Result<PollResponse, ContractError> {
         deposit_amount: poll.deposit_amount,
         execute_data: if let Some(exe_msgs) = poll.execute_data.clone() {
             for msg in exe_msgs {
-                let execute_data = PollExecuteMsg {
-                    order: msg.order,
-                    contract: deps.api.addr_humanize(&msg.contract)?.to_string(),
-                    msg: msg.msg,
-                };
-                data_list.push(execute_data)
+                data_list.push(execute_data_to_poll_msg(deps.api, msg)?)
             }
             Some(data_list)
         } else {
@@ -792,6 +1416,8 @@ fn query_poll(deps: Deps, poll_id: u64) -> Result<PollResponse, ContractError> {
         },
         yes_votes: poll.yes_votes,
         no_votes: poll.no_votes,
+        abstain_votes: poll.abstain_votes,
+        veto_votes: poll.veto_votes,
         staked_amount: poll.staked_amount,
         total_balance_at_end_poll: poll.total_balance_at_end_poll,
     })
@@ -805,7 +1431,37 @@ fn query_polls(
     order_by: Option<OrderBy>,
 ) -> Result<PollsResponse, ContractError> {
     let polls = read_polls(deps.storage, filter, start_after, limit, order_by)?;
+    polls_response(deps, polls)
+}
+
+fn query_polls_by_creator(
+    deps: Deps,
+    creator: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    order_by: Option<OrderBy>,
+) -> Result<PollsResponse, ContractError> {
+    let creator_raw = deps.api.addr_canonicalize(&creator)?;
+    let polls = read_polls_by_creator(deps.storage, &creator_raw, start_after, limit, order_by)?;
+    polls_response(deps, polls)
+}
+
+fn query_polls_ending_soon(
+    deps: Deps,
+    env: Env,
+    blocks: u64,
+    limit: Option<u32>,
+) -> Result<PollsResponse, ContractError> {
+    let polls = read_polls_ending(
+        deps.storage,
+        env.block.height,
+        env.block.height.saturating_add(blocks),
+        limit,
+    )?;
+    polls_response(deps, polls)
+}
 
+fn polls_response(deps: Deps, polls: Vec<Poll>) -> Result<PollsResponse, ContractError> {
     let poll_responses: StdResult<Vec<PollResponse>> = polls
         .iter()
         .map(|poll| {
@@ -813,6 +1469,7 @@ fn query_polls(
                 id: poll.id,
                 creator: deps.api.addr_humanize(&poll.creator)?.to_string(),
                 status: poll.status.clone(),
+                category: poll.category,
                 end_height: poll.end_height,
                 title: poll.title.to_string(),
                 description: poll.description.to_string(),
@@ -822,12 +1479,7 @@ fn query_polls(
                     let mut data_list: Vec<PollExecuteMsg> = vec![];
 
                     for msg in exe_msgs {
-                        let execute_data = PollExecuteMsg {
-                            order: msg.order,
-                            contract: deps.api.addr_humanize(&msg.contract)?.to_string(),
-                            msg: msg.msg,
-                        };
-                        data_list.push(execute_data)
+                        data_list.push(execute_data_to_poll_msg(deps.api, msg)?)
                     }
                     Some(data_list)
                 } else {
@@ -835,6 +1487,8 @@ fn query_polls(
                 },
                 yes_votes: poll.yes_votes,
                 no_votes: poll.no_votes,
+                abstain_votes: poll.abstain_votes,
+                veto_votes: poll.veto_votes,
                 staked_amount: poll.staked_amount,
                 total_balance_at_end_poll: poll.total_balance_at_end_poll,
             })
@@ -853,7 +1507,7 @@ fn query_voters(
     limit: Option<u32>,
     order_by: Option<OrderBy>,
 ) -> Result<VotersResponse, ContractError> {
-    let poll: Poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+    let poll: Poll = match polls().may_load(deps.storage, U64Key::new(poll_id))? {
         Some(poll) => Some(poll),
         None => return Err(ContractError::PollNotFound {}),
     }
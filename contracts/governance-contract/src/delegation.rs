@@ -0,0 +1,306 @@
+use crate::error::ContractError;
+use crate::state::{
+    polls, read_delegators, read_ve_point, store_token_manager, update_ve_point, Config, Poll,
+    State, TokenManager, BANK, CONFIG, DELEGATORS, POLL_DELEGATES, POLL_VOTERS, STATE,
+};
+use crate::voting_escrow::weight_to_balance;
+
+use astroport::querier::query_token_balance;
+use cosmwasm_std::{
+    CanonicalAddr, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint128,
+};
+use cw_storage_plus::U64Key;
+use terra_vegas::gov::{
+    DelegatePowerResponse, DelegatorsResponse, DelegatorsResponseItem, PollStatus,
+};
+
+pub fn delegate_voting_power(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    delegate: String,
+) -> Result<Response, ContractError> {
+    let delegator_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let delegate_raw = deps.api.addr_canonicalize(&delegate)?;
+    if delegator_raw == delegate_raw {
+        return Err(ContractError::CannotDelegateToSelf {});
+    }
+
+    let key = delegator_raw.as_slice();
+    let mut token_manager = match BANK.may_load(deps.storage, key)? {
+        Some(token_manager) if !token_manager.share.is_zero() => token_manager,
+        _ => return Err(ContractError::NothingStaked {}),
+    };
+    if delegated_vote_active(deps.storage, &token_manager, &delegator_raw)? {
+        return Err(ContractError::DelegationLocked {});
+    }
+
+    let height = env.block.height;
+    let lock = token_manager.lock.as_ref();
+    if let Some(previous) = token_manager.delegate.take() {
+        remove_delegated_share(deps.storage, height, &previous, token_manager.share)?;
+        update_ve_point(deps.storage, previous.as_slice(), height, lock, None)?;
+        DELEGATORS.remove(deps.storage, (previous.as_slice(), key));
+    }
+    add_delegated_share(deps.storage, height, &delegate_raw, token_manager.share)?;
+    update_ve_point(deps.storage, delegate_raw.as_slice(), height, None, lock)?;
+    DELEGATORS.save(deps.storage, (delegate_raw.as_slice(), key), &true)?;
+
+    token_manager.delegate = Some(delegate_raw);
+    store_token_manager(deps.storage, height, key, &token_manager)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "delegate"),
+        ("delegator", info.sender.as_str()),
+        ("delegate", delegate.as_str()),
+        ("share", token_manager.share.to_string().as_str()),
+    ]))
+}
+
+pub fn undelegate_voting_power(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let delegator_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let key = delegator_raw.as_slice();
+
+    let mut token_manager = BANK.may_load(deps.storage, key)?.unwrap_or_default();
+    if token_manager.delegate.is_none() {
+        return Err(ContractError::NotDelegated {});
+    }
+    if delegated_vote_active(deps.storage, &token_manager, &delegator_raw)? {
+        return Err(ContractError::DelegationLocked {});
+    }
+
+    let delegate = token_manager.delegate.take().unwrap();
+    remove_delegated_share(
+        deps.storage,
+        env.block.height,
+        &delegate,
+        token_manager.share,
+    )?;
+    update_ve_point(
+        deps.storage,
+        delegate.as_slice(),
+        env.block.height,
+        token_manager.lock.as_ref(),
+        None,
+    )?;
+    DELEGATORS.remove(deps.storage, (delegate.as_slice(), key));
+    store_token_manager(deps.storage, env.block.height, key, &token_manager)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "undelegate"),
+        ("delegator", info.sender.as_str()),
+        (
+            "delegate",
+            deps.api.addr_humanize(&delegate)?.to_string().as_str(),
+        ),
+    ]))
+}
+
+pub fn add_delegated_share(
+    storage: &mut dyn Storage,
+    height: u64,
+    delegate: &CanonicalAddr,
+    share: Uint128,
+) -> StdResult<()> {
+    let key = delegate.as_slice();
+    let mut token_manager = BANK.may_load(storage, key)?.unwrap_or_default();
+    token_manager.delegated_share += share;
+    store_token_manager(storage, height, key, &token_manager)
+}
+
+pub fn remove_delegated_share(
+    storage: &mut dyn Storage,
+    height: u64,
+    delegate: &CanonicalAddr,
+    share: Uint128,
+) -> StdResult<()> {
+    let key = delegate.as_slice();
+    let mut token_manager = BANK.may_load(storage, key)?.unwrap_or_default();
+    token_manager.delegated_share = token_manager.delegated_share.checked_sub(share)?;
+    store_token_manager(storage, height, key, &token_manager)
+}
+
+// polls the staker has voted on that are still in progress
+fn in_progress_votes(storage: &dyn Storage, token_manager: &TokenManager) -> StdResult<Vec<u64>> {
+    let mut poll_ids = vec![];
+    for (poll_id, _) in token_manager.locked_balance.iter() {
+        let poll: Poll = polls().load(storage, U64Key::new(*poll_id))?;
+        if poll.status == PollStatus::InProgress {
+            poll_ids.push(*poll_id);
+        }
+    }
+
+    Ok(poll_ids)
+}
+
+/// Returns true while the delegate of the staker has an in-progress vote counting
+/// the staker weight, the whole stake is locked until then
+pub fn delegated_vote_active(
+    storage: &dyn Storage,
+    token_manager: &TokenManager,
+    voter: &CanonicalAddr,
+) -> StdResult<bool> {
+    let delegate = match &token_manager.delegate {
+        Some(delegate) => delegate,
+        None => return Ok(false),
+    };
+
+    let delegate_manager = BANK
+        .may_load(storage, delegate.as_slice())?
+        .unwrap_or_default();
+    for poll_id in in_progress_votes(storage, &delegate_manager)? {
+        let delegated = POLL_DELEGATES
+            .may_load(storage, (U64Key::new(poll_id), delegate.as_slice()))?
+            .unwrap_or_default();
+        let overridden = POLL_VOTERS
+            .may_load(storage, (U64Key::new(poll_id), voter.as_slice()))?
+            .is_some();
+
+        if !delegated.balance.is_zero() && !overridden {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Takes the weight of a delegator voting on its own out of the vote of the delegate
+/// it had when the poll started
+pub fn override_delegated_vote(
+    storage: &mut dyn Storage,
+    poll: &mut Poll,
+    delegate: Option<&CanonicalAddr>,
+    weight: Uint128,
+    balance: Uint128,
+) -> StdResult<()> {
+    let delegate = match delegate {
+        Some(delegate) => delegate,
+        None => return Ok(()),
+    };
+
+    let mut delegated = POLL_DELEGATES
+        .may_load(storage, (U64Key::new(poll.id), delegate.as_slice()))?
+        .unwrap_or_default();
+    if let Some(mut vote_info) =
+        POLL_VOTERS.may_load(storage, (U64Key::new(poll.id), delegate.as_slice()))?
+    {
+        let deducted = std::cmp::min(balance, delegated.balance);
+        delegated.balance = delegated.balance.checked_sub(deducted)?;
+        vote_info.balance = vote_info.balance.checked_sub(deducted)?;
+        let votes = poll.votes_mut(&vote_info.vote);
+        *votes = votes.checked_sub(deducted)?;
+
+        POLL_VOTERS.save(
+            storage,
+            (U64Key::new(poll.id), delegate.as_slice()),
+            &vote_info,
+        )?;
+    }
+
+    delegated.overridden_share += weight;
+    POLL_DELEGATES.save(
+        storage,
+        (U64Key::new(poll.id), delegate.as_slice()),
+        &delegated,
+    )
+}
+
+pub fn share_to_balance(share: Uint128, total_balance: Uint128, total_share: Uint128) -> Uint128 {
+    if total_share.is_zero() {
+        Uint128::zero()
+    } else {
+        share.multiply_ratio(total_balance, total_share)
+    }
+}
+
+pub fn query_total_balance(deps: Deps, config: &Config, state: &State) -> StdResult<Uint128> {
+    Ok(query_token_balance(
+        &deps.querier,
+        deps.api.addr_humanize(&config.token_contract)?,
+        deps.api.addr_humanize(&state.contract_addr)?,
+    )?
+    .checked_sub(state.total_deposit)?)
+}
+
+pub fn query_delegate_power(
+    deps: Deps,
+    env: Env,
+    delegate: String,
+) -> StdResult<DelegatePowerResponse> {
+    let delegate_raw = deps.api.addr_canonicalize(&delegate)?;
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state: State = STATE.load(deps.storage)?;
+    let token_manager = BANK
+        .may_load(deps.storage, delegate_raw.as_slice())?
+        .unwrap_or_default();
+
+    let total_balance = query_total_balance(deps, &config, &state)?;
+    let balance = share_to_balance(token_manager.share, total_balance, state.total_share);
+    let delegated_balance = share_to_balance(
+        token_manager.delegated_share,
+        total_balance,
+        state.total_share,
+    );
+
+    // the locks weigh the votes once they are enabled
+    let voting_power = if config.max_lock_period == 0 {
+        balance + delegated_balance
+    } else {
+        let height = env.block.height + 1;
+        let own_weight = match &token_manager.lock {
+            Some(lock) => lock.weight(height)?,
+            None => Uint128::zero(),
+        };
+        let delegated_weight =
+            read_ve_point(deps.storage, delegate_raw.as_slice(), height)?.weight(height)?;
+        weight_to_balance(
+            own_weight + delegated_weight,
+            total_balance,
+            state.total_share,
+            config.max_lock_period,
+        )
+    };
+
+    Ok(DelegatePowerResponse {
+        delegate,
+        balance,
+        delegated_balance,
+        voting_power,
+    })
+}
+
+pub fn query_delegators(
+    deps: Deps,
+    delegate: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<DelegatorsResponse> {
+    let delegate_raw = deps.api.addr_canonicalize(&delegate)?;
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state: State = STATE.load(deps.storage)?;
+    let total_balance = query_total_balance(deps, &config, &state)?;
+
+    let start_after = start_after
+        .map(|addr| deps.api.addr_canonicalize(&addr))
+        .transpose()?;
+    let delegators = read_delegators(deps.storage, &delegate_raw, start_after, limit)?
+        .into_iter()
+        .map(|delegator| {
+            let share = BANK
+                .may_load(deps.storage, delegator.as_slice())?
+                .unwrap_or_default()
+                .share;
+            Ok(DelegatorsResponseItem {
+                delegator: deps.api.addr_humanize(&delegator)?.to_string(),
+                share,
+                balance: share_to_balance(share, total_balance, state.total_share),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(DelegatorsResponse { delegators })
+}
@@ -71,4 +71,52 @@ pub enum ContractError {
 
     #[error("No contract to registered")]
     EmptyContracts {},
+
+    #[error("Cannot delegate to yourself")]
+    CannotDelegateToSelf {},
+
+    #[error("Voting power is not delegated")]
+    NotDelegated {},
+
+    #[error("Delegated voting power is used in an in-progress poll")]
+    DelegationLocked {},
+
+    #[error("Locks are not enabled")]
+    LocksDisabled {},
+
+    #[error("Lock already exists")]
+    LockAlreadyExists {},
+
+    #[error("No lock found")]
+    NoLock {},
+
+    #[error("Lock has expired")]
+    LockExpired {},
+
+    #[error("Lock has not expired")]
+    LockNotExpired {},
+
+    #[error("Lock end must be after the current end and within the max lock period")]
+    InvalidLockEnd {},
+
+    #[error("Invalid poll action: {0}")]
+    InvalidPollAction(String),
+
+    #[error("Poll actions do not fit the {0} category")]
+    PollCategoryMismatch(String),
+
+    #[error("Emergency polls are not enabled")]
+    EmergencyPollsDisabled {},
+
+    #[error("Timelock period has expired")]
+    TimelockExpired {},
+
+    #[error("Poll has expired")]
+    PollExpired {},
+
+    #[error("Poll already has votes")]
+    PollHasVotes {},
+
+    #[error("Poll has not ended")]
+    PollNotEnded {},
 }
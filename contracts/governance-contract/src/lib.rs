@@ -1,8 +1,12 @@
 pub mod contract;
 
+mod action;
+mod delegation;
 mod error;
+mod simulation;
 mod staking;
 mod state;
+mod voting_escrow;
 
 #[cfg(test)]
 mod tests;
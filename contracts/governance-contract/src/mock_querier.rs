@@ -1,10 +1,12 @@
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, from_slice, to_binary, Coin, ContractResult, Empty, OwnedDeps, Querier,
+    from_binary, from_slice, to_binary, Binary, Coin, ContractResult, OwnedDeps, Querier,
     QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
 };
 use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg};
+use serde::Serialize;
 use std::collections::HashMap;
+use terra_cosmwasm::{ContractInfoResponse, TerraQuery, TerraQueryWrapper};
 
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
 /// this uses our CustomQuerier.
@@ -22,8 +24,13 @@ pub fn mock_dependencies(
 }
 
 pub struct WasmMockQuerier {
-    base: MockQuerier<Empty>,
+    base: MockQuerier<TerraQueryWrapper>,
     token_querier: TokenQuerier,
+    // maps the instantiated contracts to their admins
+    contract_admins: HashMap<String, Option<String>>,
+    // answers every query of the contract other than the token balance
+    contract_configs: HashMap<String, Binary>,
+    raw_storage: HashMap<(String, Binary), Binary>,
 }
 
 #[derive(Clone, Default)]
@@ -58,7 +65,7 @@ pub(crate) fn balances_to_map(
 impl Querier for WasmMockQuerier {
     fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
         // MockQuerier doesn't support Custom, so we ignore it completely here
-        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+        let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
             Ok(v) => v,
             Err(e) => {
                 return SystemResult::Err(SystemError::InvalidRequest {
@@ -72,9 +79,37 @@ impl Querier for WasmMockQuerier {
 }
 
 impl WasmMockQuerier {
-    pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+    pub fn handle_query(&self, request: &QueryRequest<TerraQueryWrapper>) -> QuerierResult {
         match &request {
+            QueryRequest::Custom(TerraQueryWrapper {
+                query_data: TerraQuery::ContractInfo { contract_address },
+                ..
+            }) => match self.contract_admins.get(contract_address) {
+                Some(admin) => {
+                    SystemResult::Ok(ContractResult::from(to_binary(&ContractInfoResponse {
+                        address: contract_address.to_string(),
+                        creator: contract_address.to_string(),
+                        code_id: 1,
+                        admin: admin.clone(),
+                    })))
+                }
+                None => SystemResult::Err(SystemError::NoSuchContract {
+                    addr: contract_address.to_string(),
+                }),
+            },
+            // missing keys are empty
+            QueryRequest::Wasm(WasmQuery::Raw { contract_addr, key }) => {
+                SystemResult::Ok(ContractResult::Ok(
+                    self.raw_storage
+                        .get(&(contract_addr.to_string(), key.clone()))
+                        .cloned()
+                        .unwrap_or_default(),
+                ))
+            }
             QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if let Some(config) = self.contract_configs.get(contract_addr) {
+                    return SystemResult::Ok(ContractResult::Ok(config.clone()));
+                }
                 match from_binary(msg).unwrap() {
                     Cw20QueryMsg::Balance { address } => {
                         let balances: &HashMap<String, Uint128> =
@@ -116,10 +151,13 @@ impl WasmMockQuerier {
 }
 
 impl WasmMockQuerier {
-    pub fn new(base: MockQuerier<Empty>) -> Self {
+    pub fn new(base: MockQuerier<TerraQueryWrapper>) -> Self {
         WasmMockQuerier {
             base,
             token_querier: TokenQuerier::default(),
+            contract_admins: HashMap::new(),
+            contract_configs: HashMap::new(),
+            raw_storage: HashMap::new(),
         }
     }
 
@@ -127,4 +165,23 @@ impl WasmMockQuerier {
     pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
         self.token_querier = TokenQuerier::new(balances);
     }
+
+    pub fn with_contract_admins(&mut self, admins: &[(&str, Option<&str>)]) {
+        for (contract, admin) in admins {
+            self.contract_admins
+                .insert(contract.to_string(), admin.map(|admin| admin.to_string()));
+        }
+    }
+
+    pub fn with_contract_config<T: Serialize>(&mut self, contract: &str, config: &T) {
+        self.contract_configs
+            .insert(contract.to_string(), to_binary(config).unwrap());
+    }
+
+    pub fn with_raw_storage<T: Serialize>(&mut self, contract: &str, key: &[u8], value: &T) {
+        self.raw_storage.insert(
+            (contract.to_string(), Binary::from(key)),
+            to_binary(value).unwrap(),
+        );
+    }
 }
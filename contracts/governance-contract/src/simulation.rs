@@ -0,0 +1,293 @@
+use crate::action::{build_execute_data, execute_data_to_poll_msg, GameMsg, ReserveMsg};
+use crate::error::ContractError;
+use crate::state::{polls, Config, Poll, State, CONFIG, STATE};
+
+use cosmwasm_std::{from_binary, from_slice, Addr, Binary, Deps, Env, StdResult};
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::U64Key;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use terra_cosmwasm::TerraQuerier;
+use terra_vegas::gov::{
+    PollAction, PollExecuteMsg, SimulationResponse, SimulationResult, SimulationStatus,
+};
+use terra_vegas::{community, distribution, gov};
+
+/// Raw storage key of the owner of the distribution, the reserve and the games
+static OWNER_KEY: &[u8] = b"owner";
+/// Raw storage keys of the reserve and the game configs
+static RESERVE_CONFIG_KEY: &[u8] = b"config";
+static GAME_CONFIG_KEY: &[u8] = b"casino_config";
+
+/// The part of the reserve and game configs the simulation reads
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub(crate) struct GovernedConfig {
+    pub gov_contract_address: Addr,
+}
+
+pub fn query_simulate_poll(
+    deps: Deps,
+    env: Env,
+    poll_id: u64,
+) -> Result<SimulationResponse, ContractError> {
+    let poll: Poll = match polls().may_load(deps.storage, U64Key::new(poll_id))? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound {}),
+    };
+
+    let msgs = poll
+        .execute_data
+        .unwrap_or_default()
+        .into_iter()
+        .map(|data| execute_data_to_poll_msg(deps.api, data))
+        .collect::<StdResult<Vec<PollExecuteMsg>>>()?;
+
+    query_simulate_execute_msgs(deps, env, msgs)
+}
+
+pub fn query_simulate_execute_msgs(
+    deps: Deps,
+    env: Env,
+    mut msgs: Vec<PollExecuteMsg>,
+) -> Result<SimulationResponse, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state: State = STATE.load(deps.storage)?;
+
+    msgs.sort_by_key(|msg| msg.order);
+    let results = msgs
+        .into_iter()
+        .map(|msg| {
+            Ok(SimulationResult {
+                order: msg.order,
+                contract: msg.action.contract().to_string(),
+                status: simulate_msg(deps, &env, &config, &state, msg)?,
+            })
+        })
+        .collect::<StdResult<Vec<SimulationResult>>>()?;
+
+    Ok(SimulationResponse { results })
+}
+
+fn simulate_msg(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    state: &State,
+    msg: PollExecuteMsg,
+) -> StdResult<SimulationStatus> {
+    let contract = match deps.api.addr_validate(msg.action.contract()) {
+        Ok(contract) => contract,
+        Err(err) => return Ok(invalid_contract(err)),
+    };
+    let contract_info = match TerraQuerier::new(&deps.querier).query_contract_info(&contract) {
+        Ok(contract_info) => contract_info,
+        Err(_) => return Ok(invalid_contract("not a contract")),
+    };
+
+    let action = msg.action.clone();
+    let data = match build_execute_data(deps.api, msg.order, msg.action) {
+        Ok(data) => data,
+        Err(err) => return Ok(invalid_msg(err)),
+    };
+
+    // the migrate message is checked by the new code only
+    if let PollAction::MigrateContract { .. } = action {
+        return Ok(
+            if contract_info.admin.as_deref() == Some(env.contract.address.as_str()) {
+                SimulationStatus::Ok
+            } else {
+                unauthorized("governance is not the contract admin")
+            },
+        );
+    }
+
+    let contract_raw = deps.api.addr_canonicalize(contract.as_str())?;
+    if contract == env.contract.address {
+        simulate_gov_msg(config, state, &data.msg)
+    } else if contract_raw == config.token_contract {
+        Ok(match from_binary::<Cw20ExecuteMsg>(&data.msg) {
+            Ok(_) => SimulationStatus::Ok,
+            Err(err) => invalid_msg(err),
+        })
+    } else if contract_raw == config.community_contract
+        || matches!(action, PollAction::CommunitySpend { .. })
+    {
+        simulate_community_msg(deps, env, &contract, &data.msg)
+    } else if contract_raw == config.distribution_contract
+        || matches!(action, PollAction::UpdateDistributionConfig { .. })
+    {
+        simulate_distribution_msg(deps, env, &contract, &data.msg)
+    } else if matches!(
+        action,
+        PollAction::AddGame { .. } | PollAction::RemoveGame { .. }
+    ) || (action.is_raw() && from_binary::<ReserveMsg>(&data.msg).is_ok())
+    {
+        simulate_governed_msg::<ReserveMsg>(
+            deps,
+            env,
+            &contract,
+            &data.msg,
+            RESERVE_CONFIG_KEY,
+            "reserve",
+        )
+    } else if matches!(action, PollAction::UpdateGameParams { .. })
+        || (action.is_raw() && from_binary::<GameMsg>(&data.msg).is_ok())
+    {
+        simulate_governed_msg::<GameMsg>(deps, env, &contract, &data.msg, GAME_CONFIG_KEY, "game")
+    } else if action.is_raw() {
+        Ok(SimulationStatus::UnknownSchema)
+    } else {
+        Ok(SimulationStatus::Ok)
+    }
+}
+
+fn simulate_gov_msg(config: &Config, state: &State, msg: &Binary) -> StdResult<SimulationStatus> {
+    let msg: gov::ExecuteMsg = match from_binary(msg) {
+        Ok(msg) => msg,
+        Err(err) => return Ok(invalid_msg(err)),
+    };
+
+    Ok(match msg {
+        gov::ExecuteMsg::UpdateConfig { .. } if config.owner != state.contract_addr => {
+            unauthorized("governance is not its own owner")
+        }
+        _ => SimulationStatus::Ok,
+    })
+}
+
+fn simulate_community_msg(
+    deps: Deps,
+    env: &Env,
+    contract: &Addr,
+    msg: &Binary,
+) -> StdResult<SimulationStatus> {
+    let msg: community::ExecuteMsg = match from_binary(msg) {
+        Ok(msg) => msg,
+        Err(err) => return Ok(invalid_msg(err)),
+    };
+    let community_config: community::ConfigResponse = match deps
+        .querier
+        .query_wasm_smart(contract, &community::QueryMsg::Config {})
+    {
+        Ok(community_config) => community_config,
+        Err(_) => return Ok(invalid_contract("not a community contract")),
+    };
+
+    if community_config.gov_contract != env.contract.address.as_str() {
+        return Ok(unauthorized("governance is not the community governance"));
+    }
+
+    Ok(match msg {
+        community::ExecuteMsg::Spend { amount, .. } if amount > community_config.spend_limit => {
+            invalid_msg("amount is above the spend limit")
+        }
+        _ => SimulationStatus::Ok,
+    })
+}
+
+fn simulate_distribution_msg(
+    deps: Deps,
+    env: &Env,
+    contract: &Addr,
+    msg: &Binary,
+) -> StdResult<SimulationStatus> {
+    let msg: distribution::ExecuteMsg = match from_binary(msg) {
+        Ok(msg) => msg,
+        Err(err) => return Ok(invalid_msg(err)),
+    };
+
+    // the operations of the owner, every other message is public or sent by the holders
+    if !matches!(
+        msg,
+        distribution::ExecuteMsg::UpdateReserve { .. }
+            | distribution::ExecuteMsg::UpdateConfig { .. }
+            | distribution::ExecuteMsg::RegisterRewardAsset { .. }
+            | distribution::ExecuteMsg::RemoveRewardAsset { .. }
+            | distribution::ExecuteMsg::SetEmission { .. }
+            | distribution::ExecuteMsg::UpdateCompoundConfig { .. }
+            | distribution::ExecuteMsg::UpdateLockConfig { .. }
+    ) {
+        return Ok(SimulationStatus::Ok);
+    }
+
+    let distribution_config: distribution::ConfigResponse = match deps
+        .querier
+        .query_wasm_smart(contract, &distribution::QueryMsg::Config {})
+    {
+        Ok(distribution_config) => distribution_config,
+        Err(_) => return Ok(invalid_contract("not a distribution contract")),
+    };
+    let owner: Option<Addr> = deps
+        .querier
+        .query_wasm_raw(contract, OWNER_KEY)?
+        .map(|owner| from_slice(&owner))
+        .transpose()?;
+
+    Ok(
+        if distribution_config.governance_token_addr == env.contract.address.as_str()
+            || owner.as_ref() == Some(&env.contract.address)
+        {
+            SimulationStatus::Ok
+        } else {
+            unauthorized("governance is not the distribution owner")
+        },
+    )
+}
+
+/// Checks a reserve or game message, these contracts take them from their governance or owner
+fn simulate_governed_msg<T: DeserializeOwned>(
+    deps: Deps,
+    env: &Env,
+    contract: &Addr,
+    msg: &Binary,
+    config_key: &[u8],
+    name: &str,
+) -> StdResult<SimulationStatus> {
+    if let Err(err) = from_binary::<T>(msg) {
+        return Ok(invalid_msg(err));
+    }
+
+    let config: GovernedConfig = match deps
+        .querier
+        .query_wasm_raw(contract, config_key)?
+        .map(|config| from_slice(&config))
+    {
+        Some(Ok(config)) => config,
+        _ => return Ok(invalid_contract(format!("not a {} contract", name))),
+    };
+    let owner: Option<Addr> = deps
+        .querier
+        .query_wasm_raw(contract, OWNER_KEY)?
+        .map(|owner| from_slice(&owner))
+        .transpose()?;
+
+    Ok(
+        if config.gov_contract_address == env.contract.address
+            || owner.as_ref() == Some(&env.contract.address)
+        {
+            SimulationStatus::Ok
+        } else {
+            unauthorized(format!("governance is not the {} governance", name))
+        },
+    )
+}
+
+fn invalid_contract(reason: impl Display) -> SimulationStatus {
+    SimulationStatus::InvalidContract {
+        reason: reason.to_string(),
+    }
+}
+
+fn invalid_msg(reason: impl Display) -> SimulationStatus {
+    SimulationStatus::InvalidMsg {
+        reason: reason.to_string(),
+    }
+}
+
+fn unauthorized(reason: impl Display) -> SimulationStatus {
+    SimulationStatus::Unauthorized {
+        reason: reason.to_string(),
+    }
+}
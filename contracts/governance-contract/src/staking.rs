@@ -1,20 +1,23 @@
+use crate::delegation::{add_delegated_share, delegated_vote_active, remove_delegated_share};
 use crate::error::ContractError;
 use crate::state::{
-    bank_read, bank_store, config_read, config_store, poll_read, poll_voter_store, state_read,
-    state_store, Config, Poll, State, TokenManager,
+    polls, store_token_manager, store_total_share_snapshot, Config, Poll, State, TokenManager,
+    BANK, CONFIG, POLL_DELEGATES, POLL_VOTERS, STATE,
 };
 
 use astroport::querier::query_token_balance;
 use cosmwasm_std::{
-    to_binary, Addr, CanonicalAddr, CosmosMsg, Deps, DepsMut, MessageInfo, Response, StdResult,
-    Storage, Uint128, WasmMsg,
+    to_binary, Addr, CanonicalAddr, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response,
+    StdResult, Storage, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::U64Key;
 use terra_vegas::distribution::ExecuteMsg;
 use terra_vegas::gov::{PollStatus, StakerResponse};
 
 pub fn stake_voting_tokens(
     deps: DepsMut,
+    env: &Env,
     sender: Addr,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
@@ -25,9 +28,9 @@ pub fn stake_voting_tokens(
     let sender_address_raw = deps.api.addr_canonicalize(sender.as_str())?;
     let key = &sender_address_raw.as_slice();
 
-    let mut token_manager = bank_read(deps.storage).may_load(key)?.unwrap_or_default();
-    let config: Config = config_store(deps.storage).load()?;
-    let mut state: State = state_store(deps.storage).load()?;
+    let mut token_manager = BANK.may_load(deps.storage, key)?.unwrap_or_default();
+    let config: Config = CONFIG.load(deps.storage)?;
+    let mut state: State = STATE.load(deps.storage)?;
 
     // balance already increased, so subtract deposit amount
     let total_balance = query_token_balance(
@@ -44,10 +47,21 @@ pub fn stake_voting_tokens(
     };
 
     token_manager.share += share;
+    store_total_share_snapshot(
+        deps.storage,
+        env.block.height,
+        state.total_share,
+        state.total_share + share,
+    )?;
     state.total_share += share;
 
-    state_store(deps.storage).save(&state)?;
-    bank_store(deps.storage).save(key, &token_manager)?;
+    // the delegate votes with the new share as well
+    if let Some(delegate) = &token_manager.delegate {
+        add_delegated_share(deps.storage, env.block.height, delegate, share)?;
+    }
+
+    STATE.save(deps.storage, &state)?;
+    store_token_manager(deps.storage, env.block.height, key, &token_manager)?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "staking"),
@@ -60,15 +74,16 @@ pub fn stake_voting_tokens(
 // Withdraw amount if not staked. By default all funds will be withdrawn.
 pub fn withdraw_voting_tokens(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     amount: Option<Uint128>,
 ) -> Result<Response, ContractError> {
     let sender_address_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     let key = sender_address_raw.as_slice();
 
-    if let Some(mut token_manager) = bank_read(deps.storage).may_load(key)? {
-        let config: Config = config_store(deps.storage).load()?;
-        let mut state: State = state_store(deps.storage).load()?;
+    if let Some(mut token_manager) = BANK.may_load(deps.storage, key)? {
+        let config: Config = CONFIG.load(deps.storage)?;
+        let mut state: State = STATE.load(deps.storage)?;
 
         // Load total share & total balance except proposal deposit amount
         let total_share = state.total_share.u128();
@@ -80,9 +95,19 @@ pub fn withdraw_voting_tokens(
         .checked_sub(state.total_deposit)?
         .u128();
 
-        let locked_balance =
+        let mut locked_balance =
             compute_locked_balance(deps.storage, &mut token_manager, &sender_address_raw);
-        let locked_share = locked_balance * total_share / total_balance;
+        if delegated_vote_active(deps.storage, &token_manager, &sender_address_raw)? {
+            locked_balance = token_manager
+                .share
+                .multiply_ratio(total_balance, total_share)
+                .u128();
+        }
+        let mut locked_share = locked_balance * total_share / total_balance;
+        // the locked share stays staked until the lock is withdrawn
+        if let Some(lock) = &token_manager.lock {
+            locked_share = std::cmp::max(locked_share, lock.share.u128());
+        }
         let user_share = token_manager.share.u128();
 
         let withdraw_share = amount
@@ -97,25 +122,41 @@ pub fn withdraw_voting_tokens(
         } else {
             let share = user_share - withdraw_share;
             token_manager.share = Uint128::from(share);
+            if let Some(delegate) = &token_manager.delegate {
+                remove_delegated_share(
+                    deps.storage,
+                    env.block.height,
+                    delegate,
+                    Uint128::from(withdraw_share),
+                )?;
+            }
 
-            bank_store(deps.storage).save(key, &token_manager)?;
+            store_token_manager(deps.storage, env.block.height, key, &token_manager)?;
 
             state.total_share = Uint128::from(total_share - withdraw_share);
-            state_store(deps.storage).save(&state)?;
-
-            let res = send_tokens(
+            store_total_share_snapshot(
+                deps.storage,
+                env.block.height,
+                Uint128::from(total_share),
+                state.total_share,
+            )?;
+            STATE.save(deps.storage, &state)?;
+
+            let mut res = send_tokens(
                 &deps,
                 &config.token_contract,
                 &sender_address_raw,
                 withdraw_amount,
                 "withdraw",
-            )?
-            .add_message(unbond_tokens_distribution_contract(
-                deps,
-                &config.distribution_contract,
-                &sender_address_raw,
-                withdraw_amount,
-            )?);
+            )?;
+            if config.distribution_contract != CanonicalAddr::from(vec![]) {
+                res = res.add_message(unbond_tokens_distribution_contract(
+                    deps,
+                    &config.distribution_contract,
+                    &sender_address_raw,
+                    withdraw_amount,
+                )?);
+            }
 
             Ok(res)
         }
@@ -132,11 +173,12 @@ fn compute_locked_balance(
     voter: &CanonicalAddr,
 ) -> u128 {
     token_manager.locked_balance.retain(|(poll_id, _)| {
-        let poll: Poll = poll_read(storage).load(&poll_id.to_be_bytes()).unwrap();
+        let poll: Poll = polls().load(storage, U64Key::new(*poll_id)).unwrap();
 
         if poll.status != PollStatus::InProgress {
             // remove voter info from the poll
-            poll_voter_store(storage, *poll_id).remove(voter.as_slice());
+            POLL_VOTERS.remove(storage, (U64Key::new(*poll_id), voter.as_slice()));
+            POLL_DELEGATES.remove(storage, (U64Key::new(*poll_id), voter.as_slice()));
         }
 
         poll.status == PollStatus::InProgress
@@ -199,17 +241,15 @@ fn unbond_tokens_distribution_contract(
 
 pub fn query_staker(deps: Deps, address: String) -> StdResult<StakerResponse> {
     let addr_raw = deps.api.addr_canonicalize(&address).unwrap();
-    let config: Config = config_read(deps.storage).load()?;
-    let state: State = state_read(deps.storage).load()?;
-    let mut token_manager = bank_read(deps.storage)
-        .may_load(addr_raw.as_slice())?
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state: State = STATE.load(deps.storage)?;
+    let mut token_manager = BANK
+        .may_load(deps.storage, addr_raw.as_slice())?
         .unwrap_or_default();
 
     // filter out not in-progress polls
     token_manager.locked_balance.retain(|(poll_id, _)| {
-        let poll: Poll = poll_read(deps.storage)
-            .load(&poll_id.to_be_bytes())
-            .unwrap();
+        let poll: Poll = polls().load(deps.storage, U64Key::new(*poll_id)).unwrap();
 
         poll.status == PollStatus::InProgress
     });
@@ -231,5 +271,10 @@ pub fn query_staker(deps: Deps, address: String) -> StdResult<StakerResponse> {
         },
         share: token_manager.share,
         locked_balance: token_manager.locked_balance,
+        delegate: token_manager
+            .delegate
+            .map(|delegate| deps.api.addr_humanize(&delegate))
+            .transpose()?
+            .map(|delegate| delegate.to_string()),
     })
 }
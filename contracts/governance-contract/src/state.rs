@@ -1,23 +1,42 @@
-use cosmwasm_std::{Binary, CanonicalAddr, Decimal, StdResult, Storage, Uint128};
-use cosmwasm_storage::{
-    bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
-    Singleton,
+use crate::action::has_raw_msgs;
+
+use cosmwasm_std::{Binary, CanonicalAddr, Decimal, Order, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::{
+    Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex, PrimaryKey, U64Key,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use std::cmp::Ordering;
+use std::cmp::{max, Ordering};
+use std::convert::TryInto;
 use terra_vegas::common::OrderBy;
-use terra_vegas::gov::{PollStatus, VoterInfo};
+use terra_vegas::gov::{
+    PollAction, PollCategory, PollCategoryParams, PollStatus, VoteOption, VoterInfo,
+};
+
+// the keys of the config and state are length prefixed
+// as they were stored before the move to cw_storage_plus
+pub const CONFIG: Item<Config> = Item::new("\u{0}\u{6}config");
+pub const STATE: Item<State> = Item::new("\u{0}\u{5}state");
+pub const TMP_POLL_ID: Item<u64> = Item::new("\u{0}\u{b}tmp_poll_id");
+
+pub const BANK: Map<&[u8], TokenManager> = Map::new("bank");
+pub const POLL_VOTERS: Map<(U64Key, &[u8]), VoterInfo> = Map::new("poll_voter");
+/// Delegated weight in the votes of the delegates
+pub const POLL_DELEGATES: Map<(U64Key, &[u8]), DelegatedVote> = Map::new("poll_delegate");
+/// Delegators of each delegate
+pub const DELEGATORS: Map<(&[u8], &[u8]), bool> = Map::new("delegator");
+
+const STAKER_SNAPSHOTS: Map<(&[u8], U64Key), StakerSnapshot> = Map::new("staker_snapshot");
+const TOTAL_SHARE_SNAPSHOTS: Map<U64Key, Uint128> = Map::new("total_share_snapshot");
+const VE_POINTS: Map<(&[u8], U64Key), VePoint> = Map::new("ve_point");
+const VE_EXPIRIES: Map<(&[u8], U64Key), VePoint> = Map::new("ve_expiry");
 
-static KEY_CONFIG: &[u8] = b"config";
-static KEY_STATE: &[u8] = b"state";
-static KEY_TMP_POLL_ID: &[u8] = b"tmp_poll_id";
+// polls by status from before the indexed map, removed by the migration
+const LEGACY_POLL_INDEXER: Map<(&[u8], U64Key), bool> = Map::new("poll_indexer");
 
-static PREFIX_POLL_INDEXER: &[u8] = b"poll_indexer";
-static PREFIX_POLL_VOTER: &[u8] = b"poll_voter";
-static PREFIX_POLL: &[u8] = b"poll";
-static PREFIX_BANK: &[u8] = b"bank";
+/// Account of the voting escrow totals holding every lock
+pub static VE_TOTAL: &[u8] = b"";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -31,6 +50,112 @@ pub struct Config {
     pub expiration_period: u64,
     pub proposal_deposit: Uint128,
     pub snapshot_period: u64,
+    #[serde(default = "default_veto_threshold")]
+    pub veto_threshold: Decimal,
+    #[serde(default = "default_community_contract")]
+    pub community_contract: CanonicalAddr,
+    #[serde(default)]
+    pub max_lock_period: u64,
+    #[serde(default = "default_raw_msg_threshold")]
+    pub raw_msg_threshold: Decimal,
+    #[serde(default)]
+    pub categories: Vec<PollCategoryParams>,
+    #[serde(default = "default_guardian")]
+    pub guardian: CanonicalAddr,
+    #[serde(default)]
+    pub cancel_refund_ratio: Decimal,
+}
+
+impl Config {
+    /// Parameters of the category, the base parameters when the category is not configured
+    /// or the poll was created before the categories
+    pub fn category_params(&self, category: Option<PollCategory>) -> PollCategoryParams {
+        let params = category.and_then(|category| {
+            self.categories
+                .iter()
+                .find(|params| params.category == category)
+        });
+        match params {
+            Some(params) => params.clone(),
+            None => PollCategoryParams {
+                category: category.unwrap_or(PollCategory::Text),
+                quorum: self.quorum,
+                threshold: self.threshold,
+                voting_period: self.voting_period,
+                timelock_period: self.timelock_period,
+                proposal_deposit: self.proposal_deposit,
+            },
+        }
+    }
+
+    /// Parameters of a poll in the category, raw messages can do what the actions
+    /// of any category do, so they take the highest requirements of all the categories.
+    /// Emergency polls and the polls created before the categories keep their own
+    pub fn execution_params(
+        &self,
+        category: Option<PollCategory>,
+        raw_msgs: bool,
+    ) -> PollCategoryParams {
+        let mut params = self.category_params(category);
+        if !raw_msgs || category.is_none() || category == Some(PollCategory::Emergency) {
+            return params;
+        }
+
+        for other in [
+            PollCategory::ParameterChange,
+            PollCategory::TreasurySpend,
+            PollCategory::ContractMigration,
+        ] {
+            let other = self.category_params(Some(other));
+            params.quorum = max(params.quorum, other.quorum);
+            params.threshold = max(params.threshold, other.threshold);
+            params.timelock_period = max(params.timelock_period, other.timelock_period);
+            params.proposal_deposit = max(params.proposal_deposit, other.proposal_deposit);
+        }
+        params
+    }
+
+    /// Parameters the poll is voted and executed with
+    pub fn poll_params(&self, poll: &Poll) -> PollCategoryParams {
+        self.execution_params(poll.category, has_raw_msgs(&poll.execute_data))
+    }
+
+    /// First height the poll can be executed at, emergency polls skip the timelock
+    pub fn timelock_end(&self, poll: &Poll) -> u64 {
+        if poll.category == Some(PollCategory::Emergency) {
+            poll.end_height
+        } else {
+            poll.end_height + self.poll_params(poll).timelock_period
+        }
+    }
+
+    /// Height after which a passed poll can no longer be executed
+    pub fn expiration_height(&self, poll: &Poll) -> Option<u64> {
+        if self.expiration_period == 0 {
+            None
+        } else {
+            Some(self.timelock_end(poll) + self.expiration_period)
+        }
+    }
+}
+
+// configs stored before the veto was introduced use the Cosmos SDK defaults
+fn default_veto_threshold() -> Decimal {
+    Decimal::permille(334)
+}
+
+// configs stored before the raw message threshold require a supermajority for them,
+// polls still need the threshold when it is higher
+fn default_raw_msg_threshold() -> Decimal {
+    Decimal::permille(667)
+}
+
+fn default_community_contract() -> CanonicalAddr {
+    CanonicalAddr::from(vec![])
+}
+
+fn default_guardian() -> CanonicalAddr {
+    CanonicalAddr::from(vec![])
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -45,6 +170,90 @@ pub struct State {
 pub struct TokenManager {
     pub share: Uint128,                        // total staked balance
     pub locked_balance: Vec<(u64, VoterInfo)>, // maps poll_id to weight voted
+    #[serde(default)]
+    pub delegate: Option<CanonicalAddr>, // votes with the share on behalf of the staker
+    #[serde(default)]
+    pub delegated_share: Uint128, // share delegated to the staker
+    #[serde(default)]
+    pub lock: Option<VeLock>, // share locked for the voting escrow weight
+}
+
+/// Staked share locked until the end height
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VeLock {
+    pub share: Uint128,
+    pub end_height: u64,
+}
+
+impl VeLock {
+    /// Share times the blocks left in the lock at the given height
+    pub fn weight(&self, height: u64) -> StdResult<Uint128> {
+        if self.end_height <= height {
+            return Ok(Uint128::zero());
+        }
+
+        Ok(self
+            .share
+            .checked_mul(Uint128::from(self.end_height - height))?)
+    }
+}
+
+/// Sum of the locks of an account, `share_end` adds up the shares times their end heights
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VePoint {
+    pub share: Uint128,
+    pub share_end: Uint128,
+}
+
+impl VePoint {
+    fn add(&mut self, lock: &VeLock) -> StdResult<()> {
+        self.share = self.share.checked_add(lock.share)?;
+        self.share_end = self.share_end.checked_add(lock.weight(0)?)?;
+        Ok(())
+    }
+
+    fn sub(&mut self, lock: &VePoint) -> StdResult<()> {
+        self.share = self.share.checked_sub(lock.share)?;
+        self.share_end = self.share_end.checked_sub(lock.share_end)?;
+        Ok(())
+    }
+
+    /// Total weight of the locks still running at the given height
+    pub fn weight(&self, height: u64) -> StdResult<Uint128> {
+        Ok(self
+            .share_end
+            .checked_sub(self.share.checked_mul(Uint128::from(height))?)?)
+    }
+}
+
+/// Voting weight of a staker at the end of a block
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakerSnapshot {
+    pub share: Uint128,
+    pub delegated_share: Uint128,
+    pub delegate: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub lock: Option<VeLock>,
+}
+
+impl From<&TokenManager> for StakerSnapshot {
+    fn from(token_manager: &TokenManager) -> Self {
+        StakerSnapshot {
+            share: token_manager.share,
+            delegated_share: token_manager.delegated_share,
+            delegate: token_manager.delegate.clone(),
+            lock: token_manager.lock.clone(),
+        }
+    }
+}
+
+/// Delegated weight of a delegate on a poll
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegatedVote {
+    /// Delegated balance counted in the vote of the delegate
+    pub balance: Uint128,
+    /// Weight of the delegators who voted themselves
+    pub overridden_share: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -54,6 +263,10 @@ pub struct Poll {
     pub status: PollStatus,
     pub yes_votes: Uint128,
     pub no_votes: Uint128,
+    #[serde(default)]
+    pub abstain_votes: Uint128,
+    #[serde(default)]
+    pub veto_votes: Uint128,
     pub end_height: u64,
     pub title: String,
     pub description: String,
@@ -63,6 +276,30 @@ pub struct Poll {
     /// Total balance at the end poll
     pub total_balance_at_end_poll: Option<Uint128>,
     pub staked_amount: Option<Uint128>,
+    /// Voting weights are taken before this height
+    #[serde(default)]
+    pub start_height: u64,
+    /// Total weight matching the staked amount snapshotted on creation,
+    /// not set for polls created before the weights were snapshotted
+    #[serde(default)]
+    pub snapshot_share: Option<Uint128>,
+    /// Weights are lock weights instead of staked shares
+    #[serde(default)]
+    pub vote_escrow: bool,
+    #[serde(default)]
+    pub category: Option<PollCategory>,
+}
+
+impl Poll {
+    /// Tally of the given vote option
+    pub fn votes_mut(&mut self, vote: &VoteOption) -> &mut Uint128 {
+        match vote {
+            VoteOption::Yes => &mut self.yes_votes,
+            VoteOption::No => &mut self.no_votes,
+            VoteOption::Abstain => &mut self.abstain_votes,
+            VoteOption::NoWithVeto => &mut self.veto_votes,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -70,6 +307,10 @@ pub struct ExecuteData {
     pub order: u64,
     pub contract: CanonicalAddr,
     pub msg: Binary,
+    /// Action the message was built from,
+    /// not set for polls created before the actions were typed
+    #[serde(default)]
+    pub action: Option<PollAction>,
 }
 impl Eq for ExecuteData {}
 
@@ -91,73 +332,87 @@ impl PartialEq for ExecuteData {
     }
 }
 
-pub fn config_store(storage: &mut dyn Storage) -> Singleton<Config> {
-    singleton(storage, KEY_CONFIG)
-}
-
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<Config> {
-    singleton_read(storage, KEY_CONFIG)
-}
-
-pub fn state_store(storage: &mut dyn Storage) -> Singleton<State> {
-    singleton(storage, KEY_STATE)
-}
-
-pub fn state_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
-    singleton_read(storage, KEY_STATE)
+pub struct PollIndexes<'a> {
+    pub status: MultiIndex<'a, (Vec<u8>, Vec<u8>), Poll>,
+    pub creator: MultiIndex<'a, (Vec<u8>, Vec<u8>), Poll>,
+    pub end_height: MultiIndex<'a, (U64Key, Vec<u8>), Poll>,
 }
 
-pub fn store_tmp_poll_id(storage: &mut dyn Storage, tmp_poll_id: u64) -> StdResult<()> {
-    singleton(storage, KEY_TMP_POLL_ID).save(&tmp_poll_id)
+impl<'a> IndexList<Poll> for PollIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Poll>> + '_> {
+        let v: Vec<&dyn Index<Poll>> = vec![&self.status, &self.creator, &self.end_height];
+        Box::new(v.into_iter())
+    }
 }
 
-pub fn read_tmp_poll_id(storage: &dyn Storage) -> StdResult<u64> {
-    singleton_read(storage, KEY_TMP_POLL_ID).load()
+/// Polls by id, the indexes are updated on every save
+pub fn polls<'a>() -> IndexedMap<'a, U64Key, Poll, PollIndexes<'a>> {
+    let indexes = PollIndexes {
+        status: MultiIndex::new(
+            |poll, pk| (poll.status.to_string().into_bytes(), pk),
+            "poll",
+            "poll__status",
+        ),
+        creator: MultiIndex::new(
+            |poll, pk| (poll.creator.to_vec(), pk),
+            "poll",
+            "poll__creator",
+        ),
+        end_height: MultiIndex::new(
+            |poll, pk| (U64Key::new(poll.end_height), pk),
+            "poll",
+            "poll__end_height",
+        ),
+    };
+    IndexedMap::new("poll", indexes)
 }
 
-pub fn poll_store(storage: &mut dyn Storage) -> Bucket<Poll> {
-    bucket(storage, PREFIX_POLL)
-}
+/// Indexes a batch of the polls stored before the indexed map and removes their
+/// status indexer, returns the id of the last poll migrated
+pub fn migrate_polls(
+    storage: &mut dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Option<u64>> {
+    let limit = limit.unwrap_or(DEFAULT_MIGRATE_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+    let stored = polls()
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
 
-pub fn poll_read(storage: &dyn Storage) -> ReadonlyBucket<Poll> {
-    bucket_read(storage, PREFIX_POLL)
-}
+    let mut last_poll_id = None;
+    for (key, poll) in stored {
+        let poll_id = u64_from_key(&key)?;
+        polls().save(storage, U64Key::new(poll_id), &poll)?;
 
-pub fn poll_indexer_store<'a>(
-    storage: &'a mut dyn Storage,
-    status: &PollStatus,
-) -> Bucket<'a, bool> {
-    Bucket::multilevel(
-        storage,
-        &[PREFIX_POLL_INDEXER, status.to_string().as_bytes()],
-    )
-}
-
-pub fn poll_voter_store(storage: &mut dyn Storage, poll_id: u64) -> Bucket<VoterInfo> {
-    Bucket::multilevel(storage, &[PREFIX_POLL_VOTER, &poll_id.to_be_bytes()])
-}
+        LEGACY_POLL_INDEXER.remove(
+            storage,
+            (poll.status.to_string().as_bytes(), U64Key::new(poll.id)),
+        );
+        last_poll_id = Some(poll_id);
+    }
 
-pub fn poll_voter_read(storage: &dyn Storage, poll_id: u64) -> ReadonlyBucket<VoterInfo> {
-    ReadonlyBucket::multilevel(storage, &[PREFIX_POLL_VOTER, &poll_id.to_be_bytes()])
+    Ok(last_poll_id)
 }
 
-pub fn read_poll_voters<'a>(
-    storage: &'a dyn Storage,
+pub fn read_poll_voters(
+    storage: &dyn Storage,
     poll_id: u64,
     start_after: Option<CanonicalAddr>,
     limit: Option<u32>,
     order_by: Option<OrderBy>,
 ) -> StdResult<Vec<(CanonicalAddr, VoterInfo)>> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.map(|addr| Bound::exclusive(addr.as_slice()));
     let (start, end, order_by) = match order_by {
-        Some(OrderBy::Asc) => (calc_range_start_addr(start_after), None, OrderBy::Asc),
-        _ => (None, calc_range_end_addr(start_after), OrderBy::Desc),
+        Some(OrderBy::Asc) => (start_after, None, OrderBy::Asc),
+        _ => (None, start_after, OrderBy::Desc),
     };
 
-    let voters: ReadonlyBucket<'a, VoterInfo> =
-        ReadonlyBucket::multilevel(storage, &[PREFIX_POLL_VOTER, &poll_id.to_be_bytes()]);
-    voters
-        .range(start.as_deref(), end.as_deref(), order_by.into())
+    POLL_VOTERS
+        .prefix(U64Key::new(poll_id))
+        .range(storage, start, end, order_by.into())
         .take(limit)
         .map(|item| {
             let (k, v) = item?;
@@ -166,80 +421,308 @@ pub fn read_poll_voters<'a>(
         .collect()
 }
 
+pub fn read_delegators(
+    storage: &dyn Storage,
+    delegate: &CanonicalAddr,
+    start_after: Option<CanonicalAddr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|addr| Bound::exclusive(addr.as_slice()));
+
+    DELEGATORS
+        .prefix(delegate.as_slice())
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|k| Ok(CanonicalAddr::from(k)))
+        .collect()
+}
+
 const MAX_LIMIT: u32 = 30;
 const DEFAULT_LIMIT: u32 = 10;
-pub fn read_polls<'a>(
-    storage: &'a dyn Storage,
+const DEFAULT_MIGRATE_LIMIT: u32 = 100;
+pub fn read_polls(
+    storage: &dyn Storage,
     filter: Option<PollStatus>,
     start_after: Option<u64>,
     limit: Option<u32>,
     order_by: Option<OrderBy>,
 ) -> StdResult<Vec<Poll>> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.map(Bound::exclusive_int);
+    let (start, end, order_by) = match order_by {
+        Some(OrderBy::Asc) => (start_after, None, OrderBy::Asc),
+        _ => (None, start_after, OrderBy::Desc),
+    };
+
+    let polls = polls();
+    let range = match filter {
+        Some(status) => polls
+            .idx
+            .status
+            .prefix(status.to_string().into_bytes())
+            .range(storage, start, end, order_by.into()),
+        None => polls.range(storage, start, end, order_by.into()),
+    };
+    range
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn read_polls_by_creator(
+    storage: &dyn Storage,
+    creator: &CanonicalAddr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    order_by: Option<OrderBy>,
+) -> StdResult<Vec<Poll>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.map(Bound::exclusive_int);
     let (start, end, order_by) = match order_by {
-        Some(OrderBy::Asc) => (calc_range_start(start_after), None, OrderBy::Asc),
-        _ => (None, calc_range_end(start_after), OrderBy::Desc),
+        Some(OrderBy::Asc) => (start_after, None, OrderBy::Asc),
+        _ => (None, start_after, OrderBy::Desc),
     };
 
-    if let Some(status) = filter {
-        let poll_indexer: ReadonlyBucket<'a, bool> = ReadonlyBucket::multilevel(
+    polls()
+        .idx
+        .creator
+        .prefix(creator.to_vec())
+        .range(storage, start, end, order_by.into())
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+/// In-progress polls ending from the given height up to `until`, the earliest first
+pub fn read_polls_ending(
+    storage: &dyn Storage,
+    from: u64,
+    until: u64,
+    limit: Option<u32>,
+) -> StdResult<Vec<Poll>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    // an empty pk keeps only the end height part of the index key
+    let start = Bound::inclusive((U64Key::new(from), vec![]).joined_key());
+    let end = Bound::exclusive((U64Key::new(until.saturating_add(1)), vec![]).joined_key());
+
+    polls()
+        .idx
+        .end_height
+        .range(storage, Some(start), Some(end), Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, poll)) if poll.status != PollStatus::InProgress))
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+/// Saves the staker and checkpoints its voting weight at the given height
+pub fn store_token_manager(
+    storage: &mut dyn Storage,
+    height: u64,
+    key: &[u8],
+    token_manager: &TokenManager,
+) -> StdResult<()> {
+    let previous = BANK.may_load(storage, key)?.unwrap_or_default();
+
+    // stakers from before the checkpoints start with their stored weight
+    if STAKER_SNAPSHOTS
+        .prefix(key)
+        .range(storage, None, None, Order::Ascending)
+        .next()
+        .is_none()
+    {
+        STAKER_SNAPSHOTS.save(
             storage,
-            &[PREFIX_POLL_INDEXER, status.to_string().as_bytes()],
-        );
-        poll_indexer
-            .range(start.as_deref(), end.as_deref(), order_by.into())
-            .take(limit)
-            .map(|item| {
-                let (k, _) = item?;
-                poll_read(storage).load(&k)
-            })
-            .collect()
+            (key, U64Key::new(0)),
+            &StakerSnapshot::from(&previous),
+        )?;
+    }
+    STAKER_SNAPSHOTS.save(
+        storage,
+        (key, U64Key::new(height)),
+        &StakerSnapshot::from(token_manager),
+    )?;
+
+    BANK.save(storage, key, token_manager)
+}
+
+/// Returns the voting weight of the staker before the given height
+pub fn read_staker_snapshot(
+    storage: &dyn Storage,
+    key: &[u8],
+    height: u64,
+) -> StdResult<StakerSnapshot> {
+    let snapshots = STAKER_SNAPSHOTS.prefix(key);
+
+    let latest = snapshots
+        .range(
+            storage,
+            None,
+            Some(Bound::exclusive_int(height)),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?;
+    if let Some((_, snapshot)) = latest {
+        Ok(snapshot)
+    } else if snapshots
+        .range(storage, None, None, Order::Ascending)
+        .next()
+        .is_none()
+    {
+        // nothing changed since the checkpoints started
+        let token_manager = BANK.may_load(storage, key)?.unwrap_or_default();
+        Ok(StakerSnapshot::from(&token_manager))
     } else {
-        let polls: ReadonlyBucket<'a, Poll> = ReadonlyBucket::new(storage, PREFIX_POLL);
+        Ok(StakerSnapshot::default())
+    }
+}
 
-        polls
-            .range(start.as_deref(), end.as_deref(), order_by.into())
-            .take(limit)
-            .map(|item| {
-                let (_, v) = item?;
-                Ok(v)
-            })
-            .collect()
+/// Checkpoints the total share at the given height
+pub fn store_total_share_snapshot(
+    storage: &mut dyn Storage,
+    height: u64,
+    previous: Uint128,
+    total_share: Uint128,
+) -> StdResult<()> {
+    if TOTAL_SHARE_SNAPSHOTS
+        .range(storage, None, None, Order::Ascending)
+        .next()
+        .is_none()
+    {
+        TOTAL_SHARE_SNAPSHOTS.save(storage, U64Key::new(0), &previous)?;
     }
+
+    TOTAL_SHARE_SNAPSHOTS.save(storage, U64Key::new(height), &total_share)
 }
 
-pub fn bank_store(storage: &mut dyn Storage) -> Bucket<TokenManager> {
-    bucket(storage, PREFIX_BANK)
+/// Returns the total share before the given height
+pub fn read_total_share_snapshot(
+    storage: &dyn Storage,
+    height: u64,
+    current: Uint128,
+) -> StdResult<Uint128> {
+    let latest = TOTAL_SHARE_SNAPSHOTS
+        .range(
+            storage,
+            None,
+            Some(Bound::exclusive_int(height)),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?;
+    if let Some((_, total_share)) = latest {
+        Ok(total_share)
+    } else if TOTAL_SHARE_SNAPSHOTS
+        .range(storage, None, None, Order::Ascending)
+        .next()
+        .is_none()
+    {
+        Ok(current)
+    } else {
+        Ok(Uint128::zero())
+    }
 }
 
-pub fn bank_read(storage: &dyn Storage) -> ReadonlyBucket<TokenManager> {
-    bucket_read(storage, PREFIX_BANK)
+/// Returns the locks of the account before the given height,
+/// without the ones expired up to it
+pub fn read_ve_point(storage: &dyn Storage, account: &[u8], height: u64) -> StdResult<VePoint> {
+    load_ve_point(storage, account, height, height)
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<u64>) -> Option<Vec<u8>> {
-    start_after.map(|id| {
-        let mut v = id.to_be_bytes().to_vec();
-        v.push(1);
-        v
-    })
+fn load_ve_point(
+    storage: &dyn Storage,
+    account: &[u8],
+    checkpoint_before: u64,
+    expired_until: u64,
+) -> StdResult<VePoint> {
+    let latest = VE_POINTS
+        .prefix(account)
+        .range(
+            storage,
+            None,
+            Some(Bound::exclusive_int(checkpoint_before)),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?;
+    let (checkpoint, mut point) = match latest {
+        Some((key, point)) => (u64_from_key(&key)?, point),
+        None => return Ok(VePoint::default()),
+    };
+
+    // expiries after the checkpoint are not part of it yet
+    for item in VE_EXPIRIES.prefix(account).range(
+        storage,
+        Some(Bound::inclusive_int(checkpoint + 1)),
+        Some(Bound::exclusive_int(expired_until + 1)),
+        Order::Ascending,
+    ) {
+        let (_, expiry) = item?;
+        point.sub(&expiry)?;
+    }
+
+    Ok(point)
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_end(start_after: Option<u64>) -> Option<Vec<u8>> {
-    start_after.map(|id| id.to_be_bytes().to_vec())
+/// Replaces a lock of the account and checkpoints its locks at the given height
+pub fn update_ve_point(
+    storage: &mut dyn Storage,
+    account: &[u8],
+    height: u64,
+    removed: Option<&VeLock>,
+    added: Option<&VeLock>,
+) -> StdResult<()> {
+    let mut point = load_ve_point(storage, account, height + 1, height)?;
+
+    // expired locks are already out of the point
+    if let Some(lock) = removed.filter(|lock| lock.end_height > height) {
+        let mut removed_point = VePoint::default();
+        removed_point.add(lock)?;
+        point.sub(&removed_point)?;
+        update_ve_expiry(storage, account, lock.end_height, |expiry| {
+            expiry.sub(&removed_point)
+        })?;
+    }
+    if let Some(lock) = added.filter(|lock| lock.end_height > height) {
+        point.add(lock)?;
+        update_ve_expiry(storage, account, lock.end_height, |expiry| expiry.add(lock))?;
+    }
+
+    VE_POINTS.save(storage, (account, U64Key::new(height)), &point)
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start_addr(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| {
-        let mut v = addr.as_slice().to_vec();
-        v.push(1);
-        v
-    })
+fn update_ve_expiry<F>(
+    storage: &mut dyn Storage,
+    account: &[u8],
+    end_height: u64,
+    action: F,
+) -> StdResult<()>
+where
+    F: FnOnce(&mut VePoint) -> StdResult<()>,
+{
+    let key = (account, U64Key::new(end_height));
+    let mut expiry = VE_EXPIRIES
+        .may_load(storage, key.clone())?
+        .unwrap_or_default();
+    action(&mut expiry)?;
+    VE_EXPIRIES.save(storage, key, &expiry)
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_end_addr(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| addr.as_slice().to_vec())
+/// Parses the big-endian u64 keys of the poll ids and heights
+fn u64_from_key(key: &[u8]) -> StdResult<u64> {
+    let bytes: [u8; 8] = key
+        .try_into()
+        .map_err(|_| StdError::generic_err("invalid u64 key"))?;
+    Ok(u64::from_be_bytes(bytes))
 }
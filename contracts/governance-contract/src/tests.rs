@@ -1,24 +1,28 @@
-use crate::contract::{execute, instantiate, query, reply};
+use crate::contract::{execute, instantiate, migrate, query, reply};
 use crate::error::ContractError;
-use crate::mock_querier::mock_dependencies;
-use crate::state::{
-    bank_read, bank_store, config_read, poll_store, poll_voter_read, poll_voter_store, state_read,
-    Config, Poll, State, TokenManager,
-};
+use crate::mock_querier::{mock_dependencies, WasmMockQuerier};
+use crate::simulation::GovernedConfig;
+use crate::state::{polls, Config, Poll, State, TokenManager, BANK, CONFIG, POLL_VOTERS, STATE};
 
-use terra_vegas::common::OrderBy;
-use terra_vegas::gov::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, PollExecuteMsg, PollResponse,
-    PollStatus, PollsResponse, QueryMsg, StakerResponse, VoteOption, VoterInfo, VotersResponse,
-    VotersResponseItem,
-};
 use astroport::querier::query_token_balance;
-use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, coins, from_binary, to_binary, Addr, Api, CanonicalAddr, ContractResult, CosmosMsg,
-    Decimal, Deps, DepsMut, Env, Reply, Response, StdError, SubMsg, Timestamp, Uint128, WasmMsg,
+    attr, coins, from_binary, from_slice, to_binary, to_vec, Addr, Api, Binary, CanonicalAddr,
+    ContractResult, CosmosMsg, Decimal, Deps, DepsMut, Env, OwnedDeps, Reply, Response, StdError,
+    Storage, SubMsg, Timestamp, Uint128, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_storage_plus::U64Key;
+use terra_vegas::common::OrderBy;
+use terra_vegas::gov::{
+    ConfigResponse, Cw20HookMsg, DelegatePowerResponse, DelegatorsResponse, DelegatorsResponseItem,
+    ExecuteMsg, GameParam, InstantiateMsg, MigrateMsg, PollAction, PollCategory,
+    PollCategoryParams, PollExecuteMsg, PollResponse, PollStatus, PollsResponse, QueryMsg,
+    SimulationResponse, SimulationResult, SimulationStatus, StakerResponse,
+    TotalVotingEscrowResponse, VoteOption, VoterInfo, VotersResponse, VotersResponseItem,
+    VotingEscrowResponse,
+};
+use terra_vegas::{community, distribution};
 
 const VOTING_TOKEN: &str = "voting_token";
 const TEST_CREATOR: &str = "creator";
@@ -31,6 +35,9 @@ const DEFAULT_VOTING_PERIOD: u64 = 20000u64;
 const DEFAULT_FIX_PERIOD: u64 = 10u64;
 const DEFAULT_TIMELOCK_PERIOD: u64 = 10000u64;
 const DEFAULT_PROPOSAL_DEPOSIT: u128 = 10000000000u128;
+const DEFAULT_VETO_THRESHOLD: u64 = 33u64;
+const DEFAULT_RAW_MSG_THRESHOLD: u64 = 67u64;
+const DEFAULT_CANCEL_REFUND_RATIO: u64 = 50u64;
 
 fn mock_instantiate(deps: DepsMut) {
     let msg = InstantiateMsg {
@@ -40,6 +47,13 @@ fn mock_instantiate(deps: DepsMut) {
         timelock_period: DEFAULT_TIMELOCK_PERIOD,
         proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
         snapshot_period: DEFAULT_FIX_PERIOD,
+        veto_threshold: Decimal::percent(DEFAULT_VETO_THRESHOLD),
+        max_lock_period: 0,
+        raw_msg_threshold: Decimal::percent(DEFAULT_RAW_MSG_THRESHOLD),
+        categories: vec![],
+        guardian: None,
+        expiration_period: 0,
+        cancel_refund_ratio: Decimal::percent(DEFAULT_CANCEL_REFUND_RATIO),
     };
 
     let info = mock_info(TEST_CREATOR, &[]);
@@ -71,6 +85,13 @@ fn instantiate_msg() -> InstantiateMsg {
         timelock_period: DEFAULT_TIMELOCK_PERIOD,
         proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
         snapshot_period: DEFAULT_FIX_PERIOD,
+        veto_threshold: Decimal::percent(DEFAULT_VETO_THRESHOLD),
+        max_lock_period: 0,
+        raw_msg_threshold: Decimal::percent(DEFAULT_RAW_MSG_THRESHOLD),
+        categories: vec![],
+        guardian: None,
+        expiration_period: 0,
+        cancel_refund_ratio: Decimal::percent(DEFAULT_CANCEL_REFUND_RATIO),
     }
 }
 
@@ -83,19 +104,27 @@ fn proper_initialization() {
     let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     assert_eq!(0, res.messages.len());
 
-    let config: Config = config_read(deps.as_ref().storage).load().unwrap();
+    let config: Config = CONFIG.load(deps.as_ref().storage).unwrap();
     assert_eq!(
         config,
         Config {
             token_contract: CanonicalAddr::from(vec![]),
+            distribution_contract: CanonicalAddr::from(vec![]),
             owner: deps.api.addr_canonicalize(TEST_CREATOR).unwrap(),
             quorum: Decimal::percent(DEFAULT_QUORUM),
             threshold: Decimal::percent(DEFAULT_THRESHOLD),
             voting_period: DEFAULT_VOTING_PERIOD,
             timelock_period: DEFAULT_TIMELOCK_PERIOD,
-            expiration_period: 0u64, // Deprecated
+            expiration_period: 0,
             proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
-            snapshot_period: DEFAULT_FIX_PERIOD
+            snapshot_period: DEFAULT_FIX_PERIOD,
+            veto_threshold: Decimal::percent(DEFAULT_VETO_THRESHOLD),
+            community_contract: CanonicalAddr::from(vec![]),
+            max_lock_period: 0,
+            raw_msg_threshold: Decimal::percent(DEFAULT_RAW_MSG_THRESHOLD),
+            categories: vec![],
+            guardian: CanonicalAddr::from(vec![]),
+            cancel_refund_ratio: Decimal::percent(DEFAULT_CANCEL_REFUND_RATIO),
         }
     );
 
@@ -103,13 +132,13 @@ fn proper_initialization() {
         token_contract: VOTING_TOKEN.to_string(),
     };
     let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    let config: Config = config_read(deps.as_ref().storage).load().unwrap();
+    let config: Config = CONFIG.load(deps.as_ref().storage).unwrap();
     assert_eq!(
         config.token_contract,
         deps.api.addr_canonicalize(VOTING_TOKEN).unwrap()
     );
 
-    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    let state: State = STATE.load(deps.as_ref().storage).unwrap();
     assert_eq!(
         state,
         State {
@@ -147,6 +176,13 @@ fn fails_init_invalid_quorum() {
         timelock_period: DEFAULT_TIMELOCK_PERIOD,
         proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
         snapshot_period: DEFAULT_FIX_PERIOD,
+        veto_threshold: Decimal::percent(DEFAULT_VETO_THRESHOLD),
+        max_lock_period: 0,
+        raw_msg_threshold: Decimal::percent(DEFAULT_RAW_MSG_THRESHOLD),
+        categories: vec![],
+        guardian: None,
+        expiration_period: 0,
+        cancel_refund_ratio: Decimal::percent(DEFAULT_CANCEL_REFUND_RATIO),
     };
 
     let res = instantiate(deps.as_mut(), mock_env(), info, msg);
@@ -171,6 +207,13 @@ fn fails_init_invalid_threshold() {
         timelock_period: DEFAULT_TIMELOCK_PERIOD,
         proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
         snapshot_period: DEFAULT_FIX_PERIOD,
+        veto_threshold: Decimal::percent(DEFAULT_VETO_THRESHOLD),
+        max_lock_period: 0,
+        raw_msg_threshold: Decimal::percent(DEFAULT_RAW_MSG_THRESHOLD),
+        categories: vec![],
+        guardian: None,
+        expiration_period: 0,
+        cancel_refund_ratio: Decimal::percent(DEFAULT_CANCEL_REFUND_RATIO),
     };
 
     let res = instantiate(deps.as_mut(), mock_env(), info, msg);
@@ -184,6 +227,37 @@ fn fails_init_invalid_threshold() {
     }
 }
 
+#[test]
+fn fails_init_invalid_raw_msg_threshold() {
+    let mut deps = mock_dependencies(&[]);
+    let info = mock_info("voter", &coins(11, VOTING_TOKEN));
+    let msg = InstantiateMsg {
+        quorum: Decimal::percent(DEFAULT_QUORUM),
+        threshold: Decimal::percent(DEFAULT_THRESHOLD),
+        voting_period: DEFAULT_VOTING_PERIOD,
+        timelock_period: DEFAULT_TIMELOCK_PERIOD,
+        proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+        snapshot_period: DEFAULT_FIX_PERIOD,
+        veto_threshold: Decimal::percent(DEFAULT_VETO_THRESHOLD),
+        max_lock_period: 0,
+        raw_msg_threshold: Decimal::percent(DEFAULT_THRESHOLD - 1),
+        categories: vec![],
+        guardian: None,
+        expiration_period: 0,
+        cancel_refund_ratio: Decimal::percent(DEFAULT_CANCEL_REFUND_RATIO),
+    };
+
+    let res = instantiate(deps.as_mut(), mock_env(), info, msg);
+
+    match res {
+        Ok(_) => panic!("Must return error"),
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "raw_msg_threshold must be threshold to 1")
+        }
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+}
+
 #[test]
 fn fails_contract_already_registered() {
     let mut deps = mock_dependencies(&[]);
@@ -195,6 +269,13 @@ fn fails_contract_already_registered() {
         timelock_period: DEFAULT_TIMELOCK_PERIOD,
         proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
         snapshot_period: DEFAULT_FIX_PERIOD,
+        veto_threshold: Decimal::percent(DEFAULT_VETO_THRESHOLD),
+        max_lock_period: 0,
+        raw_msg_threshold: Decimal::percent(DEFAULT_RAW_MSG_THRESHOLD),
+        categories: vec![],
+        guardian: None,
+        expiration_period: 0,
+        cancel_refund_ratio: Decimal::percent(DEFAULT_CANCEL_REFUND_RATIO),
     };
 
     let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -326,6 +407,7 @@ fn fails_create_poll_invalid_deposit() {
             description: "TESTTEST".to_string(),
             link: None,
             execute_msgs: None,
+            category: None,
         })
         .unwrap(),
     });
@@ -352,6 +434,26 @@ fn create_poll_msg(
             description,
             link,
             execute_msgs: execute_msg,
+            category: None,
+        })
+        .unwrap(),
+    })
+}
+
+fn create_category_poll_msg(
+    execute_msgs: Option<Vec<PollExecuteMsg>>,
+    category: Option<PollCategory>,
+    deposit: u128,
+) -> ExecuteMsg {
+    ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_CREATOR.to_string(),
+        amount: Uint128::from(deposit),
+        msg: to_binary(&Cw20HookMsg::CreatePoll {
+            title: "test".to_string(),
+            description: "test".to_string(),
+            link: None,
+            execute_msgs,
+            category,
         })
         .unwrap(),
     })
@@ -403,18 +505,24 @@ fn query_polls() {
     let execute_msgs: Vec<PollExecuteMsg> = vec![
         PollExecuteMsg {
             order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz,
+            },
         },
         PollExecuteMsg {
             order: 3u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz3,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz3,
+            },
         },
         PollExecuteMsg {
             order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz2,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz2,
+            },
         },
     ];
 
@@ -448,6 +556,7 @@ fn query_polls() {
                 id: 1u64,
                 creator: TEST_CREATOR.to_string(),
                 status: PollStatus::InProgress,
+                category: Some(PollCategory::ParameterChange),
                 end_height: 20000u64,
                 title: "test".to_string(),
                 description: "test".to_string(),
@@ -456,13 +565,16 @@ fn query_polls() {
                 execute_data: Some(execute_msgs.clone()),
                 yes_votes: Uint128::zero(),
                 no_votes: Uint128::zero(),
-                staked_amount: None,
+                abstain_votes: Uint128::zero(),
+                veto_votes: Uint128::zero(),
+                staked_amount: Some(Uint128::zero()),
                 total_balance_at_end_poll: None,
             },
             PollResponse {
                 id: 2u64,
                 creator: TEST_CREATOR.to_string(),
                 status: PollStatus::InProgress,
+                category: Some(PollCategory::Text),
                 end_height: 20000u64,
                 title: "test2".to_string(),
                 description: "test2".to_string(),
@@ -471,7 +583,9 @@ fn query_polls() {
                 execute_data: None,
                 yes_votes: Uint128::zero(),
                 no_votes: Uint128::zero(),
-                staked_amount: None,
+                abstain_votes: Uint128::zero(),
+                veto_votes: Uint128::zero(),
+                staked_amount: Some(Uint128::zero()),
                 total_balance_at_end_poll: None,
             },
         ]
@@ -495,6 +609,7 @@ fn query_polls() {
             id: 2u64,
             creator: TEST_CREATOR.to_string(),
             status: PollStatus::InProgress,
+            category: Some(PollCategory::Text),
             end_height: 20000u64,
             title: "test2".to_string(),
             description: "test2".to_string(),
@@ -503,7 +618,9 @@ fn query_polls() {
             execute_data: None,
             yes_votes: Uint128::zero(),
             no_votes: Uint128::zero(),
-            staked_amount: None,
+            abstain_votes: Uint128::zero(),
+            veto_votes: Uint128::zero(),
+            staked_amount: Some(Uint128::zero()),
             total_balance_at_end_poll: None,
         },]
     );
@@ -526,6 +643,7 @@ fn query_polls() {
             id: 1u64,
             creator: TEST_CREATOR.to_string(),
             status: PollStatus::InProgress,
+            category: Some(PollCategory::ParameterChange),
             end_height: 20000u64,
             title: "test".to_string(),
             description: "test".to_string(),
@@ -534,7 +652,9 @@ fn query_polls() {
             execute_data: Some(execute_msgs),
             yes_votes: Uint128::zero(),
             no_votes: Uint128::zero(),
-            staked_amount: None,
+            abstain_votes: Uint128::zero(),
+            veto_votes: Uint128::zero(),
+            staked_amount: Some(Uint128::zero()),
             total_balance_at_end_poll: None,
         }]
     );
@@ -557,6 +677,7 @@ fn query_polls() {
             id: 2u64,
             creator: TEST_CREATOR.to_string(),
             status: PollStatus::InProgress,
+            category: Some(PollCategory::Text),
             end_height: 20000u64,
             title: "test2".to_string(),
             description: "test2".to_string(),
@@ -565,7 +686,9 @@ fn query_polls() {
             execute_data: None,
             yes_votes: Uint128::zero(),
             no_votes: Uint128::zero(),
-            staked_amount: None,
+            abstain_votes: Uint128::zero(),
+            veto_votes: Uint128::zero(),
+            staked_amount: Some(Uint128::zero()),
             total_balance_at_end_poll: None,
         },]
     );
@@ -672,21 +795,53 @@ fn happy_days_end_poll() {
     let execute_msgs: Vec<PollExecuteMsg> = vec![
         PollExecuteMsg {
             order: 3u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz3.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz3.clone(),
+            },
         },
         PollExecuteMsg {
             order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz2.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz2.clone(),
+            },
         },
         PollExecuteMsg {
             order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz.clone(),
+            },
         },
     ];
 
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(stake_amount as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(stake_amount, 0, stake_amount, 0, execute_res, deps.as_ref());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
     let msg = create_poll_msg(
         "test".to_string(),
         "test".to_string(),
@@ -710,31 +865,6 @@ fn happy_days_end_poll() {
         deps.as_ref(),
     );
 
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
-        )],
-    )]);
-
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
-
     let msg = ExecuteMsg::CastVote {
         poll_id: 1,
         vote: VoteOption::Yes,
@@ -942,14 +1072,18 @@ fn happy_days_end_poll() {
         StakerResponse {
             balance: Uint128::from(stake_amount),
             share: Uint128::from(stake_amount),
-            locked_balance: vec![]
+            locked_balance: vec![],
+            delegate: None,
         }
     );
 
     // But the data is still in the store
     let voter_addr_raw = deps.api.addr_canonicalize(TEST_VOTER).unwrap();
-    let voter = poll_voter_read(&deps.storage, 1u64)
-        .load(voter_addr_raw.as_slice())
+    let voter = POLL_VOTERS
+        .load(
+            &deps.storage,
+            (U64Key::new(1u64), voter_addr_raw.as_slice()),
+        )
         .unwrap();
     assert_eq!(
         voter,
@@ -959,9 +1093,7 @@ fn happy_days_end_poll() {
         }
     );
 
-    let token_manager = bank_read(&deps.storage)
-        .load(voter_addr_raw.as_slice())
-        .unwrap();
+    let token_manager = BANK.load(&deps.storage, voter_addr_raw.as_slice()).unwrap();
     assert_eq!(
         token_manager.locked_balance,
         vec![(
@@ -992,9 +1124,37 @@ fn fail_poll() {
     .unwrap();
     let execute_msgs: Vec<PollExecuteMsg> = vec![PollExecuteMsg {
         order: 1u64,
-        contract: VOTING_TOKEN.to_string(),
-        msg: exec_msg_bz.clone(),
+        action: PollAction::Raw {
+            contract: VOTING_TOKEN.to_string(),
+            msg: exec_msg_bz.clone(),
+        },
     }];
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(stake_amount as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(stake_amount, 0, stake_amount, 0, execute_res, deps.as_ref());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
     let msg = create_poll_msg(
         "test".to_string(),
         "test".to_string(),
@@ -1018,31 +1178,6 @@ fn fail_poll() {
         deps.as_ref(),
     );
 
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
-        )],
-    )]);
-
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
-
     let msg = ExecuteMsg::CastVote {
         poll_id: 1,
         vote: VoteOption::Yes,
@@ -1173,11 +1308,13 @@ fn end_poll_zero_quorum() {
 
     let execute_msgs: Vec<PollExecuteMsg> = vec![PollExecuteMsg {
         order: 1u64,
-        contract: VOTING_TOKEN.to_string(),
-        msg: to_binary(&Cw20ExecuteMsg::Burn {
-            amount: Uint128::new(123),
-        })
-        .unwrap(),
+        action: PollAction::Raw {
+            contract: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(123),
+            })
+            .unwrap(),
+        },
     }];
 
     let msg = create_poll_msg(
@@ -1287,6 +1424,30 @@ fn end_poll_quorum_rejected() {
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
+    let stake_amount = 100;
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(100u128))],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(stake_amount, 0, stake_amount, 0, execute_res, deps.as_ref());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
     let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
     let mut creator_env = mock_env();
     let mut creator_info = mock_info(VOTING_TOKEN, &[]);
@@ -1307,32 +1468,6 @@ fn end_poll_quorum_rejected() {
         ]
     );
 
-    let stake_amount = 100;
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(100u128 + DEFAULT_PROPOSAL_DEPOSIT),
-        )],
-    )]);
-
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
-
     let msg = ExecuteMsg::CastVote {
         poll_id: 1,
         vote: VoteOption::Yes,
@@ -1422,30 +1557,11 @@ fn end_poll_nay_rejected() {
     let mut creator_env = mock_env();
     let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
 
-    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
-
-    let execute_res = execute(
-        deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
-    )
-    .unwrap();
-    assert_eq!(
-        execute_res.attributes,
-        vec![
-            attr("action", "create_poll"),
-            attr("creator", TEST_CREATOR),
-            attr("poll_id", "1"),
-            attr("end_height", "32345"),
-        ]
-    );
-
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((voter1_stake + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+            &Uint128::from(voter1_stake as u128),
         )],
     )]);
 
@@ -1456,21 +1572,14 @@ fn end_poll_nay_rejected() {
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        voter1_stake,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        voter1_stake,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(voter1_stake, 0, voter1_stake, 0, execute_res, deps.as_ref());
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((voter1_stake + voter2_stake + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+            &Uint128::from((voter1_stake + voter2_stake) as u128),
         )],
     )]);
 
@@ -1481,25 +1590,52 @@ fn end_poll_nay_rejected() {
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
     assert_stake_tokens_result(
         voter1_stake + voter2_stake,
-        DEFAULT_PROPOSAL_DEPOSIT,
+        0,
         voter2_stake,
-        1,
+        0,
         execute_res,
         deps.as_ref(),
     );
 
-    let info = mock_info(TEST_VOTER_2, &[]);
-    let msg = ExecuteMsg::CastVote {
-        poll_id: 1,
-        vote: VoteOption::No,
-        amount: Uint128::from(voter2_stake),
-    };
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_cast_vote_success(TEST_VOTER_2, voter2_stake, 1, VoteOption::No, execute_res);
-
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((voter1_stake + voter2_stake + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        execute_res.attributes,
+        vec![
+            attr("action", "create_poll"),
+            attr("creator", TEST_CREATOR),
+            attr("poll_id", "1"),
+            attr("end_height", "32345"),
+        ]
+    );
+
+    let info = mock_info(TEST_VOTER_2, &[]);
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::No,
+        amount: Uint128::from(voter2_stake),
+    };
+    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_cast_vote_success(TEST_VOTER_2, voter2_stake, 1, VoteOption::No, execute_res);
+
     let msg = ExecuteMsg::EndPoll { poll_id: 1 };
 
     creator_info.sender = Addr::unchecked(TEST_CREATOR);
@@ -1583,25 +1719,9 @@ fn happy_days_cast_vote() {
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
-    let env = mock_env_height(0, 10000);
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
-
-    let execute_res = execute(deps.as_mut(), env, info, msg).unwrap();
-    assert_create_poll_result(
-        1,
-        DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
-    );
-
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from(11u128 + DEFAULT_PROPOSAL_DEPOSIT),
-        )],
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(11u128))],
     )]);
 
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
@@ -1611,17 +1731,30 @@ fn happy_days_cast_vote() {
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        11,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        11,
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(11, 0, 11, 0, execute_res, deps.as_ref());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(11u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+
+    let env = mock_env_height(1, 10000);
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+
+    let execute_res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_create_poll_result(
         1,
+        env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
         execute_res,
         deps.as_ref(),
     );
 
-    let env = mock_env_height(0, 10000);
     let info = mock_info(TEST_VOTER, &coins(11, VOTING_TOKEN));
     let amount = 10u128;
     let msg = ExecuteMsg::CastVote {
@@ -1663,7 +1796,8 @@ fn happy_days_cast_vote() {
                     vote: VoteOption::Yes,
                     balance: Uint128::from(amount),
                 }
-            )]
+            )],
+            delegate: None,
         }
     );
 
@@ -1725,7 +1859,7 @@ fn happy_days_withdraw_voting_tokens() {
     let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_stake_tokens_result(11, 0, 11, 0, execute_res, deps.as_ref());
 
-    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    let state: State = STATE.load(deps.as_ref().storage).unwrap();
     assert_eq!(
         state,
         State {
@@ -1763,7 +1897,7 @@ fn happy_days_withdraw_voting_tokens() {
         }))
     );
 
-    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    let state: State = STATE.load(deps.as_ref().storage).unwrap();
     assert_eq!(
         state,
         State {
@@ -1796,7 +1930,7 @@ fn happy_days_withdraw_voting_tokens_all() {
     let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_stake_tokens_result(11, 0, 11, 0, execute_res, deps.as_ref());
 
-    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    let state: State = STATE.load(deps.as_ref().storage).unwrap();
     assert_eq!(
         state,
         State {
@@ -1832,7 +1966,7 @@ fn happy_days_withdraw_voting_tokens_all() {
         }))
     );
 
-    let state: State = state_read(deps.as_ref().storage).load().unwrap();
+    let state: State = STATE.load(deps.as_ref().storage).unwrap();
     assert_eq!(
         state,
         State {
@@ -1866,15 +2000,18 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
     assert_stake_tokens_result(11, 0, 11, 0, execute_res, deps.as_ref());
 
     // make fake polls; one in progress & one in passed
-    poll_store(&mut deps.storage)
+    polls()
         .save(
-            &1u64.to_be_bytes(),
+            &mut deps.storage,
+            U64Key::new(1),
             &Poll {
                 id: 1u64,
                 creator: CanonicalAddr::from(vec![]),
                 status: PollStatus::InProgress,
                 yes_votes: Uint128::zero(),
                 no_votes: Uint128::zero(),
+                abstain_votes: Uint128::zero(),
+                veto_votes: Uint128::zero(),
                 end_height: 0u64,
                 title: "title".to_string(),
                 description: "description".to_string(),
@@ -1883,19 +2020,26 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
                 execute_data: None,
                 total_balance_at_end_poll: None,
                 staked_amount: None,
+                start_height: 0,
+                snapshot_share: None,
+                vote_escrow: false,
+                category: None,
             },
         )
         .unwrap();
 
-    poll_store(&mut deps.storage)
+    polls()
         .save(
-            &2u64.to_be_bytes(),
+            &mut deps.storage,
+            U64Key::new(2),
             &Poll {
                 id: 1u64,
                 creator: CanonicalAddr::from(vec![]),
                 status: PollStatus::Passed,
                 yes_votes: Uint128::zero(),
                 no_votes: Uint128::zero(),
+                abstain_votes: Uint128::zero(),
+                veto_votes: Uint128::zero(),
                 end_height: 0u64,
                 title: "title".to_string(),
                 description: "description".to_string(),
@@ -1904,53 +2048,62 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
                 execute_data: None,
                 total_balance_at_end_poll: None,
                 staked_amount: None,
+                start_height: 0,
+                snapshot_share: None,
+                vote_escrow: false,
+                category: None,
             },
         )
         .unwrap();
 
     let voter_addr_raw = deps.api.addr_canonicalize(TEST_VOTER).unwrap();
-    poll_voter_store(&mut deps.storage, 1u64)
+    POLL_VOTERS
         .save(
-            voter_addr_raw.as_slice(),
+            &mut deps.storage,
+            (U64Key::new(1), voter_addr_raw.as_slice()),
             &VoterInfo {
                 vote: VoteOption::Yes,
                 balance: Uint128::from(5u128),
             },
         )
         .unwrap();
-    poll_voter_store(&mut deps.storage, 2u64)
+    POLL_VOTERS
         .save(
-            voter_addr_raw.as_slice(),
+            &mut deps.storage,
+            (U64Key::new(2), voter_addr_raw.as_slice()),
             &VoterInfo {
                 vote: VoteOption::Yes,
                 balance: Uint128::from(5u128),
             },
         )
         .unwrap();
-    bank_store(&mut deps.storage)
-        .save(
-            voter_addr_raw.as_slice(),
-            &TokenManager {
-                share: Uint128::from(11u128),
-                locked_balance: vec![
-                    (
-                        1u64,
-                        VoterInfo {
-                            vote: VoteOption::Yes,
-                            balance: Uint128::from(5u128),
-                        },
-                    ),
-                    (
-                        2u64,
-                        VoterInfo {
-                            vote: VoteOption::Yes,
-                            balance: Uint128::from(5u128),
-                        },
-                    ),
-                ],
-            },
-        )
-        .unwrap();
+    BANK.save(
+        &mut deps.storage,
+        voter_addr_raw.as_slice(),
+        &TokenManager {
+            share: Uint128::from(11u128),
+            delegate: None,
+            delegated_share: Uint128::zero(),
+            lock: None,
+            locked_balance: vec![
+                (
+                    1u64,
+                    VoterInfo {
+                        vote: VoteOption::Yes,
+                        balance: Uint128::from(5u128),
+                    },
+                ),
+                (
+                    2u64,
+                    VoterInfo {
+                        vote: VoteOption::Yes,
+                        balance: Uint128::from(5u128),
+                    },
+                ),
+            ],
+        },
+    )
+    .unwrap();
 
     // withdraw voting token must remove not in-progress votes infos from the store
     let info = mock_info(TEST_VOTER, &[]);
@@ -1959,8 +2112,11 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
     };
 
     let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    let voter = poll_voter_read(&deps.storage, 1u64)
-        .load(voter_addr_raw.as_slice())
+    let voter = POLL_VOTERS
+        .load(
+            &deps.storage,
+            (U64Key::new(1u64), voter_addr_raw.as_slice()),
+        )
         .unwrap();
     assert_eq!(
         voter,
@@ -1969,13 +2125,14 @@ fn withdraw_voting_tokens_remove_not_in_progress_poll_voter_info() {
             balance: Uint128::from(5u128),
         }
     );
-    assert!(poll_voter_read(&deps.storage, 2u64)
-        .load(voter_addr_raw.as_slice())
+    assert!(POLL_VOTERS
+        .load(
+            &deps.storage,
+            (U64Key::new(2u64), voter_addr_raw.as_slice())
+        )
         .is_err(),);
 
-    let token_manager = bank_read(&deps.storage)
-        .load(voter_addr_raw.as_slice())
-        .unwrap();
+    let token_manager = BANK.load(&deps.storage, voter_addr_raw.as_slice()).unwrap();
     assert_eq!(
         token_manager.locked_balance,
         vec![(
@@ -2049,19 +2206,20 @@ fn fails_cast_vote_twice() {
     mock_instantiate(deps.as_mut());
     mock_register_voting_token(deps.as_mut());
 
-    let env = mock_env_height(0, 10000);
-    let info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(11u128))],
+    )]);
 
-    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
-    let execute_res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(11u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    });
 
-    assert_create_poll_result(
-        1,
-        env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
-    );
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(11, 0, 11, 0, execute_res, deps.as_ref());
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
@@ -2071,19 +2229,15 @@ fn fails_cast_vote_twice() {
         )],
     )]);
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(11u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
+    let env = mock_env_height(1, 10000);
+    let info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    let execute_res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        11,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        11,
+    assert_create_poll_result(
         1,
+        env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
         execute_res,
         deps.as_ref(),
     );
@@ -2094,7 +2248,6 @@ fn fails_cast_vote_twice() {
         vote: VoteOption::Yes,
         amount: Uint128::from(amount),
     };
-    let env = mock_env_height(0, 10000);
     let info = mock_info(TEST_VOTER, &[]);
     let execute_res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
     assert_cast_vote_success(TEST_VOTER, amount, 1, VoteOption::Yes, execute_res);
@@ -2315,16 +2468,9 @@ fn assert_create_poll_result(
     );
 
     //confirm poll count
-    let state: State = state_read(deps.storage).load().unwrap();
-    assert_eq!(
-        state,
-        State {
-            contract_addr: deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
-            poll_count: 1,
-            total_share: Uint128::zero(),
-            total_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
-        }
-    );
+    let state: State = STATE.load(deps.storage).unwrap();
+    assert_eq!(state.poll_count, 1);
+    assert_eq!(state.total_deposit, Uint128::from(DEFAULT_PROPOSAL_DEPOSIT));
 }
 
 fn assert_stake_tokens_result(
@@ -2340,7 +2486,7 @@ fn assert_stake_tokens_result(
         &attr("share", new_share.to_string())
     );
 
-    let state: State = state_read(deps.storage).load().unwrap();
+    let state: State = STATE.load(deps.storage).unwrap();
     assert_eq!(
         state,
         State {
@@ -2352,6 +2498,41 @@ fn assert_stake_tokens_result(
     );
 }
 
+// drops the creation snapshot like a poll stored before voting power snapshots
+fn clear_poll_snapshot(deps: DepsMut, poll_id: u64) {
+    let mut poll: Poll = polls().load(deps.storage, U64Key::new(poll_id)).unwrap();
+    poll.staked_amount = None;
+    poll.snapshot_share = None;
+    polls()
+        .save(deps.storage, U64Key::new(poll_id), &poll)
+        .unwrap();
+}
+
+// stakes for every voter, the contract balance also holds the given deposits
+fn stake_voters(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    stakers: &[(&str, u128)],
+    deposits: u128,
+) {
+    let mut total_balance = deposits;
+    for (staker, amount) in stakers {
+        total_balance += amount;
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(total_balance),
+            )],
+        )]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: staker.to_string(),
+            amount: Uint128::from(*amount),
+            msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        });
+        execute(deps.as_mut(), mock_env(), mock_info(VOTING_TOKEN, &[]), msg).unwrap();
+    }
+}
+
 fn assert_cast_vote_success(
     voter: &str,
     amount: u128,
@@ -2387,6 +2568,13 @@ fn update_config() {
         timelock_period: None,
         proposal_deposit: None,
         snapshot_period: None,
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: None,
+        raw_msg_threshold: None,
+        categories: None,
+        expiration_period: None,
+        cancel_refund_ratio: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -2402,7 +2590,7 @@ fn update_config() {
     assert_eq!(DEFAULT_TIMELOCK_PERIOD, config.timelock_period);
     assert_eq!(DEFAULT_PROPOSAL_DEPOSIT, config.proposal_deposit.u128());
 
-    // update left items
+    // update left items, raw messages can not pass below the threshold
     let info = mock_info("addr0001", &[]);
     let msg = ExecuteMsg::UpdateConfig {
         owner: None,
@@ -2412,8 +2600,39 @@ fn update_config() {
         timelock_period: Some(20000u64),
         proposal_deposit: Some(Uint128::from(123u128)),
         snapshot_period: Some(11),
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: None,
+        raw_msg_threshold: None,
+        categories: None,
+        expiration_period: None,
+        cancel_refund_ratio: None,
     };
 
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "raw_msg_threshold must be threshold to 1"
+        ))
+    );
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        quorum: Some(Decimal::percent(20)),
+        threshold: Some(Decimal::percent(75)),
+        voting_period: Some(20000u64),
+        timelock_period: Some(20000u64),
+        proposal_deposit: Some(Uint128::from(123u128)),
+        snapshot_period: Some(11),
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: None,
+        raw_msg_threshold: Some(Decimal::percent(80)),
+        categories: None,
+        expiration_period: None,
+        cancel_refund_ratio: None,
+    };
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(0, res.messages.len());
 
@@ -2427,6 +2646,7 @@ fn update_config() {
     assert_eq!(20000u64, config.timelock_period);
     assert_eq!(123u128, config.proposal_deposit.u128());
     assert_eq!(11u64, config.snapshot_period);
+    assert_eq!(Decimal::percent(80), config.raw_msg_threshold);
 
     // Unauthorzied err
     let info = mock_info(TEST_CREATOR, &[]);
@@ -2438,6 +2658,13 @@ fn update_config() {
         timelock_period: None,
         proposal_deposit: None,
         snapshot_period: None,
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: None,
+        raw_msg_threshold: None,
+        categories: None,
+        expiration_period: None,
+        cancel_refund_ratio: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -2474,18 +2701,24 @@ fn add_several_execute_msgs() {
     let execute_msgs: Vec<PollExecuteMsg> = vec![
         PollExecuteMsg {
             order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz,
+            },
         },
         PollExecuteMsg {
             order: 3u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz3,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz3,
+            },
         },
         PollExecuteMsg {
             order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz2,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz2,
+            },
         },
     ];
 
@@ -2552,31 +2785,67 @@ fn execute_poll_with_order() {
     let execute_msgs: Vec<PollExecuteMsg> = vec![
         PollExecuteMsg {
             order: 3u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz3.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz3.clone(),
+            },
         },
         PollExecuteMsg {
             order: 4u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz4.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz4.clone(),
+            },
         },
         PollExecuteMsg {
             order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz2.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz2.clone(),
+            },
         },
         PollExecuteMsg {
             order: 5u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz5.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz5.clone(),
+            },
         },
         PollExecuteMsg {
             order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz.clone(),
+            },
         },
     ];
 
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(stake_amount as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(stake_amount, 0, stake_amount, 0, execute_res, deps.as_ref());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
     let msg = create_poll_msg(
         "test".to_string(),
         "test".to_string(),
@@ -2600,31 +2869,6 @@ fn execute_poll_with_order() {
         deps.as_ref(),
     );
 
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
-        )],
-    )]);
-
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
-
     let msg = ExecuteMsg::CastVote {
         poll_id: 1,
         vote: VoteOption::Yes,
@@ -2754,6 +2998,32 @@ fn poll_with_empty_execute_data_marked_as_executed() {
     let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
     let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
 
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(stake_amount as u128),
+        )],
+    )]);
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: TEST_VOTER.to_string(),
+        amount: Uint128::from(stake_amount as u128),
+        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+    });
+
+    let info = mock_info(VOTING_TOKEN, &[]);
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(stake_amount, 0, stake_amount, 0, execute_res, deps.as_ref());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
     let msg = create_poll_msg("test".to_string(), "test".to_string(), None, Some(vec![]));
 
     let execute_res = execute(
@@ -2772,31 +3042,6 @@ fn poll_with_empty_execute_data_marked_as_executed() {
         deps.as_ref(),
     );
 
-    deps.querier.with_token_balances(&[(
-        &VOTING_TOKEN.to_string(),
-        &[(
-            &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
-        )],
-    )]);
-
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: TEST_VOTER.to_string(),
-        amount: Uint128::from(stake_amount as u128),
-        msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
-    });
-
-    let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
-        1,
-        execute_res,
-        deps.as_ref(),
-    );
-
     let msg = ExecuteMsg::CastVote {
         poll_id: 1,
         vote: VoteOption::Yes,
@@ -2916,29 +3161,11 @@ fn poll_with_none_execute_data_marked_as_executed() {
     let mut creator_env = mock_env_height(POLL_START_HEIGHT, 10000);
     let mut creator_info = mock_info(VOTING_TOKEN, &coins(2, VOTING_TOKEN));
 
-    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
-
-    let execute_res = execute(
-        deps.as_mut(),
-        creator_env.clone(),
-        creator_info.clone(),
-        msg,
-    )
-    .unwrap();
-
-    assert_create_poll_result(
-        1,
-        creator_env.block.height + DEFAULT_VOTING_PERIOD,
-        TEST_CREATOR,
-        execute_res,
-        deps.as_ref(),
-    );
-
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
         &[(
             &MOCK_CONTRACT_ADDR.to_string(),
-            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+            &Uint128::from(stake_amount as u128),
         )],
     )]);
 
@@ -2949,12 +3176,31 @@ fn poll_with_none_execute_data_marked_as_executed() {
     });
 
     let info = mock_info(VOTING_TOKEN, &[]);
-    let execute_res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    assert_stake_tokens_result(
-        stake_amount,
-        DEFAULT_PROPOSAL_DEPOSIT,
-        stake_amount,
+    let execute_res = execute(deps.as_mut(), mock_env_height(0, 10000), info, msg).unwrap();
+    assert_stake_tokens_result(stake_amount, 0, stake_amount, 0, execute_res, deps.as_ref());
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from((stake_amount + DEFAULT_PROPOSAL_DEPOSIT) as u128),
+        )],
+    )]);
+
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+
+    let execute_res = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        msg,
+    )
+    .unwrap();
+
+    assert_create_poll_result(
         1,
+        creator_env.block.height + DEFAULT_VOTING_PERIOD,
+        TEST_CREATOR,
         execute_res,
         deps.as_ref(),
     );
@@ -3115,6 +3361,19 @@ fn snapshot_poll() {
         )],
     )]);
 
+    // the staked amount is already recorded at poll creation
+    let snapshot_err = execute(
+        deps.as_mut(),
+        creator_env.clone(),
+        creator_info.clone(),
+        ExecuteMsg::SnapshotPoll { poll_id: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::SnapshotAlreadyOccurred {}, snapshot_err);
+
+    // polls created before voting power snapshots still take it near the end
+    clear_poll_snapshot(deps.as_mut(), 1);
+
     let fix_res = execute(
         deps.as_mut(),
         creator_env.clone(),
@@ -3161,6 +3420,7 @@ fn happy_days_cast_vote_with_snapshot() {
         execute_res,
         deps.as_ref(),
     );
+    clear_poll_snapshot(deps.as_mut(), 1);
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
@@ -3305,13 +3565,17 @@ fn fails_end_poll_quorum_inflation_without_snapshot_poll() {
     let execute_msgs: Vec<PollExecuteMsg> = vec![
         PollExecuteMsg {
             order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz.clone(),
+            },
         },
         PollExecuteMsg {
             order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz,
+            },
         },
     ];
 
@@ -3337,6 +3601,7 @@ fn fails_end_poll_quorum_inflation_without_snapshot_poll() {
         execute_res,
         deps.as_ref(),
     );
+    clear_poll_snapshot(deps.as_mut(), 1);
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
@@ -3474,13 +3739,17 @@ fn happy_days_end_poll_with_controlled_quorum() {
     let execute_msgs: Vec<PollExecuteMsg> = vec![
         PollExecuteMsg {
             order: 1u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz.clone(),
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz.clone(),
+            },
         },
         PollExecuteMsg {
             order: 2u64,
-            contract: VOTING_TOKEN.to_string(),
-            msg: exec_msg_bz,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: exec_msg_bz,
+            },
         },
     ];
 
@@ -3506,6 +3775,7 @@ fn happy_days_end_poll_with_controlled_quorum() {
         execute_res,
         deps.as_ref(),
     );
+    clear_poll_snapshot(deps.as_mut(), 1);
 
     deps.querier.with_token_balances(&[(
         &VOTING_TOKEN.to_string(),
@@ -3661,3 +3931,2055 @@ fn happy_days_end_poll_with_controlled_quorum() {
 
     assert_eq!(actual_staked_weight.u128(), (10 * stake_amount))
 }
+
+#[test]
+fn delegate_voting_power() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    stake_voters(
+        &mut deps,
+        &[(TEST_VOTER, 10), (TEST_VOTER_2, 20), (TEST_VOTER_3, 30)],
+        0,
+    );
+    let mut env = mock_env();
+
+    let msg = ExecuteMsg::Delegate {
+        delegate: TEST_VOTER_3.to_string(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_3, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::CannotDelegateToSelf {});
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER, &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::DelegatePower {
+            delegate: TEST_VOTER_3.to_string(),
+        },
+    )
+    .unwrap();
+    let power: DelegatePowerResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        power,
+        DelegatePowerResponse {
+            delegate: TEST_VOTER_3.to_string(),
+            balance: Uint128::from(30u128),
+            delegated_balance: Uint128::from(30u128),
+            voting_power: Uint128::from(60u128),
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Delegators {
+            delegate: TEST_VOTER_3.to_string(),
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let delegators: DelegatorsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        delegators.delegators,
+        vec![
+            DelegatorsResponseItem {
+                delegator: TEST_VOTER_2.to_string(),
+                share: Uint128::from(20u128),
+                balance: Uint128::from(20u128),
+            },
+            DelegatorsResponseItem {
+                delegator: TEST_VOTER.to_string(),
+                share: Uint128::from(10u128),
+                balance: Uint128::from(10u128),
+            },
+        ]
+    );
+
+    // the poll weighs the delegations made before its creation
+    env.block.height += 1;
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(60u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(VOTING_TOKEN, &[]),
+        msg,
+    )
+    .unwrap();
+
+    // a delegator voting first keeps its weight out of the delegate vote
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::No,
+        amount: Uint128::from(10u128),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(51u128),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_3, &[]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InsufficientStaked {});
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(50u128),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_3, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.yes_votes, Uint128::from(50u128));
+    assert_eq!(poll.no_votes, Uint128::from(10u128));
+
+    // the delegated stake stays locked while the delegate vote counts it
+    let msg = ExecuteMsg::WithdrawVotingTokens {
+        amount: Some(Uint128::from(1u128)),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidWithdrawAmount {});
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::Undelegate {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::DelegationLocked {});
+
+    // a delegator voting afterwards takes its weight back
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::No,
+        amount: Uint128::from(20u128),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.yes_votes, Uint128::from(30u128));
+    assert_eq!(poll.no_votes, Uint128::from(30u128));
+    let delegate_raw = deps.api.addr_canonicalize(TEST_VOTER_3).unwrap();
+    let voter = POLL_VOTERS
+        .load(&deps.storage, (U64Key::new(1u64), delegate_raw.as_slice()))
+        .unwrap();
+    assert_eq!(voter.balance, Uint128::from(30u128));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::Undelegate {},
+    )
+    .unwrap();
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::DelegatePower {
+            delegate: TEST_VOTER_3.to_string(),
+        },
+    )
+    .unwrap();
+    let power: DelegatePowerResponse = from_binary(&res).unwrap();
+    assert_eq!(power.delegated_balance, Uint128::from(10u128));
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::Staker {
+            address: TEST_VOTER.to_string(),
+        },
+    )
+    .unwrap();
+    let staker: StakerResponse = from_binary(&res).unwrap();
+    assert_eq!(staker.delegate, Some(TEST_VOTER_3.to_string()));
+}
+
+#[test]
+fn end_poll_abstain_and_veto() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        quorum: None,
+        threshold: None,
+        voting_period: None,
+        timelock_period: None,
+        proposal_deposit: None,
+        snapshot_period: None,
+        veto_threshold: Some(Decimal::percent(101)),
+        community_contract: Some("community".to_string()),
+        max_lock_period: None,
+        raw_msg_threshold: None,
+        categories: None,
+        expiration_period: None,
+        cancel_refund_ratio: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("veto_threshold must be 0 to 1"))
+    );
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        quorum: None,
+        threshold: None,
+        voting_period: None,
+        timelock_period: None,
+        proposal_deposit: None,
+        snapshot_period: None,
+        veto_threshold: None,
+        community_contract: Some("community".to_string()),
+        max_lock_period: None,
+        raw_msg_threshold: None,
+        categories: None,
+        expiration_period: None,
+        cancel_refund_ratio: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    stake_voters(
+        &mut deps,
+        &[(TEST_VOTER, 100), (TEST_VOTER_2, 100), (TEST_VOTER_3, 100)],
+        0,
+    );
+    let mut env = mock_env();
+    env.block.height += 1;
+    for deposits in 1..=3 {
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(300u128 + deposits * DEFAULT_PROPOSAL_DEPOSIT),
+            )],
+        )]);
+        let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let votes = [
+        (1u64, TEST_VOTER, VoteOption::Yes),
+        (1, TEST_VOTER_3, VoteOption::NoWithVeto),
+        (2, TEST_VOTER, VoteOption::Yes),
+        (2, TEST_VOTER_2, VoteOption::Abstain),
+        (3, TEST_VOTER_2, VoteOption::Abstain),
+    ];
+    for (poll_id, voter, vote) in votes {
+        let msg = ExecuteMsg::CastVote {
+            poll_id,
+            vote: vote.clone(),
+            amount: Uint128::from(100u128),
+        };
+        let execute_res = execute(deps.as_mut(), env.clone(), mock_info(voter, &[]), msg).unwrap();
+        assert_cast_vote_success(voter, 100, poll_id, vote, execute_res);
+    }
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Poll { poll_id: 2 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.yes_votes, Uint128::from(100u128));
+    assert_eq!(poll.abstain_votes, Uint128::from(100u128));
+
+    env.block.height += DEFAULT_VOTING_PERIOD;
+    let deposit_transfer = |recipient: &str| {
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
+    };
+
+    // the veto share is above the threshold, the deposit goes to the community
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(res.attributes[2], attr("rejected_reason", "Vetoed"));
+    assert_eq!(res.messages, vec![deposit_transfer("community")]);
+
+    // abstain votes count toward the quorum only
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(300u128 + 2 * DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    let msg = ExecuteMsg::EndPoll { poll_id: 2 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(res.attributes[3], attr("passed", "true"));
+    assert_eq!(res.messages, vec![deposit_transfer(TEST_CREATOR)]);
+
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(300u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    let msg = ExecuteMsg::EndPoll { poll_id: 3 };
+    let res = execute(deps.as_mut(), env, mock_info(TEST_CREATOR, &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes[2],
+        attr("rejected_reason", "Threshold not reached")
+    );
+    assert_eq!(res.messages, vec![deposit_transfer(TEST_CREATOR)]);
+}
+
+#[test]
+fn cast_vote_with_voting_power_at_poll_creation() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    stake_voters(&mut deps, &[(TEST_VOTER, 100)], 0);
+
+    let mut env = mock_env();
+    env.block.height += 1;
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(VOTING_TOKEN, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.staked_amount, Some(Uint128::from(100u128)));
+
+    // stake added after the poll creation, even in the same block, has no weight
+    for (staker, total_balance) in [(TEST_VOTER_2, 200u128), (TEST_VOTER, 300u128)] {
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(total_balance + DEFAULT_PROPOSAL_DEPOSIT),
+            )],
+        )]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: staker.to_string(),
+            amount: Uint128::from(100u128),
+            msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        });
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::No,
+        amount: Uint128::from(1u128),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InsufficientStaked {});
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(101u128),
+    };
+    let err = execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientStaked {});
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(40u128),
+    };
+    let execute_res = execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+    assert_cast_vote_success(TEST_VOTER, 40, 1, VoteOption::Yes, execute_res);
+
+    // the quorum is measured against the total staked at the poll creation
+    env.block.height += DEFAULT_VOTING_PERIOD;
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let res = execute(deps.as_mut(), env, mock_info(TEST_CREATOR, &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "end_poll"),
+            attr("poll_id", "1"),
+            attr("rejected_reason", ""),
+            attr("passed", "true"),
+        ]
+    );
+}
+
+#[test]
+fn vote_escrow_weights() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    stake_voters(&mut deps, &[(TEST_VOTER, 100), (TEST_VOTER_2, 100)], 0);
+
+    let mut env = mock_env();
+    let start = env.block.height;
+    let msg = ExecuteMsg::CreateLock {
+        amount: Uint128::from(100u128),
+        end_height: start + 1000,
+    };
+    let err = execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::LocksDisabled {});
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        quorum: None,
+        threshold: None,
+        voting_period: None,
+        timelock_period: None,
+        proposal_deposit: None,
+        snapshot_period: None,
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: Some(1000),
+        raw_msg_threshold: None,
+        categories: None,
+        expiration_period: None,
+        cancel_refund_ratio: None,
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::CreateLock {
+        amount: Uint128::from(100u128),
+        end_height: start + 1001,
+    };
+    let err = execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidLockEnd {});
+    let msg = ExecuteMsg::CreateLock {
+        amount: Uint128::from(101u128),
+        end_height: start + 1000,
+    };
+    let err = execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientStaked {});
+
+    let msg = ExecuteMsg::CreateLock {
+        amount: Uint128::from(100u128),
+        end_height: start + 1000,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+    let msg = ExecuteMsg::CreateLock {
+        amount: Uint128::from(20u128),
+        end_height: start + 500,
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap();
+    let msg = ExecuteMsg::IncreaseLockAmount {
+        amount: Uint128::from(20u128),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap();
+    let msg = ExecuteMsg::Delegate {
+        delegate: TEST_VOTER.to_string(),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap();
+
+    // the locked share can not be withdrawn
+    let msg = ExecuteMsg::WithdrawVotingTokens {
+        amount: Some(Uint128::from(1u128)),
+    };
+    let err = execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidWithdrawAmount {});
+
+    // the weight decays with the remaining lock period
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::VotingEscrow {
+            address: TEST_VOTER_2.to_string(),
+            height: Some(start + 250),
+        },
+    )
+    .unwrap();
+    let escrow: VotingEscrowResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        escrow,
+        VotingEscrowResponse {
+            address: TEST_VOTER_2.to_string(),
+            height: start + 250,
+            locked_amount: Uint128::from(40u128),
+            end_height: start + 500,
+            voting_power: Uint128::from(10u128),
+        }
+    );
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::TotalVotingEscrow {
+            height: Some(start + 500),
+        },
+    )
+    .unwrap();
+    let total: TotalVotingEscrowResponse = from_binary(&res).unwrap();
+    assert_eq!(total.voting_power, Uint128::from(50u128));
+
+    env.block.height = start + 250;
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(200u128 + DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    let msg = create_poll_msg("test".to_string(), "test".to_string(), None, None);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(VOTING_TOKEN, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.staked_amount, Some(Uint128::from(85u128)));
+
+    // the delegate votes with its lock and the lock of the delegator
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(86u128),
+    };
+    let err = execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientStaked {});
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(85u128),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 1,
+        vote: VoteOption::No,
+        amount: Uint128::from(10u128),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.yes_votes, Uint128::from(75u128));
+    assert_eq!(poll.no_votes, Uint128::from(10u128));
+
+    // locks changed after the poll creation do not change its weights
+    let msg = ExecuteMsg::ExtendLock {
+        end_height: start + 1250,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::TotalVotingEscrow {
+            height: Some(start + 250),
+        },
+    )
+    .unwrap();
+    let total: TotalVotingEscrowResponse = from_binary(&res).unwrap();
+    assert_eq!(total.voting_power, Uint128::from(85u128));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::WithdrawLock {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::LockNotExpired {});
+
+    env.block.height = start + 500;
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info(TEST_VOTER_2, &[]),
+        ExecuteMsg::WithdrawLock {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_VOTER_2.to_string(),
+                amount: Uint128::from(40u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+}
+
+#[test]
+fn typed_poll_actions() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    stake_voters(&mut deps, &[(TEST_VOTER, 70), (TEST_VOTER_2, 30)], 0);
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        quorum: None,
+        threshold: None,
+        voting_period: None,
+        timelock_period: None,
+        proposal_deposit: None,
+        snapshot_period: None,
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: None,
+        raw_msg_threshold: Some(Decimal::percent(75)),
+        categories: Some(vec![PollCategoryParams {
+            category: PollCategory::Emergency,
+            quorum: Decimal::percent(DEFAULT_QUORUM),
+            threshold: Decimal::percent(67),
+            voting_period: 100,
+            timelock_period: 0,
+            proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+        }]),
+        expiration_period: None,
+        cancel_refund_ratio: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    let mut env = mock_env();
+    env.block.height += 1;
+    let invalid_actions = vec![
+        (
+            PollAction::CommunitySpend {
+                community: "community".to_string(),
+                recipient: TEST_VOTER.to_string(),
+                amount: Uint128::zero(),
+            },
+            "spend amount is zero",
+        ),
+        (
+            PollAction::UpdateGameParams {
+                game: "game".to_string(),
+                param: GameParam::WinTax(Decimal::percent(101)),
+            },
+            "win tax must be 0 to 1",
+        ),
+    ];
+    for (action, reason) in invalid_actions {
+        let msg = create_poll_msg(
+            "test".to_string(),
+            "test".to_string(),
+            None,
+            Some(vec![PollExecuteMsg { order: 1, action }]),
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidPollAction(reason.to_string()));
+    }
+
+    // emergency polls only remove games and update their parameters
+    for action in [
+        PollAction::CommunitySpend {
+            community: "community".to_string(),
+            recipient: TEST_VOTER.to_string(),
+            amount: Uint128::from(10u128),
+        },
+        PollAction::Raw {
+            contract: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(123),
+            })
+            .unwrap(),
+        },
+    ] {
+        let msg = create_category_poll_msg(
+            Some(vec![PollExecuteMsg { order: 1, action }]),
+            Some(PollCategory::Emergency),
+            DEFAULT_PROPOSAL_DEPOSIT,
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::PollCategoryMismatch("emergency".to_string())
+        );
+    }
+
+    let typed_msgs = vec![
+        PollExecuteMsg {
+            order: 1,
+            action: PollAction::RemoveGame {
+                reserve: "reserve".to_string(),
+                game: "game".to_string(),
+            },
+        },
+        PollExecuteMsg {
+            order: 2,
+            action: PollAction::UpdateGameParams {
+                game: "game".to_string(),
+                param: GameParam::MaxNumberOfBets(10),
+            },
+        },
+    ];
+    let raw_msgs = vec![PollExecuteMsg {
+        order: 1,
+        action: PollAction::Raw {
+            contract: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(123),
+            })
+            .unwrap(),
+        },
+    }];
+    for (deposits, execute_msgs, category) in [
+        (1u128, typed_msgs.clone(), PollCategory::Emergency),
+        (2, raw_msgs, PollCategory::ParameterChange),
+    ] {
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(100u128 + deposits * DEFAULT_PROPOSAL_DEPOSIT),
+            )],
+        )]);
+        let msg =
+            create_category_poll_msg(Some(execute_msgs), Some(category), DEFAULT_PROPOSAL_DEPOSIT);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.execute_data, Some(typed_msgs));
+
+    for poll_id in 1..=2 {
+        for (voter, vote, amount) in [
+            (TEST_VOTER, VoteOption::Yes, 70u128),
+            (TEST_VOTER_2, VoteOption::No, 30),
+        ] {
+            let msg = ExecuteMsg::CastVote {
+                poll_id,
+                vote,
+                amount: Uint128::from(amount),
+            };
+            execute(deps.as_mut(), env.clone(), mock_info(voter, &[]), msg).unwrap();
+        }
+    }
+
+    // the same votes do not pass the raw message threshold
+    env.block.height += DEFAULT_VOTING_PERIOD;
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(res.attributes[3], attr("passed", "true"));
+    let msg = ExecuteMsg::EndPoll { poll_id: 2 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[2],
+        attr("rejected_reason", "Threshold not reached")
+    );
+
+    let msg = ExecuteMsg::ExecutePollMsgs { poll_id: 1 };
+    let res = execute(deps.as_mut(), env, mock_info(MOCK_CONTRACT_ADDR, &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "reserve".to_string(),
+                msg: Binary::from(br#"{"remove_game":{"addr":"game"}}"#),
+                funds: vec![],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "game".to_string(),
+                msg: Binary::from(br#"{"change_max_number_of_bets":{"number_of_bets":10}}"#),
+                funds: vec![],
+            })),
+        ]
+    );
+}
+
+#[test]
+fn simulate_poll_msgs() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    deps.querier.with_contract_admins(&[
+        (MOCK_CONTRACT_ADDR, None),
+        (VOTING_TOKEN, None),
+        ("reserve", None),
+        ("game", Some(TEST_CREATOR)),
+        ("community", None),
+        ("distribution", None),
+        ("other", None),
+    ]);
+    deps.querier.with_contract_config(
+        "community",
+        &community::ConfigResponse {
+            gov_contract: MOCK_CONTRACT_ADDR.to_string(),
+            token_contract: VOTING_TOKEN.to_string(),
+            spend_limit: Uint128::from(100u128),
+        },
+    );
+    deps.querier.with_contract_config(
+        "distribution",
+        &distribution::ConfigResponse {
+            governance_token_addr: VOTING_TOKEN.to_string(),
+            staking_token: None,
+            reward_denom: "uusd".to_string(),
+            unbonding_period: 0,
+        },
+    );
+    deps.querier
+        .with_raw_storage("distribution", b"owner", &Addr::unchecked(TEST_CREATOR));
+    deps.querier.with_raw_storage(
+        "reserve",
+        b"config",
+        &GovernedConfig {
+            gov_contract_address: Addr::unchecked(MOCK_CONTRACT_ADDR),
+        },
+    );
+    deps.querier.with_raw_storage(
+        "game",
+        b"casino_config",
+        &GovernedConfig {
+            gov_contract_address: Addr::unchecked("old-gov"),
+        },
+    );
+    deps.querier
+        .with_raw_storage("game", b"owner", &Addr::unchecked(TEST_CREATOR));
+
+    let msgs = vec![
+        (
+            PollAction::Raw {
+                contract: MOCK_CONTRACT_ADDR.to_string(),
+                msg: to_binary(&ExecuteMsg::UpdateConfig {
+                    owner: None,
+                    quorum: None,
+                    threshold: None,
+                    voting_period: None,
+                    timelock_period: None,
+                    proposal_deposit: None,
+                    snapshot_period: None,
+                    veto_threshold: None,
+                    community_contract: None,
+                    max_lock_period: None,
+                    raw_msg_threshold: None,
+                    categories: None,
+                    expiration_period: None,
+                    cancel_refund_ratio: None,
+                })
+                .unwrap(),
+            },
+            unauthorized("governance is not its own owner"),
+        ),
+        (
+            PollAction::AddGame {
+                reserve: "reserve".to_string(),
+                game: "game".to_string(),
+            },
+            SimulationStatus::Ok,
+        ),
+        (
+            PollAction::MigrateContract {
+                contract: "game".to_string(),
+                new_code_id: 7,
+                msg: Binary::from(br#"{}"#),
+            },
+            unauthorized("governance is not the contract admin"),
+        ),
+        (
+            PollAction::CommunitySpend {
+                community: "community".to_string(),
+                recipient: TEST_VOTER.to_string(),
+                amount: Uint128::from(101u128),
+            },
+            SimulationStatus::InvalidMsg {
+                reason: "amount is above the spend limit".to_string(),
+            },
+        ),
+        (
+            PollAction::CommunitySpend {
+                community: "community".to_string(),
+                recipient: TEST_VOTER.to_string(),
+                amount: Uint128::zero(),
+            },
+            SimulationStatus::InvalidMsg {
+                reason: "Invalid poll action: spend amount is zero".to_string(),
+            },
+        ),
+        (
+            PollAction::UpdateDistributionConfig {
+                distribution: "distribution".to_string(),
+                reward_denom: None,
+                distribution_ratio: Some(10),
+                unbonding_period: None,
+            },
+            unauthorized("governance is not the distribution owner"),
+        ),
+        (
+            PollAction::Raw {
+                contract: "unknown".to_string(),
+                msg: Binary::from(br#"{}"#),
+            },
+            SimulationStatus::InvalidContract {
+                reason: "not a contract".to_string(),
+            },
+        ),
+        (
+            PollAction::Raw {
+                contract: "game".to_string(),
+                msg: Binary::from(br#"{"stop_game":{}}"#),
+            },
+            SimulationStatus::UnknownSchema,
+        ),
+        (
+            PollAction::Raw {
+                contract: "reserve".to_string(),
+                msg: Binary::from(br#"{"remove_game":{"addr":"game"}}"#),
+            },
+            SimulationStatus::Ok,
+        ),
+        (
+            PollAction::UpdateGameParams {
+                game: "game".to_string(),
+                param: GameParam::MaxNumberOfBets(10),
+            },
+            unauthorized("governance is not the game governance"),
+        ),
+        (
+            PollAction::Raw {
+                contract: "game".to_string(),
+                msg: Binary::from(br#"{"change_round_duration":{"duration":60}}"#),
+            },
+            unauthorized("governance is not the game governance"),
+        ),
+        (
+            PollAction::AddGame {
+                reserve: "other".to_string(),
+                game: "game".to_string(),
+            },
+            SimulationStatus::InvalidContract {
+                reason: "not a reserve contract".to_string(),
+            },
+        ),
+    ];
+
+    // the results are in the execution order
+    let execute_msgs: Vec<PollExecuteMsg> = msgs
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(order, (action, _))| PollExecuteMsg {
+            order: order as u64,
+            action: action.clone(),
+        })
+        .collect();
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SimulateExecuteMsgs { msgs: execute_msgs },
+    )
+    .unwrap();
+    let simulation: SimulationResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        simulation.results,
+        msgs.into_iter()
+            .enumerate()
+            .map(|(order, (action, status))| SimulationResult {
+                order: order as u64,
+                contract: action.contract().to_string(),
+                status,
+            })
+            .collect::<Vec<SimulationResult>>()
+    );
+
+    // raw messages to the known contracts are checked against their schema
+    let msg = create_poll_msg(
+        "test".to_string(),
+        "test".to_string(),
+        None,
+        Some(vec![PollExecuteMsg {
+            order: 1,
+            action: PollAction::Raw {
+                contract: VOTING_TOKEN.to_string(),
+                msg: Binary::from(br#"{"stop_game":{}}"#),
+            },
+        }]),
+    );
+    execute(deps.as_mut(), mock_env(), mock_info(VOTING_TOKEN, &[]), msg).unwrap();
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SimulatePoll { poll_id: 1 },
+    )
+    .unwrap();
+    let simulation: SimulationResponse = from_binary(&res).unwrap();
+    assert!(matches!(
+        simulation.results[0].status,
+        SimulationStatus::InvalidMsg { .. }
+    ));
+
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SimulatePoll { poll_id: 2 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollNotFound {});
+}
+
+fn unauthorized(reason: &str) -> SimulationStatus {
+    SimulationStatus::Unauthorized {
+        reason: reason.to_string(),
+    }
+}
+
+#[test]
+fn poll_categories() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    let treasury_params = PollCategoryParams {
+        category: PollCategory::TreasurySpend,
+        quorum: Decimal::percent(50),
+        threshold: Decimal::percent(80),
+        voting_period: 100,
+        timelock_period: 10,
+        proposal_deposit: Uint128::from(2 * DEFAULT_PROPOSAL_DEPOSIT),
+    };
+    let parameter_params = PollCategoryParams {
+        category: PollCategory::ParameterChange,
+        quorum: Decimal::percent(50),
+        threshold: Decimal::percent(60),
+        voting_period: 100,
+        timelock_period: 10,
+        proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+    };
+    let update_categories = |categories: Vec<PollCategoryParams>| ExecuteMsg::UpdateConfig {
+        owner: None,
+        quorum: None,
+        threshold: None,
+        voting_period: None,
+        timelock_period: None,
+        proposal_deposit: None,
+        snapshot_period: None,
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: None,
+        raw_msg_threshold: None,
+        categories: Some(categories),
+        expiration_period: None,
+        cancel_refund_ratio: None,
+    };
+    let msg = update_categories(vec![PollCategoryParams {
+        quorum: Decimal::percent(101),
+        ..treasury_params.clone()
+    }]);
+    let err = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("quorum must be 0 to 1"))
+    );
+
+    // the parameters of a category are replaced
+    for categories in [
+        vec![parameter_params.clone()],
+        vec![treasury_params.clone(), parameter_params.clone()],
+    ] {
+        let msg = update_categories(categories);
+        execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+    }
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        config.categories,
+        vec![treasury_params, parameter_params.clone()]
+    );
+
+    stake_voters(&mut deps, &[(TEST_VOTER, 70), (TEST_VOTER_2, 30)], 0);
+    let mut env = mock_env();
+    env.block.height += 1;
+
+    let spend_msgs = vec![PollExecuteMsg {
+        order: 1,
+        action: PollAction::CommunitySpend {
+            community: "community".to_string(),
+            recipient: TEST_VOTER.to_string(),
+            amount: Uint128::from(100u128),
+        },
+    }];
+    let game_msgs = vec![PollExecuteMsg {
+        order: 1,
+        action: PollAction::RemoveGame {
+            reserve: "reserve".to_string(),
+            game: "game".to_string(),
+        },
+    }];
+    let raw_msgs = vec![PollExecuteMsg {
+        order: 1,
+        action: PollAction::Raw {
+            contract: "community".to_string(),
+            msg: to_binary(&community::ExecuteMsg::Spend {
+                recipient: TEST_VOTER.to_string(),
+                amount: Uint128::from(100u128),
+            })
+            .unwrap(),
+        },
+    }];
+    let invalid_polls = [
+        (
+            spend_msgs.clone(),
+            None,
+            DEFAULT_PROPOSAL_DEPOSIT,
+            ContractError::InsufficientProposalDeposit(2 * DEFAULT_PROPOSAL_DEPOSIT),
+        ),
+        (
+            spend_msgs.clone(),
+            Some(PollCategory::Text),
+            DEFAULT_PROPOSAL_DEPOSIT,
+            ContractError::PollCategoryMismatch("text".to_string()),
+        ),
+        (
+            game_msgs.clone(),
+            Some(PollCategory::TreasurySpend),
+            2 * DEFAULT_PROPOSAL_DEPOSIT,
+            ContractError::PollCategoryMismatch("treasury_spend".to_string()),
+        ),
+        // raw messages take the highest deposit of all the categories
+        (
+            raw_msgs.clone(),
+            Some(PollCategory::ParameterChange),
+            DEFAULT_PROPOSAL_DEPOSIT,
+            ContractError::InsufficientProposalDeposit(2 * DEFAULT_PROPOSAL_DEPOSIT),
+        ),
+    ];
+    for (execute_msgs, category, deposit, expected) in invalid_polls {
+        let msg = create_category_poll_msg(Some(execute_msgs), category, deposit);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, expected);
+    }
+
+    let mut total_deposit = 0u128;
+    for (execute_msgs, deposit) in [
+        (spend_msgs, 2 * DEFAULT_PROPOSAL_DEPOSIT),
+        (game_msgs, DEFAULT_PROPOSAL_DEPOSIT),
+        (raw_msgs, 2 * DEFAULT_PROPOSAL_DEPOSIT),
+    ] {
+        total_deposit += deposit;
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(100u128 + total_deposit),
+            )],
+        )]);
+        let msg = create_category_poll_msg(Some(execute_msgs), None, deposit);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.category, Some(PollCategory::TreasurySpend));
+    assert_eq!(poll.end_height, env.block.height + 100);
+
+    for poll_id in 1..=3 {
+        for (voter, vote, amount) in [
+            (TEST_VOTER, VoteOption::Yes, 70u128),
+            (TEST_VOTER_2, VoteOption::No, 30),
+        ] {
+            let msg = ExecuteMsg::CastVote {
+                poll_id,
+                vote,
+                amount: Uint128::from(amount),
+            };
+            execute(deps.as_mut(), env.clone(), mock_info(voter, &[]), msg).unwrap();
+        }
+    }
+
+    // the same votes pass the threshold of one category only
+    env.block.height += 100;
+    let msg = ExecuteMsg::EndPoll { poll_id: 1 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[2],
+        attr("rejected_reason", "Threshold not reached")
+    );
+    let msg = ExecuteMsg::EndPoll { poll_id: 2 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(res.attributes[3], attr("passed", "true"));
+    // the parameter change with a raw message needs the treasury threshold
+    let msg = ExecuteMsg::EndPoll { poll_id: 3 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[2],
+        attr("rejected_reason", "Threshold not reached")
+    );
+
+    let msg = ExecuteMsg::ExecutePoll { poll_id: 2 };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::TimelockNotExpired {});
+    env.block.height += parameter_params.timelock_period;
+    execute(deps.as_mut(), env, mock_info(TEST_CREATOR, &[]), msg).unwrap();
+}
+
+#[test]
+fn guardian_veto_and_emergency_polls() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    stake_voters(&mut deps, &[(TEST_VOTER, 100)], 0);
+
+    // only governance itself rotates the guardian
+    let msg = ExecuteMsg::UpdateGuardian {
+        guardian: Some("guardian".to_string()),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(TEST_CREATOR, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_guardian"),
+            attr("guardian", "guardian"),
+        ]
+    );
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.guardian, "guardian");
+
+    let actions = vec![PollExecuteMsg {
+        order: 1,
+        action: PollAction::RemoveGame {
+            reserve: "reserve".to_string(),
+            game: "game".to_string(),
+        },
+    }];
+    let msg = create_category_poll_msg(
+        Some(actions.clone()),
+        Some(PollCategory::Emergency),
+        DEFAULT_PROPOSAL_DEPOSIT,
+    );
+    let err = execute(deps.as_mut(), mock_env(), mock_info(VOTING_TOKEN, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::EmergencyPollsDisabled {});
+
+    let emergency_params = PollCategoryParams {
+        category: PollCategory::Emergency,
+        quorum: Decimal::percent(DEFAULT_QUORUM),
+        threshold: Decimal::percent(60),
+        voting_period: 100,
+        timelock_period: DEFAULT_TIMELOCK_PERIOD,
+        proposal_deposit: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+    };
+    let update_categories = |categories: Vec<PollCategoryParams>| ExecuteMsg::UpdateConfig {
+        owner: None,
+        quorum: None,
+        threshold: None,
+        voting_period: None,
+        timelock_period: None,
+        proposal_deposit: None,
+        snapshot_period: None,
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: None,
+        raw_msg_threshold: None,
+        categories: Some(categories),
+        expiration_period: None,
+        cancel_refund_ratio: None,
+    };
+    let msg = update_categories(vec![emergency_params.clone()]);
+    let err = execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "emergency threshold must be a supermajority"
+        ))
+    );
+    let msg = update_categories(vec![PollCategoryParams {
+        threshold: Decimal::percent(67),
+        ..emergency_params.clone()
+    }]);
+    execute(deps.as_mut(), mock_env(), mock_info(TEST_CREATOR, &[]), msg).unwrap();
+
+    // stake taken before the polls is used for the votes
+    let mut env = mock_env();
+    env.block.height += 1;
+    for (deposits, category) in [
+        (1u128, PollCategory::ParameterChange),
+        (2, PollCategory::Emergency),
+    ] {
+        deps.querier.with_token_balances(&[(
+            &VOTING_TOKEN.to_string(),
+            &[(
+                &MOCK_CONTRACT_ADDR.to_string(),
+                &Uint128::from(100u128 + deposits * DEFAULT_PROPOSAL_DEPOSIT),
+            )],
+        )]);
+        let msg = create_category_poll_msg(
+            Some(actions.clone()),
+            Some(category),
+            DEFAULT_PROPOSAL_DEPOSIT,
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    for poll_id in 1..=2 {
+        let msg = ExecuteMsg::CastVote {
+            poll_id,
+            vote: VoteOption::Yes,
+            amount: Uint128::from(100u128),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+    }
+
+    // passed polls are vetoed by the guardian only
+    let msg = ExecuteMsg::VetoPoll { poll_id: 1 };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("guardian", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollNotPassed {});
+
+    env.block.height += DEFAULT_VOTING_PERIOD;
+    for poll_id in 1..=2 {
+        let msg = ExecuteMsg::EndPoll { poll_id };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(TEST_CREATOR, &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(res.attributes[3], attr("passed", "true"));
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(deps.as_mut(), env.clone(), mock_info("guardian", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "veto_poll"),
+            attr("poll_id", "1"),
+            attr("guardian", "guardian"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Polls {
+            filter: Some(PollStatus::Vetoed),
+            start_after: None,
+            limit: None,
+            order_by: None,
+        },
+    )
+    .unwrap();
+    let response: PollsResponse = from_binary(&res).unwrap();
+    assert_eq!(response.polls.len(), 1);
+    assert_eq!(response.polls[0].id, 1);
+    assert_eq!(response.polls[0].status, PollStatus::Vetoed);
+
+    env.block.height += DEFAULT_TIMELOCK_PERIOD;
+    let msg = ExecuteMsg::ExecutePoll { poll_id: 1 };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollNotPassed {});
+
+    // emergency polls skip the timelock, so they can not be vetoed afterwards
+    let msg = ExecuteMsg::VetoPoll { poll_id: 2 };
+    let err = execute(deps.as_mut(), env.clone(), mock_info("guardian", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::TimelockExpired {});
+
+    let msg = ExecuteMsg::ExecutePoll { poll_id: 2 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "execute_emergency_poll"),
+            attr("poll_id", "2"),
+        ]
+    );
+
+    // the guardian is removed by governance
+    let msg = ExecuteMsg::UpdateGuardian { guardian: None };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        msg,
+    )
+    .unwrap();
+    let res = query(deps.as_ref(), env, QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.guardian, "");
+}
+
+#[test]
+fn poll_cancellation_and_expiry() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+    stake_voters(&mut deps, &[(TEST_VOTER, 100)], 0);
+
+    let mut env = mock_env();
+    env.block.height += 1;
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100u128 + 2 * DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    for _ in 1..=2 {
+        let msg = create_category_poll_msg(None, None, DEFAULT_PROPOSAL_DEPOSIT);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 2,
+        vote: VoteOption::Yes,
+        amount: Uint128::from(100u128),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+
+    // only the creator amends or cancels a poll without votes
+    let amend_msg = |poll_id: u64| ExecuteMsg::AmendPoll {
+        poll_id,
+        title: Some("amended".to_string()),
+        description: None,
+        link: Some("http://amended.io".to_string()),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER, &[]),
+        amend_msg(1),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        amend_msg(2),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollHasVotes {});
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        amend_msg(1),
+    )
+    .unwrap();
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.title, "amended");
+    assert_eq!(poll.description, "test");
+    assert_eq!(poll.link, Some("http://amended.io".to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::CancelPoll { poll_id: 2 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollHasVotes {});
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::CancelPoll { poll_id: 1 },
+    )
+    .unwrap();
+    let refund_amount = Uint128::from(DEFAULT_PROPOSAL_DEPOSIT / 2);
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: VOTING_TOKEN.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: TEST_CREATOR.to_string(),
+                amount: refund_amount,
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "cancel_poll"),
+            attr("poll_id", "1"),
+            attr("refund_amount", refund_amount.to_string()),
+        ]
+    );
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 1 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.status, PollStatus::Cancelled);
+    let state: State = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(state.total_deposit, Uint128::from(DEFAULT_PROPOSAL_DEPOSIT));
+
+    // voter records are kept while the poll is in progress
+    let msg = ExecuteMsg::PrunePollVoters {
+        poll_id: 2,
+        limit: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_VOTER, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollNotEnded {});
+
+    let msg_update = ExecuteMsg::UpdateConfig {
+        owner: None,
+        quorum: None,
+        threshold: None,
+        voting_period: None,
+        timelock_period: None,
+        proposal_deposit: None,
+        snapshot_period: None,
+        veto_threshold: None,
+        community_contract: None,
+        max_lock_period: None,
+        raw_msg_threshold: None,
+        categories: None,
+        expiration_period: Some(100),
+        cancel_refund_ratio: None,
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        msg_update,
+    )
+    .unwrap();
+
+    env.block.height += DEFAULT_VOTING_PERIOD;
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::EndPoll { poll_id: 2 },
+    )
+    .unwrap();
+
+    // passed polls can be executed until the expiration period after the timelock ends
+    env.block.height += DEFAULT_TIMELOCK_PERIOD + 100;
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExpirePoll { poll_id: 2 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollNotExpired {});
+
+    env.block.height += 1;
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExecutePoll { poll_id: 2 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollExpired {});
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::ExpirePoll { poll_id: 2 },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "expire_poll"), attr("poll_id", "2")]
+    );
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Poll { poll_id: 2 }).unwrap();
+    let poll: PollResponse = from_binary(&res).unwrap();
+    assert_eq!(poll.status, PollStatus::Expired);
+
+    let voter_raw = deps.api.addr_canonicalize(TEST_VOTER).unwrap();
+    assert!(POLL_VOTERS
+        .may_load(
+            deps.as_ref().storage,
+            (U64Key::new(2), voter_raw.as_slice())
+        )
+        .unwrap()
+        .is_some());
+    let res = execute(deps.as_mut(), env, mock_info(TEST_VOTER, &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "prune_poll_voters"),
+            attr("poll_id", "2"),
+            attr("pruned", "1"),
+        ]
+    );
+    assert!(POLL_VOTERS
+        .may_load(
+            deps.as_ref().storage,
+            (U64Key::new(2), voter_raw.as_slice())
+        )
+        .unwrap()
+        .is_none());
+
+    // a vote of zero leaves the tally empty but still counts
+    let env = mock_env();
+    deps.querier.with_token_balances(&[(
+        &VOTING_TOKEN.to_string(),
+        &[(
+            &MOCK_CONTRACT_ADDR.to_string(),
+            &Uint128::from(100u128 + 3 * DEFAULT_PROPOSAL_DEPOSIT),
+        )],
+    )]);
+    let msg = create_category_poll_msg(None, None, DEFAULT_PROPOSAL_DEPOSIT);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(VOTING_TOKEN, &[]),
+        msg,
+    )
+    .unwrap();
+    let msg = ExecuteMsg::CastVote {
+        poll_id: 3,
+        vote: VoteOption::No,
+        amount: Uint128::zero(),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(TEST_VOTER, &[]), msg).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::CancelPoll { poll_id: 3 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PollHasVotes {});
+}
+
+#[test]
+fn query_polls_by_creator_and_ending_soon() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+    mock_register_voting_token(deps.as_mut());
+
+    // polls 1 and 3 by the creator, poll 2 by the voter, each 10 blocks apart
+    let mut env = mock_env();
+    for sender in [TEST_CREATOR, TEST_VOTER, TEST_CREATOR] {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::from(DEFAULT_PROPOSAL_DEPOSIT),
+            msg: to_binary(&Cw20HookMsg::CreatePoll {
+                title: "test".to_string(),
+                description: "test".to_string(),
+                link: None,
+                execute_msgs: None,
+                category: None,
+            })
+            .unwrap(),
+        });
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(VOTING_TOKEN, &[]),
+            msg,
+        )
+        .unwrap();
+        env.block.height += 10;
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(TEST_CREATOR, &[]),
+        ExecuteMsg::CancelPoll { poll_id: 3 },
+    )
+    .unwrap();
+
+    let poll_ids = |msg: QueryMsg| -> Vec<u64> {
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let response: PollsResponse = from_binary(&res).unwrap();
+        response.polls.iter().map(|poll| poll.id).collect()
+    };
+    assert_eq!(
+        poll_ids(QueryMsg::PollsByCreator {
+            creator: TEST_CREATOR.to_string(),
+            start_after: None,
+            limit: None,
+            order_by: None,
+        }),
+        vec![3, 1]
+    );
+    assert_eq!(
+        poll_ids(QueryMsg::PollsByCreator {
+            creator: TEST_CREATOR.to_string(),
+            start_after: Some(1),
+            limit: None,
+            order_by: Some(OrderBy::Asc),
+        }),
+        vec![3]
+    );
+    assert_eq!(
+        poll_ids(QueryMsg::PollsByCreator {
+            creator: TEST_VOTER.to_string(),
+            start_after: None,
+            limit: None,
+            order_by: None,
+        }),
+        vec![2]
+    );
+
+    // the cancelled poll is no longer ending
+    assert_eq!(
+        poll_ids(QueryMsg::PollsEndingSoon {
+            blocks: DEFAULT_VOTING_PERIOD - 30,
+            limit: None,
+        }),
+        vec![1]
+    );
+    assert_eq!(
+        poll_ids(QueryMsg::PollsEndingSoon {
+            blocks: DEFAULT_VOTING_PERIOD,
+            limit: None,
+        }),
+        vec![1, 2]
+    );
+    assert_eq!(
+        poll_ids(QueryMsg::PollsEndingSoon {
+            blocks: DEFAULT_VOTING_PERIOD,
+            limit: Some(1),
+        }),
+        vec![1]
+    );
+}
+
+#[test]
+fn migrate_legacy_poll_storage() {
+    let mut deps = mock_dependencies(&[]);
+    mock_instantiate(deps.as_mut());
+
+    // the config is still where the singleton stored it
+    let config: Config =
+        from_slice(&deps.storage.get(&legacy_key(&[b"config"], b"")).unwrap()).unwrap();
+    assert_eq!(config.quorum, Decimal::percent(DEFAULT_QUORUM));
+
+    // a poll and its status index stored by the buckets
+    let poll = Poll {
+        id: 1u64,
+        creator: deps.api.addr_canonicalize(TEST_VOTER).unwrap(),
+        status: PollStatus::Passed,
+        yes_votes: Uint128::zero(),
+        no_votes: Uint128::zero(),
+        abstain_votes: Uint128::zero(),
+        veto_votes: Uint128::zero(),
+        end_height: 100u64,
+        title: "title".to_string(),
+        description: "description".to_string(),
+        deposit_amount: Uint128::zero(),
+        link: None,
+        execute_data: None,
+        total_balance_at_end_poll: None,
+        staked_amount: None,
+        start_height: 0,
+        snapshot_share: None,
+        vote_escrow: false,
+        category: None,
+    };
+    let indexer_key = legacy_key(&[b"poll_indexer", b"Passed"], &1u64.to_be_bytes());
+    for poll_id in 1..=2u64 {
+        let poll = Poll {
+            id: poll_id,
+            ..poll.clone()
+        };
+        deps.storage.set(
+            &legacy_key(&[b"poll"], &poll_id.to_be_bytes()),
+            &to_vec(&poll).unwrap(),
+        );
+        deps.storage.set(
+            &legacy_key(&[b"poll_indexer", b"Passed"], &poll_id.to_be_bytes()),
+            &to_vec(&true).unwrap(),
+        );
+    }
+
+    let passed_polls = |deps: Deps| -> Vec<u64> {
+        let msg = QueryMsg::Polls {
+            filter: Some(PollStatus::Passed),
+            start_after: None,
+            limit: None,
+            order_by: None,
+        };
+        let response: PollsResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        response.polls.iter().map(|poll| poll.id).collect()
+    };
+    assert_eq!(passed_polls(deps.as_ref()), Vec::<u64>::new());
+
+    // the polls are migrated in batches
+    let msg = MigrateMsg {
+        start_after: None,
+        limit: Some(1),
+    };
+    let res = migrate(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(res.attributes[1], attr("last_poll_id", "1"));
+    assert_eq!(passed_polls(deps.as_ref()), vec![1]);
+
+    let msg = MigrateMsg {
+        start_after: Some(1),
+        limit: None,
+    };
+    let res = migrate(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(res.attributes[1], attr("last_poll_id", "2"));
+    assert_eq!(passed_polls(deps.as_ref()), vec![2, 1]);
+
+    let msg = MigrateMsg {
+        start_after: Some(2),
+        limit: None,
+    };
+    let res = migrate(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(res.attributes, vec![attr("action", "migrate")]);
+    let msg = QueryMsg::PollsByCreator {
+        creator: TEST_VOTER.to_string(),
+        start_after: None,
+        limit: None,
+        order_by: None,
+    };
+    let response: PollsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(response.polls.len(), 2);
+    assert_eq!(deps.storage.get(&indexer_key), None);
+}
+
+// key of a value stored by the cosmwasm_storage buckets and singletons
+fn legacy_key(namespaces: &[&[u8]], key: &[u8]) -> Vec<u8> {
+    let mut legacy_key = vec![];
+    for namespace in namespaces {
+        legacy_key.extend_from_slice(&(namespace.len() as u16).to_be_bytes());
+        legacy_key.extend_from_slice(namespace);
+    }
+    legacy_key.extend_from_slice(key);
+    legacy_key
+}
@@ -0,0 +1,275 @@
+use crate::delegation::{query_total_balance, share_to_balance};
+use crate::error::ContractError;
+use crate::staking::withdraw_voting_tokens;
+use crate::state::{
+    read_staker_snapshot, read_ve_point, store_token_manager, update_ve_point, Config, State,
+    TokenManager, VeLock, BANK, CONFIG, STATE, VE_TOTAL,
+};
+
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint128};
+use terra_vegas::gov::{TotalVotingEscrowResponse, VotingEscrowResponse};
+
+pub fn create_lock(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    end_height: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    validate_lock_end(&config, &env, None, end_height)?;
+
+    let staker_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let key = staker_raw.as_slice();
+    let mut token_manager = BANK.may_load(deps.storage, key)?.unwrap_or_default();
+    if token_manager.lock.is_some() {
+        return Err(ContractError::LockAlreadyExists {});
+    }
+
+    let share = amount_to_share(deps.as_ref(), &config, amount)?;
+    if share > token_manager.share {
+        return Err(ContractError::InsufficientStaked {});
+    }
+
+    token_manager.lock = Some(VeLock { share, end_height });
+    store_lock(deps.storage, env.block.height, key, &token_manager, None)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "create_lock"),
+        ("staker", info.sender.as_str()),
+        ("share", share.to_string().as_str()),
+        ("end_height", end_height.to_string().as_str()),
+    ]))
+}
+
+pub fn increase_lock_amount(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.max_lock_period == 0 {
+        return Err(ContractError::LocksDisabled {});
+    }
+
+    let staker_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let key = staker_raw.as_slice();
+    let mut token_manager = BANK.may_load(deps.storage, key)?.unwrap_or_default();
+    let previous = running_lock(&token_manager, &env)?;
+
+    let share = previous
+        .share
+        .checked_add(amount_to_share(deps.as_ref(), &config, amount)?)?;
+    if share > token_manager.share {
+        return Err(ContractError::InsufficientStaked {});
+    }
+
+    token_manager.lock = Some(VeLock {
+        share,
+        end_height: previous.end_height,
+    });
+    store_lock(
+        deps.storage,
+        env.block.height,
+        key,
+        &token_manager,
+        Some(&previous),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "increase_lock_amount"),
+        ("staker", info.sender.as_str()),
+        ("share", share.to_string().as_str()),
+    ]))
+}
+
+pub fn extend_lock(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    end_height: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    let staker_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let key = staker_raw.as_slice();
+    let mut token_manager = BANK.may_load(deps.storage, key)?.unwrap_or_default();
+    let previous = running_lock(&token_manager, &env)?;
+    validate_lock_end(&config, &env, Some(previous.end_height), end_height)?;
+
+    token_manager.lock = Some(VeLock {
+        share: previous.share,
+        end_height,
+    });
+    store_lock(
+        deps.storage,
+        env.block.height,
+        key,
+        &token_manager,
+        Some(&previous),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "extend_lock"),
+        ("staker", info.sender.as_str()),
+        ("end_height", end_height.to_string().as_str()),
+    ]))
+}
+
+pub fn withdraw_lock(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state: State = STATE.load(deps.storage)?;
+
+    let staker_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let key = staker_raw.as_slice();
+    let mut token_manager = BANK.may_load(deps.storage, key)?.unwrap_or_default();
+    let lock = match token_manager.lock.take() {
+        Some(lock) if lock.end_height > env.block.height => {
+            return Err(ContractError::LockNotExpired {})
+        }
+        Some(lock) => lock,
+        None => return Err(ContractError::NoLock {}),
+    };
+    store_lock(
+        deps.storage,
+        env.block.height,
+        key,
+        &token_manager,
+        Some(&lock),
+    )?;
+
+    let total_balance = query_total_balance(deps.as_ref(), &config, &state)?;
+    let amount = share_to_balance(lock.share, total_balance, state.total_share);
+    withdraw_voting_tokens(deps, env, info, Some(amount))
+}
+
+fn validate_lock_end(
+    config: &Config,
+    env: &Env,
+    current_end: Option<u64>,
+    end_height: u64,
+) -> Result<(), ContractError> {
+    if config.max_lock_period == 0 {
+        return Err(ContractError::LocksDisabled {});
+    }
+
+    let min_end = std::cmp::max(env.block.height, current_end.unwrap_or_default());
+    if end_height <= min_end || end_height > env.block.height + config.max_lock_period {
+        return Err(ContractError::InvalidLockEnd {});
+    }
+
+    Ok(())
+}
+
+fn running_lock(token_manager: &TokenManager, env: &Env) -> Result<VeLock, ContractError> {
+    match &token_manager.lock {
+        Some(lock) if lock.end_height <= env.block.height => Err(ContractError::LockExpired {}),
+        Some(lock) => Ok(lock.clone()),
+        None => Err(ContractError::NoLock {}),
+    }
+}
+
+fn amount_to_share(deps: Deps, config: &Config, amount: Uint128) -> Result<Uint128, ContractError> {
+    let state: State = STATE.load(deps.storage)?;
+    let total_balance = query_total_balance(deps, config, &state)?;
+    if amount.is_zero() || total_balance.is_zero() {
+        return Err(ContractError::InsufficientFunds {});
+    }
+
+    Ok(amount.multiply_ratio(state.total_share, total_balance))
+}
+
+// saves the staker and moves its lock in the totals and in the delegate weight
+fn store_lock(
+    storage: &mut dyn Storage,
+    height: u64,
+    key: &[u8],
+    token_manager: &TokenManager,
+    previous: Option<&VeLock>,
+) -> StdResult<()> {
+    let lock = token_manager.lock.as_ref();
+    update_ve_point(storage, VE_TOTAL, height, previous, lock)?;
+    if let Some(delegate) = &token_manager.delegate {
+        update_ve_point(storage, delegate.as_slice(), height, previous, lock)?;
+    }
+
+    store_token_manager(storage, height, key, token_manager)
+}
+
+/// Converts a lock weight to the balance locked for the whole max lock period
+pub fn weight_to_balance(
+    weight: Uint128,
+    total_balance: Uint128,
+    total_share: Uint128,
+    max_lock_period: u64,
+) -> Uint128 {
+    if max_lock_period == 0 {
+        return Uint128::zero();
+    }
+
+    share_to_balance(weight, total_balance, total_share).multiply_ratio(1u128, max_lock_period)
+}
+
+pub fn query_voting_escrow(
+    deps: Deps,
+    env: Env,
+    address: String,
+    height: Option<u64>,
+) -> StdResult<VotingEscrowResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state: State = STATE.load(deps.storage)?;
+    let total_balance = query_total_balance(deps, &config, &state)?;
+
+    let height = height.unwrap_or(env.block.height + 1);
+    let (locked_amount, end_height, weight) =
+        match read_staker_snapshot(deps.storage, address_raw.as_slice(), height)?.lock {
+            Some(lock) => (
+                share_to_balance(lock.share, total_balance, state.total_share),
+                lock.end_height,
+                lock.weight(height)?,
+            ),
+            None => (Uint128::zero(), 0, Uint128::zero()),
+        };
+
+    Ok(VotingEscrowResponse {
+        address,
+        height,
+        locked_amount,
+        end_height,
+        voting_power: weight_to_balance(
+            weight,
+            total_balance,
+            state.total_share,
+            config.max_lock_period,
+        ),
+    })
+}
+
+pub fn query_total_voting_escrow(
+    deps: Deps,
+    env: Env,
+    height: Option<u64>,
+) -> StdResult<TotalVotingEscrowResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state: State = STATE.load(deps.storage)?;
+    let total_balance = query_total_balance(deps, &config, &state)?;
+
+    let height = height.unwrap_or(env.block.height + 1);
+    let weight = read_ve_point(deps.storage, VE_TOTAL, height)?.weight(height)?;
+
+    Ok(TotalVotingEscrowResponse {
+        height,
+        voting_power: weight_to_balance(
+            weight,
+            total_balance,
+            state.total_share,
+            config.max_lock_period,
+        ),
+    })
+}
@@ -0,0 +1,431 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+
+use crate::state::{
+    read_config, read_staker_info, read_state, remove_staker_info, store_config, store_staker_info,
+    store_state, Config, StakerInfo, State,
+};
+
+use cosmwasm_std::{
+    from_binary, to_binary, Binary, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Response, StdError, StdResult, Uint128, WasmMsg,
+};
+
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use terra_vegas::staking::{
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+    StakerInfoResponse, StateResponse,
+};
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    assert_schedule(&msg.distribution_schedule)?;
+
+    store_config(
+        deps.storage,
+        &Config {
+            owner: deps.api.addr_canonicalize(info.sender.as_str())?,
+            anchor_token: deps.api.addr_canonicalize(&msg.anchor_token)?,
+            staking_token: deps.api.addr_canonicalize(&msg.staking_token)?,
+            distribution_schedule: msg.distribution_schedule,
+        },
+    )?;
+
+    store_state(
+        deps.storage,
+        &State {
+            last_distributed: env.block.time.seconds(),
+            total_bond_amount: Uint128::zero(),
+            global_reward_index: Decimal::zero(),
+        },
+    )?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+        ExecuteMsg::Unbond { amount } => unbond(deps, env, info, amount),
+        ExecuteMsg::Withdraw {} => withdraw(deps, env, info),
+        ExecuteMsg::MigrateStaking {
+            new_staking_contract,
+        } => migrate_staking(deps, env, info, new_staking_contract),
+        ExecuteMsg::UpdateConfig {
+            distribution_schedule,
+        } => update_config(deps, env, info, distribution_schedule),
+    }
+}
+
+pub fn receive_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> StdResult<Response> {
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::Bond {} => {
+            // only staking token contract can execute this message
+            let config: Config = read_config(deps.storage)?;
+            if config.staking_token != deps.api.addr_canonicalize(info.sender.as_str())? {
+                return Err(StdError::generic_err("unauthorized"));
+            }
+
+            bond(deps, env, cw20_msg.sender, cw20_msg.amount)
+        }
+    }
+}
+
+pub fn bond(deps: DepsMut, env: Env, sender: String, amount: Uint128) -> StdResult<Response> {
+    let sender_addr_raw: CanonicalAddr = deps.api.addr_canonicalize(&sender)?;
+
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+    let mut staker_info: StakerInfo = read_staker_info(deps.storage, &sender_addr_raw)?;
+
+    // Compute global reward & staker reward
+    compute_reward(&config, &mut state, env.block.time.seconds());
+    compute_staker_reward(&state, &mut staker_info)?;
+
+    // Increase bond_amount
+    state.total_bond_amount += amount;
+    staker_info.bond_amount += amount;
+
+    store_staker_info(deps.storage, &sender_addr_raw, &staker_info)?;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "bond"),
+        ("owner", sender.as_str()),
+        ("amount", amount.to_string().as_str()),
+    ]))
+}
+
+pub fn unbond(deps: DepsMut, env: Env, info: MessageInfo, amount: Uint128) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    let sender_addr_raw: CanonicalAddr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    let mut state: State = read_state(deps.storage)?;
+    let mut staker_info: StakerInfo = read_staker_info(deps.storage, &sender_addr_raw)?;
+
+    if staker_info.bond_amount < amount {
+        return Err(StdError::generic_err("Cannot unbond more than bond amount"));
+    }
+
+    // Compute global reward & staker reward
+    compute_reward(&config, &mut state, env.block.time.seconds());
+    compute_staker_reward(&state, &mut staker_info)?;
+
+    // Decrease bond_amount
+    state.total_bond_amount = state.total_bond_amount.checked_sub(amount)?;
+    staker_info.bond_amount = staker_info.bond_amount.checked_sub(amount)?;
+
+    // Store or remove updated rewards info
+    // depends on the left pending reward and bond amount
+    if staker_info.pending_reward.is_zero() && staker_info.bond_amount.is_zero() {
+        remove_staker_info(deps.storage, &sender_addr_raw);
+    } else {
+        store_staker_info(deps.storage, &sender_addr_raw, &staker_info)?;
+    }
+
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.staking_token)?.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }))
+        .add_attributes(vec![
+            ("action", "unbond"),
+            ("owner", info.sender.as_str()),
+            ("amount", amount.to_string().as_str()),
+        ]))
+}
+
+// withdraw rewards to executor
+pub fn withdraw(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+    let sender_addr_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+    let mut staker_info = read_staker_info(deps.storage, &sender_addr_raw)?;
+
+    // Compute global reward & staker reward
+    compute_reward(&config, &mut state, env.block.time.seconds());
+    compute_staker_reward(&state, &mut staker_info)?;
+
+    let amount = staker_info.pending_reward;
+    if amount.is_zero() {
+        return Err(StdError::generic_err("No rewards have accrued yet"));
+    }
+    staker_info.pending_reward = Uint128::zero();
+
+    // Store or remove updated rewards info
+    // depends on the left pending reward and bond amount
+    if staker_info.bond_amount.is_zero() {
+        remove_staker_info(deps.storage, &sender_addr_raw);
+    } else {
+        store_staker_info(deps.storage, &sender_addr_raw, &staker_info)?;
+    }
+
+    // Store updated state
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.anchor_token)?.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }))
+        .add_attributes(vec![
+            ("action", "withdraw"),
+            ("owner", info.sender.as_str()),
+            ("amount", amount.to_string().as_str()),
+        ]))
+}
+
+/// Replaces the distribution schedule,
+/// the entries that already started cannot be changed
+pub fn update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    distribution_schedule: Vec<(u64, u64, Uint128)>,
+) -> StdResult<Response> {
+    let mut config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    assert_schedule(&distribution_schedule)?;
+
+    // distribute the rewards of the current schedule up to now
+    let mut state: State = read_state(deps.storage)?;
+    let block_time = env.block.time.seconds();
+    compute_reward(&config, &mut state, block_time);
+
+    for started in config
+        .distribution_schedule
+        .iter()
+        .filter(|slot| slot.0 <= block_time)
+    {
+        if !distribution_schedule.contains(started) {
+            return Err(StdError::generic_err(
+                "cannot change a distribution slot that already started",
+            ));
+        }
+    }
+    if distribution_schedule
+        .iter()
+        .any(|slot| slot.0 <= block_time && !config.distribution_schedule.contains(slot))
+    {
+        return Err(StdError::generic_err(
+            "new distribution slots must start in the future",
+        ));
+    }
+
+    config.distribution_schedule = distribution_schedule;
+    store_config(deps.storage, &config)?;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![("action", "update_config")]))
+}
+
+/// Stops the distribution and sends the rewards that were not distributed yet
+/// to the new staking contract, the stakers keep their accrued rewards
+pub fn migrate_staking(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_staking_contract: String,
+) -> StdResult<Response> {
+    let mut config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    let new_staking_contract = deps.api.addr_validate(&new_staking_contract)?;
+
+    let mut state: State = read_state(deps.storage)?;
+    let block_time = env.block.time.seconds();
+
+    // compute global reward, sets last_distributed to the block time
+    compute_reward(&config, &mut state, block_time);
+
+    let total_distribution_amount: Uint128 =
+        config.distribution_schedule.iter().map(|slot| slot.2).sum();
+
+    // eliminate distribution slots that have not started
+    config
+        .distribution_schedule
+        .retain(|slot| slot.0 < block_time);
+
+    let mut distributed_amount = Uint128::zero();
+    for slot in config.distribution_schedule.iter_mut() {
+        if slot.1 <= block_time {
+            // all distributed
+            distributed_amount += slot.2;
+        } else {
+            // partially distributed slot ends now
+            let distributed_amount_on_slot =
+                slot.2.multiply_ratio(block_time - slot.0, slot.1 - slot.0);
+            distributed_amount += distributed_amount_on_slot;
+
+            slot.1 = block_time;
+            slot.2 = distributed_amount_on_slot;
+        }
+    }
+
+    // the remaining rewards were already sent by an earlier migration
+    let remaining_amount = total_distribution_amount.checked_sub(distributed_amount)?;
+    if remaining_amount.is_zero() {
+        return Err(StdError::generic_err("nothing to migrate"));
+    }
+
+    store_config(deps.storage, &config)?;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.anchor_token)?.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: new_staking_contract.to_string(),
+                amount: remaining_amount,
+            })?,
+            funds: vec![],
+        }))
+        .add_attributes(vec![
+            ("action", "migrate_staking"),
+            ("distributed_amount", &distributed_amount.to_string()),
+            ("remaining_amount", &remaining_amount.to_string()),
+        ]))
+}
+
+fn assert_schedule(distribution_schedule: &[(u64, u64, Uint128)]) -> StdResult<()> {
+    if distribution_schedule.iter().any(|slot| slot.0 >= slot.1) {
+        return Err(StdError::generic_err(
+            "distribution slot start time must be before end time",
+        ));
+    }
+    Ok(())
+}
+
+/// Amount released by the schedule between `start` and `end`,
+/// every slot is distributed linearly over its period
+fn distributed_amount(schedule: &[(u64, u64, Uint128)], start: u64, end: u64) -> Uint128 {
+    schedule
+        .iter()
+        .filter(|slot| slot.0 < end && slot.1 > start)
+        .map(|slot| {
+            let passed_time = end.min(slot.1) - start.max(slot.0);
+            slot.2.multiply_ratio(passed_time, slot.1 - slot.0)
+        })
+        .sum()
+}
+
+// compute distributed rewards and update global reward index
+fn compute_reward(config: &Config, state: &mut State, block_time: u64) {
+    if state.total_bond_amount.is_zero() {
+        state.last_distributed = block_time;
+        return;
+    }
+    if block_time <= state.last_distributed {
+        return;
+    }
+
+    let distributed_amount = distributed_amount(
+        &config.distribution_schedule,
+        state.last_distributed,
+        block_time,
+    );
+
+    state.last_distributed = block_time;
+    state.global_reward_index = state.global_reward_index
+        + Decimal::from_ratio(distributed_amount, state.total_bond_amount);
+}
+
+// withdraw reward to pending reward
+fn compute_staker_reward(state: &State, staker_info: &mut StakerInfo) -> StdResult<()> {
+    let pending_reward = (staker_info.bond_amount * state.global_reward_index)
+        .checked_sub(staker_info.bond_amount * staker_info.reward_index)?;
+
+    staker_info.reward_index = state.global_reward_index;
+    staker_info.pending_reward += pending_reward;
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::State { block_time } => to_binary(&query_state(deps, block_time)?),
+        QueryMsg::StakerInfo { staker, block_time } => {
+            to_binary(&query_staker_info(deps, staker, block_time)?)
+        }
+    }
+}
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let state = read_config(deps.storage)?;
+    let resp = ConfigResponse {
+        anchor_token: deps.api.addr_humanize(&state.anchor_token)?.to_string(),
+        staking_token: deps.api.addr_humanize(&state.staking_token)?.to_string(),
+        distribution_schedule: state.distribution_schedule,
+    };
+
+    Ok(resp)
+}
+
+pub fn query_state(deps: Deps, block_time: Option<u64>) -> StdResult<StateResponse> {
+    let mut state: State = read_state(deps.storage)?;
+    if let Some(block_time) = block_time {
+        let config = read_config(deps.storage)?;
+        compute_reward(&config, &mut state, block_time);
+    }
+
+    Ok(StateResponse {
+        last_distributed: state.last_distributed,
+        total_bond_amount: state.total_bond_amount,
+        global_reward_index: state.global_reward_index,
+    })
+}
+
+pub fn query_staker_info(
+    deps: Deps,
+    staker: String,
+    block_time: Option<u64>,
+) -> StdResult<StakerInfoResponse> {
+    let staker_raw = deps.api.addr_canonicalize(&staker)?;
+
+    let mut staker_info: StakerInfo = read_staker_info(deps.storage, &staker_raw)?;
+    if let Some(block_time) = block_time {
+        let config = read_config(deps.storage)?;
+        let mut state = read_state(deps.storage)?;
+
+        compute_reward(&config, &mut state, block_time);
+        compute_staker_reward(&state, &mut staker_info)?;
+    }
+
+    Ok(StakerInfoResponse {
+        staker,
+        reward_index: staker_info.reward_index,
+        bond_amount: staker_info.bond_amount,
+        pending_reward: staker_info.pending_reward,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    Ok(Response::default())
+}
@@ -0,0 +1,344 @@
+use crate::contract::{execute, instantiate, query};
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{
+    from_binary, to_binary, CosmosMsg, Decimal, Env, OwnedDeps, StdError, SubMsg, Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use terra_vegas::staking::{
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg, StakerInfoResponse,
+    StateResponse,
+};
+
+const VEGA: &str = "vega";
+const LP_TOKEN: &str = "lp_token";
+
+fn start_time() -> u64 {
+    mock_env().block.time.seconds()
+}
+
+fn env_at(seconds: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(seconds);
+    env
+}
+
+fn schedule() -> Vec<(u64, u64, Uint128)> {
+    vec![
+        (start_time(), start_time() + 100, Uint128::from(1000u128)),
+        (
+            start_time() + 100,
+            start_time() + 200,
+            Uint128::from(2000u128),
+        ),
+    ]
+}
+
+fn init() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        anchor_token: VEGA.to_string(),
+        staking_token: LP_TOKEN.to_string(),
+        distribution_schedule: schedule(),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    deps
+}
+
+fn bond(
+    deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    env: Env,
+    staker: &str,
+    amount: u128,
+) {
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: staker.to_string(),
+        amount: Uint128::from(amount),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), env, mock_info(LP_TOKEN, &[]), msg).unwrap();
+}
+
+fn staker_info(
+    deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    staker: &str,
+    block_time: Option<u64>,
+) -> StakerInfoResponse {
+    from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::StakerInfo {
+                staker: staker.to_string(),
+                block_time,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+fn transfer(token: &str, recipient: &str, amount: u128) -> SubMsg {
+    SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount: Uint128::from(amount),
+        })
+        .unwrap(),
+        funds: vec![],
+    }))
+}
+
+#[test]
+fn proper_initialization() {
+    let deps = init();
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(
+        config,
+        ConfigResponse {
+            anchor_token: VEGA.to_string(),
+            staking_token: LP_TOKEN.to_string(),
+            distribution_schedule: schedule(),
+        }
+    );
+
+    let state: StateResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::State { block_time: None },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        state,
+        StateResponse {
+            last_distributed: start_time(),
+            total_bond_amount: Uint128::zero(),
+            global_reward_index: Decimal::zero(),
+        }
+    );
+
+    // invalid schedule
+    let msg = InstantiateMsg {
+        anchor_token: VEGA.to_string(),
+        staking_token: LP_TOKEN.to_string(),
+        distribution_schedule: vec![(100, 100, Uint128::from(1u128))],
+    };
+    instantiate(
+        mock_dependencies(&[]).as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        msg,
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn bond_only_staking_token() {
+    let mut deps = init();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "alice".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info(VEGA, &[]), msg).unwrap_err();
+    assert_eq!(err, StdError::generic_err("unauthorized"));
+
+    bond(&mut deps, mock_env(), "alice", 100);
+    assert_eq!(
+        staker_info(&deps, "alice", None).bond_amount,
+        Uint128::from(100u128)
+    );
+}
+
+#[test]
+fn rewards_follow_schedule() {
+    let mut deps = init();
+    bond(&mut deps, mock_env(), "alice", 100);
+
+    // half of the first slot goes to alice alone
+    bond(&mut deps, env_at(50), "bob", 300);
+    assert_eq!(
+        staker_info(&deps, "alice", Some(start_time() + 50)).pending_reward,
+        Uint128::from(500u128)
+    );
+
+    // the rest of the first slot and half of the second one are shared 1:3
+    assert_eq!(
+        staker_info(&deps, "alice", Some(start_time() + 150)).pending_reward,
+        Uint128::from(875u128)
+    );
+    assert_eq!(
+        staker_info(&deps, "bob", Some(start_time() + 150)).pending_reward,
+        Uint128::from(1125u128)
+    );
+
+    // nothing is distributed after the schedule
+    assert_eq!(
+        staker_info(&deps, "bob", Some(start_time() + 1000)).pending_reward,
+        Uint128::from(1875u128)
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        env_at(150),
+        mock_info("alice", &[]),
+        ExecuteMsg::Withdraw {},
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![transfer(VEGA, "alice", 875)]);
+    assert_eq!(
+        staker_info(&deps, "alice", None).pending_reward,
+        Uint128::zero()
+    );
+
+    execute(
+        deps.as_mut(),
+        env_at(150),
+        mock_info("alice", &[]),
+        ExecuteMsg::Withdraw {},
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn unbond_keeps_pending_rewards() {
+    let mut deps = init();
+    bond(&mut deps, mock_env(), "alice", 100);
+
+    let unbond = |amount: u128| ExecuteMsg::Unbond {
+        amount: Uint128::from(amount),
+    };
+    execute(
+        deps.as_mut(),
+        env_at(10),
+        mock_info("alice", &[]),
+        unbond(101),
+    )
+    .unwrap_err();
+
+    let res = execute(
+        deps.as_mut(),
+        env_at(10),
+        mock_info("alice", &[]),
+        unbond(100),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![transfer(LP_TOKEN, "alice", 100)]);
+
+    let info = staker_info(&deps, "alice", Some(start_time() + 100));
+    assert_eq!(info.bond_amount, Uint128::zero());
+    assert_eq!(info.pending_reward, Uint128::from(100u128));
+
+    // the staker info is removed once everything is withdrawn
+    execute(
+        deps.as_mut(),
+        env_at(20),
+        mock_info("alice", &[]),
+        ExecuteMsg::Withdraw {},
+    )
+    .unwrap();
+    assert_eq!(
+        staker_info(&deps, "alice", None),
+        StakerInfoResponse {
+            staker: "alice".to_string(),
+            reward_index: Decimal::zero(),
+            bond_amount: Uint128::zero(),
+            pending_reward: Uint128::zero(),
+        }
+    );
+}
+
+#[test]
+fn update_schedule() {
+    let mut deps = init();
+    bond(&mut deps, mock_env(), "alice", 100);
+
+    let mut new_schedule = schedule();
+    new_schedule[1].2 = Uint128::from(4000u128);
+    let msg = ExecuteMsg::UpdateConfig {
+        distribution_schedule: new_schedule.clone(),
+    };
+    execute(
+        deps.as_mut(),
+        env_at(50),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+
+    // the first slot already started
+    let mut changed_started = schedule();
+    changed_started[0].2 = Uint128::from(1u128);
+    execute(
+        deps.as_mut(),
+        env_at(50),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            distribution_schedule: changed_started,
+        },
+    )
+    .unwrap_err();
+
+    execute(deps.as_mut(), env_at(50), mock_info("owner", &[]), msg).unwrap();
+    assert_eq!(
+        staker_info(&deps, "alice", Some(start_time() + 200)).pending_reward,
+        Uint128::from(5000u128)
+    );
+}
+
+#[test]
+fn migrate_staking() {
+    let mut deps = init();
+    bond(&mut deps, mock_env(), "alice", 100);
+
+    let msg = ExecuteMsg::MigrateStaking {
+        new_staking_contract: "new_staking".to_string(),
+    };
+    execute(
+        deps.as_mut(),
+        env_at(150),
+        mock_info("alice", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+
+    let res = execute(
+        deps.as_mut(),
+        env_at(150),
+        mock_info("owner", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![transfer(VEGA, "new_staking", 1000)]);
+
+    // the remaining rewards are only sent once
+    let err = execute(deps.as_mut(), env_at(200), mock_info("owner", &[]), msg).unwrap_err();
+    assert_eq!(err, StdError::generic_err("nothing to migrate"));
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(
+        config.distribution_schedule,
+        vec![
+            (start_time(), start_time() + 100, Uint128::from(1000u128)),
+            (
+                start_time() + 100,
+                start_time() + 150,
+                Uint128::from(1000u128)
+            ),
+        ]
+    );
+
+    // the accrued rewards can still be withdrawn, nothing more is distributed
+    assert_eq!(
+        staker_info(&deps, "alice", Some(start_time() + 200)).pending_reward,
+        Uint128::from(2000u128)
+    );
+}
@@ -0,0 +1,110 @@
+use cosmwasm_bignumber::Uint256;
+use cosmwasm_std::{
+    to_binary, Addr, CosmosMsg, Decimal, Deps, Order, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terra_cosmwasm::TerraQuerier;
+
+use crate::error::ContractError;
+use crate::state::{AssetConfig, AssetInfo, Config, PriceSource, YieldStrategy, ASSETS};
+use crate::utils::{anchor_balance, anchor_exchange_rate};
+
+pub fn native_asset(config: &Config) -> AssetInfo {
+    AssetInfo::NativeToken {
+        denom: config.native_denom.clone(),
+    }
+}
+
+pub fn load_asset(storage: &dyn Storage, info: &AssetInfo) -> Result<AssetConfig, ContractError> {
+    ASSETS
+        .may_load(storage, &info.key())?
+        .ok_or(ContractError::AssetNotWhitelisted {})
+}
+
+pub fn all_assets(storage: &dyn Storage) -> StdResult<Vec<AssetConfig>> {
+    ASSETS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect()
+}
+
+/// Liquid balance of the asset held by `address`
+pub fn asset_balance(deps: Deps, info: &AssetInfo, address: &Addr) -> StdResult<Uint128> {
+    match info {
+        AssetInfo::NativeToken { denom } => {
+            Ok(deps.querier.query_balance(address, denom)?.amount)
+        }
+        AssetInfo::Token { contract_addr } => {
+            anchor_balance(deps, address.to_string(), contract_addr.to_string())
+        }
+    }
+}
+
+/// Amount of the asset deposited into the yield strategy, including the accrued yield
+pub fn strategy_balance(deps: Deps, asset: &AssetConfig, address: &Addr) -> StdResult<Uint128> {
+    match &asset.strategy {
+        YieldStrategy::Hold => Ok(Uint128::zero()),
+        YieldStrategy::Anchor { market, aterra } => {
+            let aterra_balance = anchor_balance(deps, address.to_string(), aterra.to_string())?;
+            let exchange_rate = anchor_exchange_rate(deps, market.to_string())?;
+            Ok((Uint256::from(aterra_balance) * exchange_rate).into())
+        }
+    }
+}
+
+/// Price of one unit of the asset in the native denom
+pub fn asset_price(deps: Deps, config: &Config, asset: &AssetConfig) -> StdResult<Decimal> {
+    match (&asset.info, &asset.price_source) {
+        (AssetInfo::NativeToken { denom }, _) if denom == &config.native_denom => {
+            Ok(Decimal::one())
+        }
+        (_, PriceSource::Fixed { price }) => Ok(*price),
+        (AssetInfo::NativeToken { denom }, PriceSource::Oracle) => {
+            let terra_querier = TerraQuerier::new(&deps.querier);
+            let rates =
+                terra_querier.query_exchange_rates(denom.clone(), vec![config.native_denom.clone()])?;
+            rates
+                .exchange_rates
+                .first()
+                .map(|rate| rate.exchange_rate)
+                .ok_or_else(|| StdError::generic_err(format!("no oracle price for {}", denom)))
+        }
+        (AssetInfo::Token { contract_addr }, PriceSource::Oracle) => Err(StdError::generic_err(
+            format!("no oracle price for {}", contract_addr),
+        )),
+    }
+}
+
+pub fn native_value(
+    deps: Deps,
+    config: &Config,
+    asset: &AssetConfig,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    if amount.is_zero() {
+        return Ok(amount);
+    }
+    Ok(amount * asset_price(deps, config, asset)?)
+}
+
+pub fn cw20_transfer(contract_addr: &Addr, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: contract_addr.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    }))
+}
+
+pub fn validate_asset(asset: &AssetConfig) -> Result<(), ContractError> {
+    match (&asset.info, &asset.strategy, &asset.price_source) {
+        // anchor markets only take native stablecoins
+        (AssetInfo::Token { .. }, YieldStrategy::Anchor { .. }, _) => {
+            Err(ContractError::InvalidAsset {})
+        }
+        (AssetInfo::Token { .. }, _, PriceSource::Oracle) => Err(ContractError::InvalidAsset {}),
+        _ => Ok(()),
+    }
+}
@@ -1,25 +1,46 @@
-use std::convert::TryInto;
 use cosmwasm_bignumber::Uint256;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order, Reply,
-    Response, StdResult, SubMsg, Uint128, WasmMsg,
+    from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Order, Reply, Response, StdResult, SubMsg, Uint128, WasmMsg,
 };
-use cw0::nonpayable;
+use cw0::{nonpayable, PaymentError};
 use cw2::set_contract_version;
-use cw20::{Cw20ExecuteMsg};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 use moneymarket::market::{Cw20HookMsg as AnchorHookMsg, ExecuteMsg as AnchorMsg};
 
+use crate::assets::{
+    all_assets, asset_balance, cw20_transfer, load_asset, native_asset, native_value,
+    strategy_balance, validate_asset,
+};
+use crate::debt::{
+    query_debts, query_player_debt, query_total_debt, record_debt, repay_debts, take_debts,
+};
 use crate::error::ContractError;
-use crate::msg::{CurrentBalance, ExecuteMsg, Games, InstantiateMsg, QueryMsg, Threshold, BALANCEHISTORY};
-use crate::state::{Config, CONFIG, GAMES, OWNER, REQUESTING_CONTRACT, BALANCE_HISTORY};
+use crate::msg::{
+    AssetBalance, AssetsResponse, CircuitBreaker, CurrentBalance, Cw20HookMsg, DebtRegistration,
+    ExecuteMsg, GameFlowResponse, GameRegistration, Games, InstantiateMsg,
+    PendingWithdrawalResponse, QueryMsg, RegisteredContractMsg, Threshold,
+};
+use crate::ledger::{
+    accrue_anchor_yield, add_anchor_principal, query_daily_aggregates, query_ledger,
+    record_flow, reduce_anchor_principal,
+};
+use crate::state::{
+    AssetConfig, AssetInfo, Config, FlowSource, PendingWithdrawal, PriceSource, WithdrawalKind,
+    YieldStrategy, ANCHOR_PRINCIPAL, ASSETS, CONFIG, GAMES, GAME_FLOWS, OWNER, PENDING_WITHDRAWAL,
+    REQUESTING_CONTRACT,
+};
 use crate::utils::*;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:reserve-contract";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const REQUEST_FROM_ANCHOR_REPLY_ID: u64 = 1;
+const WITHDRAW_REPLY_ID: u64 = 2;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -35,15 +56,32 @@ pub fn instantiate(
     let anchor_market_address = deps.api.addr_validate(&msg.anchor_market_address)?;
     let anchor_token_address = deps.api.addr_validate(&msg.anchor_token_address)?;
     let gov_contract_address = deps.api.addr_validate(&msg.gov_contract_address)?;
+    let predecessor = msg
+        .predecessor
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
     let config = Config {
-        anchor_market_address,
         gov_contract_address,
-        anchor_token_address,
-        threshold: msg.threshold,
         native_denom: msg.native_denom,
+        flow_window: msg.flow_window,
+        max_loss_ratio: msg.max_loss_ratio,
+        distribution_contract_address: None,
+        timelock: msg.timelock,
+        predecessor,
     };
     CONFIG.save(deps.storage, &config)?;
 
+    let native = AssetConfig {
+        info: native_asset(&config),
+        threshold: msg.threshold,
+        strategy: YieldStrategy::Anchor {
+            market: anchor_market_address,
+            aterra: anchor_token_address,
+        },
+        price_source: PriceSource::Oracle,
+    };
+    ASSETS.save(deps.storage, &native.info.key(), &native)?;
+
     Ok(Response::default())
 }
 
@@ -60,8 +98,50 @@ pub fn execute(
         }
         ExecuteMsg::AddGame { addr } => execute_add_game(deps, info, addr),
         ExecuteMsg::RemoveGame { addr } => execute_remove_game(deps, info, addr),
-        ExecuteMsg::RequestFunds { amount } => execute_requeset_funds(deps, env, info, amount),
+        ExecuteMsg::RequestFunds { asset, amount } => {
+            execute_requeset_funds(deps, env, info, asset, amount)
+        }
         ExecuteMsg::DepositFunds {} => execute_deposit_funds(deps, env, info),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::WhitelistAsset {
+            asset,
+            threshold,
+            strategy,
+            price_source,
+        } => execute_whitelist_asset(deps, info, asset, threshold, strategy, price_source),
+        ExecuteMsg::RemoveAsset { asset } => execute_remove_asset(deps, env, info, asset),
+        ExecuteMsg::SetCreditLimit { addr, limit } => {
+            execute_set_credit_limit(deps, info, addr, limit)
+        }
+        ExecuteMsg::UpdateCircuitBreaker {
+            flow_window,
+            max_loss_ratio,
+        } => execute_update_circuit_breaker(deps, info, flow_window, max_loss_ratio),
+        ExecuteMsg::ResumeGame { addr } => execute_resume_game(deps, info, addr),
+        ExecuteMsg::SetDistributionContract { addr } => {
+            execute_set_distribution_contract(deps, info, addr)
+        }
+        ExecuteMsg::LpDeposit {} => execute_lp_deposit(deps, env, info),
+        ExecuteMsg::RecordDebt { player, amount } => {
+            execute_record_debt(deps, env, info, player, amount)
+        }
+        ExecuteMsg::ProposeEmergencyWithdraw { recipient } => {
+            let recipient = deps.api.addr_validate(&recipient)?;
+            execute_propose_withdrawal(deps, env, info, WithdrawalKind::Emergency { recipient })
+        }
+        ExecuteMsg::MigrateReserve { new_reserve } => {
+            let new_reserve = deps.api.addr_validate(&new_reserve)?;
+            execute_propose_withdrawal(deps, env, info, WithdrawalKind::Migration { new_reserve })
+        }
+        ExecuteMsg::CancelWithdraw {} => execute_cancel_withdraw(deps, info),
+        ExecuteMsg::ExecuteWithdraw {} => execute_withdraw(deps, env, info),
+        ExecuteMsg::ImportRegistrations {
+            games,
+            distribution_contract,
+            debts,
+            assets,
+        } => execute_import_registrations(deps, info, games, distribution_contract, debts, assets),
+        ExecuteMsg::UpdateTimelock { timelock } => execute_update_timelock(deps, info, timelock),
     }
 }
 
@@ -73,16 +153,88 @@ pub fn execute_change_threshold(
     nonpayable(&info)?;
     only_owner(deps.as_ref(), &info)?;
 
-    CONFIG.update(
+    let config = CONFIG.load(deps.storage)?;
+    ASSETS.update(
         deps.storage,
-        move |mut config| -> Result<_, ContractError> {
-            config.threshold = threshold;
-            Ok(config)
+        &config.native_denom,
+        move |asset| -> Result<_, ContractError> {
+            let mut asset = asset.ok_or(ContractError::AssetNotWhitelisted {})?;
+            asset.threshold = threshold;
+            Ok(asset)
         },
     )?;
     Ok(Response::default())
 }
 
+pub fn execute_whitelist_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: AssetInfo,
+    threshold: Uint128,
+    strategy: YieldStrategy,
+    price_source: PriceSource,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_owner(deps.as_ref(), &info)?;
+
+    let info = match asset {
+        AssetInfo::NativeToken { denom } => AssetInfo::NativeToken { denom },
+        AssetInfo::Token { contract_addr } => AssetInfo::Token {
+            contract_addr: deps.api.addr_validate(contract_addr.as_str())?,
+        },
+    };
+    let strategy = match strategy {
+        YieldStrategy::Hold => YieldStrategy::Hold,
+        YieldStrategy::Anchor { market, aterra } => YieldStrategy::Anchor {
+            market: deps.api.addr_validate(market.as_str())?,
+            aterra: deps.api.addr_validate(aterra.as_str())?,
+        },
+    };
+    let asset = AssetConfig {
+        info,
+        threshold,
+        strategy,
+        price_source,
+    };
+    validate_asset(&asset)?;
+    ASSETS.save(deps.storage, &asset.info.key(), &asset)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "whitelist asset")
+        .add_attribute("asset", asset.info.key()))
+}
+
+pub fn execute_remove_asset(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset: AssetInfo,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_owner(deps.as_ref(), &info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    if asset == native_asset(&config) {
+        return Err(ContractError::NativeAssetRemoval {});
+    }
+    let asset = load_asset(deps.storage, &asset)?;
+
+    // withdrawals and migrations only move whitelisted assets
+    let balance = asset_balance(deps.as_ref(), &asset.info, &env.contract.address)?
+        + strategy_balance(deps.as_ref(), &asset, &env.contract.address)?;
+    let principal = ANCHOR_PRINCIPAL
+        .may_load(deps.storage, &asset.info.key())?
+        .unwrap_or_default();
+    if !balance.is_zero() || !principal.is_zero() {
+        return Err(ContractError::AssetNotEmpty {});
+    }
+    ASSETS.remove(deps.storage, &asset.info.key());
+
+    Ok(Response::new()
+        .add_attribute("action", "remove asset")
+        .add_attribute("asset", asset.info.key()))
+}
+
 pub fn execute_add_game(
     deps: DepsMut,
     info: MessageInfo,
@@ -106,47 +258,219 @@ pub fn execute_remove_game(
     only_owner(deps.as_ref(), &info)?;
 
     let game_addr = deps.api.addr_validate(&addr)?;
+    // the flow is kept, so adding the game again does not lift its suspension
     GAMES.remove(deps.storage, game_addr);
 
     Ok(Response::default())
 }
 
-pub fn execute_requeset_funds(
+pub fn execute_set_credit_limit(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+    limit: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_gov(deps.as_ref(), &info)?;
+
+    let game_addr = deps.api.addr_validate(&addr)?;
+    if GAMES.may_load(deps.storage, game_addr.clone())?.is_none() {
+        return Err(ContractError::GameNotFound {});
+    }
+
+    let mut flow = GAME_FLOWS
+        .may_load(deps.storage, game_addr.clone())?
+        .unwrap_or_default();
+    flow.credit_limit = limit;
+    GAME_FLOWS.save(deps.storage, game_addr, &flow)?;
+
+    Ok(Response::new().add_attribute("action", "set credit limit"))
+}
+
+pub fn execute_update_circuit_breaker(
     deps: DepsMut,
+    info: MessageInfo,
+    flow_window: Option<u64>,
+    max_loss_ratio: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_gov(deps.as_ref(), &info)?;
+
+    CONFIG.update(
+        deps.storage,
+        move |mut config| -> Result<_, ContractError> {
+            if let Some(flow_window) = flow_window {
+                config.flow_window = flow_window;
+            }
+            if let Some(max_loss_ratio) = max_loss_ratio {
+                config.max_loss_ratio = max_loss_ratio;
+            }
+            Ok(config)
+        },
+    )?;
+    Ok(Response::default())
+}
+
+pub fn execute_resume_game(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_gov(deps.as_ref(), &info)?;
+
+    let game_addr = deps.api.addr_validate(&addr)?;
+    let mut flow = GAME_FLOWS
+        .may_load(deps.storage, game_addr.clone())?
+        .unwrap_or_default();
+    // losses of the window that triggered the suspension should not
+    // suspend the game again right away
+    flow.suspended = false;
+    flow.buckets.clear();
+    GAME_FLOWS.save(deps.storage, game_addr, &flow)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "resume game")
+        .add_attribute("game", addr))
+}
+
+pub fn execute_set_distribution_contract(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_owner(deps.as_ref(), &info)?;
+
+    let distribution_contract_address = deps.api.addr_validate(&addr)?;
+    CONFIG.update(
+        deps.storage,
+        move |mut config| -> Result<_, ContractError> {
+            config.distribution_contract_address = Some(distribution_contract_address);
+            Ok(config)
+        },
+    )?;
+    Ok(Response::default())
+}
+
+/// Adds the value taken by the game to its flow. Suspended games and games over
+/// their credit limit are refused, the suspension response is returned when the
+/// game drains the reserve, and only governance can resume it
+fn track_game_request(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    game: &Addr,
+    value: Uint128,
+) -> Result<Option<Response>, ContractError> {
+    let mut flow = GAME_FLOWS
+        .may_load(deps.storage, game.clone())?
+        .unwrap_or_default();
+    if flow.suspended {
+        return Err(ContractError::GameSuspended {});
+    }
+    let now = env.block.time.seconds();
+    flow.roll_window(now, config.flow_window);
+    flow.current_bucket(now, config.flow_window).requested += value;
+
+    let net_loss = flow.net_loss();
+    if let Some(limit) = flow.credit_limit {
+        if net_loss > limit {
+            return Err(ContractError::CreditLimitExceeded { limit, net_loss });
+        }
+    }
+
+    let reserve_balance = query_current_balance(deps.as_ref(), env)?.balance;
+    if net_loss > reserve_balance * config.max_loss_ratio {
+        flow.suspended = true;
+        GAME_FLOWS.save(deps.storage, game.clone(), &flow)?;
+        return Ok(Some(
+            Response::new()
+                .add_attribute("action", "suspend game")
+                .add_attribute("alert", "game drain")
+                .add_attribute("game", game)
+                .add_attribute("net_loss", net_loss),
+        ));
+    }
+    GAME_FLOWS.save(deps.storage, game.clone(), &flow)?;
+    Ok(None)
+}
+
+pub fn execute_requeset_funds(
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    asset: Option<AssetInfo>,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
     only_game(deps.as_ref(), &info)?;
 
     let config = CONFIG.load(deps.storage)?;
+    let asset = load_asset(
+        deps.storage,
+        &asset.unwrap_or_else(|| native_asset(&config)),
+    )?;
+    let value = native_value(deps.as_ref(), &config, &asset, amount)?;
+
+    // rewards paid to the distribution contract are not game losses
+    let distribution = config.distribution_contract_address.as_ref() == Some(&info.sender);
+    if !distribution {
+        if let Some(suspension) =
+            track_game_request(deps.branch(), &env, &config, &info.sender, value)?
+        {
+            return Ok(suspension);
+        }
+    }
 
-    let contract_balance = deps
-        .querier
-        .query_balance(&env.contract.address, &config.native_denom)?;
+    accrue_anchor_yield(deps.branch(), &env, &asset)?;
+    let source = if distribution {
+        FlowSource::DistributionRequest
+    } else {
+        FlowSource::GameRequest
+    };
+    record_flow(
+        deps.storage,
+        env.block.time.seconds(),
+        source,
+        Some(info.sender.clone()),
+        value,
+    )?;
 
-    let coin = Coin {
-        amount,
-        denom: config.native_denom.clone(),
+    let contract_balance = asset_balance(deps.as_ref(), &asset.info, &env.contract.address)?;
+    let denom = match &asset.info {
+        AssetInfo::NativeToken { denom } => denom.clone(),
+        AssetInfo::Token { contract_addr } => {
+            // cw20 transfers are not taxed and tokens are never deposited anywhere
+            if contract_balance < amount {
+                return Err(ContractError::InsufficientFunds {});
+            }
+            return Ok(Response::new()
+                .add_attribute("action", "send to game")
+                .add_message(cw20_transfer(contract_addr, &info.sender, amount)?));
+        }
     };
 
+    let coin = Coin { amount, denom };
+
     // because we need to send exact amount we check if we have
     // enoungh balance to do the transaction with taxes
     let tax = compute_tax(deps.as_ref(), &coin.clone())?;
     let with_tax = amount + tax;
-    if contract_balance.amount >= with_tax {
+    if contract_balance >= with_tax {
         // sending exact amount
         send_to_game(info.sender.to_string(), coin)
+    } else if asset.strategy == YieldStrategy::Hold {
+        Err(ContractError::InsufficientFunds {})
     } else {
-        REQUESTING_CONTRACT.save(deps.storage, &(info.sender, coin.amount))?;
+        REQUESTING_CONTRACT.save(deps.storage, &(info.sender, coin))?;
         // adding aditional tax because we pay to get funds from anchor
         // and to send them to the game
         //
         // also adding 1 to help with rounding error when converting to the aUST
         let with_tax = with_tax + tax + Uint128::from(1u128);
-        let request_amount = with_tax - contract_balance.amount;
-        request_from_anchor(deps.as_ref(), &config, request_amount)
+        let request_amount = with_tax - contract_balance;
+        request_from_anchor(deps, &asset, request_amount)
     }
 }
 
@@ -157,184 +481,628 @@ pub fn execute_deposit_funds(
 ) -> Result<Response, ContractError> {
     only_game(deps.as_ref(), &info)?;
 
-    let config = CONFIG.load(deps.storage)?;
-
-    let contract_balance = deps
-        .querier
-        .query_balance(&env.contract.address, &config.native_denom)?;
-    //Contract_balance is uusd and doesn't include aUST
+    let deposits = native_deposits(deps.as_ref(), &info)?;
+    deposit(deps, env, info.sender, deposits, FlowSource::GameSweep)
+}
 
-    if contract_balance.amount > config.threshold {
-        //We want to deposit to anchor
-        //TODO Add min threshhold 
-        let coin = Coin {
-            amount: contract_balance.amount - config.threshold,
-            denom: config.native_denom.clone(),
-        };
-        send_to_anchor(deps.as_ref(), &config, coin)
-    } else { //the amount we deposited is small enough to keep it in uusd
-        let current_total_balance=&query_current_balance(deps.as_ref(), &env).unwrap().balance;
-        store_balance_in_state(deps, &env,current_total_balance)?;
-        Ok(Response::default())    
+pub fn execute_lp_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let deposits = native_deposits(deps.as_ref(), &info)?;
+    if deposits.is_empty() {
+        return Err(ContractError::Payment(PaymentError::NoFunds {}));
     }
+    deposit(deps, env, info.sender, deposits, FlowSource::LpDeposit)
 }
 
-fn send_to_game(addr: String, coin: Coin) -> Result<Response, ContractError> {
-    let msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: addr,
-        amount: vec![coin.clone()],
-    });
-    Ok(Response::new()
-        .add_attribute("action", "send to game")
-        .add_message(msg))
-}
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let asset = load_asset(
+        deps.storage,
+        &AssetInfo::Token {
+            contract_addr: info.sender,
+        },
+    )?;
+    let depositor = deps.api.addr_validate(&cw20_msg.sender)?;
+    let deposits = vec![(asset, cw20_msg.amount)];
 
-fn store_balance_in_state(deps: DepsMut, env: &Env, amount: &Uint128) -> Result<Response, ContractError>{
-    let height: u64 =u64::from_le(env.block.height.try_into().unwrap());
-    
-    BALANCE_HISTORY.save(deps.storage,  cw_storage_plus::U64Key::from(height), &amount)?;
-    //BALANCE_HISTORY.save(deps.storage, env.block.height , &amount)?;
-    Ok(Response::default())
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::DepositFunds {} => {
+            if GAMES.may_load(deps.storage, depositor.clone())?.is_none() {
+                return Err(ContractError::Unauthorized {});
+            }
+            deposit(deps, env, depositor, deposits, FlowSource::GameSweep)
+        }
+        Cw20HookMsg::LpDeposit {} => {
+            deposit(deps, env, depositor, deposits, FlowSource::LpDeposit)
+        }
+    }
 }
 
-fn request_from_anchor(
+// every attached coin has to be a whitelisted asset
+fn native_deposits(
     deps: Deps,
-    config: &Config,
-    amount: Uint128,
-) -> Result<Response, ContractError> {
-    // converting amount from uusd into aUST
-    let exchange_rate = anchor_exchange_rate(deps, config.anchor_market_address.to_string())?;
-    let amount = Uint256::from(amount) / exchange_rate;
-
-    let msg = AnchorHookMsg::RedeemStable {};
-    let msg = Cw20ExecuteMsg::Send {
-        amount: amount.into(),
-        contract: config.anchor_market_address.to_string(),
-        msg: to_binary(&msg)?,
-    };
-    let msg = WasmMsg::Execute {
-        contract_addr: config.anchor_token_address.to_string(),
-        msg: to_binary(&msg)?,
-        funds: vec![],
-    };
-    let msg = SubMsg::reply_on_success(msg, 1);
-    Ok(Response::new()
-        .add_attribute("action", "request from anchor")
-        .add_submessage(msg))
+    info: &MessageInfo,
+) -> Result<Vec<(AssetConfig, Uint128)>, ContractError> {
+    info.funds
+        .iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .map(|coin| {
+            let asset = load_asset(
+                deps.storage,
+                &AssetInfo::NativeToken {
+                    denom: coin.denom.clone(),
+                },
+            )?;
+            Ok((asset, coin.amount))
+        })
+        .collect()
 }
 
-fn send_to_anchor(deps: Deps, config: &Config, coin: Coin) -> Result<Response, ContractError> {
-    let without_tax = deduct_tax(deps, coin)?;
-    let msg = WasmMsg::Execute {
-        contract_addr: config.anchor_market_address.to_string(),
-        funds: vec![without_tax],
-        msg: to_binary(&AnchorMsg::DepositStable {})?,
-    };
-    Ok(Response::new()
-        .add_attribute("action", "send to anchor")
-        .add_message(msg))
-}
+fn deposit(
+    mut deps: DepsMut,
+    env: Env,
+    depositor: Addr,
+    deposits: Vec<(AssetConfig, Uint128)>,
+    source: FlowSource,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
-    match msg.id {
-        1 => {
-            let (contract_addr, amount) = REQUESTING_CONTRACT.load(deps.storage)?;
-            let config = CONFIG.load(deps.storage)?;
-            send_to_game(
-                contract_addr.to_string(),
-                Coin {
-                    amount,
-                    denom: config.native_denom,
-                },
-            )
+    let mut value = Uint128::zero();
+    let mut native_deposited = Uint128::zero();
+    for (asset, amount) in &deposits {
+        value += native_value(deps.as_ref(), &config, asset, *amount)?;
+        if asset.info == native_asset(&config) {
+            native_deposited += *amount;
         }
-        _ => Err(ContractError::InvalidReplyId {}),
     }
-}
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::CurrentBalance {} => to_binary(&query_current_balance(deps, &env)?),
-        QueryMsg::GetThreshold {} => to_binary(&query_get_threshold(deps)?),
-        QueryMsg::ListGames {} => to_binary(&query_list_games(deps)?),
-        QueryMsg::BalanceHistory {} => to_binary(&query_balance_history(deps)?),
+    if source == FlowSource::GameSweep {
+        let mut flow = GAME_FLOWS
+            .may_load(deps.storage, depositor.clone())?
+            .unwrap_or_default();
+        let now = env.block.time.seconds();
+        flow.roll_window(now, config.flow_window);
+        flow.current_bucket(now, config.flow_window).deposited += value;
+        GAME_FLOWS.save(deps.storage, depositor.clone(), &flow)?;
     }
-}
 
-fn query_current_balance(deps: Deps, env: &Env) -> StdResult<CurrentBalance> {
-    let config = CONFIG.load(deps.storage)?;
-    let native_balance = deps
-        .querier
-        .query_balance(&env.contract.address, &config.native_denom)?
-        .amount;
-
-    let anchor_balance = anchor_balance(
-        deps,
-        env.contract.address.to_string(),
-        config.anchor_token_address.to_string(),
+    record_flow(
+        deps.storage,
+        env.block.time.seconds(),
+        source.clone(),
+        Some(depositor),
+        value,
     )?;
 
-    // converting amount from aUST into uusd
-    let exchange_rate = anchor_exchange_rate(deps, config.anchor_market_address.to_string())?;
-    let anchor_balance: Uint128 = (Uint256::from(anchor_balance) * exchange_rate).into();
-
-    Ok(CurrentBalance {
-        balance: native_balance + anchor_balance,
-    })
-}
-
-fn query_get_threshold(deps: Deps) -> StdResult<Threshold> {
-    let threshold = CONFIG.load(deps.storage)?.threshold;
-    Ok(Threshold { threshold })
-}
+    // outstanding debts are paid from the sweep before anything goes to anchor
+    let (repayments, repaid) = if source == FlowSource::GameSweep {
+        repay_debts(
+            deps.branch(),
+            &config,
+            env.block.time.seconds(),
+            native_deposited,
+        )?
+    } else {
+        (vec![], Uint128::zero())
+    };
 
-fn query_list_games(deps: Deps) -> StdResult<Games> {
-    let games = GAMES
-        .keys(deps.storage, None, None, Order::Ascending)
-        .map(|game| String::from_utf8(game).unwrap())
-        .collect();
-    Ok(Games { games })
+    let mut response = Response::new();
+    for (asset, _) in deposits {
+        let spent = if asset.info == native_asset(&config) {
+            repaid
+        } else {
+            Uint128::zero()
+        };
+        let sweep = sweep_to_anchor(deps.branch(), &env, &asset, spent)?;
+        response = response
+            .add_attributes(sweep.attributes)
+            .add_submessages(sweep.messages);
+    }
+    Ok(response.add_messages(repayments))
 }
-//fn query_balance_history(deps: Deps) -> StdResult<BALANCEHISTORY> {
-//    Ok(BALANCEHISTORY {
-//        balanceHistory: BALANCE_HISTORY
-//            .range(deps.storage, None, None, Order::Ascending)
-//            .collect::<StdResult<Vec<_>>>()?
-//            .into_iter()
-//            .map(|(timePoint, balance)| (u64::from_be_bytes(timePoint[0].try_into().unwrap()), balance))
-//            .collect::<Vec<_>>(),
-//    })
-//}
-fn query_balance_history(deps: Deps) -> StdResult<BALANCEHISTORY> {
-    let balance_history = BALANCE_HISTORY
-    .range(deps.storage, None, None, Order::Ascending)
-    .map(|time_point| (u64::from_be_bytes(time_point.as_ref().unwrap().0[0..8].try_into().unwrap()) , time_point.unwrap().1))
-    //.map(|time_point| (time_point.as_ref() , time_point.unwrap().1))
-    .collect::<Vec<_>>();
 
-    Ok( BALANCEHISTORY { balance_history})
+pub fn execute_record_debt(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    player: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_game(deps.as_ref(), &info)?;
 
+    // the debt is repaid from the reserve, so it counts as a loss of the game
+    let config = CONFIG.load(deps.storage)?;
+    if let Some(suspension) =
+        track_game_request(deps.branch(), &env, &config, &info.sender, amount)?
+    {
+        return Ok(suspension);
+    }
 
+    let player = deps.api.addr_validate(&player)?;
+    record_debt(
+        deps,
+        player.clone(),
+        info.sender.clone(),
+        amount,
+        env.block.time.seconds(),
+    )?;
 
+    Ok(Response::new()
+        .add_attribute("action", "record debt")
+        .add_attribute("game", info.sender)
+        .add_attribute("player", player)
+        .add_attribute("amount", amount))
+}
 
+// `spent` is the part of the balance already committed to other transfers
+fn sweep_to_anchor(
+    mut deps: DepsMut,
+    env: &Env,
+    asset: &AssetConfig,
+    spent: Uint128,
+) -> Result<Response, ContractError> {
+    if asset.strategy == YieldStrategy::Hold {
+        return Ok(Response::default());
+    }
+    accrue_anchor_yield(deps.branch(), env, asset)?;
 
+    let contract_balance = asset_balance(deps.as_ref(), &asset.info, &env.contract.address)?;
+    //Contract_balance is uusd and doesn't include aUST
+    let available = contract_balance.saturating_sub(spent);
 
-//    Ok(BALANCEHISTORY {
-//        balanceHistory: BALANCE_HISTORY
-//            .range(deps.storage, None, None, Order::Ascending)
-//            .collect::<StdResult<Vec<_>>>()?
-//            .into_iter()
-//            .map(|(timePoint, balance)| (u64::from_be_bytes(timePoint[0].try_into().unwrap()), balance))
-//            .collect::<Vec<_>>(),
-//    })
+    if available > asset.threshold {
+        //We want to deposit to anchor
+        //TODO Add min threshhold 
+        let coin = Coin {
+            amount: available - asset.threshold,
+            denom: asset.info.key(),
+        };
+        send_to_anchor(deps, asset, coin)
+    } else { //the amount we deposited is small enough to keep it in uusd
+        Ok(Response::default())
+    }
 }
 
-
-
-#[cfg(test)]
-mod tests {
+pub fn execute_propose_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    kind: WithdrawalKind,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_gov(deps.as_ref(), &info)?;
+
+    if PENDING_WITHDRAWAL.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::WithdrawalPending {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let executable_at = env.block.time.seconds() + config.timelock;
+    let action = match &kind {
+        WithdrawalKind::Emergency { .. } => "propose emergency withdraw",
+        WithdrawalKind::Migration { .. } => "propose reserve migration",
+    };
+    PENDING_WITHDRAWAL.save(
+        deps.storage,
+        &PendingWithdrawal {
+            kind,
+            executable_at,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", action)
+        .add_attribute("executable_at", executable_at.to_string()))
+}
+
+pub fn execute_cancel_withdraw(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_gov(deps.as_ref(), &info)?;
+
+    if PENDING_WITHDRAWAL.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NoPendingWithdrawal {});
+    }
+    PENDING_WITHDRAWAL.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "cancel withdraw"))
+}
+
+pub fn execute_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let pending = PENDING_WITHDRAWAL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingWithdrawal {})?;
+    if env.block.time.seconds() < pending.executable_at {
+        return Err(ContractError::WithdrawalLocked {
+            executable_at: pending.executable_at,
+        });
+    }
+
+    let mut redeems = vec![];
+    for asset in all_assets(deps.storage)? {
+        if let YieldStrategy::Anchor { market, aterra } = asset.strategy {
+            let aterra_balance = anchor_balance(
+                deps.as_ref(),
+                env.contract.address.to_string(),
+                aterra.to_string(),
+            )?;
+            if aterra_balance.is_zero() {
+                continue;
+            }
+            let msg = Cw20ExecuteMsg::Send {
+                amount: aterra_balance,
+                contract: market.to_string(),
+                msg: to_binary(&AnchorHookMsg::RedeemStable {})?,
+            };
+            redeems.push(WasmMsg::Execute {
+                contract_addr: aterra.to_string(),
+                msg: to_binary(&msg)?,
+                funds: vec![],
+            });
+        }
+    }
+
+    // everything is redeemed first, the transfers happen once the last
+    // redemption is back
+    let last = match redeems.pop() {
+        Some(last) => last,
+        None => return complete_withdrawal(deps, env),
+    };
+    Ok(Response::new()
+        .add_attribute("action", "redeem all from anchor")
+        .add_messages(redeems)
+        .add_submessage(SubMsg::reply_on_success(last, WITHDRAW_REPLY_ID)))
+}
+
+fn complete_withdrawal(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let pending = PENDING_WITHDRAWAL.load(deps.storage)?;
+    PENDING_WITHDRAWAL.remove(deps.storage);
+
+    let config = CONFIG.load(deps.storage)?;
+    let recipient = match &pending.kind {
+        WithdrawalKind::Emergency { recipient } => recipient.clone(),
+        WithdrawalKind::Migration { new_reserve } => new_reserve.clone(),
+    };
+
+    let mut coins = vec![];
+    let mut response = Response::new();
+    for asset in all_assets(deps.storage)? {
+        ANCHOR_PRINCIPAL.remove(deps.storage, &asset.info.key());
+        let balance = asset_balance(deps.as_ref(), &asset.info, &env.contract.address)?;
+        if balance.is_zero() {
+            continue;
+        }
+        match &asset.info {
+            AssetInfo::NativeToken { denom } => coins.push(deduct_tax(
+                deps.as_ref(),
+                Coin {
+                    denom: denom.clone(),
+                    amount: balance,
+                },
+            )?),
+            AssetInfo::Token { contract_addr } => {
+                response = response.add_message(cw20_transfer(contract_addr, &recipient, balance)?)
+            }
+        }
+    }
+    if !coins.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins,
+        });
+    }
+
+    match pending.kind {
+        WithdrawalKind::Emergency { .. } => Ok(response
+            .add_attribute("action", "emergency withdraw")
+            .add_attribute("recipient", recipient)),
+        WithdrawalKind::Migration { new_reserve } => {
+            let games = GAMES
+                .keys(deps.storage, None, None, Order::Ascending)
+                .map(|game| String::from_utf8(game).unwrap())
+                .collect::<Vec<_>>();
+            let registrations = games
+                .iter()
+                .map(|game| {
+                    let flow = GAME_FLOWS
+                        .may_load(deps.storage, Addr::unchecked(game))?
+                        .unwrap_or_default();
+                    Ok(GameRegistration {
+                        addr: game.clone(),
+                        flow,
+                    })
+                })
+                .collect::<StdResult<Vec<_>>>()?;
+
+            // the new reserve has to know the games before they start requesting funds from it,
+            // and it repays the debts from now on
+            let import = WasmMsg::Execute {
+                contract_addr: new_reserve.to_string(),
+                msg: to_binary(&ExecuteMsg::ImportRegistrations {
+                    games: registrations,
+                    distribution_contract: config
+                        .distribution_contract_address
+                        .as_ref()
+                        .map(|addr| addr.to_string()),
+                    debts: take_debts(deps.branch())?,
+                    assets: all_assets(deps.storage)?,
+                })?,
+                funds: vec![],
+            };
+            response = response.add_message(import);
+
+            let migrate = to_binary(&RegisteredContractMsg::MigrateReserve {
+                new_reserve: new_reserve.to_string(),
+            })?;
+            for contract in games {
+                // the distribution contract is registered as a game as well
+                response = response.add_message(WasmMsg::Execute {
+                    contract_addr: contract,
+                    msg: migrate.clone(),
+                    funds: vec![],
+                });
+            }
+            Ok(response
+                .add_attribute("action", "migrate reserve")
+                .add_attribute("new_reserve", new_reserve))
+        }
+    }
+}
+
+pub fn execute_import_registrations(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    games: Vec<GameRegistration>,
+    distribution_contract: Option<String>,
+    debts: Vec<DebtRegistration>,
+    assets: Vec<AssetConfig>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    if config.predecessor.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for game in games {
+        let game_addr = deps.api.addr_validate(&game.addr)?;
+        GAMES.save(deps.storage, game_addr.clone(), &())?;
+        GAME_FLOWS.save(deps.storage, game_addr, &game.flow)?;
+    }
+    if let Some(distribution_contract) = distribution_contract {
+        let distribution_contract_address = deps.api.addr_validate(&distribution_contract)?;
+        CONFIG.update(
+            deps.storage,
+            move |mut config| -> Result<_, ContractError> {
+                config.distribution_contract_address = Some(distribution_contract_address);
+                Ok(config)
+            },
+        )?;
+    }
+    for debt in debts {
+        let player = deps.api.addr_validate(&debt.player)?;
+        let game = deps.api.addr_validate(&debt.game)?;
+        record_debt(deps.branch(), player, game, debt.amount, debt.created_at)?;
+    }
+    // the configuration of this reserve wins for assets both reserves list
+    for asset in assets {
+        if ASSETS.may_load(deps.storage, &asset.info.key())?.is_none() {
+            validate_asset(&asset)?;
+            ASSETS.save(deps.storage, &asset.info.key(), &asset)?;
+        }
+    }
+
+    Ok(Response::new().add_attribute("action", "import registrations"))
+}
+
+pub fn execute_update_timelock(
+    deps: DepsMut,
+    info: MessageInfo,
+    timelock: u64,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    only_gov(deps.as_ref(), &info)?;
+
+    CONFIG.update(
+        deps.storage,
+        move |mut config| -> Result<_, ContractError> {
+            config.timelock = timelock;
+            Ok(config)
+        },
+    )?;
+    Ok(Response::default())
+}
+
+fn send_to_game(addr: String, coin: Coin) -> Result<Response, ContractError> {
+    let msg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: addr,
+        amount: vec![coin.clone()],
+    });
+    Ok(Response::new()
+        .add_attribute("action", "send to game")
+        .add_message(msg))
+}
+
+fn request_from_anchor(
+    deps: DepsMut,
+    asset: &AssetConfig,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let (market, aterra) = match &asset.strategy {
+        YieldStrategy::Anchor { market, aterra } => (market, aterra),
+        YieldStrategy::Hold => return Err(ContractError::InsufficientFunds {}),
+    };
+    reduce_anchor_principal(deps.storage, &asset.info, amount)?;
+
+    // converting amount from uusd into aUST
+    let exchange_rate = anchor_exchange_rate(deps.as_ref(), market.to_string())?;
+    let amount = Uint256::from(amount) / exchange_rate;
+
+    let msg = AnchorHookMsg::RedeemStable {};
+    let msg = Cw20ExecuteMsg::Send {
+        amount: amount.into(),
+        contract: market.to_string(),
+        msg: to_binary(&msg)?,
+    };
+    let msg = WasmMsg::Execute {
+        contract_addr: aterra.to_string(),
+        msg: to_binary(&msg)?,
+        funds: vec![],
+    };
+    let msg = SubMsg::reply_on_success(msg, REQUEST_FROM_ANCHOR_REPLY_ID);
+    Ok(Response::new()
+        .add_attribute("action", "request from anchor")
+        .add_submessage(msg))
+}
+
+fn send_to_anchor(
+    deps: DepsMut,
+    asset: &AssetConfig,
+    coin: Coin,
+) -> Result<Response, ContractError> {
+    let market = match &asset.strategy {
+        YieldStrategy::Anchor { market, .. } => market,
+        YieldStrategy::Hold => return Ok(Response::default()),
+    };
+    let without_tax = deduct_tax(deps.as_ref(), coin)?;
+    add_anchor_principal(deps.storage, &asset.info, without_tax.amount)?;
+    let msg = WasmMsg::Execute {
+        contract_addr: market.to_string(),
+        funds: vec![without_tax],
+        msg: to_binary(&AnchorMsg::DepositStable {})?,
+    };
+    Ok(Response::new()
+        .add_attribute("action", "send to anchor")
+        .add_message(msg))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        WITHDRAW_REPLY_ID => complete_withdrawal(deps, env),
+        REQUEST_FROM_ANCHOR_REPLY_ID => {
+            let (contract_addr, coin) = REQUESTING_CONTRACT.load(deps.storage)?;
+            send_to_game(contract_addr.to_string(), coin)
+        }
+        _ => Err(ContractError::InvalidReplyId {}),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::CurrentBalance {} => to_binary(&query_current_balance(deps, &env)?),
+        QueryMsg::GetThreshold {} => to_binary(&query_get_threshold(deps)?),
+        QueryMsg::Assets {} => to_binary(&query_assets(deps)?),
+        QueryMsg::ListGames {} => to_binary(&query_list_games(deps)?),
+        QueryMsg::Ledger { start_after, limit } => {
+            to_binary(&query_ledger(deps, start_after, limit)?)
+        }
+        QueryMsg::DailyAggregates { start_after, limit } => {
+            to_binary(&query_daily_aggregates(deps, start_after, limit)?)
+        }
+        QueryMsg::PlayerDebt { addr } => to_binary(&query_player_debt(deps, addr)?),
+        QueryMsg::TotalDebt {} => to_binary(&query_total_debt(deps)?),
+        QueryMsg::Debts { start_after, limit } => {
+            to_binary(&query_debts(deps, start_after, limit)?)
+        }
+        QueryMsg::GameFlow { addr } => to_binary(&query_game_flow(deps, &env, addr)?),
+        QueryMsg::CircuitBreaker {} => to_binary(&query_circuit_breaker(deps)?),
+        QueryMsg::PendingWithdrawal {} => to_binary(&query_pending_withdrawal(deps)?),
+    }
+}
+
+fn query_current_balance(deps: Deps, env: &Env) -> StdResult<CurrentBalance> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let mut balance = Uint128::zero();
+    let mut assets = vec![];
+    for asset in all_assets(deps.storage)? {
+        // liquid balance plus the deposit converted from aTerra
+        let amount = asset_balance(deps, &asset.info, &env.contract.address)?
+            + strategy_balance(deps, &asset, &env.contract.address)?;
+        let value = native_value(deps, &config, &asset, amount)?;
+        balance += value;
+        assets.push(AssetBalance {
+            info: asset.info,
+            amount,
+            value,
+        });
+    }
+
+    Ok(CurrentBalance { balance, assets })
+}
+
+fn query_get_threshold(deps: Deps) -> StdResult<Threshold> {
+    let config = CONFIG.load(deps.storage)?;
+    let threshold = ASSETS.load(deps.storage, &config.native_denom)?.threshold;
+    Ok(Threshold { threshold })
+}
+
+fn query_assets(deps: Deps) -> StdResult<AssetsResponse> {
+    Ok(AssetsResponse {
+        assets: all_assets(deps.storage)?,
+    })
+}
+
+fn query_list_games(deps: Deps) -> StdResult<Games> {
+    let games = GAMES
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|game| String::from_utf8(game).unwrap())
+        .collect();
+    Ok(Games { games })
+}
+fn query_game_flow(deps: Deps, env: &Env, addr: String) -> StdResult<GameFlowResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let game_addr = deps.api.addr_validate(&addr)?;
+    let mut flow = GAME_FLOWS
+        .may_load(deps.storage, game_addr)?
+        .unwrap_or_default();
+    let now = env.block.time.seconds();
+    flow.roll_window(now, config.flow_window);
+    Ok(GameFlowResponse {
+        window_start: now.saturating_sub(config.flow_window),
+        requested: flow.requested(),
+        deposited: flow.deposited(),
+        net_loss: flow.net_loss(),
+        credit_limit: flow.credit_limit,
+        suspended: flow.suspended,
+    })
+}
+
+fn query_circuit_breaker(deps: Deps) -> StdResult<CircuitBreaker> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(CircuitBreaker {
+        flow_window: config.flow_window,
+        max_loss_ratio: config.max_loss_ratio,
+    })
+}
+
+fn query_pending_withdrawal(deps: Deps) -> StdResult<PendingWithdrawalResponse> {
+    Ok(PendingWithdrawalResponse {
+        pending: PENDING_WITHDRAWAL.may_load(deps.storage)?,
+        timelock: CONFIG.load(deps.storage)?.timelock,
+    })
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
     use cosmwasm_std::from_binary;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
@@ -349,6 +1117,10 @@ mod tests {
             anchor_token_address: "anchor-token".to_string(),
             threshold: 1000u128.into(),
             native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
         };
         let info = mock_info("creator", &[]);
 
@@ -375,6 +1147,10 @@ mod tests {
             anchor_token_address: "anchor-token".to_string(),
             threshold: 1000u128.into(),
             native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
         };
         let info = mock_info("creator", &[]);
 
@@ -425,6 +1201,10 @@ mod tests {
             anchor_token_address: "anchor-token".to_string(),
             threshold: 1000u128.into(),
             native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
         };
         let info = mock_info("creator", &[]);
 
@@ -460,35 +1240,20 @@ mod tests {
         );
     }
 
-    use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+    use crate::msg::{
+        DailyAggregatesResponse, DebtsResponse, LedgerResponse, PlayerDebtResponse,
+        TotalDebtResponse,
+    };
+    use crate::state::GameFlow;
+    use crate::utils::tests_utils::CustomQuerier;
+    use cosmwasm_bignumber::Decimal256;
+    use cosmwasm_std::testing::{MockApi, MockStorage};
     use cosmwasm_std::OwnedDeps;
-    use terra_cosmwasm::TerraQueryWrapper;
-    fn custom_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier<TerraQueryWrapper>> {
-        use cosmwasm_std::testing::MockQuerierCustomHandlerResult;
-        use cosmwasm_std::{ContractResult, Decimal};
-        use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery};
-
-        let querier = MockQuerier::new(&[]).with_custom_handler(
-            |q: &TerraQueryWrapper| -> MockQuerierCustomHandlerResult {
-                let res = match q.query_data {
-                    TerraQuery::TaxRate {} => to_binary(&TaxRateResponse {
-                        rate: Decimal::zero(),
-                    })
-                    .unwrap(),
-                    TerraQuery::TaxCap { .. } => to_binary(&TaxCapResponse {
-                        cap: 1000u128.into(),
-                    })
-                    .unwrap(),
-                    _ => unreachable!(),
-                };
-                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(res))
-            },
-        );
-
+    fn custom_deps() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
         OwnedDeps {
             storage: MockStorage::default(),
             api: MockApi::default(),
-            querier,
+            querier: CustomQuerier::default(),
         }
     }
 
@@ -502,6 +1267,10 @@ mod tests {
             anchor_token_address: "anchor-token".to_string(),
             threshold: 1000u128.into(),
             native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(50),
+            timelock: 86400,
+            predecessor: None,
         };
         let info = mock_info("creator", &[]);
 
@@ -516,6 +1285,8 @@ mod tests {
         };
         let _res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
 
+        deps.querier.anchor_balance = 1000u128.into();
+        deps.querier.exchange_rate = Decimal256::percent(101);
         deps.querier.update_balance(
             env.contract.address.clone(),
             vec![Coin {
@@ -526,6 +1297,7 @@ mod tests {
 
         let user_info = mock_info("user", &[]);
         let msg = ExecuteMsg::RequestFunds {
+            asset: None,
             amount: 69u128.into(),
         };
         let res = execute(deps.as_mut(), env.clone(), user_info, msg);
@@ -534,6 +1306,7 @@ mod tests {
         // if there is enough balance to send to the game
         let game_info = mock_info("game1", &[]);
         let msg = ExecuteMsg::RequestFunds {
+            asset: None,
             amount: 69u128.into(),
         };
         let res = execute(deps.as_mut(), env.clone(), game_info, msg).unwrap();
@@ -553,6 +1326,7 @@ mod tests {
         // if we need to request additional funds from Anchor
         let game_info = mock_info("game1", &[]);
         let msg = ExecuteMsg::RequestFunds {
+            asset: None,
             amount: 200u128.into(),
         };
         let res = execute(deps.as_mut(), env.clone(), game_info, msg).unwrap();
@@ -568,7 +1342,7 @@ mod tests {
                 msg: to_binary(&msg).unwrap(),
                 funds: vec![],
             };
-            let msg = SubMsg::reply_on_success(msg, 1);
+            let msg = SubMsg::reply_on_success(msg, REQUEST_FROM_ANCHOR_REPLY_ID);
             Response::new()
                 .add_attribute("action", "request from anchor")
                 .add_submessage(msg)
@@ -585,6 +1359,10 @@ mod tests {
             anchor_token_address: "anchor-token".to_string(),
             threshold: 1000u128.into(),
             native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
         };
         let info = mock_info("creator", &[]);
 
@@ -655,4 +1433,1143 @@ mod tests {
                 .add_message(msg)
         });
     }
+
+    #[test]
+    fn game_drain_circuit_breaker() {
+        let mut deps = custom_deps();
+
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+
+        let user_info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddGame {
+            addr: "game1".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
+
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: 1000u128.into(),
+            }],
+        );
+
+        // credit limit caps the net amount requested within the window,
+        // only governance sets it
+        let msg = ExecuteMsg::SetCreditLimit {
+            addr: "game1".to_string(),
+            limit: Some(80u128.into()),
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            msg.clone(),
+        );
+        assert_eq!(res, Err(ContractError::Unauthorized {}));
+        let gov_info = mock_info("gov-contract", &[]);
+        let _res = execute(deps.as_mut(), env.clone(), gov_info, msg).unwrap();
+
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 90u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), game_info, msg);
+        assert_eq!(
+            res,
+            Err(ContractError::CreditLimitExceeded {
+                limit: 80u128.into(),
+                net_loss: 90u128.into(),
+            })
+        );
+
+        let gov_info = mock_info("gov-contract", &[]);
+        let msg = ExecuteMsg::SetCreditLimit {
+            addr: "game1".to_string(),
+            limit: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), gov_info, msg).unwrap();
+
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 90u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), game_info, msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        // deposits reduce the net loss of the game
+        let game_info = mock_info(
+            "game1",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: 50u128.into(),
+            }],
+        );
+        let _res = execute(deps.as_mut(), env.clone(), game_info, ExecuteMsg::DepositFunds {})
+            .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GameFlow {
+                addr: "game1".to_string(),
+            },
+        )
+        .unwrap();
+        let value: GameFlowResponse = from_binary(&res).unwrap();
+        assert_eq!(value.net_loss, Uint128::from(40u128));
+
+        // debts recorded by the game count as losses
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RecordDebt {
+            player: "player1".to_string(),
+            amount: 10u128.into(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), game_info, msg).unwrap();
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GameFlow {
+                addr: "game1".to_string(),
+            },
+        )
+        .unwrap();
+        let value: GameFlowResponse = from_binary(&res).unwrap();
+        assert_eq!(value.net_loss, Uint128::from(50u128));
+
+        // losing more than 10% of the reserve within the window suspends the game
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 70u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), game_info, msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "alert" && attr.value == "game drain"));
+
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 1u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), game_info, msg);
+        assert_eq!(res, Err(ContractError::GameSuspended {}));
+
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RecordDebt {
+            player: "player1".to_string(),
+            amount: 1u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), game_info, msg);
+        assert_eq!(res, Err(ContractError::GameSuspended {}));
+
+        // a new window does not lift the suspension
+        env.block.time = env.block.time.plus_seconds(86400);
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 1u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), game_info, msg);
+        assert_eq!(res, Err(ContractError::GameSuspended {}));
+
+        // adding the game again does not lift the suspension either
+        for msg in [
+            ExecuteMsg::RemoveGame {
+                addr: "game1".to_string(),
+            },
+            ExecuteMsg::AddGame {
+                addr: "game1".to_string(),
+            },
+        ] {
+            let user_info = mock_info("creator", &[]);
+            let _res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
+        }
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 1u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), game_info, msg);
+        assert_eq!(res, Err(ContractError::GameSuspended {}));
+
+        // only governance can resume the game
+        let user_info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ResumeGame {
+            addr: "game1".to_string(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), user_info, msg);
+        assert_eq!(res, Err(ContractError::Unauthorized {}));
+
+        let gov_info = mock_info("gov-contract", &[]);
+        let msg = ExecuteMsg::ResumeGame {
+            addr: "game1".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), gov_info, msg).unwrap();
+
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 70u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), game_info, msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
+
+    #[test]
+    fn rolling_flow_window() {
+        let mut deps = custom_deps();
+
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        let start = env.block.time;
+
+        let user_info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddGame {
+            addr: "game1".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
+
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: 1000u128.into(),
+            }],
+        );
+
+        let request = |amount: u128| ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: amount.into(),
+        };
+        let game_info = mock_info("game1", &[]);
+        let _res = execute(deps.as_mut(), env.clone(), game_info.clone(), request(1)).unwrap();
+
+        // just under the limit at the end of the first day
+        env.block.time = start.plus_seconds(86000);
+        let res = execute(deps.as_mut(), env.clone(), game_info.clone(), request(90)).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        // the losses of the last day are still counted right after it
+        env.block.time = start.plus_seconds(86401);
+        let res = execute(deps.as_mut(), env.clone(), game_info.clone(), request(90)).unwrap();
+        assert!(res.messages.is_empty());
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "alert" && attr.value == "game drain"));
+
+        // flows older than the window are no longer counted
+        env.block.time = start.plus_seconds(86401 + 86400 + 3600);
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GameFlow {
+                addr: "game1".to_string(),
+            },
+        )
+        .unwrap();
+        let value: GameFlowResponse = from_binary(&res).unwrap();
+        assert_eq!(value.net_loss, Uint128::zero());
+        assert!(value.suspended);
+    }
+
+    #[test]
+    fn ledger() {
+        let mut deps = custom_deps();
+
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(50),
+            timelock: 86400,
+            predecessor: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+
+        for addr in ["game1", "distribution"] {
+            let user_info = mock_info("creator", &[]);
+            let msg = ExecuteMsg::AddGame {
+                addr: addr.to_string(),
+            };
+            let _res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
+        }
+        let user_info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::SetDistributionContract {
+            addr: "distribution".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
+
+        deps.querier.anchor_balance = 1000u128.into();
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: 1000u128.into(),
+            }],
+        );
+
+        let game_info = mock_info(
+            "game1",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: 300u128.into(),
+            }],
+        );
+        let _res = execute(deps.as_mut(), env.clone(), game_info, ExecuteMsg::DepositFunds {})
+            .unwrap();
+
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 100u128.into(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), game_info, msg).unwrap();
+
+        let distribution_info = mock_info("distribution", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 50u128.into(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), distribution_info, msg).unwrap();
+
+        // requests of the distribution contract are not tracked as game losses
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GameFlow {
+                addr: "distribution".to_string(),
+            },
+        )
+        .unwrap();
+        let value: GameFlowResponse = from_binary(&res).unwrap();
+        assert_eq!(value.requested, Uint128::zero());
+
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("lp", &[Coin {
+                denom: "uusd".to_string(),
+                amount: 200u128.into(),
+            }]),
+            ExecuteMsg::LpDeposit {},
+        )
+        .unwrap();
+
+        // aUST appreciation is accounted as yield
+        env.block.time = env.block.time.plus_seconds(86400);
+        deps.querier.exchange_rate = Decimal256::percent(110);
+        let game_info = mock_info("game1", &[]);
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 10u128.into(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), game_info, msg).unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Ledger {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: LedgerResponse = from_binary(&res).unwrap();
+        let sources = value
+            .entries
+            .iter()
+            .map(|entry| (entry.source.clone(), entry.amount.u128()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![
+                (FlowSource::GameSweep, 300),
+                (FlowSource::GameRequest, 100),
+                (FlowSource::DistributionRequest, 50),
+                (FlowSource::LpDeposit, 200),
+                (FlowSource::AnchorYield, 100),
+                (FlowSource::GameRequest, 10),
+            ]
+        );
+        assert!(!value.entries[1].inflow);
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Ledger {
+                start_after: Some(4),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+        let value: LedgerResponse = from_binary(&res).unwrap();
+        assert_eq!(value.entries.len(), 1);
+        assert_eq!(value.entries[0].id, 5);
+        assert_eq!(value.entries[0].source, FlowSource::AnchorYield);
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::DailyAggregates {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: DailyAggregatesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.days.len(), 2);
+        let first_day = &value.days[0];
+        assert_eq!(first_day.game_sweeps, Uint128::from(300u128));
+        assert_eq!(first_day.game_requests, Uint128::from(100u128));
+        assert_eq!(first_day.distribution_requests, Uint128::from(50u128));
+        assert_eq!(first_day.lp_deposits, Uint128::from(200u128));
+        assert_eq!(first_day.anchor_yield, Uint128::zero());
+        let second_day = &value.days[1];
+        assert_eq!(second_day.day, first_day.day + 86400);
+        assert_eq!(second_day.anchor_yield, Uint128::from(100u128));
+        assert_eq!(second_day.game_requests, Uint128::from(10u128));
+
+        let res = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::DailyAggregates {
+                start_after: Some(first_day.day),
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: DailyAggregatesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.days, vec![second_day.clone()]);
+    }
+
+    #[test]
+    fn debt_queue() {
+        let mut deps = custom_deps();
+
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(50),
+            timelock: 86400,
+            predecessor: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let env = mock_env();
+
+        let user_info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddGame {
+            addr: "game1".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
+
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: 1000u128.into(),
+            }],
+        );
+
+        // only games can record debts
+        let msg = ExecuteMsg::RecordDebt {
+            player: "player1".to_string(),
+            amount: 100u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("player1", &[]), msg);
+        assert_eq!(res, Err(ContractError::Unauthorized {}));
+
+        for (player, amount) in [("player1", 100u128), ("player2", 50), ("player1", 30)] {
+            let msg = ExecuteMsg::RecordDebt {
+                player: player.to_string(),
+                amount: amount.into(),
+            };
+            let _res = execute(deps.as_mut(), env.clone(), mock_info("game1", &[]), msg).unwrap();
+        }
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TotalDebt {}).unwrap();
+        let value: TotalDebtResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            TotalDebtResponse {
+                total: 180u128.into(),
+                count: 3,
+            }
+        );
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PlayerDebt {
+                addr: "player1".to_string(),
+            },
+        )
+        .unwrap();
+        let value: PlayerDebtResponse = from_binary(&res).unwrap();
+        assert_eq!(value.debt, Uint128::from(130u128));
+
+        // the sweep repays the oldest debt first and partially pays the next one
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: 1120u128.into(),
+            }],
+        );
+        let game_info = mock_info(
+            "game1",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: 120u128.into(),
+            }],
+        );
+        let res = execute(deps.as_mut(), env.clone(), game_info, ExecuteMsg::DepositFunds {})
+            .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "player1".to_string(),
+                    amount: vec![Coin {
+                        denom: "uusd".to_string(),
+                        amount: 100u128.into(),
+                    }],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "player2".to_string(),
+                    amount: vec![Coin {
+                        denom: "uusd".to_string(),
+                        amount: 20u128.into(),
+                    }],
+                }),
+            ]
+        );
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Debts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: DebtsResponse = from_binary(&res).unwrap();
+        let debts = value
+            .debts
+            .iter()
+            .map(|debt| (debt.id, debt.player.as_str(), debt.amount.u128()))
+            .collect::<Vec<_>>();
+        assert_eq!(debts, vec![(1, "player2", 30), (2, "player1", 30)]);
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PlayerDebt {
+                addr: "player1".to_string(),
+            },
+        )
+        .unwrap();
+        let value: PlayerDebtResponse = from_binary(&res).unwrap();
+        assert_eq!(value.debt, Uint128::from(30u128));
+
+        let res = query(deps.as_ref(), env, QueryMsg::TotalDebt {}).unwrap();
+        let value: TotalDebtResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            TotalDebtResponse {
+                total: 60u128.into(),
+                count: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn reserve_migration() {
+        let mut deps = custom_deps();
+
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+
+        for addr in ["game1", "distribution"] {
+            let msg = ExecuteMsg::AddGame {
+                addr: addr.to_string(),
+            };
+            let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg)
+                .unwrap();
+        }
+        let msg = ExecuteMsg::SetDistributionContract {
+            addr: "distribution".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        // only governance can move the funds, the owner can not
+        let msg = ExecuteMsg::ProposeEmergencyWithdraw {
+            recipient: "multisig".to_string(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        assert_eq!(res, Err(ContractError::Unauthorized {}));
+
+        let msg = ExecuteMsg::ProposeEmergencyWithdraw {
+            recipient: "multisig".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("gov-contract", &[]), msg)
+            .unwrap();
+        let msg = ExecuteMsg::MigrateReserve {
+            new_reserve: "new-reserve".to_string(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("gov-contract", &[]), msg);
+        assert_eq!(res, Err(ContractError::WithdrawalPending {}));
+
+        let msg = ExecuteMsg::CancelWithdraw {};
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("gov-contract", &[]), msg)
+            .unwrap();
+
+        let msg = ExecuteMsg::MigrateReserve {
+            new_reserve: "new-reserve".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("gov-contract", &[]), msg)
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::ExecuteWithdraw {},
+        );
+        assert_eq!(
+            res,
+            Err(ContractError::WithdrawalLocked {
+                executable_at: env.block.time.seconds() + 86400,
+            })
+        );
+
+        // after the timelock anyone can execute, all aUST is redeemed first
+        env.block.time = env.block.time.plus_seconds(86400);
+        deps.querier.anchor_balance = 500u128.into();
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::ExecuteWithdraw {},
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, WITHDRAW_REPLY_ID);
+
+        deps.querier.anchor_balance = Uint128::zero();
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: 1500u128.into(),
+            }],
+        );
+        let res = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: WITHDRAW_REPLY_ID,
+                result: cosmwasm_std::ContractResult::Ok(cosmwasm_std::SubMsgExecutionResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            },
+        )
+        .unwrap();
+
+        let migrate = to_binary(&RegisteredContractMsg::MigrateReserve {
+            new_reserve: "new-reserve".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "new-reserve".to_string(),
+                    amount: vec![Coin {
+                        denom: "uusd".to_string(),
+                        amount: 1500u128.into(),
+                    }],
+                }),
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: "new-reserve".to_string(),
+                    msg: to_binary(&ExecuteMsg::ImportRegistrations {
+                        games: vec![
+                            GameRegistration {
+                                addr: "distribution".to_string(),
+                                flow: GameFlow::default(),
+                            },
+                            GameRegistration {
+                                addr: "game1".to_string(),
+                                flow: GameFlow::default(),
+                            },
+                        ],
+                        distribution_contract: Some("distribution".to_string()),
+                        debts: vec![],
+                        assets: all_assets(&deps.storage).unwrap(),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }),
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: "distribution".to_string(),
+                    msg: migrate.clone(),
+                    funds: vec![],
+                }),
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: "game1".to_string(),
+                    msg: migrate,
+                    funds: vec![],
+                }),
+            ]
+        );
+
+        let res = query(deps.as_ref(), env, QueryMsg::PendingWithdrawal {}).unwrap();
+        let value: PendingWithdrawalResponse = from_binary(&res).unwrap();
+        assert_eq!(value.pending, None);
+
+        // the new reserve only accepts registrations from its predecessor
+        let mut new_deps = custom_deps();
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: Some("old-reserve".to_string()),
+        };
+        let _res = instantiate(new_deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+            .unwrap();
+        let msg = ExecuteMsg::ImportRegistrations {
+            games: vec![GameRegistration {
+                addr: "game1".to_string(),
+                flow: GameFlow::default(),
+            }],
+            distribution_contract: None,
+            debts: vec![],
+            assets: vec![],
+        };
+        let res = execute(new_deps.as_mut(), mock_env(), mock_info("creator", &[]), msg.clone());
+        assert_eq!(res, Err(ContractError::Unauthorized {}));
+        let _res = execute(new_deps.as_mut(), mock_env(), mock_info("old-reserve", &[]), msg)
+            .unwrap();
+        let res = query(new_deps.as_ref(), mock_env(), QueryMsg::ListGames {}).unwrap();
+        let value: Games = from_binary(&res).unwrap();
+        assert_eq!(value.games, vec!["game1".to_string()]);
+    }
+
+    #[test]
+    fn migration_carries_debts_and_suspended_games() {
+        let mut deps = custom_deps();
+
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: 1000u128.into(),
+            }],
+        );
+        deps.querier
+            .token_balances
+            .insert("vega-token".to_string(), Uint128::zero());
+
+        for addr in ["game1", "game2"] {
+            let msg = ExecuteMsg::AddGame {
+                addr: addr.to_string(),
+            };
+            let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        }
+        let msg = ExecuteMsg::WhitelistAsset {
+            asset: AssetInfo::Token {
+                contract_addr: Addr::unchecked("vega-token"),
+            },
+            threshold: Uint128::zero(),
+            strategy: YieldStrategy::Hold,
+            price_source: PriceSource::Fixed {
+                price: Decimal::percent(50),
+            },
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        // game1 drains the reserve and gets suspended, game2 owes a player
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 150u128.into(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("game1", &[]), msg).unwrap();
+        let msg = ExecuteMsg::RecordDebt {
+            player: "player1".to_string(),
+            amount: 100u128.into(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("game2", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::MigrateReserve {
+            new_reserve: "new-reserve".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("gov-contract", &[]), msg)
+            .unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::ExecuteWithdraw {},
+        )
+        .unwrap();
+
+        let import = res
+            .messages
+            .iter()
+            .find_map(|msg| match &msg.msg {
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                }) if contract_addr == "new-reserve" => Some(msg.clone()),
+                _ => None,
+            })
+            .unwrap();
+
+        // the debts moved with the funds
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TotalDebt {}).unwrap();
+        let value: TotalDebtResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            TotalDebtResponse {
+                total: Uint128::zero(),
+                count: 0,
+            }
+        );
+
+        let mut new_deps = custom_deps();
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: Some(env.contract.address.to_string()),
+        };
+        let _res = instantiate(new_deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+            .unwrap();
+        let old_reserve_info = mock_info(env.contract.address.as_str(), &[]);
+        let _res = execute(
+            new_deps.as_mut(),
+            env.clone(),
+            old_reserve_info,
+            from_binary(&import).unwrap(),
+        )
+        .unwrap();
+
+        let res = query(
+            new_deps.as_ref(),
+            env.clone(),
+            QueryMsg::PlayerDebt {
+                addr: "player1".to_string(),
+            },
+        )
+        .unwrap();
+        let value: PlayerDebtResponse = from_binary(&res).unwrap();
+        assert_eq!(value.debt, Uint128::from(100u128));
+        let res = query(
+            new_deps.as_ref(),
+            env.clone(),
+            QueryMsg::Debts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: DebtsResponse = from_binary(&res).unwrap();
+        assert_eq!(value.debts[0].game, "game2".to_string());
+
+        // the suspension is kept, only governance can lift it
+        let msg = ExecuteMsg::RequestFunds {
+            asset: None,
+            amount: 1u128.into(),
+        };
+        let res = execute(new_deps.as_mut(), env.clone(), mock_info("game1", &[]), msg);
+        assert_eq!(res, Err(ContractError::GameSuspended {}));
+
+        let res = query(new_deps.as_ref(), env, QueryMsg::Assets {}).unwrap();
+        let value: AssetsResponse = from_binary(&res).unwrap();
+        assert!(value
+            .assets
+            .iter()
+            .any(|asset| asset.info.key() == "vega-token"));
+    }
+
+    #[test]
+    fn multi_asset() {
+        let mut deps = custom_deps();
+
+        let msg = InstantiateMsg {
+            gov_contract_address: "gov-contract".to_string(),
+            anchor_market_address: "anchor-market".to_string(),
+            anchor_token_address: "anchor-token".to_string(),
+            threshold: 1000u128.into(),
+            native_denom: "uusd".to_string(),
+            flow_window: 86400,
+            max_loss_ratio: Decimal::percent(10),
+            timelock: 86400,
+            predecessor: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let env = mock_env();
+
+        let msg = ExecuteMsg::AddGame {
+            addr: "game1".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        let ukrw = AssetInfo::NativeToken {
+            denom: "ukrw".to_string(),
+        };
+        let vega = AssetInfo::Token {
+            contract_addr: Addr::unchecked("vega-token"),
+        };
+
+        let msg = ExecuteMsg::WhitelistAsset {
+            asset: ukrw.clone(),
+            threshold: Uint128::zero(),
+            strategy: YieldStrategy::Hold,
+            price_source: PriceSource::Oracle,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg.clone());
+        assert_eq!(res, Err(ContractError::Unauthorized {}));
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        // there is no oracle price for tokens
+        let msg = ExecuteMsg::WhitelistAsset {
+            asset: vega.clone(),
+            threshold: Uint128::zero(),
+            strategy: YieldStrategy::Hold,
+            price_source: PriceSource::Oracle,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        assert_eq!(res, Err(ContractError::InvalidAsset {}));
+        let msg = ExecuteMsg::WhitelistAsset {
+            asset: vega.clone(),
+            threshold: Uint128::zero(),
+            strategy: YieldStrategy::Hold,
+            price_source: PriceSource::Fixed {
+                price: Decimal::percent(50),
+            },
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Assets {}).unwrap();
+        let value: AssetsResponse = from_binary(&res).unwrap();
+        assert_eq!(value.assets.len(), 3);
+
+        // only whitelisted denoms are accepted
+        let game_info = mock_info("game1", &[Coin::new(100, "uluna")]);
+        let res = execute(deps.as_mut(), env.clone(), game_info, ExecuteMsg::DepositFunds {});
+        assert_eq!(res, Err(ContractError::AssetNotWhitelisted {}));
+
+        let game_info = mock_info("game1", &[Coin::new(10000, "ukrw")]);
+        let res = execute(deps.as_mut(), env.clone(), game_info, ExecuteMsg::DepositFunds {})
+            .unwrap();
+        assert!(res.messages.is_empty());
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "user".to_string(),
+            amount: 200u128.into(),
+            msg: to_binary(&Cw20HookMsg::DepositFunds {}).unwrap(),
+        });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("vega-token", &[]), msg);
+        assert_eq!(res, Err(ContractError::Unauthorized {}));
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "game1".to_string(),
+            amount: 200u128.into(),
+            msg: to_binary(&Cw20HookMsg::DepositFunds {}).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("vega-token", &[]), msg)
+            .unwrap();
+
+        // deposits are valued in the native denom
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GameFlow {
+                addr: "game1".to_string(),
+            },
+        )
+        .unwrap();
+        let value: GameFlowResponse = from_binary(&res).unwrap();
+        assert_eq!(value.deposited, Uint128::from(110u128));
+
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin::new(1000, "uusd"), Coin::new(10000, "ukrw")],
+        );
+        deps.querier
+            .token_balances
+            .insert("vega-token".to_string(), 200u128.into());
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::CurrentBalance {}).unwrap();
+        let value: CurrentBalance = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            CurrentBalance {
+                balance: 1110u128.into(),
+                assets: vec![
+                    AssetBalance {
+                        info: ukrw.clone(),
+                        amount: 10000u128.into(),
+                        value: 10u128.into(),
+                    },
+                    AssetBalance {
+                        info: AssetInfo::NativeToken {
+                            denom: "uusd".to_string(),
+                        },
+                        amount: 1000u128.into(),
+                        value: 1000u128.into(),
+                    },
+                    AssetBalance {
+                        info: vega.clone(),
+                        amount: 200u128.into(),
+                        value: 100u128.into(),
+                    },
+                ],
+            }
+        );
+
+        let msg = ExecuteMsg::RequestFunds {
+            asset: Some(ukrw),
+            amount: 5000u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("game1", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "game1".to_string(),
+                amount: vec![Coin::new(5000, "ukrw")],
+            })]
+        );
+
+        let msg = ExecuteMsg::RequestFunds {
+            asset: Some(vega.clone()),
+            amount: 100u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("game1", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "vega-token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "game1".to_string(),
+                    amount: 100u128.into(),
+                })
+                .unwrap(),
+                funds: vec![],
+            })]
+        );
+
+        let msg = ExecuteMsg::RequestFunds {
+            asset: Some(vega.clone()),
+            amount: 300u128.into(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("game1", &[]), msg);
+        assert_eq!(res, Err(ContractError::InsufficientFunds {}));
+
+        let msg = ExecuteMsg::RemoveAsset {
+            asset: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        assert_eq!(res, Err(ContractError::NativeAssetRemoval {}));
+
+        // assets still held would be left behind by withdrawals
+        let msg = ExecuteMsg::RemoveAsset {
+            asset: vega.clone(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg.clone());
+        assert_eq!(res, Err(ContractError::AssetNotEmpty {}));
+
+        deps.querier
+            .token_balances
+            .insert("vega-token".to_string(), Uint128::zero());
+        let _res = execute(deps.as_mut(), env, mock_info("creator", &[]), msg).unwrap();
+    }
 }
@@ -0,0 +1,175 @@
+use cosmwasm_std::{Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Order, StdResult, Uint128};
+use cw_storage_plus::Bound;
+use std::convert::TryInto;
+
+use crate::ledger::record_flow;
+use crate::msg::{
+    DebtRegistration, DebtResponse, DebtsResponse, PlayerDebtResponse, TotalDebtResponse,
+};
+use crate::state::{Config, Debt, DebtQueue, FlowSource, DEBTS, DEBT_QUEUE, PLAYER_DEBTS};
+use crate::utils::deduct_tax;
+
+// bounds the gas a single sweep spends on repayments
+const MAX_REPAYMENTS_PER_SWEEP: usize = 10;
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+pub fn record_debt(
+    deps: DepsMut,
+    player: Addr,
+    game: Addr,
+    amount: Uint128,
+    timestamp: u64,
+) -> StdResult<()> {
+    let mut queue = DEBT_QUEUE.may_load(deps.storage)?.unwrap_or_default();
+    DEBTS.save(
+        deps.storage,
+        queue.tail.into(),
+        &Debt {
+            player: player.clone(),
+            game,
+            amount,
+            created_at: timestamp,
+        },
+    )?;
+    queue.tail += 1;
+    queue.total += amount;
+    DEBT_QUEUE.save(deps.storage, &queue)?;
+
+    PLAYER_DEBTS.update(deps.storage, player, |debt| -> StdResult<_> {
+        Ok(debt.unwrap_or_default() + amount)
+    })?;
+    Ok(())
+}
+
+/// Removes all outstanding debts and returns them in repayment order,
+/// the debts are handed over to the reserve taking over the funds
+pub fn take_debts(deps: DepsMut) -> StdResult<Vec<DebtRegistration>> {
+    let queue = DEBT_QUEUE.may_load(deps.storage)?.unwrap_or_default();
+    let mut debts = vec![];
+    for id in queue.head..queue.tail {
+        let debt = DEBTS.load(deps.storage, id.into())?;
+        DEBTS.remove(deps.storage, id.into());
+        PLAYER_DEBTS.remove(deps.storage, debt.player.clone());
+        debts.push(DebtRegistration {
+            player: debt.player.to_string(),
+            game: debt.game.to_string(),
+            amount: debt.amount,
+            created_at: debt.created_at,
+        });
+    }
+    DEBT_QUEUE.save(
+        deps.storage,
+        &DebtQueue {
+            head: queue.tail,
+            tail: queue.tail,
+            total: Uint128::zero(),
+        },
+    )?;
+    Ok(debts)
+}
+
+/// Repays the oldest debts with up to `budget` of the native denom,
+/// returns the transfers and the amount spent
+pub fn repay_debts(
+    deps: DepsMut,
+    config: &Config,
+    timestamp: u64,
+    budget: Uint128,
+) -> StdResult<(Vec<CosmosMsg>, Uint128)> {
+    let mut queue = DEBT_QUEUE.may_load(deps.storage)?.unwrap_or_default();
+    let mut remaining = budget;
+    let mut msgs = vec![];
+
+    while queue.head < queue.tail && !remaining.is_zero() && msgs.len() < MAX_REPAYMENTS_PER_SWEEP
+    {
+        let mut debt = DEBTS.load(deps.storage, queue.head.into())?;
+        let payment = std::cmp::min(debt.amount, remaining);
+
+        // players pay the transfer tax the same way they do on a game payout
+        let coin = deduct_tax(
+            deps.as_ref(),
+            Coin {
+                denom: config.native_denom.clone(),
+                amount: payment,
+            },
+        )?;
+        if !coin.amount.is_zero() {
+            msgs.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: debt.player.to_string(),
+                amount: vec![coin],
+            }));
+        }
+        remaining -= payment;
+        queue.total -= payment;
+
+        PLAYER_DEBTS.update(deps.storage, debt.player.clone(), |owed| -> StdResult<_> {
+            Ok(owed.unwrap_or_default().saturating_sub(payment))
+        })?;
+        if PLAYER_DEBTS.load(deps.storage, debt.player.clone())?.is_zero() {
+            PLAYER_DEBTS.remove(deps.storage, debt.player.clone());
+        }
+        record_flow(
+            deps.storage,
+            timestamp,
+            FlowSource::DebtRepayment,
+            Some(debt.player.clone()),
+            payment,
+        )?;
+
+        debt.amount -= payment;
+        if debt.amount.is_zero() {
+            DEBTS.remove(deps.storage, queue.head.into());
+            queue.head += 1;
+        } else {
+            DEBTS.save(deps.storage, queue.head.into(), &debt)?;
+        }
+    }
+    DEBT_QUEUE.save(deps.storage, &queue)?;
+
+    Ok((msgs, budget - remaining))
+}
+
+pub fn query_player_debt(deps: Deps, addr: String) -> StdResult<PlayerDebtResponse> {
+    let player = deps.api.addr_validate(&addr)?;
+    let debt = PLAYER_DEBTS
+        .may_load(deps.storage, player)?
+        .unwrap_or_default();
+    Ok(PlayerDebtResponse { debt })
+}
+
+pub fn query_total_debt(deps: Deps) -> StdResult<TotalDebtResponse> {
+    let queue = DEBT_QUEUE.may_load(deps.storage)?.unwrap_or_default();
+    Ok(TotalDebtResponse {
+        total: queue.total,
+        count: queue.tail - queue.head,
+    })
+}
+
+pub fn query_debts(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<DebtsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let debts = DEBTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, debt) = item?;
+            Ok(DebtResponse {
+                id: u64::from_be_bytes(k.as_slice().try_into().unwrap()),
+                player: debt.player.to_string(),
+                game: debt.game.to_string(),
+                amount: debt.amount,
+                created_at: debt.created_at,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(DebtsResponse { debts })
+}
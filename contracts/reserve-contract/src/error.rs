@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use cw0::PaymentError;
 use thiserror::Error;
 
@@ -12,4 +12,26 @@ pub enum ContractError {
     Unauthorized {},
     #[error("InvalidReplyId")]
     InvalidReplyId {},
+    #[error("Game is not registered")]
+    GameNotFound {},
+    #[error("Game funding is suspended")]
+    GameSuspended {},
+    #[error("Game credit limit exceeded")]
+    CreditLimitExceeded { limit: Uint128, net_loss: Uint128 },
+    #[error("A withdrawal is already pending")]
+    WithdrawalPending {},
+    #[error("No withdrawal is pending")]
+    NoPendingWithdrawal {},
+    #[error("Asset is not whitelisted")]
+    AssetNotWhitelisted {},
+    #[error("Invalid asset configuration")]
+    InvalidAsset {},
+    #[error("Native denom can not be removed")]
+    NativeAssetRemoval {},
+    #[error("Asset can not be removed while the reserve holds it")]
+    AssetNotEmpty {},
+    #[error("Insufficient funds")]
+    InsufficientFunds {},
+    #[error("Withdrawal is time locked")]
+    WithdrawalLocked { executable_at: u64 },
 }
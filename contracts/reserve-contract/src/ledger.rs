@@ -0,0 +1,138 @@
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::Bound;
+use std::convert::TryInto;
+
+use crate::assets::{native_value, strategy_balance};
+use crate::msg::{DailyAggregatesResponse, LedgerEntryResponse, LedgerResponse};
+use crate::state::{
+    AssetConfig, AssetInfo, DailyAggregate, FlowSource, LedgerEntry, YieldStrategy,
+    ANCHOR_PRINCIPAL, CONFIG, DAILY_AGGREGATES, LEDGER, LEDGER_COUNT,
+};
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+pub fn record_flow(
+    storage: &mut dyn Storage,
+    timestamp: u64,
+    source: FlowSource,
+    address: Option<Addr>,
+    amount: Uint128,
+) -> StdResult<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+
+    let day = timestamp / SECONDS_PER_DAY * SECONDS_PER_DAY;
+    DAILY_AGGREGATES.update(storage, day.into(), |aggregate| -> StdResult<_> {
+        let mut aggregate = aggregate.unwrap_or_else(|| DailyAggregate::new(day));
+        aggregate.add(&source, amount);
+        Ok(aggregate)
+    })?;
+
+    let id = LEDGER_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    LEDGER.save(
+        storage,
+        id.into(),
+        &LedgerEntry {
+            timestamp,
+            source,
+            address,
+            amount,
+        },
+    )?;
+    LEDGER_COUNT.save(storage, &id)
+}
+
+/// Records growth of the aTerra position of the asset since the last update as yield
+pub fn accrue_anchor_yield(deps: DepsMut, env: &Env, asset: &AssetConfig) -> StdResult<()> {
+    if asset.strategy == YieldStrategy::Hold {
+        return Ok(());
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let key = asset.info.key();
+    let position = strategy_balance(deps.as_ref(), asset, &env.contract.address)?;
+
+    // the first accrual only takes the checkpoint, the position held
+    // before the ledger existed is not yield
+    if let Some(principal) = ANCHOR_PRINCIPAL.may_load(deps.storage, &key)? {
+        if position > principal {
+            let value = native_value(deps.as_ref(), &config, asset, position - principal)?;
+            record_flow(
+                deps.storage,
+                env.block.time.seconds(),
+                FlowSource::AnchorYield,
+                None,
+                value,
+            )?;
+        }
+    }
+    ANCHOR_PRINCIPAL.save(deps.storage, &key, &position)
+}
+
+pub fn add_anchor_principal(
+    storage: &mut dyn Storage,
+    asset: &AssetInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    let key = asset.key();
+    let principal = ANCHOR_PRINCIPAL.may_load(storage, &key)?.unwrap_or_default();
+    ANCHOR_PRINCIPAL.save(storage, &key, &(principal + amount))
+}
+
+pub fn reduce_anchor_principal(
+    storage: &mut dyn Storage,
+    asset: &AssetInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    let key = asset.key();
+    let principal = ANCHOR_PRINCIPAL.may_load(storage, &key)?.unwrap_or_default();
+    ANCHOR_PRINCIPAL.save(storage, &key, &principal.saturating_sub(amount))
+}
+
+pub fn query_ledger(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<LedgerResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let entries = LEDGER
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, entry) = item?;
+            Ok(LedgerEntryResponse {
+                id: u64::from_be_bytes(k.as_slice().try_into().unwrap()),
+                timestamp: entry.timestamp,
+                inflow: entry.source.is_inflow(),
+                source: entry.source,
+                address: entry.address.map(|addr| addr.to_string()),
+                amount: entry.amount,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(LedgerResponse { entries })
+}
+
+pub fn query_daily_aggregates(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<DailyAggregatesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let days = DAILY_AGGREGATES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, aggregate)| aggregate))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(DailyAggregatesResponse { days })
+}
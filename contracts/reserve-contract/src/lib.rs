@@ -1,5 +1,8 @@
+mod assets;
 pub mod contract;
+mod debt;
 mod error;
+mod ledger;
 pub mod msg;
 pub mod state;
 mod utils;
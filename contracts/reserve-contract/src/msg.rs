@@ -1,7 +1,13 @@
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::state::{
+    AssetConfig, AssetInfo, DailyAggregate, FlowSource, GameFlow, PendingWithdrawal, PriceSource,
+    YieldStrategy,
+};
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub anchor_market_address: String,
@@ -9,16 +15,101 @@ pub struct InstantiateMsg {
     pub anchor_token_address: String,
     pub threshold: Uint128,
     pub native_denom: String,
+    pub flow_window: u64,
+    pub max_loss_ratio: Decimal,
+    pub timelock: u64,
+    pub predecessor: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    /// Changes the threshold of the native denom
     ChangeThreshold { threshold: Uint128 },
     AddGame { addr: String },
     RemoveGame { addr: String },
-    RequestFunds { amount: Uint128 },
+    /// Sends the amount of the asset to the calling game, the native denom when no asset is given
+    RequestFunds {
+        asset: Option<AssetInfo>,
+        amount: Uint128,
+    },
+    /// Takes any whitelisted native denoms, whitelisted CW20s are deposited through `Receive`
     DepositFunds {},
+    Receive(Cw20ReceiveMsg),
+    WhitelistAsset {
+        asset: AssetInfo,
+        threshold: Uint128,
+        strategy: YieldStrategy,
+        price_source: PriceSource,
+    },
+    RemoveAsset { asset: AssetInfo },
+    /// Caps the net amount a game can request within one flow window,
+    /// `None` removes the limit, only callable by the governance contract
+    SetCreditLimit { addr: String, limit: Option<Uint128> },
+    /// Only callable by the governance contract
+    UpdateCircuitBreaker {
+        flow_window: Option<u64>,
+        max_loss_ratio: Option<Decimal>,
+    },
+    /// Lifts the suspension of a game, only callable by the governance contract
+    ResumeGame { addr: String },
+    /// Requests from the distribution contract are accounted separately from game requests
+    SetDistributionContract { addr: String },
+    /// Liquidity provided to the reserve outside of the games
+    LpDeposit {},
+    /// Records winnings the calling game could not pay out, the player is repaid
+    /// from later `DepositFunds` sweeps in the order debts were recorded
+    RecordDebt { player: String, amount: Uint128 },
+    /// Schedules a transfer of all funds to the recipient once the timelock passes,
+    /// only callable by the governance contract
+    ProposeEmergencyWithdraw { recipient: String },
+    /// Schedules a transfer of all funds to the new reserve once the timelock passes,
+    /// games and the distribution contract are moved to it in the same transaction
+    MigrateReserve { new_reserve: String },
+    CancelWithdraw {},
+    /// Redeems all aUST and executes the pending withdrawal
+    ExecuteWithdraw {},
+    /// Registers the games, outstanding debts and assets of the reserve this one replaces,
+    /// only callable by the predecessor
+    ImportRegistrations {
+        games: Vec<GameRegistration>,
+        distribution_contract: Option<String>,
+        /// Outstanding debts in repayment order
+        debts: Vec<DebtRegistration>,
+        /// Assets not whitelisted yet are added with the predecessor's configuration
+        assets: Vec<AssetConfig>,
+    },
+    UpdateTimelock { timelock: u64 },
+}
+
+/// Game carried over to the new reserve with its flows, credit limit and suspension
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GameRegistration {
+    pub addr: String,
+    pub flow: GameFlow,
+}
+
+/// Debt carried over to the new reserve
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DebtRegistration {
+    pub player: String,
+    pub game: String,
+    pub amount: Uint128,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    DepositFunds {},
+    LpDeposit {},
+}
+
+/// Messages the reserve sends to the games and the distribution contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisteredContractMsg {
+    MigrateReserve { new_reserve: String },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -26,13 +117,49 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     CurrentBalance {},
     GetThreshold {},
+    Assets {},
     ListGames {},
-    BalanceHistory {},
+    /// Inflows and outflows in the order they happened
+    Ledger {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Ledger totals per day, `start_after` is the timestamp of a day start
+    DailyAggregates {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    PlayerDebt { addr: String },
+    TotalDebt {},
+    /// Outstanding debts in repayment order
+    Debts {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GameFlow { addr: String },
+    CircuitBreaker {},
+    PendingWithdrawal {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CurrentBalance {
+    /// Value of all assets in the native denom
     pub balance: Uint128,
+    #[serde(default)]
+    pub assets: Vec<AssetBalance>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetBalance {
+    pub info: AssetInfo,
+    /// Liquid balance plus the amount deposited into the yield strategy
+    pub amount: Uint128,
+    pub value: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetsResponse {
+    pub assets: Vec<AssetConfig>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -44,7 +171,71 @@ pub struct Threshold {
 pub struct Games {
     pub games: Vec<String>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LedgerEntryResponse {
+    pub id: u64,
+    pub timestamp: u64,
+    pub source: FlowSource,
+    pub inflow: bool,
+    pub address: Option<String>,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LedgerResponse {
+    pub entries: Vec<LedgerEntryResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DailyAggregatesResponse {
+    pub days: Vec<DailyAggregate>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GameFlowResponse {
+    /// Start of the rolling window the flows are summed over
+    pub window_start: u64,
+    pub requested: Uint128,
+    pub deposited: Uint128,
+    pub net_loss: Uint128,
+    pub credit_limit: Option<Uint128>,
+    pub suspended: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CircuitBreaker {
+    pub flow_window: u64,
+    pub max_loss_ratio: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PlayerDebtResponse {
+    pub debt: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalDebtResponse {
+    pub total: Uint128,
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DebtResponse {
+    pub id: u64,
+    pub player: String,
+    pub game: String,
+    pub amount: Uint128,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DebtsResponse {
+    pub debts: Vec<DebtResponse>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct BALANCEHISTORY {
-    pub balance_history: Vec<(u64, Uint128)>,
+pub struct PendingWithdrawalResponse {
+    pub pending: Option<PendingWithdrawal>,
+    pub timelock: u64,
 }
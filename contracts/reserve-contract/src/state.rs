@@ -1,21 +1,250 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
 use cw_storage_plus::{Item, Map, U64Key};
 
 pub const OWNER: Item<Addr> = Item::new("owner");
-pub const BALANCE_HISTORY: Map<U64Key,Uint128> = Map::new("balance_history");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    pub anchor_market_address: Addr,
     pub gov_contract_address: Addr,
-    pub anchor_token_address: Addr,
-    pub threshold: Uint128,
+    /// Denom the reserve is valued in, games are paid and debts are settled in it
     pub native_denom: String,
+    /// Length of the rolling window (in seconds) over which game flows are tracked
+    pub flow_window: u64,
+    /// Fraction of the reserve balance a game may lose within one window
+    /// before its funding gets suspended
+    pub max_loss_ratio: Decimal,
+    /// Requests from this contract are accounted as staker distributions
+    pub distribution_contract_address: Option<Addr>,
+    /// Delay (in seconds) between proposing and executing a withdrawal of all funds
+    pub timelock: u64,
+    /// Reserve this one replaces, allowed to import its registrations
+    pub predecessor: Option<Addr>,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    NativeToken { denom: String },
+    Token { contract_addr: Addr },
+}
+
+impl AssetInfo {
+    pub fn key(&self) -> String {
+        match self {
+            AssetInfo::NativeToken { denom } => denom.clone(),
+            AssetInfo::Token { contract_addr } => contract_addr.to_string(),
+        }
+    }
+
+    pub fn is_native(&self) -> bool {
+        matches!(self, AssetInfo::NativeToken { .. })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum YieldStrategy {
+    /// Keeps the whole balance in the reserve
+    Hold,
+    /// Deposits everything above the threshold to an Anchor money market
+    Anchor { market: Addr, aterra: Addr },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// Terra market oracle, only available for native denoms
+    Oracle,
+    /// Price in the native denom set by governance
+    Fixed { price: Decimal },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetConfig {
+    pub info: AssetInfo,
+    /// Amount kept liquid in the reserve, the rest goes to the yield strategy
+    pub threshold: Uint128,
+    pub strategy: YieldStrategy,
+    pub price_source: PriceSource,
+}
+
+// whitelisted assets by denom or token address, the native denom is always listed
+pub const ASSETS: Map<&str, AssetConfig> = Map::new("assets");
 pub const GAMES: Map<Addr, ()> = Map::new("games");
-pub const REQUESTING_CONTRACT: Item<(Addr, Uint128)> = Item::new("requesting_contract");
+// number of buckets a flow window is split into, flows are summed over the buckets
+// that overlap the last `flow_window` seconds
+const FLOW_BUCKETS: u64 = 24;
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct FlowBucket {
+    pub start: u64,
+    pub requested: Uint128,
+    pub deposited: Uint128,
+}
+
+// flow of funds between the reserve and a game within the rolling window
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct GameFlow {
+    #[serde(default)]
+    pub buckets: Vec<FlowBucket>,
+    pub credit_limit: Option<Uint128>,
+    pub suspended: bool,
+}
+
+impl GameFlow {
+    pub fn requested(&self) -> Uint128 {
+        self.buckets.iter().map(|bucket| bucket.requested).sum()
+    }
+
+    pub fn deposited(&self) -> Uint128 {
+        self.buckets.iter().map(|bucket| bucket.deposited).sum()
+    }
+
+    pub fn net_loss(&self) -> Uint128 {
+        self.requested().saturating_sub(self.deposited())
+    }
+
+    // drops the buckets that ended before the window, a bucket partly inside
+    // the window is counted whole
+    pub fn roll_window(&mut self, now: u64, flow_window: u64) {
+        let bucket_length = bucket_length(flow_window);
+        self.buckets
+            .retain(|bucket| bucket.start + bucket_length + flow_window > now);
+    }
+
+    // bucket the flows happening now are added to
+    pub fn current_bucket(&mut self, now: u64, flow_window: u64) -> &mut FlowBucket {
+        let bucket_length = bucket_length(flow_window);
+        let start = now - now % bucket_length;
+        if self.buckets.last().map(|bucket| bucket.start) != Some(start) {
+            self.buckets.push(FlowBucket {
+                start,
+                ..FlowBucket::default()
+            });
+        }
+        self.buckets.last_mut().unwrap()
+    }
+}
+
+fn bucket_length(flow_window: u64) -> u64 {
+    (flow_window / FLOW_BUCKETS).max(1)
+}
+
+pub const GAME_FLOWS: Map<Addr, GameFlow> = Map::new("game_flows");
+pub const REQUESTING_CONTRACT: Item<(Addr, Coin)> = Item::new("requesting_contract");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowSource {
+    GameSweep,
+    GameRequest,
+    DistributionRequest,
+    AnchorYield,
+    LpDeposit,
+    DebtRepayment,
+}
+
+impl FlowSource {
+    pub fn is_inflow(&self) -> bool {
+        !matches!(
+            self,
+            FlowSource::GameRequest | FlowSource::DistributionRequest | FlowSource::DebtRepayment
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LedgerEntry {
+    pub timestamp: u64,
+    pub source: FlowSource,
+    pub address: Option<Addr>,
+    pub amount: Uint128,
+}
+
+// totals of all ledger entries within one day, keyed by the day start timestamp
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DailyAggregate {
+    pub day: u64,
+    pub game_sweeps: Uint128,
+    pub game_requests: Uint128,
+    pub distribution_requests: Uint128,
+    pub anchor_yield: Uint128,
+    pub lp_deposits: Uint128,
+    #[serde(default)]
+    pub debt_repayments: Uint128,
+}
+
+impl DailyAggregate {
+    pub fn new(day: u64) -> Self {
+        Self {
+            day,
+            game_sweeps: Uint128::zero(),
+            game_requests: Uint128::zero(),
+            distribution_requests: Uint128::zero(),
+            anchor_yield: Uint128::zero(),
+            lp_deposits: Uint128::zero(),
+            debt_repayments: Uint128::zero(),
+        }
+    }
+
+    pub fn add(&mut self, source: &FlowSource, amount: Uint128) {
+        match source {
+            FlowSource::GameSweep => self.game_sweeps += amount,
+            FlowSource::GameRequest => self.game_requests += amount,
+            FlowSource::DistributionRequest => self.distribution_requests += amount,
+            FlowSource::AnchorYield => self.anchor_yield += amount,
+            FlowSource::LpDeposit => self.lp_deposits += amount,
+            FlowSource::DebtRepayment => self.debt_repayments += amount,
+        }
+    }
+}
+
+pub const LEDGER: Map<U64Key, LedgerEntry> = Map::new("ledger");
+pub const LEDGER_COUNT: Item<u64> = Item::new("ledger_count");
+pub const DAILY_AGGREGATES: Map<U64Key, DailyAggregate> = Map::new("daily_aggregates");
+// value of the aTerra position of an asset at the last ledger update, growth above it is yield
+pub const ANCHOR_PRINCIPAL: Map<&str, Uint128> = Map::new("anchor_principal");
+
+/// Winnings a game could not pay out, owed to the player by the reserve
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Debt {
+    pub player: Addr,
+    pub game: Addr,
+    pub amount: Uint128,
+    pub created_at: u64,
+}
+
+// debts are repaid in the order they were recorded, `head` is the id of the oldest
+// outstanding debt and `tail` the id the next debt gets
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct DebtQueue {
+    pub head: u64,
+    pub tail: u64,
+    pub total: Uint128,
+}
+
+pub const DEBTS: Map<U64Key, Debt> = Map::new("debts");
+pub const DEBT_QUEUE: Item<DebtQueue> = Item::new("debt_queue");
+pub const PLAYER_DEBTS: Map<Addr, Uint128> = Map::new("player_debts");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WithdrawalKind {
+    /// Moves all funds to the recipient
+    Emergency { recipient: Addr },
+    /// Moves all funds to the new reserve and points the registered contracts to it
+    Migration { new_reserve: Addr },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingWithdrawal {
+    pub kind: WithdrawalKind,
+    pub executable_at: u64,
+}
+
+pub const PENDING_WITHDRAWAL: Item<PendingWithdrawal> = Item::new("pending_withdrawal");
@@ -43,6 +43,16 @@ pub fn only_owner(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
     }
 }
 
+pub fn only_gov(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if info.sender == config.gov_contract_address {
+        Ok(())
+    } else {
+        Err(ContractError::Unauthorized {})
+    }
+}
+
 pub fn only_game(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
     match GAMES.may_load(deps.storage, info.sender.clone())? {
         Some(_) => Ok(()),
@@ -69,3 +79,123 @@ pub fn deduct_tax(deps: Deps, coin: Coin) -> StdResult<Coin> {
         amount: coin.amount - tax_amount,
     })
 }
+
+#[cfg(test)]
+pub mod tests_utils {
+    // we inplement custom moc querier because default one from cosmwasm does not support quering
+    // contracts, anchor token and market queries are answered here
+    use cosmwasm_bignumber::{Decimal256, Uint256};
+    use cosmwasm_std::testing::{MockQuerier, MockQuerierCustomHandlerResult};
+    use cosmwasm_std::{
+        from_slice, to_binary, Coin, ContractResult, Decimal, Querier, QuerierResult,
+        QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+    };
+    use cw20::BalanceResponse;
+    use moneymarket::market::StateResponse;
+    use std::collections::HashMap;
+    use terra_cosmwasm::{
+        ExchangeRateItem, ExchangeRatesResponse, TaxCapResponse, TaxRateResponse, TerraQuery,
+        TerraQueryWrapper,
+    };
+
+    pub struct CustomQuerier {
+        base: MockQuerier<TerraQueryWrapper>,
+        pub anchor_balance: Uint128,
+        pub exchange_rate: Decimal256,
+        // cw20 balances of the reserve by token address
+        pub token_balances: HashMap<String, Uint128>,
+    }
+
+    impl Default for CustomQuerier {
+        fn default() -> Self {
+            Self {
+                base: MockQuerier::new(&[]).with_custom_handler(
+                    |q: &TerraQueryWrapper| -> MockQuerierCustomHandlerResult {
+                        let res = match q.query_data {
+                            TerraQuery::TaxRate {} => to_binary(&TaxRateResponse {
+                                rate: Decimal::zero(),
+                            })
+                            .unwrap(),
+                            TerraQuery::TaxCap { .. } => to_binary(&TaxCapResponse {
+                                cap: 1000u128.into(),
+                            })
+                            .unwrap(),
+                            // every denom is worth a thousandth of the native denom
+                            TerraQuery::ExchangeRates {
+                                ref base_denom,
+                                ref quote_denoms,
+                            } => to_binary(&ExchangeRatesResponse {
+                                base_denom: base_denom.clone(),
+                                exchange_rates: quote_denoms
+                                    .iter()
+                                    .map(|quote_denom| ExchangeRateItem {
+                                        quote_denom: quote_denom.clone(),
+                                        exchange_rate: Decimal::permille(1),
+                                    })
+                                    .collect(),
+                            })
+                            .unwrap(),
+                            _ => unreachable!(),
+                        };
+                        MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(res))
+                    },
+                ),
+                anchor_balance: Uint128::zero(),
+                exchange_rate: Decimal256::one(),
+                token_balances: HashMap::new(),
+            }
+        }
+    }
+
+    impl CustomQuerier {
+        pub fn update_balance(&mut self, addr: impl Into<String>, balance: Vec<Coin>) {
+            self.base.update_balance(addr, balance);
+        }
+    }
+
+    impl Querier for CustomQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
+                Ok(v) => v,
+                Err(e) => {
+                    return SystemResult::Err(SystemError::InvalidRequest {
+                        error: format!("Parsing query request: {}", e),
+                        request: bin_request.into(),
+                    })
+                }
+            };
+            match &request {
+                QueryRequest::Wasm(WasmQuery::Smart { contract_addr, .. }) => {
+                    let res = match contract_addr.as_str() {
+                        "anchor-token" => to_binary(&BalanceResponse {
+                            balance: self.anchor_balance,
+                        }),
+                        "anchor-market" => to_binary(&StateResponse {
+                            total_liabilities: Decimal256::zero(),
+                            total_reserves: Decimal256::zero(),
+                            last_interest_updated: 0,
+                            last_reward_updated: 0,
+                            global_interest_index: Decimal256::one(),
+                            global_reward_index: Decimal256::zero(),
+                            anc_emission_rate: Decimal256::zero(),
+                            prev_aterra_supply: Uint256::zero(),
+                            prev_exchange_rate: self.exchange_rate,
+                        }),
+                        token if self.token_balances.contains_key(token) => {
+                            to_binary(&BalanceResponse {
+                                balance: self.token_balances[token],
+                            })
+                        }
+                        _ => {
+                            return SystemResult::Err(SystemError::NoSuchContract {
+                                addr: contract_addr.clone(),
+                            })
+                        }
+                    };
+                    SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+                }
+                _ => self.base.handle_query(&request),
+            }
+        }
+    }
+}
@@ -10,6 +10,8 @@ pub struct InstantiateMsg {
     pub anchor_token: String,
     pub reward_factor: Decimal,
     pub max_spread: Option<Decimal>,
+    /// receiver of the proceeds left after the rewards, burnt when not set
+    pub remainder_receiver: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -27,10 +29,11 @@ pub enum ExecuteMsg {
         gov_contract: Option<String>,
         astroport_factory: Option<String>,
         max_spread: (bool, Option<Decimal>),
+        remainder_receiver: (bool, Option<String>),
     },
     /// Public Message
-    /// Sweep all given denom balance to ANC token
-    /// and execute Distribute message
+    /// Sweep all given denom balance to VEGA token
+    /// and distribute the VEGA balance
     Sweep { denom: String },
 }
 
@@ -48,9 +51,10 @@ pub struct ConfigResponse {
     pub anchor_token: String,
     pub reward_factor: Decimal,
     pub max_spread: Option<Decimal>,
+    pub remainder_receiver: Option<String>,
 }
 
-/// We currently take no arguments for migrations
+/// Points the collector to a new factory on migration
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MigrateMsg {
     pub astroport_factory: String,
@@ -1,8 +1,37 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Decimal, Uint128};
+use cosmwasm_std::{Addr, Binary, Decimal, Uint128};
 use cw20::Cw20ReceiveMsg;
+use std::fmt;
+
+/// Asset distributed to the holders, either a native denom or a cw20 token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    NativeToken { denom: String },
+    Token { contract_addr: String },
+}
+
+impl AssetInfo {
+    /// Storage key of the asset, the denom or the token address
+    pub fn key(&self) -> String {
+        match self {
+            AssetInfo::NativeToken { denom } => denom.clone(),
+            AssetInfo::Token { contract_addr } => contract_addr.clone(),
+        }
+    }
+
+    pub fn is_native(&self) -> bool {
+        matches!(self, AssetInfo::NativeToken { .. })
+    }
+}
+
+impl fmt::Display for AssetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
@@ -12,6 +41,9 @@ pub struct InstantiateMsg {
     pub unbonding_period: u64,
     pub distribution_ratio: u64,
     pub initial_reserve_amount: Option<Uint128>,
+    /// Token bonded directly with `BondStake`,
+    /// the governance stake is mirrored when it is not set
+    pub staking_token: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -23,11 +55,19 @@ pub enum ExecuteMsg {
 
     /// Update the global index
     UpdateGlobalIndex {},
+    /// Request the payout of the next elapsed epoch from the reserve,
+    /// every epoch is closed at most once
+    CloseEpoch {},
+    /// Swap the accrued rewards of the next batch of auto compounding holders
+    /// and restake them into governance
+    Compound {},
 
     ////////////////////
     /// Owner's operations
     ///////////////////
-    UpdateReserve { amount: Uint128 },
+    UpdateReserve {
+        amount: Uint128,
+    },
     UpdateConfig {
         governance_token_addr: Option<Addr>,
         reserve_contract_addr: Option<Addr>,
@@ -35,6 +75,40 @@ pub enum ExecuteMsg {
         distribution_ratio: Option<u64>,
         unbonding_period: Option<u64>,
     },
+    /// Start distributing the balance of an asset held by the contract
+    RegisterRewardAsset {
+        asset: AssetInfo,
+    },
+    /// Stop distributing an asset, its unclaimed rewards are no longer paid out
+    RemoveRewardAsset {
+        asset: AssetInfo,
+    },
+    /// Pay the reserve out per epoch according to the policy,
+    /// `None` goes back to pulling `distribution_ratio` of the reserve profit on index updates
+    SetEmission {
+        policy: Option<EmissionPolicy>,
+        epoch_length: u64,
+    },
+    /// Pair of the reward denom and the governance token used for compounding
+    UpdateCompoundConfig {
+        astroport_factory: String,
+        vega_token: String,
+        max_spread: Option<Decimal>,
+    },
+    /// Enable locks, the multiplier grows linearly up to `max_multiplier` for a 52 week lock
+    UpdateLockConfig {
+        max_multiplier: Decimal,
+        early_exit_penalty: Decimal,
+    },
+
+    ////////////////////
+    /// Reserve's operations
+    ///////////////////
+
+    /// Points the distribution to a new reserve when the funds are migrated
+    MigrateReserve {
+        new_reserve: Addr,
+    },
 
     ////////////////////
     /// Staking operations
@@ -43,23 +117,83 @@ pub enum ExecuteMsg {
     /// Unbound user staking balance
     /// Withdraw rewards to pending rewards
     /// Set current reward index to global index
-    UnbondStake { address: Addr, amount: Uint128 },
+    UnbondStake {
+        address: Addr,
+        amount: Uint128,
+    },
+
+    /// Unbond the sender's stake, the tokens can be withdrawn after the unbonding period.
+    /// Only available when the contract has its own staking token
+    Unbond {
+        amount: Uint128,
+    },
 
-    /// Unbound user staking balance
     /// Withdraws released stake
-    // WithdrawStake { cap: Option<Uint128> },
+    WithdrawStake {
+        cap: Option<Uint128>,
+    },
+
+    /// Commit bonded stake for 1 to 52 weeks to boost its reward weight
+    Lock {
+        amount: Uint128,
+        weeks: u64,
+    },
+    /// Release a lock, the early exit penalty is distributed to the stakers
+    Unlock {
+        lock_id: u64,
+    },
 
     ////////////////////
     /// User's operations
     ///////////////////
 
-    /// return the accrued reward in usdt to the user.
-    ClaimRewards { recipient: Option<String> },
+    /// return the accrued rewards to the user,
+    /// every reward asset is claimed if `assets` is not set.
+    /// Operators of `holder` can claim on its behalf
+    ClaimRewards {
+        holder: Option<String>,
+        assets: Option<Vec<AssetInfo>>,
+        recipient: Option<String>,
+    },
+
+    /// Claim the rewards to a contract and execute its hook,
+    /// native rewards are sent as funds of `ReceiveRewards`, cw20 rewards with cw20 `Send`
+    ClaimAndCall {
+        holder: Option<String>,
+        assets: Option<Vec<AssetInfo>>,
+        contract: String,
+        msg: Binary,
+    },
+
+    /// Allow `operator` to claim the sender's rewards
+    AddOperator {
+        operator: String,
+    },
+    RemoveOperator {
+        operator: String,
+    },
+
+    /// Restake the reward denom rewards into governance instead of claiming them
+    SetAutoCompound {
+        enabled: bool,
+    },
 
     /// This accepts a properly-encoded ReceiveMsg from a cw20 contract
     Receive(Cw20ReceiveMsg),
 }
 
+/// Amount requested from the reserve when an epoch is closed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmissionPolicy {
+    /// Same amount every epoch
+    Fixed { amount: Uint128 },
+    /// Share of the reserve profit since the last epoch
+    ProfitShare { ratio: Decimal },
+    /// (start_time, end_time, amount) entries, each amount is paid linearly over its period
+    Schedule { schedule: Vec<(u64, u64, Uint128)> },
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ReceiveMsg {
@@ -67,6 +201,23 @@ pub enum ReceiveMsg {
     /// Withdraw rewards to pending rewards
     /// Set current reward index to global index
     BondStake {},
+    /// Distribute the sent tokens to the holders,
+    /// only accepted from tokens registered as a reward asset
+    DepositRewards {},
+}
+
+/// Hook executed on the contract of `ClaimAndCall` with the native rewards as funds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardsHookMsg {
+    ReceiveRewards(RewardsReceiveMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardsReceiveMsg {
+    /// Holder the rewards were claimed for
+    pub sender: String,
+    pub msg: Binary,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -88,6 +239,54 @@ pub enum QueryMsg {
         address: String,
     },
     ReserveRequestFunds {},
+    Emission {},
+    Epoch {
+        id: u64,
+    },
+    Epochs {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    CompoundConfig {},
+    AutoCompound {
+        address: String,
+    },
+    /// Reward denom index snapshots by timestamp, taken on every index update
+    Snapshots {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Reward denom earned per staked token over a year, at the pace of the window
+    Apr {
+        window_seconds: u64,
+    },
+    /// Rewards paid to the holder, per asset
+    ClaimReceipts {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Rewards moved to pending whenever the holder balance changed
+    AccrualCheckpoints {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    Operators {
+        address: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    LockConfig {},
+    Lock {
+        address: String,
+        lock_id: u64,
+    },
+    Locks {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -95,29 +294,152 @@ pub struct ReserveRequestFundsResponse {
     pub reserve_request_funds: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionResponse {
+    pub policy: Option<EmissionPolicy>,
+    pub epoch_length: u64,
+    /// Id and start time of the epoch that is closed next
+    pub next_epoch: u64,
+    pub next_epoch_start: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochResponse {
+    pub id: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub closed_at: u64,
+    /// Amount requested from the reserve
+    pub payout: Uint128,
+    /// Increase of the reward denom global index
+    pub index_delta: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochsResponse {
+    pub epochs: Vec<EpochResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SnapshotResponse {
+    pub timestamp: u64,
+    pub global_index: Decimal,
+    /// Bonded balance plus lock bonuses at the time of the snapshot
+    pub total_weight: Uint128,
+    pub rewards_added: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SnapshotsResponse {
+    pub snapshots: Vec<SnapshotResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AprResponse {
+    pub window_seconds: u64,
+    /// Time of the snapshot the window starts from
+    pub start_time: u64,
+    pub index_delta: Decimal,
+    /// Reward denom paid per unit of staking weight over a year at the window's pace,
+    /// an amount in reward denom units rather than a percentage
+    pub annualized_reward_per_token: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimReceiptResponse {
+    pub id: u64,
+    pub timestamp: u64,
+    pub asset: AssetInfo,
+    /// Rewards taken from the holder, including the tax
+    pub amount: Uint128,
+    pub recipient: String,
+    pub tax: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimReceiptsResponse {
+    pub receipts: Vec<ClaimReceiptResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccrualResponse {
+    pub asset: AssetInfo,
+    pub amount: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccrualCheckpointResponse {
+    pub id: u64,
+    pub timestamp: u64,
+    /// Balance the rewards accrued on, before the change
+    pub balance: Uint128,
+    pub accrued: Vec<AccrualResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccrualCheckpointsResponse {
+    pub checkpoints: Vec<AccrualCheckpointResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CompoundConfigResponse {
+    pub astroport_factory: String,
+    pub vega_token: String,
+    pub max_spread: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AutoCompoundResponse {
+    pub enabled: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
     pub governance_token_addr: String,
+    pub staking_token: Option<String>,
     pub reward_denom: String,
     pub unbonding_period: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StateResponse {
-    pub global_index: Decimal,
     pub total_balance: Uint128,
+    /// Bonded and unbonding tokens held by the contract
+    pub staked_tokens: Uint128,
+    pub assets: Vec<RewardAssetResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardAssetResponse {
+    pub asset: AssetInfo,
+    pub global_index: Decimal,
     pub prev_reward_balance: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AccruedRewardsResponse {
-    pub rewards: Uint128,
+    pub rewards: Vec<AccruedReward>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccruedReward {
+    pub asset: AssetInfo,
+    pub amount: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct HolderResponse {
     pub address: String,
     pub balance: Uint128,
+    pub locked: Uint128,
+    /// Reward weight, the balance boosted by the locks
+    pub weight: Uint128,
+    pub rewards: Vec<HolderRewardResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HolderRewardResponse {
+    pub asset: AssetInfo,
     pub index: Decimal,
     pub pending_rewards: Decimal,
 }
@@ -129,3 +451,31 @@ pub struct HoldersResponse {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OperatorsResponse {
+    pub operators: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockConfigResponse {
+    pub max_multiplier: Decimal,
+    pub early_exit_penalty: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockResponse {
+    pub id: u64,
+    pub amount: Uint128,
+    pub multiplier: Decimal,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub remaining_seconds: u64,
+    /// Stake lost if the lock is released now
+    pub early_exit_penalty: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LocksResponse {
+    pub locks: Vec<LockResponse>,
+}
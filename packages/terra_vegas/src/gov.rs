@@ -14,10 +14,25 @@ pub struct InstantiateMsg {
     pub timelock_period: u64,
     pub proposal_deposit: Uint128,
     pub snapshot_period: u64,
+    /// Share of NoWithVeto votes that rejects a poll and slashes its deposit
+    pub veto_threshold: Decimal,
+    /// Longest lock in blocks, the staked share votes as is while it is 0
+    pub max_lock_period: u64,
+    /// Threshold of polls executing raw messages, the higher of the two thresholds applies
+    pub raw_msg_threshold: Decimal,
+    /// Categories with their own parameters, the others use the parameters above
+    pub categories: Vec<PollCategoryParams>,
+    /// Can veto passed polls during their timelock
+    pub guardian: Option<String>,
+    /// Blocks after the timelock a passed poll can be executed in, it never expires while it is 0
+    pub expiration_period: u64,
+    /// Share of the deposit refunded when the creator cancels a poll
+    pub cancel_refund_ratio: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
     ExecutePollMsgs {
@@ -37,6 +52,15 @@ pub enum ExecuteMsg {
         timelock_period: Option<u64>,
         proposal_deposit: Option<Uint128>,
         snapshot_period: Option<u64>,
+        veto_threshold: Option<Decimal>,
+        /// Receives the deposits of vetoed polls
+        community_contract: Option<String>,
+        max_lock_period: Option<u64>,
+        raw_msg_threshold: Option<Decimal>,
+        /// Replaces the parameters of the given categories
+        categories: Option<Vec<PollCategoryParams>>,
+        expiration_period: Option<u64>,
+        cancel_refund_ratio: Option<Decimal>,
     },
     CastVote {
         poll_id: u64,
@@ -55,6 +79,54 @@ pub enum ExecuteMsg {
     SnapshotPoll {
         poll_id: u64,
     },
+    /// Delegate the voting weight of the staked share to another staker,
+    /// the delegate can not pass it on further
+    Delegate {
+        delegate: String,
+    },
+    Undelegate {},
+    /// Lock staked tokens until `end_height` for a voting weight decaying
+    /// with the remaining lock period
+    CreateLock {
+        amount: Uint128,
+        end_height: u64,
+    },
+    IncreaseLockAmount {
+        amount: Uint128,
+    },
+    ExtendLock {
+        end_height: u64,
+    },
+    /// Withdraw the tokens of an expired lock
+    WithdrawLock {},
+    /// Veto a passed poll during its timelock, only callable by the guardian
+    VetoPoll {
+        poll_id: u64,
+    },
+    /// Rotate or remove the guardian, only callable by governance itself
+    UpdateGuardian {
+        guardian: Option<String>,
+    },
+    /// Cancel a poll nobody has voted on yet, only callable by the creator
+    CancelPoll {
+        poll_id: u64,
+    },
+    /// Change the text of a poll nobody has voted on yet, only callable by the creator
+    AmendPoll {
+        poll_id: u64,
+        title: Option<String>,
+        description: Option<String>,
+        link: Option<String>,
+    },
+    /// Expire a passed poll that was not executed within the expiration period
+    ExpirePoll {
+        poll_id: u64,
+    },
+    /// Remove the voter records of a finished poll
+    PrunePollVoters {
+        poll_id: u64,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -63,12 +135,16 @@ pub enum Cw20HookMsg {
     /// StakeVotingTokens a user can stake their mirror token to receive rewards
     /// or do vote on polls
     StakeVotingTokens {},
+    /// Stake the tokens on behalf of another staker
+    StakeVotingTokensFor { staker: String },
     /// CreatePoll need to receive deposit from a proposer
     CreatePoll {
         title: String,
         description: String,
         link: Option<String>,
         execute_msgs: Option<Vec<PollExecuteMsg>>,
+        /// Taken from the actions when it is not given
+        category: Option<PollCategory>,
     },
 }
 
@@ -76,8 +152,127 @@ pub enum Cw20HookMsg {
 #[serde(rename_all = "snake_case")]
 pub struct PollExecuteMsg {
     pub order: u64,
-    pub contract: String,
-    pub msg: Binary,
+    pub action: PollAction,
+}
+
+/// Action a passed poll executes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PollAction {
+    UpdateGameParams {
+        game: String,
+        param: GameParam,
+    },
+    AddGame {
+        reserve: String,
+        game: String,
+    },
+    RemoveGame {
+        reserve: String,
+        game: String,
+    },
+    UpdateDistributionConfig {
+        distribution: String,
+        reward_denom: Option<String>,
+        distribution_ratio: Option<u64>,
+        unbonding_period: Option<u64>,
+    },
+    CommunitySpend {
+        community: String,
+        recipient: String,
+        amount: Uint128,
+    },
+    /// Governance has to be the admin of the contract
+    MigrateContract {
+        contract: String,
+        new_code_id: u64,
+        msg: Binary,
+    },
+    /// Any message to any contract, the poll has to pass the raw message threshold
+    Raw {
+        contract: String,
+        msg: Binary,
+    },
+}
+
+impl PollAction {
+    /// Contract the action is executed on
+    pub fn contract(&self) -> &str {
+        match self {
+            PollAction::UpdateGameParams { game, .. } => game,
+            PollAction::AddGame { reserve, .. } | PollAction::RemoveGame { reserve, .. } => reserve,
+            PollAction::UpdateDistributionConfig { distribution, .. } => distribution,
+            PollAction::CommunitySpend { community, .. } => community,
+            PollAction::MigrateContract { contract, .. } | PollAction::Raw { contract, .. } => {
+                contract
+            }
+        }
+    }
+
+    pub fn is_raw(&self) -> bool {
+        matches!(self, PollAction::Raw { .. })
+    }
+
+    /// Category of the polls executing the action, raw messages fit in every category
+    /// but the text polls and take the highest requirements of all of them
+    pub fn category(&self) -> Option<PollCategory> {
+        match self {
+            PollAction::UpdateGameParams { .. }
+            | PollAction::AddGame { .. }
+            | PollAction::RemoveGame { .. }
+            | PollAction::UpdateDistributionConfig { .. } => Some(PollCategory::ParameterChange),
+            PollAction::CommunitySpend { .. } => Some(PollCategory::TreasurySpend),
+            PollAction::MigrateContract { .. } => Some(PollCategory::ContractMigration),
+            PollAction::Raw { .. } => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PollCategory {
+    /// Polls without messages
+    Text,
+    ParameterChange,
+    TreasurySpend,
+    ContractMigration,
+    /// Polls removing games or updating their parameters without a timelock,
+    /// disabled until the category is configured with a supermajority threshold
+    Emergency,
+}
+
+impl fmt::Display for PollCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PollCategory::Text => write!(f, "text"),
+            PollCategory::ParameterChange => write!(f, "parameter_change"),
+            PollCategory::TreasurySpend => write!(f, "treasury_spend"),
+            PollCategory::ContractMigration => write!(f, "contract_migration"),
+            PollCategory::Emergency => write!(f, "emergency"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollCategoryParams {
+    pub category: PollCategory,
+    pub quorum: Decimal,
+    pub threshold: Decimal,
+    pub voting_period: u64,
+    pub timelock_period: u64,
+    pub proposal_deposit: Uint128,
+}
+
+/// Game parameter changed by a poll
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GameParam {
+    AdvantageValue(Decimal),
+    WinTax(Decimal),
+    MaxNumberOfBets(u64),
+    MaxBettingRatio(u64),
+    RoundDuration(u64),
+    MaxCashflow(Uint128),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -103,6 +298,49 @@ pub enum QueryMsg {
         limit: Option<u32>,
         order_by: Option<OrderBy>,
     },
+    DelegatePower {
+        delegate: String,
+    },
+    Delegators {
+        delegate: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Lock weight a poll created at `height` uses, the next block by default
+    VotingEscrow {
+        address: String,
+        height: Option<u64>,
+    },
+    TotalVotingEscrow {
+        height: Option<u64>,
+    },
+    /// Dry run of the messages of a poll
+    SimulatePoll {
+        poll_id: u64,
+    },
+    /// Dry run of the messages before they are proposed
+    SimulateExecuteMsgs {
+        msgs: Vec<PollExecuteMsg>,
+    },
+    PollsByCreator {
+        creator: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        order_by: Option<OrderBy>,
+    },
+    /// In-progress polls ending within the given number of blocks, the earliest first
+    PollsEndingSoon {
+        blocks: u64,
+        limit: Option<u32>,
+    },
+}
+
+/// Polls stored before the indexed map are migrated in batches, the migration
+/// is repeated from the `last_poll_id` attribute until no poll is left
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    pub start_after: Option<u64>,
+    pub limit: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
@@ -116,6 +354,14 @@ pub struct ConfigResponse {
     pub timelock_period: u64,
     pub proposal_deposit: Uint128,
     pub snapshot_period: u64,
+    pub veto_threshold: Decimal,
+    pub community_contract: String,
+    pub max_lock_period: u64,
+    pub raw_msg_threshold: Decimal,
+    pub categories: Vec<PollCategoryParams>,
+    pub guardian: String,
+    pub expiration_period: u64,
+    pub cancel_refund_ratio: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
@@ -130,6 +376,8 @@ pub struct PollResponse {
     pub id: u64,
     pub creator: String,
     pub status: PollStatus,
+    /// Not set for polls created before the categories
+    pub category: Option<PollCategory>,
     pub end_height: u64,
     pub title: String,
     pub description: String,
@@ -138,6 +386,8 @@ pub struct PollResponse {
     pub execute_data: Option<Vec<PollExecuteMsg>>,
     pub yes_votes: Uint128, // balance
     pub no_votes: Uint128,  // balance
+    pub abstain_votes: Uint128,
+    pub veto_votes: Uint128,
     pub staked_amount: Option<Uint128>,
     pub total_balance_at_end_poll: Option<Uint128>,
 }
@@ -157,6 +407,74 @@ pub struct StakerResponse {
     pub balance: Uint128,
     pub share: Uint128,
     pub locked_balance: Vec<(u64, VoterInfo)>,
+    pub delegate: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct DelegatePowerResponse {
+    pub delegate: String,
+    pub balance: Uint128,
+    /// Balance delegated to the staker
+    pub delegated_balance: Uint128,
+    pub voting_power: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct DelegatorsResponseItem {
+    pub delegator: String,
+    pub share: Uint128,
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct DelegatorsResponse {
+    pub delegators: Vec<DelegatorsResponseItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct VotingEscrowResponse {
+    pub address: String,
+    pub height: u64,
+    /// Current balance of the locked share
+    pub locked_amount: Uint128,
+    pub end_height: u64,
+    /// Locked amount scaled by the remaining lock period at `height`
+    pub voting_power: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct TotalVotingEscrowResponse {
+    pub height: u64,
+    pub voting_power: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct SimulationResponse {
+    /// Results in the execution order
+    pub results: Vec<SimulationResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct SimulationResult {
+    pub order: u64,
+    pub contract: String,
+    pub status: SimulationStatus,
+}
+
+/// Outcome of the checks done for a poll message, the authorization is only checked
+/// for the contracts of this repo exposing their admins
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulationStatus {
+    Ok,
+    /// The address is invalid or not a contract
+    InvalidContract { reason: String },
+    /// The message does not match the schema of the contract
+    InvalidMsg { reason: String },
+    /// The contract rejects the message from governance
+    Unauthorized { reason: String },
+    /// Raw message to a contract with an unknown schema, only the address was checked
+    UnknownSchema,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
@@ -184,8 +502,10 @@ pub enum PollStatus {
     Passed,
     Rejected,
     Executed,
-    Expired, // Depricated
+    Expired,
     Failed,
+    Vetoed,
+    Cancelled,
 }
 
 impl fmt::Display for PollStatus {
@@ -199,14 +519,19 @@ impl fmt::Display for PollStatus {
 pub enum VoteOption {
     Yes,
     No,
+    /// Counts toward the quorum only
+    Abstain,
+    /// Counts as No, and rejects the poll and slashes the deposit above the veto threshold
+    NoWithVeto,
 }
 
 impl fmt::Display for VoteOption {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if *self == VoteOption::Yes {
-            write!(f, "yes")
-        } else {
-            write!(f, "no")
+        match self {
+            VoteOption::Yes => write!(f, "yes"),
+            VoteOption::No => write!(f, "no"),
+            VoteOption::Abstain => write!(f, "abstain"),
+            VoteOption::NoWithVeto => write!(f, "no_with_veto"),
         }
     }
 }
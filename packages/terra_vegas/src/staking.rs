@@ -7,7 +7,7 @@ use cw20::Cw20ReceiveMsg;
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub anchor_token: String,
-    pub staking_token: String, // lp token of ANC-UST pair contract
+    pub staking_token: String, // lp token of VEGA-UST pair contract
     pub distribution_schedule: Vec<(u64, u64, Uint128)>,
 }
 
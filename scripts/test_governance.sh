@@ -1,7 +1,7 @@
 function deploy_governance {
   echo "Deploying governance contract"
 
-  init_msg='{"quorum":"%s","threshold":"%s","voting_period":%s,"timelock_period":%s,"proposal_deposit":"%s","snapshot_period":%s}\n'
+  init_msg='{"quorum":"%s","threshold":"%s","voting_period":%s,"timelock_period":%s,"proposal_deposit":"%s","snapshot_period":%s,"veto_threshold":"%s","max_lock_period":%s,"raw_msg_threshold":"%s","categories":[],"expiration_period":%s,"cancel_refund_ratio":"%s"}\n'
 
   quorum=0
   threshold=0
@@ -9,8 +9,13 @@ function deploy_governance {
   timelock_period=0
   proposal_deposit=0
   snapshot_period=0
+  veto_threshold=0.334
+  max_lock_period=0
+  raw_msg_threshold=0.67
+  expiration_period=0
+  cancel_refund_ratio=0.5
   
-  msg=$(printf "$init_msg" "$quorum" "$threshold" "$voting_period" "$timelock_period" "$proposal_deposit" "$snapshot_period")
+  msg=$(printf "$init_msg" "$quorum" "$threshold" "$voting_period" "$timelock_period" "$proposal_deposit" "$snapshot_period" "$veto_threshold" "$max_lock_period" "$raw_msg_threshold" "$expiration_period" "$cancel_refund_ratio")
 
   echo $msg > /dev/tty
 
@@ -1,7 +1,7 @@
 function deploy_reserve {
   echo "Deploying reserve contract"
 
-  init_msg='{"anchor_market_address":"%s","anchor_token_address":"%s","gov_contract_address":"%s","threshold":"%s","native_denom":"%s","anchor_denom":"%s"}\n'
+  init_msg='{"anchor_market_address":"%s","anchor_token_address":"%s","gov_contract_address":"%s","threshold":"%s","native_denom":"%s","anchor_denom":"%s","flow_window":%s,"max_loss_ratio":"%s","timelock":%s}\n'
 
   # money market and aUST contracts fron Anchor on testnet bombay-12
   anchor_market_address=terra15dwd5mj8v59wpj0wvt233mf5efdff808c5tkal
@@ -10,8 +10,11 @@ function deploy_reserve {
   threshold=1000
   native_denom=uusd
   anchor_denom=aUST
+  flow_window=86400
+  max_loss_ratio=0.1
+  timelock=259200
 
-  msg=$(printf "$init_msg" "$anchor_market_address" "$anchor_token_address" "$governance_contract_addr" "$threshold" "$native_denom" "$anchor_denom")
+  msg=$(printf "$init_msg" "$anchor_market_address" "$anchor_token_address" "$governance_contract_addr" "$threshold" "$native_denom" "$anchor_denom" "$flow_window" "$max_loss_ratio" "$timelock")
 
   terrad tx wasm instantiate $reserve_code_id $msg --yes --from $test_account --chain-id=$chain_id --fees=10000uluna --gas=auto --broadcast-mode=block --node $node
 